@@ -2,12 +2,27 @@
 //!
 //! This script uses bindgen to generate Rust bindings for macOS sys/proc_info.h
 //! structures, specifically proc_bsdshortinfo and related constants.
+//! It also exports the target triple and profile for version reporting.
 
 use std::path::Path;
 
 fn main() {
     println!("cargo:rerun-if-changed=src/macos/sys_proc_info.h");
 
+    // Build information reported by `caloud --caloud-version`.
+    for (key, name) in [
+        ("TARGET", "CALOUD_BUILD_TARGET"),
+        ("PROFILE", "CALOUD_BUILD_PROFILE"),
+    ] {
+        let value = std::env::var(key).expect("TARGET and PROFILE should be set by cargo");
+        println!("cargo:rustc-env={name}={value}");
+    }
+
+    // The bindings are for the macOS SDK, which other targets do not have.
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("macos") {
+        return;
+    }
+
     let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR should be set by cargo");
     let out_path = Path::new(&out_dir).join("sys_proc_info.rs");
 
@@ -0,0 +1,191 @@
+//! `--abbreviate-speech`: shorten what is useless to hear in full before it
+//! is spoken, such as a commit hash read out digit by digit.
+
+use anyhow::bail;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::fmt;
+
+/// `https://docs.rs/regex/latest/regex/` is spoken as `docs.rs`; brackets,
+/// quotes, and punctuation at the end belong to the sentence.
+const URL_PATTERN: &str =
+    r#"\bhttps?://(?:www\.)?(?P<host>[^\s/:?#()<>"'`]+)(?:[^\s()<>"'`]*[^\s()<>"'`.,;:!?])?"#;
+
+/// Hex strings at least this long, with digits and letters, are hashes.
+const HASH_PATTERN: &str = r"\b[0-9a-fA-F]{12,}\b";
+
+/// How many characters of a hash are kept.
+const HASH_PREFIX_LEN: usize = 4;
+
+/// Two or more slashes, so that `and/or` and `src/main.rs` are left alone.
+const PATH_PATTERN: &str =
+    r#"(?P<start>^|[\s"'`(\[<])(?P<path>(?:~|\.{1,2}|[\w.@+-]+)?(?:/[\w.@+-]+){2,}/?)"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Abbreviations {
+    pub hashes: bool,
+    pub paths: bool,
+    pub urls: bool,
+}
+
+impl Default for Abbreviations {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl Abbreviations {
+    pub const ALL: Self = Self {
+        hashes: true,
+        paths: true,
+        urls: true,
+    };
+
+    pub const NONE: Self = Self {
+        hashes: false,
+        paths: false,
+        urls: false,
+    };
+
+    /// Parse `none` or a comma-separated list of `hashes`, `paths`, and `urls`.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        if s == "none" {
+            return Ok(Self::NONE);
+        }
+        let mut abbreviations = Self::NONE;
+        for category in s.split(',') {
+            match category.trim() {
+                "hashes" => abbreviations.hashes = true,
+                "paths" => abbreviations.paths = true,
+                "urls" => abbreviations.urls = true,
+                category => {
+                    bail!("unknown category: {category} (expected hashes, paths, urls, none)")
+                }
+            }
+        }
+        Ok(abbreviations)
+    }
+}
+
+/// Formats the categories in the form accepted by [`Abbreviations::parse`].
+impl fmt::Display for Abbreviations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let categories: Vec<_> = [
+            (self.hashes, "hashes"),
+            (self.paths, "paths"),
+            (self.urls, "urls"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+        match categories.as_slice() {
+            [] => f.write_str("none"),
+            categories => f.write_str(&categories.join(",")),
+        }
+    }
+}
+
+pub struct Abbreviator {
+    abbreviations: Abbreviations,
+    url: Regex,
+    hash: Regex,
+    path: Regex,
+}
+
+impl Abbreviator {
+    pub fn new(abbreviations: Abbreviations) -> Self {
+        let regex = |pattern| Regex::new(pattern).expect("abbreviation patterns must be valid");
+        Self {
+            abbreviations,
+            url: regex(URL_PATTERN),
+            hash: regex(HASH_PATTERN),
+            path: regex(PATH_PATTERN),
+        }
+    }
+
+    /// `text` with URLs, hashes, and paths shortened as configured.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        // URLs first, since they contain paths and sometimes hashes.
+        if self.abbreviations.urls {
+            text = replace(text, &self.url, |c| c["host"].to_string());
+        }
+        if self.abbreviations.hashes {
+            text = replace(text, &self.hash, |c| {
+                let hash = &c[0];
+                let is_word = hash.bytes().all(|b| b.is_ascii_alphabetic())
+                    || hash.bytes().all(|b| b.is_ascii_digit());
+                match is_word {
+                    true => hash.to_string(),
+                    false => format!("{}…", &hash[..HASH_PREFIX_LEN]),
+                }
+            });
+        }
+        if self.abbreviations.paths {
+            text = replace(text, &self.path, |c| {
+                let path = c["path"].trim_end_matches('/');
+                let name = path.rsplit('/').next().unwrap_or(path);
+                format!("{}{name}", &c["start"])
+            });
+        }
+        text
+    }
+}
+
+fn replace<'a>(
+    text: Cow<'a, str>,
+    regex: &Regex,
+    replacement: impl Fn(&Captures<'_>) -> String,
+) -> Cow<'a, str> {
+    let replaced = match regex.replace_all(&text, replacement) {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(replaced) => Some(replaced),
+    };
+    replaced.map_or(text, Cow::Owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Abbreviations::parse("none").unwrap(), Abbreviations::NONE);
+        let abbreviations = Abbreviations::parse("urls, hashes").unwrap();
+        assert!(abbreviations.urls && abbreviations.hashes && !abbreviations.paths);
+        assert_eq!(abbreviations.to_string(), "hashes,urls");
+        assert_eq!(Abbreviations::ALL.to_string(), "hashes,paths,urls");
+        assert_eq!(Abbreviations::NONE.to_string(), "none");
+        assert!(Abbreviations::parse("digits").is_err());
+    }
+
+    #[test]
+    fn abbreviate() {
+        let abbreviator = Abbreviator::new(Abbreviations::ALL);
+        assert_eq!(
+            abbreviator.apply(
+                "Committed 9fa077e3c1d2b4a5e6f708192a3b4c5d6e7f8091 \
+                 touching /Users/me/src/caloud/src/main.rs (see https://www.example.com/a/b?c=d)."
+            ),
+            "Committed 9fa0… touching main.rs (see example.com)."
+        );
+        // Short hashes, numbers, words, and shallow paths are kept.
+        let kept = "Commit 9fa077e, 1234567890123 tests, and/or src/main.rs, deadbeefcafe";
+        assert_eq!(abbreviator.apply(kept), kept);
+        assert_eq!(abbreviator.apply("`./a/b/c/`"), "`c`");
+    }
+
+    #[test]
+    fn per_category() {
+        let text = "See https://example.com/x in ~/a/b/c.txt";
+        let urls_only = Abbreviator::new(Abbreviations {
+            urls: true,
+            ..Abbreviations::NONE
+        });
+        assert_eq!(urls_only.apply(text), "See example.com in ~/a/b/c.txt");
+        assert!(matches!(
+            Abbreviator::new(Abbreviations::NONE).apply(text),
+            Cow::Borrowed(_)
+        ));
+    }
+}
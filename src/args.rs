@@ -1,119 +1,1863 @@
-use crate::claude::ClaudeCommand;
-use crate::input_rewrite::rewriter::InputRewriter;
+use crate::abbreviation::Abbreviations;
+use crate::auto_respond::{AutoRespondRule, AutoResponder};
+use crate::claude::{self, ChildCommand};
+use crate::claude_session::{self, ClaudeSession};
+use crate::code_block_destination::CodeBlockDestination;
+use crate::config;
+use crate::debug_log::DebugLog;
+use crate::emoji::EmojiMode;
+use crate::env_file;
+use crate::file_watch::{FileGlob, FileMatcher};
+use crate::focus_policy::FocusRule;
+use crate::git::GitRepository;
+use crate::input_rewrite::escape::{escape_bytes, parse_escaped_str};
+use crate::input_rewrite::pipeline::InputPipeline;
+use crate::input_rewrite::presets::KeyPreset;
+use crate::input_rewrite::rewriter::{DEFAULT_PENDING_TIMEOUT, InputRewriter};
 use crate::input_rewrite::rule::RewriteRule;
-use crate::macos::say::SayCommand;
+use crate::input_rewrite::rules_file;
+use crate::macos::appearance::AppearanceMode;
+use crate::macos::say::{SayCommand, SayWhen};
+use crate::macos::sound::{self, SoundEvent, SoundTheme};
+use crate::mirror::MirrorKey;
+use crate::recording::Recorder;
+use crate::redact::Redactor;
 use crate::runtime::Runtime;
-use crate::tty_text::reformat::{LineWrapMode, Reformatter};
+use crate::shell_command::ShellCommand;
+use crate::supervisor::{DEFAULT_MAX_RESTARTS, RestartPolicy};
+use crate::terminfo;
+use crate::tool::{self, Tool};
+use crate::truncation::{LengthLimits, Truncation};
+use crate::tty_text::alert::AlertKind;
+use crate::tty_text::reformat::{LineWrapMode, Reformatter, WrapLog};
+use crate::voice_pool::VoiceAssignment;
 use anyhow::{Context, bail};
 use lexopt::prelude::*;
-use std::ffi::OsString;
+use regex::Regex;
+use std::ffi::{OsStr, OsString};
+use std::fs::OpenOptions;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    #[default]
+    Run,
+    Doctor,
+    Capabilities,
+    Config,
+    /// `config validate`
+    ConfigValidate,
+    Filter,
+    Reformat,
+    Decode,
+    Send,
+    Notify,
+    ListVoices,
+    Say,
+    Mirror,
+    Version,
+    Help,
+}
+
+/// Subcommands, recognized only as the first positional argument.  Without
+/// one, the arguments are for `run`.
+const SUBCOMMANDS: &[(&str, Command)] = &[
+    ("run", Command::Run),
+    ("doctor", Command::Doctor),
+    ("capabilities", Command::Capabilities),
+    ("config", Command::Config),
+    ("filter", Command::Filter),
+    ("reformat", Command::Reformat),
+    ("decode", Command::Decode),
+    ("send", Command::Send),
+    ("notify", Command::Notify),
+    ("list-voices", Command::ListVoices),
+    ("say", Command::Say),
+    ("mirror", Command::Mirror),
+];
+
+/// The bytes of output read from `claude` at most at a time, and held back
+/// until a fragment is complete, by default.
+const DEFAULT_BUFFER_SIZE: usize = 8192;
+/// Below this, an ordinary escape sequence may not fit.
+const MIN_BUFFER_SIZE: usize = 256;
+/// Above this, the buffer, allocated twice over, only takes memory: no
+/// fragment that `claude` writes comes close.
+const MAX_BUFFER_SIZE: usize = 1 << 20;
+/// The columns assumed when neither the terminal, `--width` nor `COLUMNS`
+/// tells.
+pub const DEFAULT_WIDTH: u16 = 80;
+
+#[derive(Debug, Default)]
 pub struct Arguments {
+    command: Command,
+    subcommand_given: bool,
+    /// Configuration files and variables that were applied, for `caloud config`.
+    config_sources: Vec<String>,
+    /// Errors in configuration files and variables, kept for `caloud config validate`.
+    config_errors: Vec<anyhow::Error>,
     notification_center_delivery_enabled: bool,
+    notify_disabled: bool,
     say_args: Option<OsString>,
+    say_disabled: bool,
+    say_when: SayWhen,
+    translate_command: Option<ShellCommand>,
+    speech_abbreviations: Abbreviations,
+    speech_emoji: EmojiMode,
+    voice_pool: Vec<String>,
+    voice_assignment: VoiceAssignment,
+    voiceover_enabled: bool,
+    speak_output_enabled: bool,
+    speak_questions_enabled: bool,
+    speak_tools_enabled: bool,
+    speak_final_enabled: bool,
+    permission_reminder_secs: Option<u64>,
+    progress_after_secs: Option<u64>,
+    stall_timeout_mins: Option<u64>,
+    progress_state_enabled: bool,
+    sounds: Vec<(SoundEvent, PathBuf)>,
     line_wrap_mode: LineWrapMode,
+    width: Option<u16>,
+    buffer_size: Option<usize>,
+    screen_reader_enabled: bool,
     rewrite_rules: Vec<RewriteRule>,
+    rewrite_files: Vec<PathBuf>,
+    key_presets: Vec<KeyPreset>,
+    rewrite_timeout_ms: Option<u64>,
     zwsp_after_updown_arrow: bool,
+    copy_key: Option<Vec<u8>>,
+    review_key: Option<Vec<u8>>,
+    code_block_destination: Option<CodeBlockDestination>,
+    code_block_min_lines: Option<usize>,
+    code_block_notify_enabled: bool,
+    auto_respond_rules: Vec<AutoRespondRule>,
+    rate_limit_notify_enabled: bool,
+    rate_limit_patterns: Vec<Regex>,
+    compaction_notify_enabled: bool,
+    exit_notify_enabled: bool,
+    watch_files: Vec<FileGlob>,
+    git_context_enabled: bool,
+    title_flash_secs: Option<u64>,
+    bell_enabled: bool,
+    digest_secs: Option<u64>,
+    redact_enabled: bool,
+    redact_patterns: Vec<Regex>,
+    max_title_chars: Option<usize>,
+    max_message_chars: Option<usize>,
+    truncation: Truncation,
+    mirror_send_enabled: bool,
+    mirror_receive_enabled: bool,
+    mirror_key_path: Option<PathBuf>,
+    focus_rules: Vec<FocusRule>,
+    broadcast_enabled: bool,
+    notify_only: bool,
+    term: Option<String>,
+    colorterm: Option<OsString>,
+    appearance: Option<AppearanceMode>,
+    env_files: Vec<PathBuf>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+    claude_path: Option<PathBuf>,
+    login_shell_enabled: bool,
+    tool: Option<&'static Tool>,
+    bypass_filter_enabled: bool,
+    /// `--restart-on-crash`: how many times.
+    max_restarts: Option<u32>,
+    restart_continue_enabled: bool,
+    claude_session_id: Option<String>,
+    summary_enabled: bool,
+    on_start: Option<ShellCommand>,
+    on_exit: Option<ShellCommand>,
+    control_socket: Option<PathBuf>,
+    input_fifo: Option<PathBuf>,
+    debug_log_path: Option<PathBuf>,
+    debug_wrap_path: Option<PathBuf>,
+    record_path: Option<PathBuf>,
     claude_argv: Vec<OsString>,
+    /// `--exec`: `claude_argv` is another tool than `claude`.
+    exec_given: bool,
+    /// The title and message for `caloud send`, or the session for
+    /// `caloud mirror`.
+    subcommand_args: Vec<OsString>,
 }
 
 impl Arguments {
+    /// Parse the command line on top of `CALOUD_*` environment variables and
+    /// the configuration files.  Errors in any layer are fatal, except for
+    /// `config validate`, which keeps all of them for reporting.
     pub fn parse() -> anyhow::Result<Self> {
-        parse_args(std::env::args_os())
+        let mut arguments = Self::default();
+        let mut errors = Vec::new();
+        let mut collect = |result: Result<(), Vec<anyhow::Error>>| {
+            errors.extend(result.err().into_iter().flatten())
+        };
+        if let Some(path) = config::user_config_path() {
+            collect(arguments.apply_config_file(&path, false));
+        }
+        collect(arguments.apply_config_file(Path::new(config::PROJECT_CONFIG_FILE), true));
+        collect(arguments.apply_environment(|name| std::env::var_os(name)));
+        let command_line = arguments.apply_options(lexopt::Parser::from_env());
+        if arguments.command == Command::ConfigValidate {
+            errors.extend(command_line.err());
+            arguments.config_errors = errors;
+            return Ok(arguments);
+        }
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
+        }
+        command_line?;
+        Ok(arguments)
+    }
+
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    pub fn config_errors(&self) -> &[anyhow::Error] {
+        &self.config_errors
+    }
+
+    pub fn subcommand_args(&self) -> &[OsString] {
+        &self.subcommand_args
+    }
+
+    pub fn notification_center_delivery_enabled(&self) -> bool {
+        self.notification_center_delivery_enabled
+    }
+
+    /// The redactor for `--redact`, if enabled.
+    pub fn redactor(&self) -> Option<Redactor> {
+        self.redact_enabled
+            .then(|| Redactor::new(&self.redact_patterns))
+    }
+
+    /// How notifications are shortened before they are shown.
+    pub fn length_limits(&self) -> LengthLimits {
+        LengthLimits {
+            max_title_chars: self.max_title_chars,
+            max_message_chars: self.max_message_chars,
+            truncation: self.truncation,
+        }
+    }
+
+    pub fn notify_disabled(&self) -> bool {
+        self.notify_disabled
+    }
+
+    pub fn say_disabled(&self) -> bool {
+        self.say_disabled
+    }
+
+    pub fn voice_pool(&self) -> &[String] {
+        &self.voice_pool
+    }
+
+    pub fn notify_only(&self) -> bool {
+        self.notify_only
+    }
+
+    pub fn bypass_filter_enabled(&self) -> bool {
+        self.bypass_filter_enabled
+    }
+
+    /// The `--input-rewrite` rules followed by those of the
+    /// `--rewrite-file`s and then of the `--keys` presets, so that the former
+    /// take precedence.
+    pub fn rewrite_rules(&self) -> anyhow::Result<Vec<RewriteRule>> {
+        let mut rules = self.rewrite_rules.clone();
+        for path in &self.rewrite_files {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read --rewrite-file: {}", path.display()))?;
+            rules.extend(
+                rules_file::parse(&content).with_context(|| {
+                    format!("failed to parse --rewrite-file: {}", path.display())
+                })?,
+            );
+        }
+        rules.extend(self.key_presets.iter().flat_map(|preset| preset.rules()));
+        Ok(rules)
+    }
+
+    /// The `--line-wrap` mode for the host terminal.
+    pub fn line_wrap_mode(&self) -> LineWrapMode {
+        let term_program = std::env::var("TERM_PROGRAM").ok();
+        resolve_line_wrap_mode(self.line_wrap_mode, term_program.as_deref())
+    }
+
+    /// `--width`, or else `COLUMNS`, for when the width of the terminal
+    /// cannot be read from it.
+    pub fn width(&self) -> Option<u16> {
+        self.width.or_else(|| {
+            std::env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.parse().ok())
+                .filter(|&columns| columns > 0)
+        })
+    }
+
+    /// The key of `--mirror-key`, which `--mirror-send` and `--mirror-receive`
+    /// cannot do without.
+    fn mirror_key(&self) -> anyhow::Result<Option<MirrorKey>> {
+        if !self.mirror_send_enabled && !self.mirror_receive_enabled {
+            return Ok(None);
+        }
+        let Some(path) = &self.mirror_key_path else {
+            bail!("--mirror-send and --mirror-receive need --mirror-key");
+        };
+        MirrorKey::read(path).map(Some)
+    }
+
+    /// The log of `--debug-wrap`, if given, readable by the current user
+    /// only, as it has what `claude` printed verbatim.
+    pub fn wrap_log(&self) -> anyhow::Result<Option<WrapLog>> {
+        self.debug_wrap_path
+            .as_ref()
+            .map(|path| {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .mode(0o600)
+                    .open(path)
+                    .with_context(|| format!("failed to open --debug-wrap: {}", path.display()))?;
+                Ok(WrapLog::new(file))
+            })
+            .transpose()
+    }
+
+    pub fn focus_rule_count(&self) -> usize {
+        self.focus_rules.len()
+    }
+
+    /// Turn down what needs macOS services, rather than leave it to do
+    /// nothing.
+    #[cfg(not(target_os = "macos"))]
+    fn check_macos_only(&self) -> anyhow::Result<()> {
+        let macos_only = [
+            ("--voiceover", self.voiceover_enabled),
+            ("--watch-files", !self.watch_files.is_empty()),
+            ("--focus-policy", !self.focus_rules.is_empty()),
+            ("--broadcast", self.broadcast_enabled),
+        ];
+        if let Some((option, _)) = macos_only.iter().find(|(_, given)| *given) {
+            bail!("{option} is only supported on macOS");
+        }
+        Ok(())
     }
 
     pub fn try_into_runtime(self) -> anyhow::Result<Runtime> {
+        #[cfg(not(target_os = "macos"))]
+        self.check_macos_only()?;
+        let cwd = match &self.cwd {
+            Some(cwd) => cwd
+                .canonicalize()
+                .with_context(|| format!("invalid --cwd: {}", cwd.display()))?,
+            None => std::env::current_dir().context("failed to get current directory")?,
+        };
+        let redactor = self.redactor();
+        let fallback_width = self.width().unwrap_or(DEFAULT_WIDTH);
+        // Another tool has no transcripts of Claude Code to join with.
+        let claude_session = Arc::new(match self.runs_claude() {
+            true => ClaudeSession::new(self.claude_session_id.clone(), &self.claude_argv, &cwd),
+            false => ClaudeSession::default(),
+        });
+        let length_limits = self.length_limits();
+        let wrap_log = self.wrap_log()?;
+        let mirror_key = self.mirror_key()?;
+        let rewrite_rules = match self.notify_only {
+            true => Vec::new(),
+            false => self.rewrite_rules()?,
+        };
+        let claude_command = self.try_build_claude_command()?;
+        let completion_patterns = tool::find(claude_command.tool())
+            .map(Tool::completion_patterns)
+            .unwrap_or_default();
+        let restart_policy = self.max_restarts.map(|max_restarts| RestartPolicy {
+            max_restarts,
+            // Other tools have no `--continue` to speak of.
+            restart_command: match self.restart_continue_enabled && self.runs_claude() {
+                true => claude_command.continuing(),
+                false => claude_command.clone(),
+            },
+        });
+        let say_command = self.try_build_say_command().transpose()?;
+        if let Some(say_command) = &say_command {
+            say_command.validate().context("invalid --say")?;
+        }
+        // --notify-only touches neither input nor output (so it also overrides
+        // --screen-reader); only notifications and titles are picked out of
+        // the output.
+        let (line_wrap_mode, rewrite_rules, zwsp_after_updown_arrow, copy_key, auto_respond_rules) =
+            if self.notify_only {
+                (LineWrapMode::Preserve, Vec::new(), false, None, Vec::new())
+            } else {
+                (
+                    self.line_wrap_mode(),
+                    rewrite_rules,
+                    self.zwsp_after_updown_arrow,
+                    self.copy_key,
+                    self.auto_respond_rules,
+                )
+            };
+        let git_repository = match self.git_context_enabled {
+            true => GitRepository::find(&cwd),
+            false => None,
+        };
         Ok(Runtime {
-            notification_center_delivery_enabled: self.notification_center_delivery_enabled,
-            say_command: self.say_args.map(Self::try_build_say_command).transpose()?,
-            input_rewriter: InputRewriter::new(self.rewrite_rules),
-            zwsp_after_updown_arrow: self.zwsp_after_updown_arrow,
-            reformatter: Reformatter::new(0, self.line_wrap_mode),
-            claude_command: Self::try_build_claude_command(self.claude_argv)?,
+            session_name: match &git_repository {
+                Some(repository) => Some(repository.session_name()),
+                None => cwd
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned()),
+            },
+            git_repository,
+            notification_center_delivery_enabled: self.notification_center_delivery_enabled
+                && !self.notify_disabled,
+            osc9_passthrough_enabled: !self.notify_disabled,
+            say_command,
+            say_when: self.say_when,
+            translate_command: self.translate_command,
+            speech_abbreviations: self.speech_abbreviations,
+            speech_emoji: self.speech_emoji,
+            voice_pool: self.voice_pool,
+            voice_assignment: self.voice_assignment,
+            exit_notify_enabled: self.exit_notify_enabled,
+            file_matcher: match self.watch_files.is_empty() {
+                true => None,
+                false => Some(FileMatcher::new(&cwd, self.watch_files)?),
+            },
+            title_flash: self.title_flash_secs.map(Duration::from_secs),
+            bell_enabled: self.bell_enabled,
+            digest: self.digest_secs.map(Duration::from_secs),
+            redactor: redactor.clone(),
+            length_limits,
+            claude_session: Arc::clone(&claude_session),
+            mirror_send: self
+                .mirror_send_enabled
+                .then(|| mirror_key.clone())
+                .flatten(),
+            mirror_receive: self.mirror_receive_enabled.then_some(mirror_key).flatten(),
+            focus_rules: self.focus_rules,
+            broadcast_enabled: self.broadcast_enabled,
+            appearance: self.appearance,
+            voiceover_enabled: self.voiceover_enabled,
+            // --speak-final speaks the last message instead of each line and tool.
+            speak_output_enabled: self.speak_output_enabled
+                && !self.speak_final_enabled
+                && !self.say_disabled,
+            speak_questions_enabled: self.speak_questions_enabled && !self.say_disabled,
+            speak_tools_enabled: self.speak_tools_enabled
+                && !self.speak_final_enabled
+                && !self.say_disabled,
+            speak_final_enabled: self.speak_final_enabled && !self.say_disabled,
+            permission_reminder: self.permission_reminder_secs.map(Duration::from_secs),
+            progress_after: self.progress_after_secs.map(Duration::from_secs),
+            stall_timeout: self
+                .stall_timeout_mins
+                .map(|mins| Duration::from_secs(mins * 60)),
+            progress_state_enabled: self.progress_state_enabled,
+            sound_theme: SoundTheme::new(self.sounds),
+            input_pipeline: InputPipeline::new(
+                InputRewriter::new(rewrite_rules).with_pending_timeout(
+                    self.rewrite_timeout_ms
+                        .map_or(DEFAULT_PENDING_TIMEOUT, Duration::from_millis),
+                ),
+                zwsp_after_updown_arrow,
+            ),
+            copy_key,
+            review_key: self.review_key.filter(|_| !self.notify_only),
+            code_block_destination: self.code_block_destination,
+            code_block_min_lines: self.code_block_min_lines.unwrap_or(1),
+            code_block_notify_enabled: self.code_block_notify_enabled,
+            auto_responder: AutoResponder::new(auto_respond_rules),
+            alert_kinds: [
+                (self.rate_limit_notify_enabled, AlertKind::RateLimit),
+                (self.compaction_notify_enabled, AlertKind::Compaction),
+                (!completion_patterns.is_empty(), AlertKind::TaskFinished),
+            ]
+            .into_iter()
+            .filter_map(|(enabled, kind)| enabled.then_some(kind))
+            .collect(),
+            alert_patterns: match self.rate_limit_notify_enabled {
+                true => self.rate_limit_patterns,
+                false => Vec::new(),
+            }
+            .into_iter()
+            .map(|regex| (AlertKind::RateLimit, regex))
+            .chain(
+                completion_patterns
+                    .into_iter()
+                    .map(|regex| (AlertKind::TaskFinished, regex)),
+            )
+            .collect(),
+            reformatter: Reformatter::new(0, line_wrap_mode).with_wrap_log(wrap_log),
+            fallback_width,
+            buffer_size: self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+            screen_reader_enabled: self.screen_reader_enabled && !self.notify_only,
+            claude_command,
+            restart_policy,
+            summary_enabled: self.summary_enabled,
+            on_start: self.on_start,
+            on_exit: self.on_exit,
+            cwd,
+            control_socket: self.control_socket,
+            input_fifo: self.input_fifo,
+            recorder: self
+                .record_path
+                .as_deref()
+                .map(|path| {
+                    Recorder::create(path)
+                        .with_context(|| format!("failed to open --record: {}", path.display()))
+                })
+                .transpose()?,
+            debug_log: match &self.debug_log_path {
+                Some(path) => DebugLog::create(path, redactor, Arc::clone(&claude_session))
+                    .with_context(|| format!("failed to open --debug: {}", path.display()))?,
+                None => DebugLog::default(),
+            },
         })
     }
 
-    fn try_build_say_command(say_args: OsString) -> anyhow::Result<SayCommand> {
-        shell_words::split(
+    /// `auto` keeps the inherited `TERM` unless this machine lacks a terminfo
+    /// entry for it; any other value is used verbatim.
+    fn resolve_term(term: &str) -> Option<OsString> {
+        if term != "auto" {
+            return Some(OsString::from(term));
+        }
+        match std::env::var("TERM") {
+            Ok(inherited) if terminfo::exists(&inherited) => None,
+            _ => Some(OsString::from(terminfo::FALLBACK_TERM)),
+        }
+    }
+
+    /// `None` unless `--say` is given and `--no-say` is not.
+    pub fn try_build_say_command(&self) -> Option<anyhow::Result<SayCommand>> {
+        if self.say_disabled {
+            return None;
+        }
+        let say_args = self.say_args.as_ref()?;
+        Some(
             say_args
                 .to_str()
-                .context("say argument contains invalid UTF-8")?,
+                .context("say argument contains invalid UTF-8")
+                .and_then(|say_args| {
+                    shell_words::split(say_args)
+                        .map(SayCommand::new)
+                        .context("failed to parse say command arguments")
+                }),
         )
-        .map(SayCommand::new)
-        .context("failed to parse say command arguments")
     }
 
-    fn try_build_claude_command(mut claude_argv: Vec<OsString>) -> anyhow::Result<ClaudeCommand> {
-        if claude_argv.is_empty() {
-            claude_argv.push(OsString::from("claude"));
+    /// Whether the child is `claude`, unless `--exec` or `--tool` runs another
+    /// tool.
+    fn runs_claude(&self) -> bool {
+        if let Some(tool) = self.tool {
+            return tool.name == claude::CLAUDE;
         }
-        ClaudeCommand::try_from(claude_argv).context("failed to build argv for claude")
+        !self.exec_given
+            || self
+                .claude_argv
+                .first()
+                .and_then(|program| Path::new(program).file_name())
+                .is_some_and(|name| name == claude::CLAUDE)
     }
-}
 
-fn parse_args(args: impl IntoIterator<Item = impl Into<OsString>>) -> anyhow::Result<Arguments> {
-    let mut notification_center_delivery_enabled = false;
-    let mut say_args = None;
-    let mut line_wrap_mode = LineWrapMode::Preserve;
-    let mut rewrite_rules: Vec<RewriteRule> = Vec::new();
-    let mut zwsp_after_updown_arrow = false;
-    let mut claude_argv: Vec<OsString> = Vec::new();
-
-    let mut parser = lexopt::Parser::from_iter(args);
-    while let Some(arg) = parser.next()? {
-        match arg {
-            Long("notification-center") => {
-                notification_center_delivery_enabled = true;
+    pub fn try_build_claude_command(&self) -> anyhow::Result<ChildCommand> {
+        let (program, args) = match (self.tool, self.claude_argv.split_first()) {
+            // `--tool` alone runs it with the arguments meant for `claude`.
+            (Some(tool), _) if !self.exec_given && tool.name != claude::CLAUDE => {
+                (Some(OsStr::new(tool.name)), &self.claude_argv[..])
+            }
+            (_, Some((program, args))) => (Some(program.as_os_str()), args),
+            (_, None) => (None, &[][..]),
+        };
+        let mut claude_argv = match program {
+            // The login shell looks up a bare name in the PATH it sets up,
+            // which caloud may not have.
+            Some(program)
+                if self.login_shell_enabled && Path::new(program).components().count() == 1 =>
+            {
+                vec![program.to_owned()]
             }
-            Long("say") => {
-                say_args = Some(parser.value()?);
+            None if self.login_shell_enabled && self.claude_path.is_none() => {
+                vec![OsString::from(claude::CLAUDE)]
             }
-            Long("line-wrap") => {
-                let value = parser.value()?.string()?;
-                line_wrap_mode = match value.as_str() {
-                    "adjust" => LineWrapMode::Adjust,
-                    "preserve" => LineWrapMode::Preserve,
-                    _ => bail!("invalid value for --line-wrap: {}", value),
-                };
+            // Not a `claude` found elsewhere instead.
+            Some(program) if self.exec_given || !self.runs_claude() => {
+                claude::discover(Some(program), None)?
+            }
+            _ => claude::discover(program, self.claude_path.as_deref())?,
+        };
+        claude_argv.extend_from_slice(args);
+        let mut claude_command =
+            ChildCommand::try_from(claude_argv).context("failed to build argv for claude")?;
+        if self.login_shell_enabled {
+            claude_command.set_login_shell();
+        }
+        if let Some(cwd) = &self.cwd {
+            claude_command.set_cwd(cwd.clone());
+        }
+        if let Some(tool) = self.tool {
+            claude_command.set_tool(tool.name);
+        } else if self.runs_claude() {
+            claude_command.set_tool(claude::CLAUDE);
+        }
+        if let Some(term) = self.term.as_deref().and_then(Self::resolve_term) {
+            claude_command.set_env("TERM", Some(term));
+        }
+        if let Some(colorterm) = &self.colorterm {
+            // An empty value removes the variable.
+            claude_command.set_env(
+                "COLORTERM",
+                (!colorterm.is_empty()).then(|| colorterm.clone()),
+            );
+        }
+        if let Some(appearance) = self.appearance {
+            claude_command.set_env("COLORFGBG", Some(appearance.resolve().colorfgbg().into()));
+        }
+        // Files first so that --env can override individual variables.
+        for path in &self.env_files {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read --env-file: {}", path.display()))?;
+            let vars = env_file::parse(&content)
+                .with_context(|| format!("failed to parse --env-file: {}", path.display()))?;
+            for (key, value) in vars {
+                claude_command.set_env(key, Some(value.into()));
+            }
+        }
+        for (key, value) in &self.env {
+            claude_command.set_env(key, Some(value.into()));
+        }
+        Ok(claude_command)
+    }
+}
+
+/// Options that configuration files may set, and how their values are given.
+const CONFIGURABLE_OPTIONS: &[(&str, OptionKind)] = &[
+    ("notification-center", OptionKind::Flag),
+    ("no-notify", OptionKind::Flag),
+    ("say", OptionKind::Value),
+    ("no-say", OptionKind::Flag),
+    ("say-when", OptionKind::Value),
+    ("translate-command", OptionKind::Value),
+    ("abbreviate-speech", OptionKind::Value),
+    ("speak-emoji", OptionKind::Value),
+    ("voice-pool", OptionKind::List),
+    ("voice-assignment", OptionKind::Value),
+    ("voiceover", OptionKind::Flag),
+    ("speak-output", OptionKind::Flag),
+    ("speak-questions", OptionKind::Flag),
+    ("speak-tools", OptionKind::Flag),
+    ("speak-final", OptionKind::Flag),
+    ("permission-reminder", OptionKind::Value),
+    ("progress-after", OptionKind::Value),
+    ("progress-state", OptionKind::Flag),
+    ("stall-timeout", OptionKind::Value),
+    ("sound", OptionKind::List),
+    ("line-wrap", OptionKind::Value),
+    ("width", OptionKind::Value),
+    ("buffer-size", OptionKind::Value),
+    ("screen-reader", OptionKind::Flag),
+    ("input-rewrite", OptionKind::List),
+    ("rewrite-file", OptionKind::List),
+    ("keys", OptionKind::List),
+    ("rewrite-timeout-ms", OptionKind::Value),
+    ("zwsp-after-updown-arrow", OptionKind::Flag),
+    ("copy-key", OptionKind::Value),
+    ("review-key", OptionKind::Value),
+    ("copy-code-blocks", OptionKind::Value),
+    ("copy-code-blocks-min-lines", OptionKind::Value),
+    ("copy-code-blocks-notify", OptionKind::Flag),
+    ("auto-respond", OptionKind::List),
+    ("rate-limit-notify", OptionKind::Flag),
+    ("rate-limit-pattern", OptionKind::List),
+    ("compaction-notify", OptionKind::Flag),
+    ("exit-notify", OptionKind::Flag),
+    ("watch-files", OptionKind::List),
+    ("git-context", OptionKind::Flag),
+    ("title-flash", OptionKind::Value),
+    ("bell", OptionKind::Flag),
+    ("digest", OptionKind::Value),
+    ("redact", OptionKind::Flag),
+    ("redact-pattern", OptionKind::List),
+    ("max-title-length", OptionKind::Value),
+    ("max-message-length", OptionKind::Value),
+    ("truncate", OptionKind::Value),
+    ("mirror-send", OptionKind::Flag),
+    ("mirror-receive", OptionKind::Flag),
+    ("mirror-key", OptionKind::Value),
+    ("focus-policy", OptionKind::List),
+    ("broadcast", OptionKind::Flag),
+    ("notify-only", OptionKind::Flag),
+    ("term", OptionKind::Value),
+    ("colorterm", OptionKind::Value),
+    ("appearance", OptionKind::Value),
+    ("env", OptionKind::List),
+    ("env-file", OptionKind::List),
+    ("cwd", OptionKind::Value),
+    ("claude-path", OptionKind::Value),
+    ("login-shell", OptionKind::Flag),
+    ("tool", OptionKind::Value),
+    ("bypass-filter", OptionKind::Flag),
+    ("restart-on-crash", OptionKind::Value),
+    ("restart-continue", OptionKind::Flag),
+    ("claude-session-id", OptionKind::Value),
+    ("summary", OptionKind::Flag),
+    ("on-start", OptionKind::Value),
+    ("on-exit", OptionKind::Value),
+    ("control-socket", OptionKind::Value),
+    ("input-fifo", OptionKind::Value),
+    ("debug", OptionKind::Value),
+    ("debug-wrap", OptionKind::Value),
+    ("record", OptionKind::Value),
+];
+
+/// The options that the configuration file of a project may set: those that
+/// only change how caloud presents a session.  `.caloud.toml` usually comes
+/// with a checked-out repository, which must not be able to run programs,
+/// answer prompts, type into `claude`, or write or expose files, so an option
+/// is left out until it is known to be harmless.
+const PROJECT_CONFIG_OPTIONS: &[&str] = &[
+    "notification-center",
+    "no-notify",
+    "say",
+    "no-say",
+    "say-when",
+    "abbreviate-speech",
+    "speak-emoji",
+    "voice-pool",
+    "voice-assignment",
+    "voiceover",
+    "speak-output",
+    "speak-questions",
+    "speak-tools",
+    "speak-final",
+    "permission-reminder",
+    "progress-after",
+    "progress-state",
+    "stall-timeout",
+    "sound",
+    "line-wrap",
+    "width",
+    "screen-reader",
+    "copy-code-blocks-min-lines",
+    "copy-code-blocks-notify",
+    "rate-limit-notify",
+    "rate-limit-pattern",
+    "compaction-notify",
+    "exit-notify",
+    "title-flash",
+    "bell",
+    "digest",
+    "redact",
+    "redact-pattern",
+    "max-title-length",
+    "max-message-length",
+    "truncate",
+    "focus-policy",
+    "notify-only",
+    "appearance",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionKind {
+    Flag,
+    Value,
+    List,
+}
+
+impl Arguments {
+    /// Apply a configuration file as if its entries were given as `--key=value`
+    /// options.  A missing file is not an error.
+    fn apply_config_file(
+        &mut self,
+        path: &Path,
+        is_project: bool,
+    ) -> Result<(), Vec<anyhow::Error>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(vec![
+                    anyhow::Error::new(e).context(format!("failed to read {}", path.display())),
+                ]);
             }
-            Long("input-rewrite") => {
-                let value = parser.value()?.string()?;
-                let rule = RewriteRule::parse(&value)
-                    .with_context(|| format!("failed to parse --input-rewrite: {}", value))?;
-                rewrite_rules.push(rule);
+        };
+        self.config_sources.push(path.display().to_string());
+        let entries = config::parse(&content).map_err(|e| {
+            vec![anyhow::anyhow!(
+                "{}:{}: {}",
+                path.display(),
+                e.line_number,
+                e.kind
+            )]
+        })?;
+        let errors: Vec<_> = entries
+            .into_iter()
+            .filter_map(|entry| self.apply_config_entry(path, is_project, entry).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn apply_config_entry(
+        &mut self,
+        path: &Path,
+        is_project: bool,
+        entry: config::Entry,
+    ) -> anyhow::Result<()> {
+        let location = || format!("{}:{}", path.display(), entry.line_number);
+        let name = entry.key.replace('_', "-");
+        let Some(&(name, kind)) = CONFIGURABLE_OPTIONS.iter().find(|(n, _)| *n == name) else {
+            bail!("{}: unknown option: {}", location(), entry.key);
+        };
+        if is_project && !PROJECT_CONFIG_OPTIONS.contains(&name) {
+            bail!(
+                "{}: {} is not allowed in {}",
+                location(),
+                name,
+                config::PROJECT_CONFIG_FILE
+            );
+        }
+        // `say` can also write to a file (`-o`) or read one out (`-f`).
+        if is_project
+            && name == "say"
+            && let config::Value::String(args) = &entry.value
+            && !shell_words::split(args).is_ok_and(|args| SayCommand::selects_voice_only(&args))
+        {
+            bail!(
+                "{}: say in {} may only give -v and -r",
+                location(),
+                config::PROJECT_CONFIG_FILE
+            );
+        }
+        match (kind, &entry.value) {
+            (OptionKind::Flag, config::Value::Boolean(_))
+            | (OptionKind::Value, config::Value::String(_) | config::Value::Integer(_))
+            | (OptionKind::List, _) => {}
+            (OptionKind::Flag, _) => bail!("{}: {} must be a boolean", location(), name),
+            (OptionKind::Value, _) => bail!("{}: {} must be a string", location(), name),
+        }
+        let args = entry
+            .value
+            .to_option_values()
+            .into_iter()
+            .map(|value| OsString::from(format!("--{name}={value}")));
+        self.apply_options(lexopt::Parser::from_args(args))
+            .with_context(location)
+    }
+
+    /// Apply `CALOUD_<OPTION>` variables, e.g. `CALOUD_LINE_WRAP` for
+    /// `--line-wrap`.  Flags take `true`/`false` or `1`/`0`, repeatable options
+    /// take one value per line, and empty variables are ignored.
+    fn apply_environment(
+        &mut self,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<(), Vec<anyhow::Error>> {
+        let mut errors = Vec::new();
+        for &(name, kind) in CONFIGURABLE_OPTIONS {
+            let var_name = env_var_name(name);
+            let Some(value) = var(&var_name).filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            match self.apply_environment_variable(name, kind, &var_name, value) {
+                Ok(()) => self.config_sources.push(var_name),
+                Err(e) => errors.push(e),
             }
-            Long("zwsp-after-updown-arrow") => {
-                zwsp_after_updown_arrow = true;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn apply_environment_variable(
+        &mut self,
+        name: &str,
+        kind: OptionKind,
+        var_name: &str,
+        value: OsString,
+    ) -> anyhow::Result<()> {
+        let values = match kind {
+            OptionKind::Flag => vec![match value.to_str() {
+                Some("1") => OsString::from("true"),
+                Some("0") => OsString::from("false"),
+                _ => value,
+            }],
+            OptionKind::Value => vec![value],
+            OptionKind::List => value
+                .to_str()
+                .with_context(|| format!("{var_name} contains invalid UTF-8"))?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(OsString::from)
+                .collect(),
+        };
+        let args = values.into_iter().map(|value| {
+            let mut arg = OsString::from(format!("--{name}="));
+            arg.push(value);
+            arg
+        });
+        self.apply_options(lexopt::Parser::from_args(args))
+            .with_context(|| var_name.to_string())
+    }
+
+    /// Apply options on top of the current values.  Single-valued options
+    /// override; repeatable options, if given at all, replace the earlier list.
+    fn apply_options(&mut self, mut parser: lexopt::Parser) -> anyhow::Result<()> {
+        let mut rewrite_rules: Vec<RewriteRule> = Vec::new();
+        let mut env_files = Vec::new();
+        let mut rewrite_files = Vec::new();
+        let mut key_presets = Vec::new();
+        let mut env = Vec::new();
+        let mut sounds = Vec::new();
+        let mut auto_respond_rules = Vec::new();
+        let mut rate_limit_patterns = Vec::new();
+        let mut voice_pool = Vec::new();
+        let mut focus_rules = Vec::new();
+        let mut watch_files = Vec::new();
+        let mut redact_patterns = Vec::new();
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("notification-center") => {
+                    self.notification_center_delivery_enabled =
+                        parse_flag(&mut parser, "notification-center")?;
+                }
+                Long("no-notify") => {
+                    self.notify_disabled = parse_flag(&mut parser, "no-notify")?;
+                }
+                Long("say") => {
+                    self.say_args = Some(parser.value()?);
+                }
+                Long("no-say") => {
+                    self.say_disabled = parse_flag(&mut parser, "no-say")?;
+                }
+                Long("say-when") => {
+                    let value = parser.value()?.string()?;
+                    self.say_when = SayWhen::parse(&value)
+                        .with_context(|| format!("failed to parse --say-when: {}", value))?;
+                }
+                Long("translate-command") => {
+                    let value = parser.value()?.string()?;
+                    let command = ShellCommand::parse(&value).with_context(|| {
+                        format!("failed to parse --translate-command: {}", value)
+                    })?;
+                    self.translate_command = Some(command);
+                }
+                Long("abbreviate-speech") => {
+                    let value = parser.value()?.string()?;
+                    self.speech_abbreviations =
+                        Abbreviations::parse(&value).with_context(|| {
+                            format!("failed to parse --abbreviate-speech: {}", value)
+                        })?;
+                }
+                Long("speak-emoji") => {
+                    let value = parser.value()?.string()?;
+                    self.speech_emoji = EmojiMode::parse(&value)
+                        .with_context(|| format!("failed to parse --speak-emoji: {}", value))?;
+                }
+                Long("voice-pool") => {
+                    let value = parser.value()?.string()?;
+                    if value.is_empty() {
+                        bail!("invalid value for --voice-pool: empty voice");
+                    }
+                    voice_pool.push(value);
+                }
+                Long("voice-assignment") => {
+                    let value = parser.value()?.string()?;
+                    self.voice_assignment = VoiceAssignment::parse(&value).with_context(|| {
+                        format!("failed to parse --voice-assignment: {}", value)
+                    })?;
+                }
+                Long("voiceover") => {
+                    self.voiceover_enabled = parse_flag(&mut parser, "voiceover")?;
+                }
+                Long("speak-output") => {
+                    self.speak_output_enabled = parse_flag(&mut parser, "speak-output")?;
+                }
+                Long("speak-questions") => {
+                    self.speak_questions_enabled = parse_flag(&mut parser, "speak-questions")?;
+                }
+                Long("speak-tools") => {
+                    self.speak_tools_enabled = parse_flag(&mut parser, "speak-tools")?;
+                }
+                Long("speak-final") => {
+                    self.speak_final_enabled = parse_flag(&mut parser, "speak-final")?;
+                }
+                Long("permission-reminder") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(secs) if secs > 0 => self.permission_reminder_secs = Some(secs),
+                        _ => bail!("invalid value for --permission-reminder: {}", value),
+                    }
+                }
+                Long("progress-after") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(secs) if secs > 0 => self.progress_after_secs = Some(secs),
+                        _ => bail!("invalid value for --progress-after: {}", value),
+                    }
+                }
+                Long("stall-timeout") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(mins) if mins > 0 => self.stall_timeout_mins = Some(mins),
+                        _ => bail!("invalid value for --stall-timeout: {}", value),
+                    }
+                }
+                Long("progress-state") => {
+                    self.progress_state_enabled = parse_flag(&mut parser, "progress-state")?;
+                }
+                Long("sound") => {
+                    let value = parser.value()?.string()?;
+                    let sound = sound::parse_sound(&value)
+                        .with_context(|| format!("failed to parse --sound: {}", value))?;
+                    sounds.push(sound);
+                }
+                Long("width") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(columns) if columns > 0 => self.width = Some(columns),
+                        _ => bail!("invalid value for --width: {}", value),
+                    }
+                }
+                Long("line-wrap") => {
+                    let value = parser.value()?.string()?;
+                    self.line_wrap_mode = match value.as_str() {
+                        "adjust" => LineWrapMode::Adjust,
+                        "preserve" => LineWrapMode::Preserve,
+                        "auto" => LineWrapMode::Auto,
+                        _ => bail!("invalid value for --line-wrap: {}", value),
+                    };
+                }
+                Long("buffer-size") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(size) if (MIN_BUFFER_SIZE..=MAX_BUFFER_SIZE).contains(&size) => {
+                            self.buffer_size = Some(size);
+                        }
+                        _ => bail!(
+                            "invalid value for --buffer-size: {} (between {} and {})",
+                            value,
+                            MIN_BUFFER_SIZE,
+                            MAX_BUFFER_SIZE
+                        ),
+                    }
+                }
+                Long("screen-reader") => {
+                    self.screen_reader_enabled = parse_flag(&mut parser, "screen-reader")?;
+                }
+                Long("input-rewrite") => {
+                    let value = parser.value()?.string()?;
+                    let rule = RewriteRule::parse(&value)
+                        .with_context(|| format!("failed to parse --input-rewrite: {}", value))?;
+                    rewrite_rules.push(rule);
+                }
+                Long("rewrite-file") => {
+                    rewrite_files.push(PathBuf::from(parser.value()?));
+                }
+                Long("keys") => {
+                    let value = parser.value()?.string()?;
+                    key_presets.push(
+                        KeyPreset::parse(&value)
+                            .with_context(|| format!("failed to parse --keys: {}", value))?,
+                    );
+                }
+                Long("rewrite-timeout-ms") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(ms) if ms > 0 => self.rewrite_timeout_ms = Some(ms),
+                        _ => bail!("invalid value for --rewrite-timeout-ms: {}", value),
+                    }
+                }
+                Long("caloud-version") => {
+                    self.command = Command::Version;
+                }
+                Long("caloud-help") => {
+                    self.command = Command::Help;
+                }
+                Long("exec") => {
+                    // The rest is the command line of the tool.
+                    self.exec_given = true;
+                    self.claude_argv = vec![parser.value()?];
+                    self.claude_argv.extend(parser.raw_args()?);
+                }
+                Long("zwsp-after-updown-arrow") => {
+                    self.zwsp_after_updown_arrow =
+                        parse_flag(&mut parser, "zwsp-after-updown-arrow")?;
+                }
+                Long("copy-key") => {
+                    let value = parser.value()?.string()?;
+                    let key = parse_escaped_str(&value)
+                        .with_context(|| format!("failed to parse --copy-key: {}", value))?;
+                    if key.is_empty() {
+                        bail!("invalid value for --copy-key: {}", value);
+                    }
+                    self.copy_key = Some(key);
+                }
+                Long("review-key") => {
+                    let value = parser.value()?.string()?;
+                    let key = parse_escaped_str(&value)
+                        .with_context(|| format!("failed to parse --review-key: {}", value))?;
+                    if key.is_empty() {
+                        bail!("invalid value for --review-key: {}", value);
+                    }
+                    self.review_key = Some(key);
+                }
+                Long("copy-code-blocks") => {
+                    let value = parser.value()?.string()?;
+                    let destination = CodeBlockDestination::parse(&value).with_context(|| {
+                        format!("failed to parse --copy-code-blocks: {}", value)
+                    })?;
+                    self.code_block_destination = Some(destination);
+                }
+                Long("copy-code-blocks-min-lines") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(min_lines) if min_lines > 0 => {
+                            self.code_block_min_lines = Some(min_lines)
+                        }
+                        _ => bail!("invalid value for --copy-code-blocks-min-lines: {}", value),
+                    }
+                }
+                Long("copy-code-blocks-notify") => {
+                    self.code_block_notify_enabled =
+                        parse_flag(&mut parser, "copy-code-blocks-notify")?;
+                }
+                Long("auto-respond") => {
+                    let value = parser.value()?.string()?;
+                    let rule = AutoRespondRule::parse(&value)
+                        .with_context(|| format!("failed to parse --auto-respond: {}", value))?;
+                    auto_respond_rules.push(rule);
+                }
+                Long("rate-limit-notify") => {
+                    self.rate_limit_notify_enabled = parse_flag(&mut parser, "rate-limit-notify")?;
+                }
+                Long("rate-limit-pattern") => {
+                    let value = parser.value()?.string()?;
+                    let regex = Regex::new(&value).with_context(|| {
+                        format!("failed to parse --rate-limit-pattern: {}", value)
+                    })?;
+                    rate_limit_patterns.push(regex);
+                }
+                Long("compaction-notify") => {
+                    self.compaction_notify_enabled = parse_flag(&mut parser, "compaction-notify")?;
+                }
+                Long("exit-notify") => {
+                    self.exit_notify_enabled = parse_flag(&mut parser, "exit-notify")?;
+                }
+                Long("watch-files") => {
+                    let value = parser.value()?.string()?;
+                    let glob = FileGlob::parse(&value)
+                        .with_context(|| format!("failed to parse --watch-files: {}", value))?;
+                    watch_files.push(glob);
+                }
+                Long("git-context") => {
+                    self.git_context_enabled = parse_flag(&mut parser, "git-context")?;
+                }
+                Long("title-flash") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(secs) if secs > 0 => self.title_flash_secs = Some(secs),
+                        _ => bail!("invalid value for --title-flash: {}", value),
+                    }
+                }
+                Long("bell") => {
+                    self.bell_enabled = parse_flag(&mut parser, "bell")?;
+                }
+                Long("digest") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(secs) if secs > 0 => self.digest_secs = Some(secs),
+                        _ => bail!("invalid value for --digest: {}", value),
+                    }
+                }
+                Long("redact") => {
+                    self.redact_enabled = parse_flag(&mut parser, "redact")?;
+                }
+                Long("redact-pattern") => {
+                    let value = parser.value()?.string()?;
+                    let regex = Regex::new(&value)
+                        .with_context(|| format!("failed to parse --redact-pattern: {}", value))?;
+                    redact_patterns.push(regex);
+                }
+                Long("max-title-length") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(max_chars) if max_chars > 0 => self.max_title_chars = Some(max_chars),
+                        _ => bail!("invalid value for --max-title-length: {}", value),
+                    }
+                }
+                Long("max-message-length") => {
+                    let value = parser.value()?.string()?;
+                    match value.parse() {
+                        Ok(max_chars) if max_chars > 0 => self.max_message_chars = Some(max_chars),
+                        _ => bail!("invalid value for --max-message-length: {}", value),
+                    }
+                }
+                Long("truncate") => {
+                    let value = parser.value()?.string()?;
+                    self.truncation = Truncation::parse(&value)
+                        .with_context(|| format!("failed to parse --truncate: {}", value))?;
+                }
+                Long("mirror-send") => {
+                    self.mirror_send_enabled = parse_flag(&mut parser, "mirror-send")?;
+                }
+                Long("mirror-receive") => {
+                    self.mirror_receive_enabled = parse_flag(&mut parser, "mirror-receive")?;
+                }
+                Long("mirror-key") => {
+                    self.mirror_key_path = Some(PathBuf::from(parser.value()?));
+                }
+                Long("focus-policy") => {
+                    let value = parser.value()?.string()?;
+                    let rule = FocusRule::parse(&value)
+                        .with_context(|| format!("failed to parse --focus-policy: {}", value))?;
+                    focus_rules.push(rule);
+                }
+                Long("broadcast") => {
+                    self.broadcast_enabled = parse_flag(&mut parser, "broadcast")?;
+                }
+                Long("notify-only") => {
+                    self.notify_only = parse_flag(&mut parser, "notify-only")?;
+                }
+                Long("term") => {
+                    let value = parser.value()?.string()?;
+                    if value.is_empty() {
+                        bail!("invalid value for --term: {}", value);
+                    }
+                    self.term = Some(value);
+                }
+                Long("colorterm") => {
+                    self.colorterm = Some(parser.value()?);
+                }
+                Long("appearance") => {
+                    let value = parser.value()?.string()?;
+                    let mode = AppearanceMode::parse(&value)
+                        .with_context(|| format!("failed to parse --appearance: {}", value))?;
+                    self.appearance = Some(mode);
+                }
+                Long("env") => {
+                    let value = parser.value()?.string()?;
+                    let assignment = env_file::parse_assignment(&value)
+                        .with_context(|| format!("failed to parse --env: {}", value))?;
+                    env.push(assignment);
+                }
+                Long("env-file") => {
+                    env_files.push(PathBuf::from(parser.value()?));
+                }
+                Long("cwd") => {
+                    self.cwd = Some(PathBuf::from(parser.value()?));
+                }
+                Long("claude-path") => {
+                    self.claude_path = Some(PathBuf::from(parser.value()?));
+                }
+                Long("login-shell") => {
+                    self.login_shell_enabled = parse_flag(&mut parser, "login-shell")?;
+                }
+                Long("bypass-filter") => {
+                    self.bypass_filter_enabled = parse_flag(&mut parser, "bypass-filter")?;
+                }
+                Long("restart-on-crash") => {
+                    self.max_restarts = match parser.optional_value() {
+                        None => Some(DEFAULT_MAX_RESTARTS),
+                        Some(value) => {
+                            let value = value.string()?;
+                            match value.parse() {
+                                Ok(0) => None,
+                                Ok(max_restarts) => Some(max_restarts),
+                                Err(_) => bail!("invalid value for --restart-on-crash: {}", value),
+                            }
+                        }
+                    };
+                }
+                Long("restart-continue") => {
+                    self.restart_continue_enabled = parse_flag(&mut parser, "restart-continue")?;
+                }
+                Long("tool") => {
+                    let value = parser.value()?.string()?;
+                    self.tool = Some(
+                        tool::parse(&value)
+                            .with_context(|| format!("failed to parse --tool: {}", value))?,
+                    );
+                }
+                Long("claude-session-id") => {
+                    let value = parser.value()?.string()?;
+                    if !claude_session::is_id(&value) {
+                        bail!("invalid value for --claude-session-id: {}", value);
+                    }
+                    self.claude_session_id = Some(value);
+                }
+                Long("summary") => {
+                    self.summary_enabled = parse_flag(&mut parser, "summary")?;
+                }
+                Long("on-start") => {
+                    let value = parser.value()?.string()?;
+                    let command = ShellCommand::parse(&value)
+                        .with_context(|| format!("failed to parse --on-start: {}", value))?;
+                    self.on_start = Some(command);
+                }
+                Long("on-exit") => {
+                    let value = parser.value()?.string()?;
+                    let command = ShellCommand::parse(&value)
+                        .with_context(|| format!("failed to parse --on-exit: {}", value))?;
+                    self.on_exit = Some(command);
+                }
+                Long("control-socket") => {
+                    self.control_socket = Some(PathBuf::from(parser.value()?));
+                }
+                Long("input-fifo") => {
+                    self.input_fifo = Some(PathBuf::from(parser.value()?));
+                }
+                Long("debug") => {
+                    self.debug_log_path = Some(PathBuf::from(parser.value()?));
+                }
+                Long("debug-wrap") => {
+                    self.debug_wrap_path = Some(PathBuf::from(parser.value()?));
+                }
+                Long("record") => {
+                    self.record_path = Some(PathBuf::from(parser.value()?));
+                }
+                Value(val)
+                    if !self.subcommand_given
+                        && self.command == Command::Run
+                        && self.claude_argv.is_empty()
+                        && SUBCOMMANDS.iter().any(|(name, _)| val == *name) =>
+                {
+                    self.subcommand_given = true;
+                    self.command = SUBCOMMANDS
+                        .iter()
+                        .find_map(|(name, command)| (val == *name).then_some(*command))
+                        .unwrap_or_default();
+                    // Its options are its own, not those of caloud.
+                    if matches!(
+                        self.command,
+                        Command::Notify | Command::ListVoices | Command::Say
+                    ) {
+                        self.subcommand_args.extend(parser.raw_args()?);
+                    }
+                }
+                Value(val)
+                    if self.command == Command::Config
+                        && self.claude_argv.is_empty()
+                        && val == "validate" =>
+                {
+                    self.command = Command::ConfigValidate;
+                }
+                Value(val)
+                    if matches!(
+                        self.command,
+                        Command::Send | Command::Mirror | Command::Reformat | Command::Decode
+                    ) =>
+                {
+                    self.subcommand_args.push(val);
+                }
+                Value(val) => {
+                    self.claude_argv.push(val);
+                }
+                _ => return Err(arg.unexpected().into()),
             }
-            Value(val) => {
-                claude_argv.push(val);
+        }
+
+        if !rewrite_rules.is_empty() {
+            self.rewrite_rules = rewrite_rules;
+        }
+        if !rewrite_files.is_empty() {
+            self.rewrite_files = rewrite_files;
+        }
+        if !key_presets.is_empty() {
+            self.key_presets = key_presets;
+        }
+        if !env_files.is_empty() {
+            self.env_files = env_files;
+        }
+        if !env.is_empty() {
+            self.env = env;
+        }
+        if !sounds.is_empty() {
+            self.sounds = sounds;
+        }
+        if !auto_respond_rules.is_empty() {
+            self.auto_respond_rules = auto_respond_rules;
+        }
+        if !rate_limit_patterns.is_empty() {
+            self.rate_limit_patterns = rate_limit_patterns;
+        }
+        if !voice_pool.is_empty() {
+            self.voice_pool = voice_pool;
+        }
+        if !focus_rules.is_empty() {
+            self.focus_rules = focus_rules;
+        }
+        if !watch_files.is_empty() {
+            self.watch_files = watch_files;
+        }
+        if !redact_patterns.is_empty() {
+            self.redact_patterns = redact_patterns;
+        }
+        Ok(())
+    }
+}
+
+impl Arguments {
+    /// The configurable options as resolved from all layers, formatted as a
+    /// configuration file.
+    pub fn effective_config(&self) -> String {
+        use config::Value;
+
+        let string = |s: &std::ffi::OsStr| Value::String(s.to_string_lossy().into_owned());
+        let path = |p: &Path| string(p.as_os_str());
+        let values = [
+            (
+                "notification-center",
+                Some(Value::Boolean(self.notification_center_delivery_enabled)),
+            ),
+            ("no-notify", Some(Value::Boolean(self.notify_disabled))),
+            ("say", self.say_args.as_deref().map(string)),
+            ("no-say", Some(Value::Boolean(self.say_disabled))),
+            ("say-when", Some(Value::String(self.say_when.to_string()))),
+            (
+                "translate-command",
+                self.translate_command
+                    .as_ref()
+                    .map(|command| Value::String(command.to_string())),
+            ),
+            (
+                "abbreviate-speech",
+                Some(Value::String(self.speech_abbreviations.to_string())),
+            ),
+            (
+                "speak-emoji",
+                Some(Value::String(self.speech_emoji.as_str().to_string())),
+            ),
+            (
+                "voice-pool",
+                Some(Value::Array(
+                    self.voice_pool.iter().cloned().map(Value::String).collect(),
+                )),
+            ),
+            (
+                "voice-assignment",
+                Some(Value::String(self.voice_assignment.to_string())),
+            ),
+            ("voiceover", Some(Value::Boolean(self.voiceover_enabled))),
+            (
+                "speak-output",
+                Some(Value::Boolean(self.speak_output_enabled)),
+            ),
+            (
+                "speak-questions",
+                Some(Value::Boolean(self.speak_questions_enabled)),
+            ),
+            (
+                "speak-tools",
+                Some(Value::Boolean(self.speak_tools_enabled)),
+            ),
+            (
+                "speak-final",
+                Some(Value::Boolean(self.speak_final_enabled)),
+            ),
+            (
+                "permission-reminder",
+                self.permission_reminder_secs
+                    .map(|secs| Value::Integer(secs as i64)),
+            ),
+            (
+                "progress-after",
+                self.progress_after_secs
+                    .map(|secs| Value::Integer(secs as i64)),
+            ),
+            (
+                "progress-state",
+                Some(Value::Boolean(self.progress_state_enabled)),
+            ),
+            (
+                "stall-timeout",
+                self.stall_timeout_mins
+                    .map(|mins| Value::Integer(mins as i64)),
+            ),
+            (
+                "sound",
+                Some(Value::Array(
+                    self.sounds
+                        .iter()
+                        .map(|(event, p)| {
+                            Value::String(format!("{}={}", event.name(), p.display()))
+                        })
+                        .collect(),
+                )),
+            ),
+            (
+                "line-wrap",
+                Some(Value::String(
+                    match self.line_wrap_mode {
+                        LineWrapMode::Adjust => "adjust",
+                        LineWrapMode::Preserve => "preserve",
+                        LineWrapMode::Auto => "auto",
+                    }
+                    .to_string(),
+                )),
+            ),
+            (
+                "width",
+                self.width.map(|columns| Value::Integer(columns as i64)),
+            ),
+            (
+                "buffer-size",
+                self.buffer_size.map(|size| Value::Integer(size as i64)),
+            ),
+            (
+                "screen-reader",
+                Some(Value::Boolean(self.screen_reader_enabled)),
+            ),
+            (
+                "input-rewrite",
+                Some(Value::Array(
+                    self.rewrite_rules
+                        .iter()
+                        .map(|rule| Value::String(rule.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "rewrite-file",
+                Some(Value::Array(
+                    self.rewrite_files.iter().map(|p| path(p)).collect(),
+                )),
+            ),
+            (
+                "keys",
+                Some(Value::Array(
+                    self.key_presets
+                        .iter()
+                        .map(|preset| Value::String(preset.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "rewrite-timeout-ms",
+                self.rewrite_timeout_ms.map(|ms| Value::Integer(ms as i64)),
+            ),
+            (
+                "zwsp-after-updown-arrow",
+                Some(Value::Boolean(self.zwsp_after_updown_arrow)),
+            ),
+            (
+                "copy-key",
+                self.copy_key
+                    .as_deref()
+                    .map(|key| Value::String(escape_bytes(key))),
+            ),
+            (
+                "review-key",
+                self.review_key
+                    .as_deref()
+                    .map(|key| Value::String(escape_bytes(key))),
+            ),
+            (
+                "copy-code-blocks",
+                self.code_block_destination
+                    .as_ref()
+                    .map(|destination| Value::String(destination.to_string())),
+            ),
+            (
+                "copy-code-blocks-min-lines",
+                self.code_block_min_lines
+                    .map(|min_lines| Value::Integer(min_lines as i64)),
+            ),
+            (
+                "copy-code-blocks-notify",
+                Some(Value::Boolean(self.code_block_notify_enabled)),
+            ),
+            (
+                "auto-respond",
+                Some(Value::Array(
+                    self.auto_respond_rules
+                        .iter()
+                        .map(|rule| Value::String(rule.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "rate-limit-notify",
+                Some(Value::Boolean(self.rate_limit_notify_enabled)),
+            ),
+            (
+                "rate-limit-pattern",
+                Some(Value::Array(
+                    self.rate_limit_patterns
+                        .iter()
+                        .map(|regex| Value::String(regex.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "compaction-notify",
+                Some(Value::Boolean(self.compaction_notify_enabled)),
+            ),
+            (
+                "exit-notify",
+                Some(Value::Boolean(self.exit_notify_enabled)),
+            ),
+            (
+                "watch-files",
+                Some(Value::Array(
+                    self.watch_files
+                        .iter()
+                        .map(|glob| Value::String(glob.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "git-context",
+                Some(Value::Boolean(self.git_context_enabled)),
+            ),
+            (
+                "title-flash",
+                self.title_flash_secs
+                    .map(|secs| Value::Integer(secs as i64)),
+            ),
+            ("bell", Some(Value::Boolean(self.bell_enabled))),
+            (
+                "digest",
+                self.digest_secs.map(|secs| Value::Integer(secs as i64)),
+            ),
+            ("redact", Some(Value::Boolean(self.redact_enabled))),
+            (
+                "redact-pattern",
+                Some(Value::Array(
+                    self.redact_patterns
+                        .iter()
+                        .map(|regex| Value::String(regex.to_string()))
+                        .collect(),
+                )),
+            ),
+            (
+                "max-title-length",
+                self.max_title_chars
+                    .map(|max_chars| Value::Integer(max_chars as i64)),
+            ),
+            (
+                "max-message-length",
+                self.max_message_chars
+                    .map(|max_chars| Value::Integer(max_chars as i64)),
+            ),
+            (
+                "truncate",
+                Some(Value::String(self.truncation.as_str().to_string())),
+            ),
+            (
+                "mirror-send",
+                Some(Value::Boolean(self.mirror_send_enabled)),
+            ),
+            (
+                "mirror-receive",
+                Some(Value::Boolean(self.mirror_receive_enabled)),
+            ),
+            ("mirror-key", self.mirror_key_path.as_deref().map(path)),
+            (
+                "focus-policy",
+                Some(Value::Array(
+                    self.focus_rules
+                        .iter()
+                        .map(|rule| Value::String(rule.to_string()))
+                        .collect(),
+                )),
+            ),
+            ("broadcast", Some(Value::Boolean(self.broadcast_enabled))),
+            ("notify-only", Some(Value::Boolean(self.notify_only))),
+            ("term", self.term.clone().map(Value::String)),
+            ("colorterm", self.colorterm.as_deref().map(string)),
+            (
+                "appearance",
+                self.appearance.map(|mode| Value::String(mode.to_string())),
+            ),
+            (
+                "env",
+                Some(Value::Array(
+                    self.env
+                        .iter()
+                        .map(|(key, value)| Value::String(format!("{key}={value}")))
+                        .collect(),
+                )),
+            ),
+            (
+                "env-file",
+                Some(Value::Array(
+                    self.env_files.iter().map(|p| path(p)).collect(),
+                )),
+            ),
+            ("cwd", self.cwd.as_deref().map(path)),
+            ("claude-path", self.claude_path.as_deref().map(path)),
+            (
+                "login-shell",
+                Some(Value::Boolean(self.login_shell_enabled)),
+            ),
+            (
+                "tool",
+                self.tool.map(|tool| Value::String(tool.name.to_string())),
+            ),
+            (
+                "bypass-filter",
+                Some(Value::Boolean(self.bypass_filter_enabled)),
+            ),
+            (
+                "restart-on-crash",
+                self.max_restarts
+                    .map(|max_restarts| Value::Integer(max_restarts as i64)),
+            ),
+            (
+                "restart-continue",
+                Some(Value::Boolean(self.restart_continue_enabled)),
+            ),
+            (
+                "claude-session-id",
+                self.claude_session_id.clone().map(Value::String),
+            ),
+            ("summary", Some(Value::Boolean(self.summary_enabled))),
+            (
+                "on-start",
+                self.on_start
+                    .as_ref()
+                    .map(|command| Value::String(command.to_string())),
+            ),
+            (
+                "on-exit",
+                self.on_exit
+                    .as_ref()
+                    .map(|command| Value::String(command.to_string())),
+            ),
+            ("control-socket", self.control_socket.as_deref().map(path)),
+            ("input-fifo", self.input_fifo.as_deref().map(path)),
+            ("debug", self.debug_log_path.as_deref().map(path)),
+            ("debug-wrap", self.debug_wrap_path.as_deref().map(path)),
+            ("record", self.record_path.as_deref().map(path)),
+        ];
+        debug_assert!(
+            values
+                .iter()
+                .map(|(name, _)| name)
+                .eq(CONFIGURABLE_OPTIONS.iter().map(|(name, _)| name))
+        );
+
+        let mut config = String::new();
+        for source in &self.config_sources {
+            config += &format!("# from {source}\n");
+        }
+        for (name, value) in values {
+            match value {
+                Some(value) => config += &format!("{name} = {value}\n"),
+                None => config += &format!("# {name} is not set\n"),
             }
-            _ => return Err(arg.unexpected().into()),
         }
+        config
     }
+}
 
-    Ok(Arguments {
-        notification_center_delivery_enabled,
-        say_args,
-        line_wrap_mode,
-        rewrite_rules,
-        zwsp_after_updown_arrow,
-        claude_argv,
-    })
+/// For `--caloud-help`: the usage, the subcommands, and every option with its
+/// environment variable, generated so that none is left out.
+pub fn help() -> String {
+    let mut help = String::from(
+        "Usage: caloud [OPTIONS] [run] -- [CLAUDE_PATH] [CLAUDE_ARGS...]\n\
+         \x20      caloud [OPTIONS] --exec COMMAND [ARGS...]\n\
+         \x20      caloud [OPTIONS] <COMMAND> [ARGS...]\n\n",
+    );
+    let commands: Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    help += &format!("Commands: {}\n\n", commands.join(", "));
+    help += "Options, also settable as the environment variable shown or as the same \
+             key in a configuration file:\n";
+    let options: Vec<String> = CONFIGURABLE_OPTIONS
+        .iter()
+        .map(|&(name, kind)| match kind {
+            OptionKind::Flag => format!("--{name}"),
+            OptionKind::Value => format!("--{name}=<VALUE>"),
+            OptionKind::List => format!("--{name}=<VALUE>..."),
+        })
+        .collect();
+    let width = options.iter().map(String::len).max().unwrap_or_default();
+    for (option, (name, _)) in options.iter().zip(CONFIGURABLE_OPTIONS) {
+        help += &format!("  {option:<width$}  {}\n", env_var_name(name));
+    }
+    help += &format!(
+        "  {:<width$}  Run COMMAND instead of claude, e.g. aider\n  \
+         {:<width$}  Print this help\n  {:<width$}  Print the version of caloud\n\n",
+        "--exec COMMAND [ARGS...]", "--caloud-help", "--caloud-version"
+    );
+    help += "Other options, such as --help and --version, are passed to claude.\n\
+             See https://github.com/hirofumi/caloud for details.\n";
+    help
+}
+
+/// `CALOUD_` followed by the option name in upper snake case.
+fn env_var_name(option_name: &str) -> String {
+    format!(
+        "CALOUD_{}",
+        option_name.to_ascii_uppercase().replace('-', "_")
+    )
+}
+
+/// `--line-wrap=auto` leaves URLs alone in terminals where `claude` emits
+/// OSC 8 hyperlinks, since those stay clickable however they are wrapped;
+/// elsewhere the decision is deferred to the reformatter, which follows the
+/// terminal width.
+fn resolve_line_wrap_mode(mode: LineWrapMode, term_program: Option<&str>) -> LineWrapMode {
+    // The terminals `claude` recognizes as hyperlink-capable via `TERM_PROGRAM`.
+    const HYPERLINK_TERM_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty"];
+    match mode {
+        LineWrapMode::Auto
+            if term_program.is_some_and(|p| HYPERLINK_TERM_PROGRAMS.contains(&p)) =>
+        {
+            LineWrapMode::Preserve
+        }
+        mode => mode,
+    }
+}
+
+/// `--flag`, `--flag=true`, or `--flag=false`; the latter overrides a configuration file.
+fn parse_flag(parser: &mut lexopt::Parser, name: &str) -> anyhow::Result<bool> {
+    let Some(value) = parser.optional_value() else {
+        return Ok(true);
+    };
+    let value = value.string()?;
+    match value.as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!("invalid value for --{}: {}", name, value),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::macos::appearance::Appearance;
+    use indoc::indoc;
+
+    fn parse_args(
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> anyhow::Result<Arguments> {
+        let mut arguments = Arguments::default();
+        arguments.apply_options(lexopt::Parser::from_iter(args))?;
+        Ok(arguments)
+    }
 
     #[test]
     fn default_values() {
         let arguments = parse_args(["prog"]).unwrap();
+        assert_eq!(arguments.command, Command::Run);
         assert!(!arguments.notification_center_delivery_enabled);
         assert!(arguments.say_args.is_none());
         assert_eq!(arguments.line_wrap_mode, LineWrapMode::Preserve);
         assert!(!arguments.zwsp_after_updown_arrow);
+        assert!(arguments.term.is_none());
+        assert!(arguments.colorterm.is_none());
     }
 
     #[test]
@@ -140,6 +1884,34 @@ mod tests {
         assert_eq!(arguments.line_wrap_mode, LineWrapMode::Preserve);
     }
 
+    #[test]
+    fn line_wrap_auto() {
+        let arguments = parse_args(["prog", "--line-wrap=auto"]).unwrap();
+        assert_eq!(arguments.line_wrap_mode, LineWrapMode::Auto);
+        for (term_program, expected) in [
+            (Some("iTerm.app"), LineWrapMode::Preserve),
+            (Some("Apple_Terminal"), LineWrapMode::Auto),
+            (None, LineWrapMode::Auto),
+        ] {
+            assert_eq!(
+                resolve_line_wrap_mode(LineWrapMode::Auto, term_program),
+                expected
+            );
+        }
+        assert_eq!(
+            resolve_line_wrap_mode(LineWrapMode::Adjust, Some("iTerm.app")),
+            LineWrapMode::Adjust
+        );
+    }
+
+    #[test]
+    fn width_option() {
+        let arguments = parse_args(["prog", "--width=120"]).unwrap();
+        assert_eq!(arguments.width(), Some(120));
+        assert!(parse_args(["prog", "--width=0"]).is_err());
+        assert!(parse_args(["prog", "--width=wide"]).is_err());
+    }
+
     #[test]
     fn invalid_line_wrap_option() {
         let result = parse_args(["prog", "--line-wrap=invalid"]);
@@ -163,6 +1935,81 @@ mod tests {
         assert_eq!(arguments.claude_argv[2], "arg2");
     }
 
+    #[test]
+    fn login_shell_option() {
+        // A bare name is left to the login shell, even if not in PATH yet.
+        let arguments =
+            parse_args(["prog", "--login-shell", "--", "claude-in-nvm", "--continue"]).unwrap();
+        assert!(arguments.login_shell_enabled);
+        let claude_command = arguments.try_build_claude_command().unwrap();
+        assert_eq!(claude_command.to_string(), "claude-in-nvm --continue");
+        let arguments = parse_args(["prog", "--login-shell"]).unwrap();
+        let claude_command = arguments.try_build_claude_command().unwrap();
+        assert_eq!(claude_command.to_string(), "claude");
+    }
+
+    #[test]
+    fn exec_option() {
+        let arguments =
+            parse_args(["prog", "--say=-v Samantha", "--exec", "sh", "-c", "--say"]).unwrap();
+        assert!(arguments.exec_given);
+        assert_eq!(arguments.claude_argv, ["sh", "-c", "--say"]);
+        assert_eq!(arguments.say_args, Some(OsString::from("-v Samantha")));
+        assert!(!arguments.runs_claude());
+        let claude_command = arguments.try_build_claude_command().unwrap();
+        assert_eq!(claude_command.tool(), "sh");
+        assert_eq!(claude_command.to_string(), "sh -c --say");
+
+        let arguments = parse_args(["prog", "--exec=/usr/local/bin/claude"]).unwrap();
+        assert!(arguments.runs_claude());
+        assert!(parse_args(["prog", "--exec"]).is_err());
+    }
+
+    #[test]
+    fn tool_option() {
+        // The login shell finds it, in case it is not installed here.
+        let arguments = parse_args([
+            "prog",
+            "--login-shell",
+            "--tool=aider",
+            "--",
+            "--model",
+            "sonnet",
+        ])
+        .unwrap();
+        assert!(!arguments.runs_claude());
+        let claude_command = arguments.try_build_claude_command().unwrap();
+        assert_eq!(claude_command.tool(), "aider");
+        assert_eq!(claude_command.to_string(), "aider --model sonnet");
+
+        // The preset for a wrapper of the tool.
+        let arguments = parse_args(["prog", "--tool", "codex", "--exec", "sh", "-c"]).unwrap();
+        let claude_command = arguments.try_build_claude_command().unwrap();
+        assert_eq!(claude_command.tool(), "codex");
+        assert_eq!(claude_command.to_string(), "sh -c");
+
+        let arguments = parse_args(["prog", "--tool=claude", "--exec", "sh"]).unwrap();
+        assert!(arguments.runs_claude());
+        let err = parse_args(["prog", "--tool=cursor"]).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "failed to parse --tool: cursor: unknown tool: cursor (expected claude, codex, aider, gemini)"
+        );
+    }
+
+    #[test]
+    fn restart_on_crash_option() {
+        let arguments = parse_args(["prog", "--restart-on-crash"]).unwrap();
+        assert_eq!(arguments.max_restarts, Some(DEFAULT_MAX_RESTARTS));
+        let arguments = parse_args(["prog", "--restart-on-crash=5", "--restart-continue"]).unwrap();
+        assert_eq!(arguments.max_restarts, Some(5));
+        assert!(arguments.restart_continue_enabled);
+        // 0 overrides a configuration file.
+        let arguments = parse_args(["prog", "--restart-on-crash=0"]).unwrap();
+        assert_eq!(arguments.max_restarts, None);
+        assert!(parse_args(["prog", "--restart-on-crash=always"]).is_err());
+    }
+
     #[test]
     fn combined_options() {
         let arguments = parse_args([
@@ -210,6 +2057,48 @@ mod tests {
         assert_eq!(arguments.rewrite_rules[1].to(), b"\x1b[C");
     }
 
+    #[test]
+    fn rewrite_file() {
+        let dir = std::env::temp_dir().join(format!("caloud-rewrite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keys");
+        std::fs::write(&path, "# movement\n\\x02:\\e[D\n\\x06:\\e[C\n").unwrap();
+        let arguments = parse_args([
+            "prog".into(),
+            r"--input-rewrite=\x02:\x01".into(),
+            format!("--rewrite-file={}", path.display()),
+        ])
+        .unwrap();
+        let rules = arguments.rewrite_rules().unwrap();
+        assert_eq!(
+            rules.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [r"\x02:\x01", r"\x02:\e[D", r"\x06:\e[C"]
+        );
+
+        std::fs::write(&path, "\\x02:\\e[D\n\\x06\n").unwrap();
+        let err = arguments.rewrite_rules().unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!(
+                "failed to parse --rewrite-file: {}: line 2: missing ':' separator in rewrite rule",
+                path.display()
+            )
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keys_option() {
+        let arguments = parse_args(["prog", r"--input-rewrite=\x02:\x01", "--keys=emacs"]).unwrap();
+        assert_eq!(arguments.key_presets, [KeyPreset::Emacs]);
+        let rules = arguments.rewrite_rules().unwrap();
+        assert_eq!(rules.len(), 5);
+        // --input-rewrite takes precedence over the preset with the same FROM.
+        assert_eq!(rules[0].to(), b"\x01");
+        let err = parse_args(["prog", "--keys=vi"]).unwrap_err();
+        assert!(err.to_string().contains("failed to parse --keys"));
+    }
+
     #[test]
     fn input_rewrite_invalid_format() {
         let result = parse_args(["prog", r"--input-rewrite=\x02"]);
@@ -239,4 +2128,904 @@ mod tests {
         let err_msg = result.unwrap_err().to_string();
         assert!(err_msg.contains("failed to parse --input-rewrite"));
     }
+
+    #[test]
+    fn term_option() {
+        let arguments = parse_args(["prog", "--term=xterm-256color"]).unwrap();
+        assert_eq!(arguments.term.as_deref(), Some("xterm-256color"));
+    }
+
+    #[test]
+    fn term_option_empty() {
+        let result = parse_args(["prog", "--term="]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn colorterm_option() {
+        let arguments = parse_args(["prog", "--colorterm=truecolor"]).unwrap();
+        assert_eq!(arguments.colorterm, Some(OsString::from("truecolor")));
+    }
+
+    #[test]
+    fn appearance_option() {
+        let arguments = parse_args(["prog", "--appearance=dark"]).unwrap();
+        assert_eq!(
+            arguments.appearance,
+            Some(AppearanceMode::Fixed(Appearance::Dark))
+        );
+        assert!(parse_args(["prog", "--appearance=sepia"]).is_err());
+    }
+
+    #[test]
+    fn env_option() {
+        let arguments =
+            parse_args(["prog", "--env=ANTHROPIC_MODEL=opus", "--env", "NO_COLOR=1"]).unwrap();
+        assert_eq!(
+            arguments.env,
+            [
+                ("ANTHROPIC_MODEL".to_string(), "opus".to_string()),
+                ("NO_COLOR".to_string(), "1".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn env_option_invalid() {
+        let result = parse_args(["prog", "--env=NO_EQUALS"]);
+        assert!(result.is_err());
+        let err_msg = result.unwrap_err().to_string();
+        assert!(err_msg.contains("failed to parse --env"));
+    }
+
+    #[test]
+    fn env_file_option() {
+        let arguments = parse_args(["prog", "--env-file=a.env", "--env-file=b.env"]).unwrap();
+        assert_eq!(
+            arguments.env_files,
+            [PathBuf::from("a.env"), PathBuf::from("b.env")],
+        );
+    }
+
+    #[test]
+    fn cwd_option() {
+        let arguments = parse_args(["prog", "--cwd=/tmp/project"]).unwrap();
+        assert_eq!(arguments.cwd, Some(PathBuf::from("/tmp/project")));
+    }
+
+    #[test]
+    fn doctor_command() {
+        let arguments = parse_args(["prog", "--say=-v Samantha", "doctor"]).unwrap();
+        assert_eq!(arguments.command, Command::Doctor);
+        assert!(arguments.claude_argv.is_empty());
+    }
+
+    #[test]
+    fn doctor_as_claude_argument() {
+        let arguments = parse_args(["prog", "/usr/bin/claude", "doctor"]).unwrap();
+        assert_eq!(arguments.command, Command::Run);
+        assert_eq!(arguments.claude_argv, ["/usr/bin/claude", "doctor"]);
+    }
+
+    #[test]
+    fn caloud_version_option() {
+        let arguments = parse_args(["prog", "--caloud-version"]).unwrap();
+        assert_eq!(arguments.command, Command::Version);
+    }
+
+    #[test]
+    fn caloud_help_option() {
+        let arguments = parse_args(["prog", "--caloud-help"]).unwrap();
+        assert_eq!(arguments.command, Command::Help);
+        let help = help();
+        for (name, _) in CONFIGURABLE_OPTIONS {
+            assert!(help.contains(&format!("--{name}")), "{name} is missing");
+        }
+        assert!(help.contains("  --say=<VALUE>  "));
+        assert!(help.contains("  --no-say  "));
+        assert!(help.contains("  --sound=<VALUE>...  "));
+        assert!(help.contains("CALOUD_REWRITE_TIMEOUT_MS\n"));
+        assert!(help.contains("Commands: run, doctor, "));
+    }
+
+    #[test]
+    fn version_option_is_forwarded_to_claude() {
+        let arguments = parse_args(["prog", "--", "claude", "--version"]).unwrap();
+        assert_eq!(arguments.command, Command::Run);
+        assert_eq!(arguments.claude_argv, ["claude", "--version"]);
+    }
+
+    #[test]
+    fn summary_option() {
+        assert!(!parse_args(["prog"]).unwrap().summary_enabled);
+        assert!(parse_args(["prog", "--summary"]).unwrap().summary_enabled);
+    }
+
+    #[test]
+    fn hook_options() {
+        let arguments = parse_args([
+            "prog",
+            "--on-start=timew start claude",
+            "--on-exit=timew stop",
+        ])
+        .unwrap();
+        assert_eq!(
+            arguments.on_start.unwrap().to_string(),
+            "timew start claude"
+        );
+        assert_eq!(arguments.on_exit.unwrap().to_string(), "timew stop");
+        assert!(parse_args(["prog", "--on-exit="]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn macos_only_options() {
+        assert!(parse_args(["prog"]).unwrap().check_macos_only().is_ok());
+        let error = parse_args(["prog", "--broadcast"])
+            .unwrap()
+            .check_macos_only()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "--broadcast is only supported on macOS");
+    }
+
+    #[test]
+    fn control_socket_option() {
+        let arguments = parse_args(["prog", "--control-socket=/tmp/caloud.sock"]).unwrap();
+        assert_eq!(
+            arguments.control_socket,
+            Some(PathBuf::from("/tmp/caloud.sock"))
+        );
+    }
+
+    #[test]
+    fn input_fifo_option() {
+        let arguments = parse_args(["prog", "--input-fifo=/tmp/caloud.fifo"]).unwrap();
+        assert_eq!(
+            arguments.input_fifo,
+            Some(PathBuf::from("/tmp/caloud.fifo"))
+        );
+    }
+
+    #[test]
+    fn voiceover_option() {
+        assert!(!parse_args(["prog"]).unwrap().voiceover_enabled);
+        assert!(
+            parse_args(["prog", "--voiceover"])
+                .unwrap()
+                .voiceover_enabled
+        );
+    }
+
+    #[test]
+    fn speak_output_option() {
+        assert!(!parse_args(["prog"]).unwrap().speak_output_enabled);
+        assert!(
+            parse_args(["prog", "--speak-output"])
+                .unwrap()
+                .speak_output_enabled
+        );
+        assert!(
+            parse_args(["prog", "--speak-questions"])
+                .unwrap()
+                .speak_questions_enabled
+        );
+        assert!(
+            parse_args(["prog", "--speak-tools"])
+                .unwrap()
+                .speak_tools_enabled
+        );
+        assert!(
+            parse_args(["prog", "--speak-final"])
+                .unwrap()
+                .speak_final_enabled
+        );
+    }
+
+    #[test]
+    fn translate_command_option() {
+        let arguments = parse_args(["prog", "--translate-command=trans -b :ja"]).unwrap();
+        assert_eq!(
+            arguments.translate_command.unwrap().to_string(),
+            "trans -b :ja"
+        );
+        assert!(parse_args(["prog", "--translate-command="]).is_err());
+    }
+
+    #[test]
+    fn abbreviate_speech_option() {
+        assert_eq!(
+            parse_args(["prog"]).unwrap().speech_abbreviations,
+            Abbreviations::ALL
+        );
+        let arguments = parse_args(["prog", "--abbreviate-speech=none"]).unwrap();
+        assert_eq!(arguments.speech_abbreviations, Abbreviations::NONE);
+        assert!(parse_args(["prog", "--abbreviate-speech=digits"]).is_err());
+    }
+
+    #[test]
+    fn speak_emoji_option() {
+        assert_eq!(parse_args(["prog"]).unwrap().speech_emoji, EmojiMode::Keep);
+        let arguments = parse_args(["prog", "--speak-emoji=strip"]).unwrap();
+        assert_eq!(arguments.speech_emoji, EmojiMode::Strip);
+        assert!(parse_args(["prog", "--speak-emoji=words"]).is_err());
+    }
+
+    #[test]
+    fn voice_pool_options() {
+        let arguments = parse_args([
+            "prog",
+            "--voice-pool=Samantha",
+            "--voice-pool=Daniel",
+            "--voice-assignment=round-robin",
+        ])
+        .unwrap();
+        assert_eq!(arguments.voice_pool, ["Samantha", "Daniel"]);
+        assert_eq!(arguments.voice_assignment, VoiceAssignment::RoundRobin);
+        assert!(parse_args(["prog", "--voice-assignment=random"]).is_err());
+    }
+
+    #[test]
+    fn permission_reminder_option() {
+        let arguments = parse_args(["prog", "--permission-reminder=60"]).unwrap();
+        assert_eq!(arguments.permission_reminder_secs, Some(60));
+        assert!(parse_args(["prog", "--permission-reminder=0"]).is_err());
+        assert!(parse_args(["prog", "--permission-reminder=1m"]).is_err());
+    }
+
+    #[test]
+    fn progress_after_option() {
+        let arguments = parse_args(["prog", "--progress-after=300"]).unwrap();
+        assert_eq!(arguments.progress_after_secs, Some(300));
+        assert!(parse_args(["prog", "--progress-after=0"]).is_err());
+    }
+
+    #[test]
+    fn stall_timeout_option() {
+        let arguments = parse_args(["prog", "--stall-timeout=10"]).unwrap();
+        assert_eq!(arguments.stall_timeout_mins, Some(10));
+        assert!(parse_args(["prog", "--stall-timeout=0"]).is_err());
+        assert!(parse_args(["prog", "--stall-timeout=10m"]).is_err());
+    }
+
+    #[test]
+    fn progress_state_option() {
+        assert!(!parse_args(["prog"]).unwrap().progress_state_enabled);
+        let arguments = parse_args(["prog", "--progress-state"]).unwrap();
+        assert!(arguments.progress_state_enabled);
+    }
+
+    #[test]
+    fn sound_option() {
+        let arguments = parse_args([
+            "prog",
+            "--sound=question=/System/Library/Sounds/Ping.aiff",
+            "--sound=exit=/System/Library/Sounds/Glass.aiff",
+        ])
+        .unwrap();
+        assert_eq!(
+            arguments.sounds,
+            [
+                (
+                    SoundEvent::Question,
+                    PathBuf::from("/System/Library/Sounds/Ping.aiff")
+                ),
+                (
+                    SoundEvent::Exit,
+                    PathBuf::from("/System/Library/Sounds/Glass.aiff")
+                ),
+            ]
+        );
+        assert!(parse_args(["prog", "--sound=done=x.aiff"]).is_err());
+    }
+
+    #[test]
+    fn copy_key_option() {
+        let arguments = parse_args(["prog", r"--copy-key=\ec"]).unwrap();
+        assert_eq!(arguments.copy_key.as_deref(), Some(&b"\x1bc"[..]));
+        assert!(parse_args(["prog", "--copy-key="]).is_err());
+        assert!(parse_args(["prog", r"--copy-key=\xGG"]).is_err());
+    }
+
+    #[test]
+    fn review_key_option() {
+        let arguments = parse_args(["prog", r"--review-key=\en"]).unwrap();
+        assert_eq!(arguments.review_key.as_deref(), Some(&b"\x1bn"[..]));
+        assert!(parse_args(["prog", "--review-key="]).is_err());
+    }
+
+    #[test]
+    fn copy_code_blocks_options() {
+        let arguments = parse_args([
+            "prog",
+            "--copy-code-blocks=pasteboard",
+            "--copy-code-blocks-min-lines=3",
+            "--copy-code-blocks-notify",
+        ])
+        .unwrap();
+        assert_eq!(
+            arguments.code_block_destination,
+            Some(CodeBlockDestination::Pasteboard)
+        );
+        assert_eq!(arguments.code_block_min_lines, Some(3));
+        assert!(arguments.code_block_notify_enabled);
+        let arguments = parse_args(["prog", "--copy-code-blocks=/tmp/blocks.md"]).unwrap();
+        assert_eq!(
+            arguments.code_block_destination,
+            Some(CodeBlockDestination::File(PathBuf::from("/tmp/blocks.md")))
+        );
+        assert!(parse_args(["prog", "--copy-code-blocks="]).is_err());
+        assert!(parse_args(["prog", "--copy-code-blocks-min-lines=0"]).is_err());
+    }
+
+    #[test]
+    fn auto_respond_option() {
+        let arguments = parse_args([
+            "prog",
+            r"--auto-respond=auto:1:Do you want to proceed\?",
+            r"--auto-respond=confirm:2:Continue",
+        ])
+        .unwrap();
+        let rules: Vec<_> = arguments
+            .auto_respond_rules
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect();
+        assert_eq!(
+            rules,
+            [r"auto:1:Do you want to proceed\?", "confirm:2:Continue"]
+        );
+        let err = parse_args(["prog", "--auto-respond=yes:1:proceed"]).unwrap_err();
+        assert!(format!("{err:#}").contains("failed to parse --auto-respond"));
+    }
+
+    #[test]
+    fn rate_limit_options() {
+        let arguments = parse_args([
+            "prog",
+            "--rate-limit-notify",
+            "--rate-limit-pattern=overloaded",
+        ])
+        .unwrap();
+        assert!(arguments.rate_limit_notify_enabled);
+        assert_eq!(arguments.rate_limit_patterns[0].as_str(), "overloaded");
+        assert!(parse_args(["prog", "--rate-limit-pattern=("]).is_err());
+    }
+
+    #[test]
+    fn compaction_notify_option() {
+        assert!(!parse_args(["prog"]).unwrap().compaction_notify_enabled);
+        let arguments = parse_args(["prog", "--compaction-notify"]).unwrap();
+        assert!(arguments.compaction_notify_enabled);
+    }
+
+    #[test]
+    fn exit_notify_option() {
+        assert!(!parse_args(["prog"]).unwrap().exit_notify_enabled);
+        let arguments = parse_args(["prog", "--exit-notify"]).unwrap();
+        assert!(arguments.exit_notify_enabled);
+    }
+
+    #[test]
+    fn watch_files() {
+        let arguments = parse_args([
+            "prog",
+            "--watch-files=Cargo.toml",
+            "--watch-files=db/**/*.sql",
+        ])
+        .unwrap();
+        let globs: Vec<_> = arguments
+            .watch_files
+            .iter()
+            .map(|g| g.to_string())
+            .collect();
+        assert_eq!(globs, ["Cargo.toml", "db/**/*.sql"]);
+        assert!(parse_args(["prog", "--watch-files=migrations/"]).is_err());
+    }
+
+    #[test]
+    fn git_context() {
+        assert!(!parse_args(["prog"]).unwrap().git_context_enabled);
+        assert!(
+            parse_args(["prog", "--git-context"])
+                .unwrap()
+                .git_context_enabled
+        );
+    }
+
+    #[test]
+    fn title_flash_option() {
+        let arguments = parse_args(["prog", "--title-flash=10"]).unwrap();
+        assert_eq!(arguments.title_flash_secs, Some(10));
+        assert!(parse_args(["prog", "--title-flash=0"]).is_err());
+        let arguments = parse_args(["prog", "--rewrite-timeout-ms=200"]).unwrap();
+        assert_eq!(arguments.rewrite_timeout_ms, Some(200));
+        assert!(parse_args(["prog", "--rewrite-timeout-ms=0"]).is_err());
+        let arguments = parse_args(["prog", "--buffer-size=65536"]).unwrap();
+        assert_eq!(arguments.buffer_size, Some(65536));
+        assert!(parse_args(["prog", "--buffer-size=16"]).is_err());
+        assert!(parse_args(["prog", "--buffer-size=1048576"]).is_ok());
+        assert!(parse_args(["prog", "--buffer-size=1048577"]).is_err());
+    }
+
+    #[test]
+    fn bell_option() {
+        assert!(!parse_args(["prog"]).unwrap().bell_enabled);
+        assert!(parse_args(["prog", "--bell"]).unwrap().bell_enabled);
+    }
+
+    #[test]
+    fn redact_options() {
+        assert!(parse_args(["prog"]).unwrap().redactor().is_none());
+        let arguments = parse_args(["prog", "--redact", "--redact-pattern=corp-[0-9]{6}"]).unwrap();
+        let redactor = arguments.redactor().unwrap();
+        assert_eq!(redactor.apply("ticket corp-123456"), "ticket [redacted]");
+        assert!(parse_args(["prog", "--redact-pattern=("]).is_err());
+    }
+
+    #[test]
+    fn length_options() {
+        let arguments =
+            parse_args(["prog", "--max-message-length=120", "--truncate=middle"]).unwrap();
+        let limits = arguments.length_limits();
+        assert_eq!(limits.max_title_chars, None);
+        assert_eq!(limits.max_message_chars, Some(120));
+        assert_eq!(limits.truncation, Truncation::Middle);
+        assert!(parse_args(["prog", "--max-title-length=0"]).is_err());
+        assert!(parse_args(["prog", "--truncate=end"]).is_err());
+    }
+
+    #[test]
+    fn claude_session_id_option() {
+        let arguments = parse_args([
+            "prog",
+            "--claude-session-id=0b6f3c1e-8a57-4d2b-9c41-5e7a2f9d8b60",
+        ])
+        .unwrap();
+        assert_eq!(
+            arguments.claude_session_id.as_deref(),
+            Some("0b6f3c1e-8a57-4d2b-9c41-5e7a2f9d8b60")
+        );
+        assert!(parse_args(["prog", "--claude-session-id=../x"]).is_err());
+    }
+
+    #[test]
+    fn digest_option() {
+        let arguments = parse_args(["prog", "--digest=600"]).unwrap();
+        assert_eq!(arguments.digest_secs, Some(600));
+        assert!(parse_args(["prog", "--digest=0"]).is_err());
+        assert!(parse_args(["prog", "--digest=10m"]).is_err());
+    }
+
+    #[test]
+    fn mirror_options() {
+        let arguments = parse_args(["prog"]).unwrap();
+        assert!(!arguments.mirror_send_enabled && !arguments.mirror_receive_enabled);
+        assert!(arguments.mirror_key().unwrap().is_none());
+        let arguments = parse_args(["prog", "--mirror-send", "--mirror-receive"]).unwrap();
+        assert!(arguments.mirror_send_enabled && arguments.mirror_receive_enabled);
+        let error = arguments.mirror_key().unwrap_err();
+        assert!(format!("{error:#}").contains("need --mirror-key"));
+    }
+
+    #[test]
+    fn focus_policy_option() {
+        let arguments = parse_args([
+            "prog",
+            "--focus-policy=Work:deliver",
+            "--focus-policy=Personal:queue",
+        ])
+        .unwrap();
+        let rules: Vec<_> = arguments
+            .focus_rules
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect();
+        assert_eq!(rules, ["Work:deliver", "Personal:queue"]);
+        let err = parse_args(["prog", "--focus-policy=Work"]).unwrap_err();
+        assert!(format!("{err:#}").contains("failed to parse --focus-policy"));
+    }
+
+    #[test]
+    fn broadcast_option() {
+        assert!(!parse_args(["prog"]).unwrap().broadcast_enabled);
+        assert!(
+            parse_args(["prog", "--broadcast"])
+                .unwrap()
+                .broadcast_enabled
+        );
+    }
+
+    #[test]
+    fn debug_option() {
+        let arguments = parse_args(["prog", "--debug=/tmp/caloud.log"]).unwrap();
+        assert_eq!(
+            arguments.debug_log_path,
+            Some(PathBuf::from("/tmp/caloud.log"))
+        );
+    }
+
+    #[test]
+    fn record_option() {
+        let arguments = parse_args(["prog", "--record=/tmp/session.rec"]).unwrap();
+        assert_eq!(
+            arguments.record_path,
+            Some(PathBuf::from("/tmp/session.rec"))
+        );
+    }
+
+    #[test]
+    fn debug_wrap_option() {
+        let arguments = parse_args(["prog", "--debug-wrap", "/tmp/wrap.log"]).unwrap();
+        assert_eq!(
+            arguments.debug_wrap_path,
+            Some(PathBuf::from("/tmp/wrap.log"))
+        );
+    }
+
+    #[test]
+    fn boolean_option_values() {
+        let arguments =
+            parse_args(["prog", "--summary=true", "--notification-center=false"]).unwrap();
+        assert!(arguments.summary_enabled);
+        assert!(!arguments.notification_center_delivery_enabled);
+        assert!(parse_args(["prog", "--summary=yes"]).is_err());
+    }
+
+    #[test]
+    fn config_files_are_layered() {
+        let dir = std::env::temp_dir().join(format!("caloud-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let user = dir.join("config.toml");
+        let project = dir.join(".caloud.toml");
+        std::fs::write(
+            &user,
+            indoc! {r#"
+                say = "-v Samantha"
+                line_wrap = "adjust"
+                notification-center = true
+                input-rewrite = ['\x02:\e[D', '\x06:\e[C']
+            "#},
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            "notification-center = false
+say = '-v Kyoko'
+",
+        )
+        .unwrap();
+
+        let mut arguments = Arguments::default();
+        arguments.apply_config_file(&user, false).unwrap();
+        arguments.apply_config_file(&project, true).unwrap();
+        arguments
+            .apply_config_file(&dir.join("missing.toml"), true)
+            .unwrap();
+        arguments
+            .apply_options(lexopt::Parser::from_iter([
+                "prog",
+                "--say=-v Alex",
+                r"--input-rewrite=\x01:\x02",
+            ]))
+            .unwrap();
+
+        assert_eq!(arguments.say_args, Some(OsString::from("-v Alex")));
+        assert_eq!(arguments.line_wrap_mode, LineWrapMode::Adjust);
+        assert!(!arguments.notification_center_delivery_enabled);
+        // The command line replaces the list instead of extending it.
+        assert_eq!(arguments.rewrite_rules.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_file_errors_have_locations() {
+        let dir = std::env::temp_dir().join(format!("caloud-config-error-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let error = |content: &str, is_project| {
+            std::fs::write(&path, content).unwrap();
+            format!(
+                "{:#}",
+                Arguments::default()
+                    .apply_config_file(&path, is_project)
+                    .unwrap_err()
+                    .remove(0)
+            )
+            .replace(&path.display().to_string(), "PATH")
+        };
+
+        assert_eq!(
+            error("say = 1\nfoo = 2\n", false),
+            "PATH:2: unknown option: foo"
+        );
+        assert_eq!(
+            error("\nsay = 'x\n", false),
+            "PATH:2: invalid literal string, expected `'`"
+        );
+        assert_eq!(
+            error("summary = 'yes'\n", false),
+            "PATH:1: summary must be a boolean"
+        );
+        assert_eq!(
+            error("line-wrap = 'wide'\n", false),
+            "PATH:1: invalid value for --line-wrap: wide"
+        );
+        assert_eq!(
+            error("claude-path = './claude'\n", true),
+            "PATH:1: claude-path is not allowed in .caloud.toml"
+        );
+        assert_eq!(
+            error("translate-command = 'sh x'\n", true),
+            "PATH:1: translate-command is not allowed in .caloud.toml"
+        );
+        assert_eq!(
+            error("on-start = 'sh x'\n", true),
+            "PATH:1: on-start is not allowed in .caloud.toml"
+        );
+        assert_eq!(
+            error("auto-respond = ['auto:1\\r:.*']\n", true),
+            "PATH:1: auto-respond is not allowed in .caloud.toml"
+        );
+        assert_eq!(
+            error("record = '/tmp/x'\n", true),
+            "PATH:1: record is not allowed in .caloud.toml"
+        );
+        assert_eq!(
+            error("say = '-v Kyoko -o ../x.aiff'\n", true),
+            "PATH:1: say in .caloud.toml may only give -v and -r"
+        );
+        assert!(Arguments::default().apply_config_file(&path, false).is_ok());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_file_reports_every_invalid_entry() {
+        let dir = std::env::temp_dir().join(format!("caloud-config-all-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "foo = 1\nsay = '-v Alex'\nsummary = 'yes'\n").unwrap();
+
+        let mut arguments = Arguments::default();
+        let errors = arguments.apply_config_file(&path, false).unwrap_err();
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| format!("{e:#}").replace(&path.display().to_string(), "PATH"))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "PATH:1: unknown option: foo",
+                "PATH:3: summary must be a boolean"
+            ]
+        );
+        // Valid entries are still applied.
+        assert_eq!(arguments.say_args, Some(OsString::from("-v Alex")));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn environment_variables() {
+        let vars = [
+            ("CALOUD_SAY", "-v Samantha"),
+            ("CALOUD_LINE_WRAP", "adjust"),
+            ("CALOUD_SUMMARY", "1"),
+            ("CALOUD_INPUT_REWRITE", "\\x02:\\e[D\n\\x06:\\e[C\n"),
+            ("CALOUD_TERM", ""),
+        ];
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| OsString::from(value))
+        };
+        let mut arguments = Arguments::default();
+        arguments.apply_environment(var).unwrap();
+        assert_eq!(arguments.say_args, Some(OsString::from("-v Samantha")));
+        assert_eq!(arguments.line_wrap_mode, LineWrapMode::Adjust);
+        assert!(arguments.summary_enabled);
+        assert_eq!(arguments.rewrite_rules.len(), 2);
+        assert!(arguments.term.is_none());
+    }
+
+    #[test]
+    fn environment_variable_errors_name_the_variable() {
+        let mut arguments = Arguments::default();
+        let error = arguments
+            .apply_environment(|name| (name == "CALOUD_LINE_WRAP").then(|| OsString::from("wide")))
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(
+            format!("{error:#}"),
+            "CALOUD_LINE_WRAP: invalid value for --line-wrap: wide"
+        );
+    }
+
+    #[test]
+    fn environment_variable_names() {
+        assert_eq!(env_var_name("line-wrap"), "CALOUD_LINE_WRAP");
+        assert_eq!(
+            env_var_name("zwsp-after-updown-arrow"),
+            "CALOUD_ZWSP_AFTER_UPDOWN_ARROW"
+        );
+    }
+
+    #[test]
+    fn no_notify_option() {
+        let arguments = parse_args(["prog", "--notification-center", "--no-notify"]).unwrap();
+        assert!(arguments.notify_disabled);
+    }
+
+    #[test]
+    fn no_say_option() {
+        let arguments = parse_args(["prog", "--say=-v Samantha", "--no-say"]).unwrap();
+        assert!(arguments.say_disabled);
+        assert!(arguments.try_build_say_command().is_none());
+    }
+
+    #[test]
+    fn say_when_option() {
+        let arguments = parse_args(["prog", "--say-when=background"]).unwrap();
+        assert_eq!(arguments.say_when, SayWhen::Background);
+        assert!(parse_args(["prog", "--say-when=idle"]).is_err());
+    }
+
+    #[test]
+    fn screen_reader_option() {
+        assert!(!parse_args(["prog"]).unwrap().screen_reader_enabled);
+        assert!(
+            parse_args(["prog", "--screen-reader"])
+                .unwrap()
+                .screen_reader_enabled
+        );
+    }
+
+    #[test]
+    fn notify_only_option() {
+        let arguments = parse_args(["prog", "--line-wrap=adjust", "--notify-only"]).unwrap();
+        assert!(arguments.notify_only);
+    }
+
+    #[test]
+    fn subcommands() {
+        let command = |args: &[&str]| parse_args(args).unwrap().command;
+        assert_eq!(command(&["prog", "run"]), Command::Run);
+        assert_eq!(command(&["prog", "config"]), Command::Config);
+        assert_eq!(command(&["prog", "capabilities"]), Command::Capabilities);
+        assert_eq!(
+            command(&["prog", "--line-wrap=adjust", "filter"]),
+            Command::Filter
+        );
+        assert_eq!(
+            command(&["prog", "config", "validate"]),
+            Command::ConfigValidate
+        );
+        assert_eq!(
+            command(&["prog", "--say=-v Samantha", "doctor"]),
+            Command::Doctor
+        );
+        let arguments = parse_args(["prog", "run", "/usr/bin/claude", "doctor"]).unwrap();
+        assert_eq!(arguments.command, Command::Run);
+        assert_eq!(arguments.claude_argv, ["/usr/bin/claude", "doctor"]);
+        let arguments = parse_args(["prog", "run", "config"]).unwrap();
+        assert_eq!(arguments.command, Command::Run);
+        assert_eq!(arguments.claude_argv, ["config"]);
+        let arguments = parse_args(["prog", "send", "Build", "finished"]).unwrap();
+        assert_eq!(arguments.command, Command::Send);
+        assert_eq!(arguments.subcommand_args(), ["Build", "finished"]);
+        assert!(arguments.claude_argv.is_empty());
+        let arguments = parse_args(["prog", "reformat", "--width=40", "bare.capture.raw"]).unwrap();
+        assert_eq!(arguments.command, Command::Reformat);
+        assert_eq!(arguments.width(), Some(40));
+        assert_eq!(arguments.subcommand_args(), ["bare.capture.raw"]);
+        let arguments = parse_args(["prog", "decode", "session.rec"]).unwrap();
+        assert_eq!(arguments.command, Command::Decode);
+        assert_eq!(arguments.subcommand_args(), ["session.rec"]);
+        let arguments = parse_args(["prog", "mirror", "/tmp/caloud.sock"]).unwrap();
+        assert_eq!(arguments.command, Command::Mirror);
+        assert_eq!(arguments.subcommand_args(), ["/tmp/caloud.sock"]);
+        let arguments = parse_args(["prog", "notify", "--title", "Build", "--say=x"]).unwrap();
+        assert_eq!(arguments.command, Command::Notify);
+        assert_eq!(arguments.subcommand_args(), ["--title", "Build", "--say=x"]);
+        assert_eq!(arguments.say_args, None);
+        let arguments = parse_args(["prog", "--say=-r 200", "say", "-v", "Kyoko"]).unwrap();
+        assert_eq!(arguments.command, Command::Say);
+        assert_eq!(arguments.subcommand_args(), ["-v", "Kyoko"]);
+        assert_eq!(arguments.say_args, Some(OsString::from("-r 200")));
+        let arguments = parse_args(["prog", "list-voices", "en_GB"]).unwrap();
+        assert_eq!(arguments.command, Command::ListVoices);
+        assert_eq!(arguments.subcommand_args(), ["en_GB"]);
+    }
+
+    #[test]
+    fn effective_config() {
+        let mut arguments = parse_args([
+            "prog",
+            "--say=-v Samantha",
+            r"--input-rewrite=\x02:\e[D",
+            "--env=A=1",
+        ])
+        .unwrap();
+        arguments.config_sources.push("CALOUD_SUMMARY".to_string());
+        insta::assert_snapshot!(arguments.effective_config(), @r#"
+        # from CALOUD_SUMMARY
+        notification-center = false
+        no-notify = false
+        say = "-v Samantha"
+        no-say = false
+        say-when = "always"
+        # translate-command is not set
+        abbreviate-speech = "hashes,paths,urls"
+        speak-emoji = "keep"
+        voice-pool = []
+        voice-assignment = "hash"
+        voiceover = false
+        speak-output = false
+        speak-questions = false
+        speak-tools = false
+        speak-final = false
+        # permission-reminder is not set
+        # progress-after is not set
+        progress-state = false
+        # stall-timeout is not set
+        sound = []
+        line-wrap = "preserve"
+        # width is not set
+        # buffer-size is not set
+        screen-reader = false
+        input-rewrite = ["\\x02:\\e[D"]
+        rewrite-file = []
+        keys = []
+        # rewrite-timeout-ms is not set
+        zwsp-after-updown-arrow = false
+        # copy-key is not set
+        # review-key is not set
+        # copy-code-blocks is not set
+        # copy-code-blocks-min-lines is not set
+        copy-code-blocks-notify = false
+        auto-respond = []
+        rate-limit-notify = false
+        rate-limit-pattern = []
+        compaction-notify = false
+        exit-notify = false
+        watch-files = []
+        git-context = false
+        # title-flash is not set
+        bell = false
+        # digest is not set
+        redact = false
+        redact-pattern = []
+        # max-title-length is not set
+        # max-message-length is not set
+        truncate = "tail"
+        mirror-send = false
+        mirror-receive = false
+        # mirror-key is not set
+        focus-policy = []
+        broadcast = false
+        notify-only = false
+        # term is not set
+        # colorterm is not set
+        # appearance is not set
+        env = ["A=1"]
+        env-file = []
+        # cwd is not set
+        # claude-path is not set
+        login-shell = false
+        # tool is not set
+        bypass-filter = false
+        # restart-on-crash is not set
+        restart-continue = false
+        # claude-session-id is not set
+        summary = false
+        # on-start is not set
+        # on-exit is not set
+        # control-socket is not set
+        # input-fifo is not set
+        # debug is not set
+        # debug-wrap is not set
+        # record is not set
+        "#);
+    }
 }
@@ -0,0 +1,159 @@
+//! `--broadcast`: post each event of the session as a distributed
+//! notification, so that local apps such as Hammerspoon or BetterTouchTool
+//! can follow it without the control socket.
+//!
+//! The names are `com.github.hirofumi.caloud.<event>`, and the user info has
+//! string values, with the `pid` of `claude` and the `session` name in each.
+
+use crate::claude_session::ClaudeSession;
+use crate::event::Notification;
+#[cfg(target_os = "macos")]
+use crate::macos::distributed_notification;
+use nix::unistd::Pid;
+use std::sync::Arc;
+
+const NAME_PREFIX: &str = "com.github.hirofumi.caloud.";
+
+/// Distributed notifications are macOS only, and `--broadcast` is turned
+/// down elsewhere.
+#[cfg(not(target_os = "macos"))]
+mod distributed_notification {
+    pub fn post(_name: &str, _user_info: &[(&str, String)]) {}
+}
+
+#[derive(Debug)]
+pub enum BroadcastEvent<'a> {
+    Started,
+    /// Posted by `claude` or caloud, as it is delivered.
+    Notification(&'a Notification),
+    Question {
+        text: &'a str,
+        is_permission_prompt: bool,
+    },
+    Exited {
+        exit_code: i32,
+    },
+}
+
+impl BroadcastEvent<'_> {
+    fn name(&self) -> String {
+        let event = match self {
+            Self::Started => "started",
+            Self::Notification(_) => "notification",
+            Self::Question { .. } => "question",
+            Self::Exited { .. } => "exited",
+        };
+        format!("{NAME_PREFIX}{event}")
+    }
+
+    fn user_info(&self) -> Vec<(&'static str, String)> {
+        let flag = |b: bool| u8::from(b).to_string();
+        match self {
+            Self::Started => Vec::new(),
+            Self::Notification(notification) => {
+                let mut user_info = vec![
+                    ("title", notification.title.clone()),
+                    ("message", notification.message.clone()),
+                    ("urgent", flag(notification.is_urgent)),
+                ];
+                user_info.extend(notification.subtitle.clone().map(|s| ("subtitle", s)));
+                // As posted: `sound=Glass;id=build`.
+                if !notification.parameters.is_empty() {
+                    let parameters: Vec<_> = notification
+                        .parameters
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect();
+                    user_info.push(("parameters", parameters.join(";")));
+                }
+                user_info
+            }
+            Self::Question {
+                text,
+                is_permission_prompt,
+            } => vec![
+                ("text", text.to_string()),
+                ("permission", flag(*is_permission_prompt)),
+            ],
+            Self::Exited { exit_code } => vec![("exit_code", exit_code.to_string())],
+        }
+    }
+}
+
+/// Posts the events of one session; cloned into each thread that has any.
+#[derive(Debug, Clone)]
+pub struct Broadcaster {
+    pid: Pid,
+    session_name: Option<String>,
+    claude_session: Arc<ClaudeSession>,
+}
+
+impl Broadcaster {
+    pub fn new(pid: Pid, session_name: Option<String>, claude_session: Arc<ClaudeSession>) -> Self {
+        Self {
+            pid,
+            session_name,
+            claude_session,
+        }
+    }
+
+    pub fn post(&self, event: BroadcastEvent<'_>) {
+        distributed_notification::post(&event.name(), &self.user_info(&event));
+    }
+
+    fn user_info(&self, event: &BroadcastEvent<'_>) -> Vec<(&'static str, String)> {
+        let mut user_info = vec![("pid", self.pid.to_string())];
+        user_info.extend(self.session_name.clone().map(|s| ("session", s)));
+        user_info.extend(
+            self.claude_session
+                .id()
+                .map(|id| ("claude_session", id.to_string())),
+        );
+        user_info.extend(event.user_info());
+        user_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events() {
+        let broadcaster = Broadcaster::new(
+            Pid::from_raw(42),
+            Some("caloud".to_string()),
+            Arc::default(),
+        );
+        let question = BroadcastEvent::Question {
+            text: "Do you want to proceed?",
+            is_permission_prompt: true,
+        };
+        assert_eq!(question.name(), "com.github.hirofumi.caloud.question");
+        assert_eq!(
+            broadcaster.user_info(&question),
+            [
+                ("pid", "42".to_string()),
+                ("session", "caloud".to_string()),
+                ("text", "Do you want to proceed?".to_string()),
+                ("permission", "1".to_string()),
+            ]
+        );
+        let notification = Notification {
+            title: "Claude Code".to_string(),
+            message: "Claude is waiting for your input".to_string(),
+            parameters: vec![("id".to_string(), "build".to_string())],
+            ..Default::default()
+        };
+        let keys: Vec<_> = BroadcastEvent::Notification(&notification)
+            .user_info()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["title", "message", "urgent", "parameters"]);
+        assert_eq!(
+            BroadcastEvent::Exited { exit_code: 1 }.user_info(),
+            [("exit_code", "1".to_string())]
+        );
+    }
+}
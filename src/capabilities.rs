@@ -0,0 +1,98 @@
+//! `caloud capabilities`: what this build supports and what was detected at
+//! run time, as `key: value` lines for scripts and bug reports.
+
+use crate::platform::{Native, Platform};
+
+/// Values of `--line-wrap`.
+const LINE_WRAP_MODES: &[&str] = &["preserve", "adjust", "auto"];
+
+/// Ways a notification can reach the user.
+#[cfg(target_os = "macos")]
+const NOTIFIERS: &[&str] = &["osc9", "notification-center"];
+#[cfg(not(target_os = "macos"))]
+const NOTIFIERS: &[&str] = &["notify-send"];
+
+/// Ways a notification can be spoken.
+#[cfg(target_os = "macos")]
+const SPEECH_BACKENDS: &[&str] = &["say", "voiceover"];
+#[cfg(not(target_os = "macos"))]
+const SPEECH_BACKENDS: &[&str] = &["tts-command"];
+
+struct Detected {
+    host_terminal: Option<String>,
+    osc9_supported: bool,
+    term_program: Option<String>,
+}
+
+pub fn run() {
+    print!(
+        "{}",
+        format_report(&Detected {
+            host_terminal: Native::host_terminal(),
+            osc9_supported: Native::is_osc9_supported(),
+            term_program: std::env::var("TERM_PROGRAM").ok(),
+        })
+    );
+}
+
+fn format_report(detected: &Detected) -> String {
+    let lines = [
+        ("version", env!("CARGO_PKG_VERSION").to_string()),
+        ("target", env!("CALOUD_BUILD_TARGET").to_string()),
+        ("platform", std::env::consts::OS.to_string()),
+        ("line_wrap_modes", LINE_WRAP_MODES.join(",")),
+        ("notifiers", NOTIFIERS.join(",")),
+        ("speech_backends", SPEECH_BACKENDS.join(",")),
+        (
+            "host_terminal",
+            detected.host_terminal.clone().unwrap_or_default(),
+        ),
+        ("osc9_supported", detected.osc9_supported.to_string()),
+        (
+            "term_program",
+            detected.term_program.clone().unwrap_or_default(),
+        ),
+    ];
+    lines
+        .iter()
+        .map(|(key, value)| format!("{key}: {value}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        #[cfg(target_os = "macos")]
+        let (notifiers, speech_backends) = (
+            "notifiers: osc9,notification-center",
+            "speech_backends: say,voiceover",
+        );
+        #[cfg(not(target_os = "macos"))]
+        let (notifiers, speech_backends) =
+            ("notifiers: notify-send", "speech_backends: tts-command");
+        let report = format_report(&Detected {
+            host_terminal: Some("com.googlecode.iterm2".to_string()),
+            osc9_supported: true,
+            term_program: None,
+        });
+        let lines: Vec<_> = report
+            .lines()
+            .filter(|line| !line.starts_with("version:") && !line.starts_with("target:"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                &format!("platform: {}", std::env::consts::OS),
+                "line_wrap_modes: preserve,adjust,auto",
+                notifiers,
+                speech_backends,
+                "host_terminal: com.googlecode.iterm2",
+                "osc9_supported: true",
+                "term_program: ",
+            ]
+        );
+    }
+}
@@ -1,41 +1,508 @@
+use crate::tool;
 use anyhow::Context;
+use nix::errno::Errno;
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
 use nix::unistd::execvp;
 use std::convert::Infallible;
-use std::ffi::{CString, NulError, OsString};
-use std::os::unix::ffi::OsStringExt;
+use std::ffi::{CString, NulError, OsStr, OsString};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 
-#[derive(Debug)]
-pub struct ClaudeCommand {
+pub const CLAUDE: &str = "claude";
+
+/// Used by `--login-shell` when `SHELL` is not set.
+const DEFAULT_SHELL: &str = "/bin/zsh";
+
+/// Run by the login shell, with the argv of `claude` as `$0` and `$@` so
+/// that nothing needs quoting.
+const LOGIN_SHELL_SCRIPT: &str = r#"exec "$0" "$@""#;
+
+/// Where installers commonly put `claude`, which may not be in the `PATH`
+/// caloud was started with, relative to the home directory if not absolute.
+const INSTALL_LOCATIONS: &[&str] = &[
+    ".npm-global/bin/claude",
+    ".bun/bin/claude",
+    ".volta/bin/claude",
+    ".local/bin/claude",
+    "/opt/homebrew/bin/claude",
+    "/usr/local/bin/claude",
+];
+
+/// npm package run through `npx`/`bunx` when no `claude` executable is installed.
+const CLAUDE_PACKAGE: &str = "@anthropic-ai/claude-code";
+
+/// The command caloud runs in the PTY: `claude`, or another tool with
+/// `--exec`.
+#[derive(Debug, Clone)]
+pub struct ChildCommand {
+    /// The name of the tool, e.g. `claude` even when run through `npx`.
+    tool: String,
     argv: Vec<CString>,
+    env: Vec<(OsString, Option<OsString>)>,
+    cwd: Option<PathBuf>,
+    login_shell: bool,
 }
 
-impl TryFrom<Vec<OsString>> for ClaudeCommand {
+impl TryFrom<Vec<OsString>> for ChildCommand {
     type Error = anyhow::Error;
 
     fn try_from(argv: Vec<OsString>) -> Result<Self, Self::Error> {
         anyhow::ensure!(!argv.is_empty(), "argv cannot be empty");
 
+        let tool = Path::new(&argv[0])
+            .file_name()
+            .unwrap_or(argv[0].as_os_str())
+            .to_string_lossy()
+            .into_owned();
         let argv_cstring = argv
             .into_iter()
             .map(|arg| CString::new(arg.into_vec()))
             .collect::<Result<Vec<_>, NulError>>()
             .context("argument contains null byte")?;
 
-        Ok(Self { argv: argv_cstring })
+        Ok(Self {
+            tool,
+            argv: argv_cstring,
+            env: Vec::new(),
+            cwd: None,
+            login_shell: false,
+        })
     }
 }
 
-impl ClaudeCommand {
+impl std::fmt::Display for ChildCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let argv = self.argv.iter().map(|arg| arg.to_string_lossy());
+        f.write_str(&shell_words::join(argv))
+    }
+}
+
+impl ChildCommand {
+    /// Name the tool when the executable is not named after it.
+    pub fn set_tool(&mut self, tool: &str) {
+        self.tool = tool.to_string();
+    }
+
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
     pub fn should_bypass_pty(&self) -> bool {
-        self.argv.iter().skip(1).any(|arg| {
-            matches!(
-                arg.to_str().ok(),
-                Some("-p" | "--print" | "-v" | "--version" | "-h" | "--help"),
-            )
-        })
+        let args = self.argv[1..]
+            .iter()
+            .map(|arg| arg.to_str().unwrap_or_default());
+        tool::is_non_interactive(&self.tool, args)
+    }
+
+    /// For `--restart-continue`: the same command continuing the most recent
+    /// conversation, unless it already continues or resumes one.
+    pub fn continuing(&self) -> Self {
+        let mut command = self.clone();
+        let continues = self.argv[1..].iter().any(|arg| {
+            matches!(arg.to_bytes(), b"-c" | b"--continue" | b"-r" | b"--resume")
+                || arg.to_bytes().starts_with(b"--resume=")
+        });
+        if !continues {
+            command.argv.push(c"--continue".into());
+        }
+        command
+    }
+
+    /// Set (`Some`) or remove (`None`) an environment variable for the child only.
+    pub fn set_env(&mut self, key: impl Into<OsString>, value: Option<OsString>) {
+        self.env.push((key.into(), value));
+    }
+
+    pub fn set_cwd(&mut self, cwd: PathBuf) {
+        self.cwd = Some(cwd);
+    }
+
+    /// Start `claude` through `$SHELL -l`, so that the `PATH` set up by
+    /// `.zprofile` or version managers such as nvm applies.
+    pub fn set_login_shell(&mut self) {
+        self.login_shell = true;
     }
 
     pub fn exec(&self) -> anyhow::Result<Infallible> {
-        execvp(&self.argv[0], &self.argv).context("execvp() failed")
+        if let Some(cwd) = &self.cwd {
+            std::env::set_current_dir(cwd)
+                .with_context(|| format!("failed to change directory: {}", cwd.display()))?;
+        }
+        for (key, value) in &self.env {
+            // SAFETY: exec() runs either in the freshly forked child or before
+            // any thread is spawned, so no other thread can observe the change.
+            unsafe {
+                match value {
+                    Some(value) => std::env::set_var(key, value),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+        let argv = self.exec_argv()?;
+        execvp(&argv[0], &argv).context("execvp() failed")
+    }
+
+    /// Start the child without a PTY, with its stdout piped to caloud, for
+    /// `--bypass-filter`.
+    pub fn spawn_with_piped_stdout(&self) -> anyhow::Result<Child> {
+        let argv = self.exec_argv()?;
+        let mut command = Command::new(OsStr::from_bytes(argv[0].as_bytes()));
+        command
+            .args(
+                argv[1..]
+                    .iter()
+                    .map(|arg| OsStr::from_bytes(arg.as_bytes())),
+            )
+            .stdout(Stdio::piped());
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        command
+            .spawn()
+            .with_context(|| format!("failed to start {}", self.tool))
+    }
+
+    /// `argv`, or through the login shell with `--login-shell`.
+    fn exec_argv(&self) -> anyhow::Result<Vec<CString>> {
+        if !self.login_shell {
+            return Ok(self.argv.clone());
+        }
+        let shell = std::env::var_os("SHELL")
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| DEFAULT_SHELL.into());
+        login_shell_argv(shell, &self.argv)
+    }
+}
+
+/// Tells the parent whether `exec()` failed in the child forked to run
+/// `claude`, which would otherwise only exit: the child writes the error to a
+/// pipe that a successful exec closes, being close-on-exec.
+pub struct ExecHandshake {
+    read: OwnedFd,
+    write: OwnedFd,
+}
+
+/// Why `claude` could not be started, as reported by the child.
+#[derive(Debug, PartialEq)]
+pub struct ExecFailure {
+    pub errno: Option<Errno>,
+    pub message: String,
+}
+
+impl ExecHandshake {
+    /// Call before forking.
+    pub fn new() -> anyhow::Result<Self> {
+        let (read, write) = nix::unistd::pipe().context("pipe() failed")?;
+        for fd in [&read, &write] {
+            fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).context("fcntl() failed")?;
+        }
+        Ok(Self { read, write })
+    }
+
+    /// In the child: exec `claude_command`, or report why not and exit 127,
+    /// as a shell does for a command it cannot run.
+    pub fn exec(self, claude_command: &ChildCommand) -> ! {
+        drop(self.read);
+        let Err(e) = claude_command.exec();
+        let errno = e.chain().find_map(|cause| {
+            cause.downcast_ref::<Errno>().copied().or_else(|| {
+                let raw = cause.downcast_ref::<io::Error>()?.raw_os_error()?;
+                Some(Errno::from_raw(raw))
+            })
+        });
+        let mut report = (errno.map_or(0, |errno| errno as i32))
+            .to_le_bytes()
+            .to_vec();
+        report.extend_from_slice(format!("{e:#}").as_bytes());
+        let _ = File::from(self.write).write_all(&report);
+        // SAFETY: _exit() is async-signal-safe and skips the atexit handlers
+        // and buffers inherited from the parent.
+        unsafe { nix::libc::_exit(127) }
+    }
+
+    /// In the parent: wait until the child has exec'd `claude`, or return why
+    /// it could not.
+    pub fn wait(self) -> io::Result<Option<ExecFailure>> {
+        drop(self.write);
+        let mut report = Vec::new();
+        File::from(self.read).read_to_end(&mut report)?;
+        Ok(parse_report(&report))
+    }
+}
+
+fn parse_report(report: &[u8]) -> Option<ExecFailure> {
+    let (errno, message) = report.split_first_chunk::<4>()?;
+    let errno = i32::from_le_bytes(*errno);
+    Some(ExecFailure {
+        errno: (errno != 0).then(|| Errno::from_raw(errno)),
+        message: String::from_utf8_lossy(message).into_owned(),
+    })
+}
+
+impl ExecFailure {
+    /// What to print when `claude_command` could not be started: the error,
+    /// and for a missing executable, where it was looked for and what to do.
+    pub fn report(&self, claude_command: &ChildCommand) -> String {
+        let mut report = format!(
+            "caloud: failed to start {}: {}\n  command: {claude_command}\n",
+            claude_command.tool(),
+            self.message
+        );
+        if self.errno != Some(Errno::ENOENT) {
+            return report;
+        }
+        let path = std::env::var("PATH").unwrap_or_default();
+        report += &format!("  searched PATH: {path}\n");
+        if claude_command.tool() != CLAUDE {
+            return report;
+        }
+        let home = std::env::home_dir();
+        let found: Vec<PathBuf> = INSTALL_LOCATIONS
+            .iter()
+            .filter_map(|location| match Path::new(location).is_absolute() {
+                true => Some(PathBuf::from(location)),
+                false => Some(home.as_ref()?.join(location)),
+            })
+            .filter(|path| is_executable(path))
+            .collect();
+        match found.first() {
+            Some(path) => {
+                report += &format!(
+                    "  found: {}\n  hint: pass it as the first argument, e.g. `caloud -- {}`, or set --claude-path\n",
+                    found
+                        .iter()
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    path.display()
+                );
+            }
+            None => {
+                report += &format!(
+                    "  hint: install it with `npm install -g {CLAUDE_PACKAGE}`, or pass its path as the first argument, e.g. `caloud -- ~/.npm-global/bin/claude`; \
+                     if it is installed by nvm or in .zprofile, try --login-shell\n"
+                );
+            }
+        }
+        report
+    }
+}
+
+/// Resolve the command line prefix used to start `claude`.
+///
+/// `program` is the first positional argument, if any.  An explicit path is
+/// used as-is after checking that it is executable, a bare name is looked up
+/// in `PATH`, and a missing or unresolvable `claude` falls back to
+/// `claude_path`, the native installer location (`~/.claude/local/claude`),
+/// and finally `npx`/`bunx`.
+pub fn discover(
+    program: Option<&OsStr>,
+    claude_path: Option<&Path>,
+) -> anyhow::Result<Vec<OsString>> {
+    match program {
+        Some(program) if Path::new(program).components().count() > 1 => {
+            anyhow::ensure!(
+                is_executable(Path::new(program)),
+                "{} is not an executable file",
+                Path::new(program).display(),
+            );
+            // Absolute, so that --cwd does not change what a relative path refers to.
+            return Ok(vec![std::path::absolute(program)?.into()]);
+        }
+        Some(program) if program != CLAUDE => {
+            anyhow::ensure!(
+                find_in_path(program).is_some(),
+                "{} not found in PATH",
+                program.display(),
+            );
+            return Ok(vec![program.to_owned()]);
+        }
+        _ => {}
+    }
+
+    if let Some(claude_path) = claude_path {
+        anyhow::ensure!(
+            is_executable(claude_path),
+            "--claude-path {} is not an executable file",
+            claude_path.display(),
+        );
+        return Ok(vec![claude_path.into()]);
+    }
+    if find_in_path(OsStr::new(CLAUDE)).is_some() {
+        return Ok(vec![CLAUDE.into()]);
+    }
+    if let Some(local) = std::env::home_dir().map(|home| home.join(".claude/local/claude"))
+        && is_executable(&local)
+    {
+        return Ok(vec![local.into()]);
+    }
+    // Without `--yes`, so that `npx` asks before it downloads the package
+    // the first time.
+    if find_in_path(OsStr::new("npx")).is_some() {
+        return Ok(vec!["npx".into(), CLAUDE_PACKAGE.into()]);
+    }
+    if find_in_path(OsStr::new("bunx")).is_some() {
+        return Ok(vec!["bunx".into(), CLAUDE_PACKAGE.into()]);
+    }
+
+    anyhow::bail!(
+        "claude not found (searched PATH and ~/.claude/local/claude, and neither npx nor bunx is available); \
+         install it with `npm install -g {CLAUDE_PACKAGE}`, or pass its path via --claude-path or as the first argument"
+    )
+}
+
+/// `SHELL -l -c 'exec "$0" "$@"' ARGV...`
+fn login_shell_argv(shell: OsString, argv: &[CString]) -> anyhow::Result<Vec<CString>> {
+    let mut login_shell_argv = vec![
+        CString::new(shell.into_vec()).context("SHELL contains null byte")?,
+        c"-l".into(),
+        c"-c".into(),
+        CString::new(LOGIN_SHELL_SCRIPT)?,
+    ];
+    login_shell_argv.extend_from_slice(argv);
+    Ok(login_shell_argv)
+}
+
+fn find_in_path(name: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_path() {
+        let argv = discover(Some(OsStr::new("/bin/sh")), None).unwrap();
+        assert_eq!(argv, [OsString::from("/bin/sh")]);
+    }
+
+    #[test]
+    fn explicit_path_not_executable() {
+        assert!(discover(Some(OsStr::new("/nonexistent/claude")), None).is_err());
+    }
+
+    #[test]
+    fn claude_path_option() {
+        let argv = discover(None, Some(Path::new("/bin/sh"))).unwrap();
+        assert_eq!(argv, [OsString::from("/bin/sh")]);
+    }
+
+    #[test]
+    fn login_shell_passes_arguments_as_is() {
+        let argv: Vec<CString> = ["printf", "%s|", "a b", "c'd", "$HOME", "\\"]
+            .into_iter()
+            .map(|arg| CString::new(arg).unwrap())
+            .collect();
+        let argv = login_shell_argv("/bin/sh".into(), &argv).unwrap();
+        let output = std::process::Command::new(argv[0].to_str().unwrap())
+            .args(argv[1..].iter().map(|arg| arg.to_str().unwrap()))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a b|c'd|$HOME|\\|");
+    }
+
+    #[test]
+    fn spawn_with_piped_stdout() {
+        let argv = ["/bin/sh", "-c", r#"echo "$CALOUD_TEST_VAR"; pwd"#];
+        let mut command =
+            ChildCommand::try_from(argv.into_iter().map(OsString::from).collect::<Vec<_>>())
+                .unwrap();
+        command.set_env("CALOUD_TEST_VAR", Some("piped".into()));
+        command.set_cwd(PathBuf::from("/"));
+        let mut child = command.spawn_with_piped_stdout().unwrap();
+        let mut output = String::new();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+        assert_eq!(output, "piped\n/\n");
+    }
+
+    #[test]
+    fn continuing() {
+        let command = |argv: &[&str]| {
+            ChildCommand::try_from(argv.iter().map(OsString::from).collect::<Vec<_>>()).unwrap()
+        };
+        assert_eq!(
+            command(&["claude", "--model", "opus"])
+                .continuing()
+                .to_string(),
+            "claude --model opus --continue"
+        );
+        assert_eq!(
+            command(&["claude", "-r", "abc"]).continuing().to_string(),
+            "claude -r abc"
+        );
+        assert_eq!(
+            command(&["claude", "--continue"]).continuing().to_string(),
+            "claude --continue"
+        );
+    }
+
+    #[test]
+    fn exec_failure_is_reported() {
+        let mut report = (Errno::ENOENT as i32).to_le_bytes().to_vec();
+        report.extend_from_slice(b"execvp() failed: ENOENT: No such file or directory");
+        let failure = parse_report(&report).unwrap();
+        assert_eq!(failure.errno, Some(Errno::ENOENT));
+        let claude_command =
+            ChildCommand::try_from(vec![OsString::from("claude"), "-c".into()]).unwrap();
+        let report = failure.report(&claude_command);
+        assert!(report.starts_with(
+            "caloud: failed to start claude: execvp() failed: ENOENT: No such file or directory\n  command: claude -c\n  searched PATH: "
+        ));
+        assert!(report.contains("  hint: "));
+        assert_eq!(parse_report(b""), None);
+    }
+
+    #[test]
+    fn exec_handshake() {
+        // Closed without a report, as by a successful exec.
+        let handshake = ExecHandshake::new().unwrap();
+        assert_eq!(handshake.wait().unwrap(), None);
+    }
+
+    #[test]
+    fn bypass_pty_by_tool() {
+        let command = |argv: &[&str]| {
+            ChildCommand::try_from(argv.iter().map(OsString::from).collect::<Vec<_>>()).unwrap()
+        };
+        assert!(command(&["/usr/local/bin/claude", "-p", "hi"]).should_bypass_pty());
+        assert!(!command(&["claude", "--resume"]).should_bypass_pty());
+        let mut npx = command(&["npx", CLAUDE_PACKAGE, "-v"]);
+        assert_eq!(npx.tool(), "npx");
+        npx.set_tool(CLAUDE);
+        assert!(npx.should_bypass_pty());
+        // -v is not --version for every tool.
+        assert!(!command(&["aider", "-v"]).should_bypass_pty());
+        assert!(command(&["aider", "--help"]).should_bypass_pty());
+    }
+
+    #[test]
+    fn bare_name_in_path() {
+        let argv = discover(Some(OsStr::new("sh")), None).unwrap();
+        assert_eq!(argv, [OsString::from("sh")]);
     }
 }
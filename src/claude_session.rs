@@ -0,0 +1,193 @@
+//! The session ID of Claude Code, attached to the records of caloud so that
+//! they can be joined with the transcripts of `claude`, which are named after
+//! it: `~/.claude/projects/<cwd>/<session ID>.jsonl`.
+//!
+//! It is `--claude-session-id` if given, or the `--session-id` or `--resume`
+//! given to `claude`.  Otherwise it is unknown until `claude` writes the
+//! transcript of the session, and found as the newest one written since
+//! caloud started.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the transcripts are looked at while the ID is unknown.
+const DETECTION_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct ClaudeSession {
+    id: OnceLock<String>,
+    detection: Option<Detection>,
+}
+
+#[derive(Debug)]
+struct Detection {
+    transcripts: PathBuf,
+    since: SystemTime,
+    last_attempt: Mutex<Option<Instant>>,
+}
+
+impl ClaudeSession {
+    /// The session of `claude` run as `claude_argv` in `cwd`, or `id`.
+    pub fn new(id: Option<String>, claude_argv: &[OsString], cwd: &Path) -> Self {
+        let id = id.or_else(|| id_from_args(claude_argv));
+        let detection = match id {
+            Some(_) => None,
+            None => config_dir().map(|dir| Detection {
+                transcripts: dir.join("projects").join(project_name(cwd)),
+                since: SystemTime::now(),
+                last_attempt: Mutex::default(),
+            }),
+        };
+        Self {
+            id: id.map(OnceLock::from).unwrap_or_default(),
+            detection,
+        }
+    }
+
+    /// The ID, if known yet.
+    pub fn id(&self) -> Option<&str> {
+        if let Some(id) = self.id.get() {
+            return Some(id);
+        }
+        let detection = self.detection.as_ref()?;
+        {
+            let mut last_attempt = detection
+                .last_attempt
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if last_attempt.is_some_and(|at| at.elapsed() < DETECTION_INTERVAL) {
+                return None;
+            }
+            *last_attempt = Some(Instant::now());
+        }
+        let id = newest_transcript(&detection.transcripts, detection.since)?;
+        Some(self.id.get_or_init(|| id))
+    }
+}
+
+/// `--session-id ID` or `--resume ID`, unless `--fork-session` starts a new
+/// session from the one resumed.
+fn id_from_args(claude_argv: &[OsString]) -> Option<String> {
+    let args: Vec<&str> = claude_argv.iter().filter_map(|arg| arg.to_str()).collect();
+    let forks = args.contains(&"--fork-session");
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) => (option, Some(value)),
+            None => (arg, None),
+        };
+        let is_session_option = match option {
+            "--session-id" => true,
+            "--resume" | "-r" => !forks,
+            _ => false,
+        };
+        if !is_session_option {
+            continue;
+        }
+        // `--resume` alone opens a picker.
+        let value = value.or_else(|| args.next_if(|next| !next.starts_with('-')));
+        if let Some(value) = value.filter(|value| is_id(value)) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Whether `id` is safe as the name of a transcript.
+pub fn is_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// `$CLAUDE_CONFIG_DIR`, or `~/.claude`.
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::home_dir().map(|home| home.join(".claude")))
+}
+
+/// `/Users/me/src/caloud` → `-Users-me-src-caloud`, as `claude` names the
+/// directory of the transcripts of a project.
+fn project_name(cwd: &Path) -> String {
+    cwd.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// The session ID of the transcript in `dir` written last, if since `since`.
+fn newest_transcript(dir: &Path, since: SystemTime) -> Option<String> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let id = path
+                .extension()
+                .filter(|extension| *extension == "jsonl")
+                .and_then(|_| path.file_stem()?.to_str())?;
+            let modified = path.metadata().ok()?.modified().ok()?;
+            (is_id(id) && modified >= since).then(|| (modified, id.to_string()))
+        })
+        .max()
+        .map(|(_, id)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn id_from_claude_args() {
+        let id = "0b6f3c1e-8a57-4d2b-9c41-5e7a2f9d8b60";
+        assert_eq!(
+            id_from_args(&argv(&["claude", "--session-id", id])).as_deref(),
+            Some(id)
+        );
+        assert_eq!(
+            id_from_args(&argv(&["claude", &format!("--resume={id}")])).as_deref(),
+            Some(id)
+        );
+        assert_eq!(id_from_args(&argv(&["claude", "-r", "--verbose"])), None);
+        assert_eq!(
+            id_from_args(&argv(&["claude", "-r", id, "--fork-session"])),
+            None
+        );
+        assert_eq!(
+            project_name(Path::new("/Users/me/src/my.app")),
+            "-Users-me-src-my-app"
+        );
+    }
+
+    #[test]
+    fn id_from_transcript() {
+        let dir = std::env::temp_dir().join(format!("caloud-session-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let since = SystemTime::now() - Duration::from_secs(60);
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        assert_eq!(newest_transcript(&dir, since), None);
+        fs::write(dir.join("0b6f3c1e.jsonl"), "{}\n").unwrap();
+        assert_eq!(newest_transcript(&dir, since).as_deref(), Some("0b6f3c1e"));
+        assert_eq!(
+            newest_transcript(&dir, SystemTime::now() + Duration::from_secs(60)),
+            None
+        );
+
+        let session = ClaudeSession {
+            id: OnceLock::new(),
+            detection: Some(Detection {
+                transcripts: dir.clone(),
+                since,
+                last_attempt: Mutex::default(),
+            }),
+        };
+        assert_eq!(session.id(), Some("0b6f3c1e"));
+        assert_eq!(ClaudeSession::default().id(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
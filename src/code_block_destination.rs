@@ -0,0 +1,54 @@
+//! Where `--copy-code-blocks` puts the blocks that
+//! [`CodeBlockDetector`](crate::tty_text::code_block::CodeBlockDetector) finds.
+
+use crate::macos::pasteboard;
+use anyhow::Context;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Where completed code blocks go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeBlockDestination {
+    /// Replace the contents of the pasteboard.
+    Pasteboard,
+    /// Append to a file, as a history of the blocks.
+    File(PathBuf),
+}
+
+impl CodeBlockDestination {
+    /// `pasteboard` or a path.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "" => anyhow::bail!("expected pasteboard or a path"),
+            "pasteboard" => Ok(Self::Pasteboard),
+            path => Ok(Self::File(PathBuf::from(path))),
+        }
+    }
+
+    pub fn save(&self, block: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Pasteboard => pasteboard::copy(block),
+            Self::File(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("failed to open {}", path.display()))?;
+                // Fenced again so that blocks in the file stay apart.
+                write!(file, "```\n{block}\n```\n")
+                    .with_context(|| format!("failed to write to {}", path.display()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CodeBlockDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pasteboard => write!(f, "pasteboard"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Parser for configuration files.
+//!
+//! Files are TOML 1.1, of which options take the top-level keys: their
+//! values are strings, booleans, integers, or arrays of those.  Tables,
+//! whether `[table]` headers, inline tables, or dotted keys, have no option
+//! to stand for and are rejected.
+
+use std::fmt;
+use std::path::PathBuf;
+use toml::de::{DeTable, DeValue};
+
+/// Looked up in the current directory; overrides the user configuration.
+pub const PROJECT_CONFIG_FILE: &str = ".caloud.toml";
+
+/// `$XDG_CONFIG_HOME/caloud/config.toml`, or `~/.config/caloud/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::home_dir().map(|home| home.join(".config")))?;
+    Some(config_home.join("caloud").join("config.toml"))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub line_number: usize,
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Boolean(bool),
+    Integer(i64),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// The option values this entry stands for: one per array element.
+    pub fn to_option_values(&self) -> Vec<String> {
+        match self {
+            Value::String(s) => vec![s.clone()],
+            Value::Boolean(b) => vec![b.to_string()],
+            Value::Integer(n) => vec![n.to_string()],
+            Value::Array(values) => values.iter().flat_map(Value::to_option_values).collect(),
+        }
+    }
+}
+
+/// Formats the value as TOML that [`parse`] reads back.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+                        c => write!(f, "{c}")?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            Value::Boolean(b) => write!(f, "{b}"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+pub fn parse(s: &str) -> Result<Vec<Entry>, ParseError> {
+    let line_number = |offset: usize| 1 + s[..offset].matches('\n').count();
+    let table = DeTable::parse(s).map_err(|e| ParseError {
+        line_number: line_number(e.span().map_or(0, |span| span.start)),
+        kind: ParseErrorKind::Syntax(e.message().to_string()),
+    })?;
+    let mut entries = table
+        .into_inner()
+        .into_iter()
+        .map(|(key, value)| {
+            let line_number = line_number(key.span().start);
+            let value =
+                convert(value.into_inner()).map_err(|kind| ParseError { line_number, kind })?;
+            Ok(Entry {
+                line_number,
+                key: key.into_inner().into_owned(),
+                value,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // In the order of the file rather than of the keys.
+    entries.sort_by_key(|entry| entry.line_number);
+    Ok(entries)
+}
+
+fn convert(value: DeValue<'_>) -> Result<Value, ParseErrorKind> {
+    match value {
+        DeValue::String(s) => Ok(Value::String(s.into_owned())),
+        DeValue::Boolean(b) => Ok(Value::Boolean(b)),
+        DeValue::Integer(n) => i64::from_str_radix(n.as_str(), n.radix())
+            .map(Value::Integer)
+            .map_err(|_| ParseErrorKind::InvalidValue),
+        DeValue::Array(values) => values
+            .into_iter()
+            .map(|value| convert(value.into_inner()))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        DeValue::Table(_) => Err(ParseErrorKind::TableNotSupported),
+        DeValue::Float(_) | DeValue::Datetime(_) => Err(ParseErrorKind::InvalidValue),
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line_number: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// Not TOML, as reported by the parser.
+    Syntax(String),
+    InvalidValue,
+    TableNotSupported,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.kind)
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Syntax(message) => f.write_str(message),
+            ParseErrorKind::InvalidValue => {
+                write!(f, "expected a string, boolean, integer, or array")
+            }
+            ParseErrorKind::TableNotSupported => write!(f, "tables are not supported"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse_file() {
+        let entries = parse(indoc! {r#"
+            # comment
+            say = "-v Samantha"  # trailing comment
+            line-wrap = 'adjust'
+            notification_center = true
+
+            input-rewrite = [
+                "\u0002:\e[D",  # Ctrl+B -> Left
+                '\x1bb:\x02',
+            ]
+            count = 1_000
+        "#})
+        .unwrap();
+        assert_eq!(
+            entries,
+            [
+                (2, "say", Value::String("-v Samantha".to_string())),
+                (3, "line-wrap", Value::String("adjust".to_string())),
+                (4, "notification_center", Value::Boolean(true)),
+                (
+                    6,
+                    "input-rewrite",
+                    Value::Array(vec![
+                        Value::String("\x02:\x1b[D".to_string()),
+                        Value::String(r"\x1bb:\x02".to_string()),
+                    ]),
+                ),
+                (10, "count", Value::Integer(1000)),
+            ]
+            .map(|(line_number, key, value)| Entry {
+                line_number,
+                key: key.to_string(),
+                value,
+            }),
+        );
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let error = |s| parse(s).unwrap_err().to_string();
+        assert_eq!(
+            error("a = 1\n\nb\n"),
+            "line 3: key with no value, expected `=`"
+        );
+        assert_eq!(error("a = 1\na = 2\n"), "line 2: duplicate key");
+        assert_eq!(
+            error("a = 1\nb = 'x\n"),
+            "line 2: invalid literal string, expected `'`"
+        );
+        assert_eq!(
+            error("a = 1\n[table]\nb = 2\n"),
+            "line 2: tables are not supported"
+        );
+        assert_eq!(
+            error("a = 1\nb.c = 2\n"),
+            "line 2: tables are not supported"
+        );
+        assert_eq!(error("a = { b = 2 }\n"), "line 1: tables are not supported");
+        assert_eq!(
+            error("a = 1.5\n"),
+            "line 1: expected a string, boolean, integer, or array"
+        );
+        assert_eq!(
+            error("a = yes\n"),
+            "line 1: string values must be quoted, expected literal string"
+        );
+    }
+
+    #[test]
+    fn multi_line_strings() {
+        let entries = parse(indoc! {r#"
+            a = """
+            x
+            y"""
+            b = '''
+            \e'''
+        "#})
+        .unwrap();
+        assert_eq!(entries[0].value, Value::String("x\ny".to_string()));
+        assert_eq!(entries[1].line_number, 4);
+        assert_eq!(entries[1].value, Value::String(r"\e".to_string()));
+    }
+
+    #[test]
+    fn option_values() {
+        let value = Value::Array(vec![Value::String("a".to_string()), Value::Integer(1)]);
+        assert_eq!(value.to_option_values(), ["a", "1"]);
+        assert_eq!(Value::Boolean(false).to_option_values(), ["false"]);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let value = Value::Array(vec![
+            Value::String("\x1b[D \"quoted\" back\\slash\n".to_string()),
+            Value::Boolean(true),
+            Value::Integer(-3),
+        ]);
+        let formatted = format!("key = {value}\n");
+        assert_eq!(
+            formatted,
+            "key = [\"\\u001b[D \\\"quoted\\\" back\\\\slash\\n\", true, -3]\n"
+        );
+        assert_eq!(parse(&formatted).unwrap()[0].value, value);
+    }
+}
@@ -0,0 +1,300 @@
+//! `--control-socket`: a Unix domain socket for querying a running session.
+//!
+//! A client connects, writes one command line, reads the response until EOF:
+//!
+//! ```text
+//! $ echo status | nc -U /tmp/caloud.sock
+//! pid: 12345
+//! uptime_secs: 63
+//! ```
+//!
+//! Commands:
+//!
+//! - `status`: the process ID, uptime, and resource usage of `claude`, with
+//!   `--git-context`, the repository and branch it works in, and the session
+//!   ID of Claude Code once known
+//! - `copy`: copy the last assistant message to the pasteboard
+//! - `titles`: the last 100 terminal titles set by `claude`, with the Unix
+//!   time each was set at
+//! - `watch`: what the session writes to its terminal from now on, for
+//!   `caloud mirror`, until either side closes the connection
+//!
+//! Responses are `key: value` lines; failures are a single `error: ...` line.
+
+use crate::claude_session::ClaudeSession;
+use crate::git::GitRepository;
+use crate::macos::pasteboard;
+use crate::macos::resource_usage::{PeakMemory, ResourceUsage};
+use crate::supervisor::ClaudePid;
+use crate::title_history::TitleHistory;
+use crate::tty_text::message::LastMessage;
+use crate::viewer::OutputTap;
+use anyhow::{Context, bail};
+use nix::pty::Winsize;
+use nix::unistd::Pid;
+use std::fs::Permissions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the PTY is a row short for `watch`; `claude` ignores a size
+/// that is changed back before it has redrawn.
+const REDRAW_DELAY: Duration = Duration::from_millis(100);
+
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+/// What the commands report about and act on.
+pub struct Session {
+    /// The current `claude`, even after a restart with `--restart-on-crash`.
+    pub pid: ClaudePid,
+    /// The PTY of `claude`, to make it redraw.
+    pub pty: OwnedFd,
+    pub started_at: Instant,
+    pub last_message: LastMessage,
+    pub title_history: TitleHistory,
+    pub git_repository: Option<GitRepository>,
+    pub output_tap: OutputTap,
+    pub claude_session: Arc<ClaudeSession>,
+}
+
+impl ControlServer {
+    /// Bind `path`, replacing a socket file left behind by a session that is
+    /// gone, but no other kind of file, for the current user alone.
+    pub fn bind(path: PathBuf) -> anyhow::Result<Self> {
+        let listener = match UnixListener::bind(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                let is_socket = std::fs::symlink_metadata(&path)
+                    .with_context(|| format!("failed to stat {}", path.display()))?
+                    .file_type()
+                    .is_socket();
+                if !is_socket {
+                    bail!("--control-socket is not a socket: {}", path.display());
+                }
+                if UnixStream::connect(&path).is_ok() {
+                    bail!(
+                        "--control-socket is in use by another session: {}",
+                        path.display()
+                    );
+                }
+                std::fs::remove_file(&path).with_context(|| {
+                    format!("failed to remove stale socket: {}", path.display())
+                })?;
+                UnixListener::bind(&path)
+            }
+            result => result,
+        }
+        .with_context(|| format!("failed to bind --control-socket: {}", path.display()))?;
+        // `watch` hands out everything the session shows, so only the user
+        // may connect, whatever the umask.
+        std::fs::set_permissions(&path, Permissions::from_mode(0o600))
+            .with_context(|| format!("failed to restrict --control-socket: {}", path.display()))?;
+        Ok(Self { listener, path })
+    }
+
+    /// Serve requests on a background thread.  The socket file is removed when
+    /// the returned guard is dropped.
+    pub fn spawn(self, session: Session) -> SocketFileGuard {
+        let guard = SocketFileGuard(self.path);
+        let listener = self.listener;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = serve(stream, &session);
+            }
+        });
+        guard
+    }
+}
+
+pub struct SocketFileGuard(PathBuf);
+
+impl Drop for SocketFileGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn serve(stream: UnixStream, session: &Session) -> io::Result<()> {
+    // A client that never sends a newline must not block the others for long.
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut command = String::new();
+    BufReader::new(&stream).read_line(&mut command)?;
+    let response = match command.trim() {
+        "watch" => {
+            watch(stream, session);
+            return Ok(());
+        }
+        "status" => {
+            let pid = session.pid.get();
+            let mut status = format_status(
+                pid,
+                session.started_at.elapsed(),
+                ResourceUsage::of_process(pid).ok().as_ref(),
+            );
+            if let Some(repository) = &session.git_repository {
+                status += &format_repository(repository.name(), repository.branch().as_deref());
+            }
+            if let Some(id) = session.claude_session.id() {
+                status += &format!("claude_session: {id}\n");
+            }
+            status
+        }
+        "copy" => match copy_last_message(&session.last_message) {
+            Ok(chars) => format!("copied_chars: {chars}\n"),
+            Err(e) => format!("error: {e:#}\n"),
+        },
+        "titles" => session.title_history.format(),
+        command => format!("error: unknown command: {command}\n"),
+    };
+    (&stream).write_all(response.as_bytes())
+}
+
+/// Stream the output to `stream` on another thread, so that the other
+/// commands are still answered.
+fn watch(mut stream: UnixStream, session: &Session) {
+    let output = session.output_tap.subscribe();
+    let pty = session.pty.try_clone();
+    thread::spawn(move || {
+        if let Ok(pty) = pty {
+            redraw(&pty);
+        }
+        for data in output {
+            if stream.write_all(&data).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// `claude` only redraws what changes, which makes no sense on its own, and
+/// draws everything again only when its size really changes: shrink the PTY
+/// by a row, and once `claude` has had the time to notice, put it back.
+fn redraw(pty: &OwnedFd) {
+    let Ok(winsize) = crate::get_winsize(pty) else {
+        return;
+    };
+    if winsize.ws_row < 2 {
+        return;
+    }
+    let shrunk = Winsize {
+        ws_row: winsize.ws_row - 1,
+        ..winsize
+    };
+    if crate::set_winsize(pty, &shrunk).is_err() {
+        return;
+    }
+    thread::sleep(REDRAW_DELAY);
+    // Unless the terminal has been resized in the meantime.
+    if crate::get_winsize(pty)
+        .is_ok_and(|current| (current.ws_row, current.ws_col) == (shrunk.ws_row, shrunk.ws_col))
+    {
+        let _ = crate::set_winsize(pty, &winsize);
+    }
+}
+
+/// Copy the last message, returning its length in characters.
+pub fn copy_last_message(last_message: &LastMessage) -> anyhow::Result<usize> {
+    let Some(message) = last_message.get() else {
+        bail!("no message to copy yet");
+    };
+    pasteboard::copy(&message)?;
+    Ok(message.chars().count())
+}
+
+fn format_status(pid: Pid, uptime: Duration, usage: Option<&ResourceUsage>) -> String {
+    let mut status = format!("pid: {pid}\nuptime_secs: {}\n", uptime.as_secs());
+    // Not available once the child has exited.
+    if let Some(usage) = usage {
+        let (key, bytes) = match usage.peak_memory {
+            PeakMemory::Rss(bytes) => ("max_rss_bytes", bytes),
+            PeakMemory::Footprint(bytes) => ("max_footprint_bytes", bytes),
+        };
+        status += &format!(
+            "cpu_user_secs: {:.3}\ncpu_system_secs: {:.3}\n{key}: {bytes}\n",
+            usage.user_time.as_secs_f64(),
+            usage.system_time.as_secs_f64(),
+        );
+    }
+    status
+}
+
+fn format_repository(name: &str, branch: Option<&str>) -> String {
+    let mut lines = format!("repository: {name}\n");
+    // Not available if `HEAD` cannot be read.
+    if let Some(branch) = branch {
+        lines += &format!("branch: {branch}\n");
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn status() {
+        let usage = ResourceUsage {
+            user_time: Duration::from_millis(1500),
+            system_time: Duration::from_millis(250),
+            peak_memory: PeakMemory::Footprint(1 << 20),
+        };
+        assert_eq!(
+            format_status(Pid::from_raw(42), Duration::from_secs(63), Some(&usage)),
+            indoc! {"
+                pid: 42
+                uptime_secs: 63
+                cpu_user_secs: 1.500
+                cpu_system_secs: 0.250
+                max_footprint_bytes: 1048576
+            "},
+        );
+        assert_eq!(
+            format_status(Pid::from_raw(42), Duration::from_secs(63), None),
+            "pid: 42\nuptime_secs: 63\n",
+        );
+    }
+
+    #[test]
+    fn bind_replaces_only_stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("caloud-control-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "keep").unwrap();
+        let error = ControlServer::bind(notes.clone()).err().unwrap();
+        assert!(format!("{error:#}").contains("is not a socket"));
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "keep");
+
+        let stale = dir.join("stale.sock");
+        drop(UnixListener::bind(&stale).unwrap());
+        ControlServer::bind(stale).unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sockets_are_private() {
+        let path = std::env::temp_dir().join(format!("caloud-private-{}.sock", std::process::id()));
+        let server = ControlServer::bind(path.clone()).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        drop(server);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn repository() {
+        assert_eq!(
+            format_repository("caloud", Some("main")),
+            "repository: caloud\nbranch: main\n"
+        );
+        assert_eq!(format_repository("caloud", None), "repository: caloud\n");
+    }
+}
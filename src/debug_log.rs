@@ -0,0 +1,126 @@
+//! `--debug`: a log of how the output of `claude` was parsed and why
+//! notifications were or were not delivered, for finding out why a
+//! notification never fired.
+
+use crate::claude_session::ClaudeSession;
+use crate::redact::Redactor;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+/// A handle shared by the threads that log; does nothing without `--debug`.
+#[derive(Clone, Default)]
+pub struct DebugLog(Option<Arc<Inner>>);
+
+struct Inner {
+    writer: Mutex<Box<dyn Write + Send>>,
+    started_at: Instant,
+    redactor: Option<Redactor>,
+    claude_session: Arc<ClaudeSession>,
+}
+
+impl DebugLog {
+    /// A log written to `path`, with secrets masked by `redactor`, if any,
+    /// and each line tagged with the session ID of Claude Code once known.
+    /// Created readable by the current user only, as what `claude` prints is
+    /// logged.
+    pub fn create(
+        path: &Path,
+        redactor: Option<Redactor>,
+        claude_session: Arc<ClaudeSession>,
+    ) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        Ok(Self::new(file, redactor, claude_session))
+    }
+
+    fn new(
+        writer: impl Write + Send + 'static,
+        redactor: Option<Redactor>,
+        claude_session: Arc<ClaudeSession>,
+    ) -> Self {
+        Self(Some(Arc::new(Inner {
+            writer: Mutex::new(Box::new(writer)),
+            started_at: Instant::now(),
+            redactor,
+            claude_session,
+        })))
+    }
+
+    /// Write a line prefixed with the seconds since the log was opened and
+    /// the session ID, if known.
+    /// Failures are ignored so that logging never disturbs the session.
+    pub fn log(&self, args: fmt::Arguments<'_>) {
+        let Some(inner) = &self.0 else {
+            return;
+        };
+        let elapsed = inner.started_at.elapsed().as_secs_f64();
+        let line = args.to_string();
+        let line = match &inner.redactor {
+            Some(redactor) => redactor.apply(&line),
+            None => line.as_str().into(),
+        };
+        let claude_session_id = inner.claude_session.id();
+        let mut writer = inner.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = match claude_session_id {
+            Some(id) => writeln!(writer, "{elapsed:10.3} {id} {line}"),
+            None => writeln!(writer, "{elapsed:10.3} {line}"),
+        };
+        let _ = writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lines_are_timestamped() {
+        let buffer = SharedBuffer::default();
+        let log = DebugLog::new(buffer.clone(), None, Arc::default());
+        log.log(format_args!("matched {}", "OSC 9"));
+        log.clone().log(format_args!("second"));
+        DebugLog::default().log(format_args!("discarded"));
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output
+            .lines()
+            .map(|line| line.trim_start().split_once(' ').unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|(secs, _)| secs.parse::<f64>().is_ok()));
+        assert_eq!(lines[0].1, "matched OSC 9");
+        assert_eq!(lines[1].1, "second");
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let buffer = SharedBuffer::default();
+        let log = DebugLog::new(buffer.clone(), Some(Redactor::new(&[])), Arc::default());
+        log.log(format_args!("say: token=0123456789abcdef"));
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(output.ends_with(" say: token=[redacted]\n"));
+    }
+}
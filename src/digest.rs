@@ -0,0 +1,142 @@
+//! `--digest`: hold notifications back and deliver them together at most
+//! once per interval, e.g. `4 updates in the last 10 min: …`, so that a
+//! long autonomous run does not interrupt with each one.  Permission
+//! requests and the death of `claude` are still delivered right away.
+
+use crate::event::Notification;
+use std::time::{Duration, Instant};
+
+pub struct Digest {
+    interval: Duration,
+    pending: Vec<Notification>,
+    /// When the notifications held back are due, counted from the first one.
+    due: Option<Instant>,
+}
+
+impl Digest {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            pending: Vec::new(),
+            due: None,
+        }
+    }
+
+    /// `notification` if it is to be delivered now, or `None` if it is held
+    /// back for the digest.
+    pub fn push(&mut self, notification: Notification, now: Instant) -> Option<Notification> {
+        if notification.is_urgent || notification.is_exit {
+            return Some(notification);
+        }
+        self.due.get_or_insert(now + self.interval);
+        self.pending.push(notification);
+        None
+    }
+
+    /// When the digest is due, if anything is held back.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.due
+    }
+
+    /// The digest, once it is due.
+    pub fn take_due(&mut self, now: Instant) -> Option<Notification> {
+        if self.due.is_none_or(|due| due > now) {
+            return None;
+        }
+        self.take()
+    }
+
+    /// The digest of what is held back, due or not, e.g. when `claude` exits.
+    /// A single notification is delivered as it is.
+    pub fn take(&mut self) -> Option<Notification> {
+        self.due = None;
+        let mut pending = std::mem::take(&mut self.pending);
+        let last = pending.pop()?;
+        if pending.is_empty() {
+            return Some(last);
+        }
+        let count = pending.len() + 1;
+        let mut messages: Vec<&str> = Vec::new();
+        for message in pending
+            .iter()
+            .chain([&last])
+            .map(|notification| notification.message.as_str())
+        {
+            if !messages.contains(&message) {
+                messages.push(message);
+            }
+        }
+        let message = format!(
+            "{count} updates in the last {}: {}",
+            format_interval(self.interval),
+            messages.join("; ")
+        );
+        Some(Notification { message, ..last })
+    }
+}
+
+/// `30 s`, `10 min`, or `2 h`, rounded down.
+fn format_interval(interval: Duration) -> String {
+    match interval.as_secs() {
+        secs @ 0..60 => format!("{secs} s"),
+        secs @ 60..3600 => format!("{} min", secs / 60),
+        secs => format!("{} h", secs / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(message: &str, is_urgent: bool) -> Notification {
+        Notification {
+            title: "Claude Code".to_string(),
+            subtitle: Some("caloud".to_string()),
+            message: message.to_string(),
+            is_urgent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn digest() {
+        let start = Instant::now();
+        let mut digest = Digest::new(Duration::from_secs(600));
+        assert!(
+            digest
+                .push(notification("Task done", false), start)
+                .is_none()
+        );
+        let urgent = notification("Claude needs your permission to use Bash", true);
+        assert!(digest.push(urgent, start).is_some());
+        let later = start + Duration::from_secs(60);
+        assert!(
+            digest
+                .push(notification("Claude is waiting", false), later)
+                .is_none()
+        );
+        assert!(
+            digest
+                .push(notification("Task done", false), later)
+                .is_none()
+        );
+        assert_eq!(digest.next_due(), Some(start + Duration::from_secs(600)));
+        assert!(digest.take_due(later).is_none());
+
+        let summary = digest.take_due(start + Duration::from_secs(600)).unwrap();
+        assert_eq!(
+            summary.message,
+            "3 updates in the last 10 min: Task done; Claude is waiting"
+        );
+        assert_eq!(summary.subtitle.as_deref(), Some("caloud"));
+        assert_eq!(digest.next_due(), None);
+        assert!(digest.take().is_none());
+
+        assert!(
+            digest
+                .push(notification("Only one", false), later)
+                .is_none()
+        );
+        assert_eq!(digest.take().unwrap().message, "Only one");
+    }
+}
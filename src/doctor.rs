@@ -0,0 +1,246 @@
+//! `caloud doctor`: check the environment caloud depends on.
+
+use crate::args::Arguments;
+#[cfg(target_os = "macos")]
+use crate::macos::focus;
+use crate::platform::{Native, Platform};
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Pass(String),
+    Skip(String),
+    Fail { reason: String, hint: String },
+}
+
+/// Run all checks, print a report, and return whether every check passed.
+pub fn run(arguments: &Arguments) -> bool {
+    let checks = [
+        check_claude(arguments),
+        check_voice(arguments),
+        check_voice_pool(arguments),
+        check_host_terminal(),
+        check_osc9(),
+        check_notification_center(arguments),
+        check_focus(arguments),
+        check_rewrite_rules(arguments),
+    ];
+
+    for check in &checks {
+        match &check.outcome {
+            Outcome::Pass(detail) => println!("[pass] {}: {}", check.name, detail),
+            Outcome::Skip(detail) => println!("[skip] {}: {}", check.name, detail),
+            Outcome::Fail { reason, hint } => {
+                println!("[fail] {}: {}", check.name, reason);
+                println!("       hint: {}", hint);
+            }
+        }
+    }
+
+    checks
+        .iter()
+        .all(|check| !matches!(check.outcome, Outcome::Fail { .. }))
+}
+
+fn check_claude(arguments: &Arguments) -> Check {
+    let outcome = match arguments.try_build_claude_command() {
+        Ok(claude_command) => Outcome::Pass(claude_command.to_string()),
+        Err(e) => Outcome::Fail {
+            reason: format!("{e:#}"),
+            hint: "install claude or pass its path via --claude-path".to_string(),
+        },
+    };
+    Check {
+        name: "claude",
+        outcome,
+    }
+}
+
+fn check_voice(arguments: &Arguments) -> Check {
+    let outcome = match arguments.try_build_say_command() {
+        None if arguments.say_disabled() => Outcome::Skip("--no-say is specified".to_string()),
+        None => Outcome::Skip("--say is not specified".to_string()),
+        Some(Err(e)) => Outcome::Fail {
+            reason: format!("{e:#}"),
+            hint: "quote --say as a single argument, e.g. --say='-v Samantha -r 200'".to_string(),
+        },
+        Some(Ok(say_command)) => match say_command.voice() {
+            None => Outcome::Pass("system default voice".to_string()),
+            Some(_) if !cfg!(target_os = "macos") => {
+                Outcome::Skip("voices are only listed on macOS".to_string())
+            }
+            Some(voice) => match Native::list_voices() {
+                Ok(voices) if voices.iter().any(|v| v.name == voice) => {
+                    Outcome::Pass(format!("{voice} is installed"))
+                }
+                Ok(_) => Outcome::Fail {
+                    reason: format!("{voice} is not installed"),
+                    hint: "run `caloud list-voices` to list voices, or download it in System Settings > Accessibility > Spoken Content".to_string(),
+                },
+                Err(e) => Outcome::Fail {
+                    reason: format!("{e:#}"),
+                    hint: "make sure /usr/bin/say is available".to_string(),
+                },
+            },
+        },
+    };
+    Check {
+        name: "say voice",
+        outcome,
+    }
+}
+
+fn check_voice_pool(arguments: &Arguments) -> Check {
+    let pool = arguments.voice_pool();
+    if pool.is_empty() {
+        return Check {
+            name: "voice pool",
+            outcome: Outcome::Skip("--voice-pool is not specified".to_string()),
+        };
+    }
+    if !cfg!(target_os = "macos") {
+        return Check {
+            name: "voice pool",
+            outcome: Outcome::Skip("voices are only listed on macOS".to_string()),
+        };
+    }
+    let outcome = match Native::list_voices() {
+        Ok(voices) => {
+            let missing: Vec<_> = pool
+                .iter()
+                .filter(|voice| !voices.iter().any(|v| &v.name == *voice))
+                .map(String::as_str)
+                .collect();
+            match missing.as_slice() {
+                [] => Outcome::Pass(format!("{} voices are installed", pool.len())),
+                missing => Outcome::Fail {
+                    reason: format!("not installed: {}", missing.join(", ")),
+                    hint: "run `caloud list-voices` to list voices, or download them in System Settings > Accessibility > Spoken Content".to_string(),
+                },
+            }
+        }
+        Err(e) => Outcome::Fail {
+            reason: format!("{e:#}"),
+            hint: "make sure /usr/bin/say is available".to_string(),
+        },
+    };
+    Check {
+        name: "voice pool",
+        outcome,
+    }
+}
+
+fn check_host_terminal() -> Check {
+    let outcome = match Native::host_terminal() {
+        Some(bundle_identifier) => Outcome::Pass(bundle_identifier),
+        None if !cfg!(target_os = "macos") => {
+            Outcome::Skip("the terminal is only looked up on macOS".to_string())
+        }
+        None => Outcome::Fail {
+            reason: "no ancestor process is an application bundle".to_string(),
+            hint: "clicking a notification cannot bring the terminal to the front; run caloud from a terminal application".to_string(),
+        },
+    };
+    Check {
+        name: "host terminal",
+        outcome,
+    }
+}
+
+fn check_osc9() -> Check {
+    let outcome = if Native::is_osc9_supported() {
+        Outcome::Pass("the host terminal displays OSC 9 notifications itself".to_string())
+    } else if !cfg!(target_os = "macos") {
+        Outcome::Skip("OSC 9 support is only detected on macOS".to_string())
+    } else {
+        Outcome::Skip(
+            "the host terminal is not known to support OSC 9; use --notification-center"
+                .to_string(),
+        )
+    };
+    Check {
+        name: "OSC 9 support",
+        outcome,
+    }
+}
+
+fn check_notification_center(arguments: &Arguments) -> Check {
+    let outcome = if arguments.notify_disabled() {
+        Outcome::Skip("--no-notify is specified".to_string())
+    } else if !arguments.notification_center_delivery_enabled() {
+        Outcome::Skip("--notification-center is not specified".to_string())
+    } else if Native::is_osc9_supported() {
+        Outcome::Skip("not used because the host terminal supports OSC 9".to_string())
+    } else if !cfg!(target_os = "macos") {
+        Outcome::Pass(format!("enabled; shown with {}", Native::NOTIFIER))
+    } else {
+        // NSUserNotificationCenter has no API to query authorization; delivery
+        // is attributed to Terminal.app, whose settings apply.
+        Outcome::Pass(
+            "enabled; check System Settings > Notifications > Terminal if banners do not appear"
+                .to_string(),
+        )
+    };
+    Check {
+        name: "Notification Center",
+        outcome,
+    }
+}
+
+fn check_focus(arguments: &Arguments) -> Check {
+    let outcome = if arguments.focus_rule_count() == 0 {
+        Outcome::Skip("--focus-policy is not specified".to_string())
+    } else {
+        check_current_focus()
+    };
+    Check {
+        name: "Focus",
+        outcome,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn check_current_focus() -> Outcome {
+    match focus::current() {
+        Ok(Some(focus)) => {
+            Outcome::Pass(format!("{} is on", focus.name.unwrap_or(focus.identifier)))
+        }
+        Ok(None) => Outcome::Pass("no Focus is on".to_string()),
+        Err(e) => Outcome::Fail {
+            reason: format!("{e:#}"),
+            hint: "give the terminal Full Disk Access in System Settings > Privacy & Security"
+                .to_string(),
+        },
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn check_current_focus() -> Outcome {
+    Outcome::Skip("Focus is only on macOS".to_string())
+}
+
+fn check_rewrite_rules(arguments: &Arguments) -> Check {
+    // Invalid --input-rewrite rules are rejected while parsing the arguments,
+    // but the files are only read here.
+    let outcome = match arguments.rewrite_rules() {
+        Err(e) => Outcome::Fail {
+            reason: format!("{e:#}"),
+            hint: "write one FROM:TO rule per line, with the escapes of --input-rewrite"
+                .to_string(),
+        },
+        Ok(rules) if rules.is_empty() => {
+            Outcome::Skip("no --input-rewrite, --rewrite-file, or --keys".to_string())
+        }
+        Ok(_) if arguments.notify_only() => Outcome::Skip(
+            "--notify-only ignores --input-rewrite, --rewrite-file, and --keys".to_string(),
+        ),
+        Ok(rules) => Outcome::Pass(format!("{} rule(s) valid", rules.len())),
+    };
+    Check {
+        name: "input rewrite rules",
+        outcome,
+    }
+}
@@ -0,0 +1,131 @@
+//! `--speak-emoji`: what becomes of emoji before text is spoken, since `say`
+//! reads out the name of each one, skin tones and all, in the middle of a
+//! sentence.
+
+use anyhow::bail;
+use std::borrow::Cow;
+
+/// Emoji read out as a word with `describe`; the others are dropped.
+const DESCRIPTIONS: &[(char, &str)] = &[
+    ('✅', "check mark"),
+    ('✓', "check mark"),
+    ('✔', "check mark"),
+    ('☑', "check mark"),
+    ('❌', "cross mark"),
+    ('✗', "cross mark"),
+    ('✘', "cross mark"),
+    ('❎', "cross mark"),
+    ('⚠', "warning"),
+    ('🚨', "warning"),
+    ('ℹ', "info"),
+    ('💡', "tip"),
+    ('🐛', "bug"),
+    ('⏳', "waiting"),
+    ('⌛', "waiting"),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmojiMode {
+    /// Left for `say` to name.
+    #[default]
+    Keep,
+    Strip,
+    /// A few common ones, such as ✅ and ⚠️, as words; the others stripped.
+    Describe,
+}
+
+impl EmojiMode {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "strip" => Ok(Self::Strip),
+            "describe" => Ok(Self::Describe),
+            _ => bail!("unknown mode: {s} (expected keep, strip, describe)"),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Strip => "strip",
+            Self::Describe => "describe",
+        }
+    }
+
+    /// `text` with its emoji stripped or described.
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        if self == Self::Keep
+            || !text
+                .chars()
+                .any(|c| is_emoji(c) || description(c).is_some())
+        {
+            return Cow::Borrowed(text);
+        }
+        let mut spoken = String::with_capacity(text.len());
+        for c in text.chars() {
+            match description(c).filter(|_| self == Self::Describe) {
+                Some(word) => {
+                    spoken.push(' ');
+                    spoken.push_str(word);
+                    spoken.push(' ');
+                }
+                None if is_emoji(c) || description(c).is_some() => spoken.push(' '),
+                None => spoken.push(c),
+            }
+        }
+        // The spaces around what was replaced are collapsed, line by line.
+        let lines: Vec<String> = spoken
+            .split('\n')
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        Cow::Owned(lines.join("\n"))
+    }
+}
+
+fn description(c: char) -> Option<&'static str> {
+    DESCRIPTIONS
+        .iter()
+        .find_map(|&(emoji, word)| (emoji == c).then_some(word))
+}
+
+/// Pictographs, dingbats, and what joins or modifies them: the variation
+/// selector, the zero width joiner, the keycap, and tags.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+            | 0x2B00..=0x2BFF
+            | 0x231A..=0x231B
+            | 0x23E9..=0x23FA
+            | 0xFE0F
+            | 0x200D
+            | 0x20E3
+            | 0xE0020..=0xE007F
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speak_emoji() {
+        let text = "✅ Tests pass 🎉\n⚠️ 2 warnings in 👩‍💻 code";
+        assert_eq!(EmojiMode::Keep.apply(text), text);
+        assert_eq!(
+            EmojiMode::Strip.apply(text),
+            "Tests pass\n2 warnings in code"
+        );
+        assert_eq!(
+            EmojiMode::Describe.apply(text),
+            "check mark Tests pass\nwarning 2 warnings in code"
+        );
+        assert!(matches!(
+            EmojiMode::Strip.apply("No emoji → here"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(EmojiMode::parse("describe").unwrap(), EmojiMode::Describe);
+        assert!(EmojiMode::parse("words").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Parser for `--env-file`.
+//!
+//! Format: one `KEY=VALUE` per line.  Blank lines and lines starting with `#`
+//! are ignored, an optional `export ` prefix is accepted, and a value wrapped
+//! in matching single or double quotes is unquoted (no escape processing).
+
+use std::fmt;
+
+pub fn parse(s: &str) -> Result<Vec<(String, String)>, ParseError> {
+    s.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            (!line.is_empty() && !line.starts_with('#')).then_some((i + 1, line))
+        })
+        .map(|(line_number, line)| {
+            let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+            parse_assignment(line).map_err(|kind| ParseError { line_number, kind })
+        })
+        .collect()
+}
+
+/// Parse `KEY=VALUE`, as also accepted by `--env`.
+pub fn parse_assignment(s: &str) -> Result<(String, String), ParseErrorKind> {
+    let (key, value) = s.split_once('=').ok_or(ParseErrorKind::MissingEquals)?;
+    let key = key.trim_end();
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '\0') {
+        return Err(ParseErrorKind::InvalidKey);
+    }
+    let value = value.trim_start();
+    let value = ['"', '\'']
+        .into_iter()
+        .find_map(|q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+        .unwrap_or(value);
+    if value.contains('\0') {
+        return Err(ParseErrorKind::NulInValue);
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    line_number: usize,
+    kind: ParseErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorKind {
+    MissingEquals,
+    InvalidKey,
+    NulInValue,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.kind)
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::MissingEquals => write!(f, "expected KEY=VALUE"),
+            ParseErrorKind::InvalidKey => write!(f, "invalid variable name"),
+            ParseErrorKind::NulInValue => write!(f, "value contains null byte"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::error::Error for ParseErrorKind {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse_file() {
+        let vars = parse(indoc! {r#"
+            # comment
+            ANTHROPIC_MODEL=claude-sonnet
+
+            export NO_COLOR=1
+            QUOTED="a b"
+            SINGLE='c=d'
+            EMPTY=
+        "#})
+        .unwrap();
+        assert_eq!(
+            vars,
+            [
+                ("ANTHROPIC_MODEL", "claude-sonnet"),
+                ("NO_COLOR", "1"),
+                ("QUOTED", "a b"),
+                ("SINGLE", "c=d"),
+                ("EMPTY", ""),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+    }
+
+    #[test]
+    fn error_has_line_number() {
+        let err = parse("A=1\n\nB\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: expected KEY=VALUE");
+    }
+
+    #[test]
+    fn invalid_key() {
+        assert_eq!(parse_assignment("=1"), Err(ParseErrorKind::InvalidKey));
+        assert_eq!(parse_assignment("A B=1"), Err(ParseErrorKind::InvalidKey));
+    }
+}
@@ -0,0 +1,68 @@
+use nix::sys::wait::WaitStatus;
+
+/// A notification posted by `claude`, ready for delivery.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub message: String,
+    /// Asks for permission, so it is delivered with a sound.
+    pub is_urgent: bool,
+    /// `claude` has died; the exit sound is played for it instead.
+    pub is_exit: bool,
+    /// Forwarded by another session with `--mirror-send`, so not forwarded again.
+    pub is_mirrored: bool,
+    /// The `key=value` fields posted with OSC 777, such as `sound` and `id`,
+    /// for the backends that make use of them.
+    pub parameters: Vec<(String, String)>,
+}
+
+impl Notification {
+    /// The value of the parameter `key`, if it was posted.
+    #[cfg_attr(not(target_os = "macos"), expect(dead_code))] // macOS only
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find_map(|(k, value)| (k == key).then_some(value.as_str()))
+    }
+
+    /// `Claude needs your permission to use Bash`
+    pub fn is_permission_request(message: &str) -> bool {
+        message.contains("needs your permission")
+    }
+
+    /// `Claude exited with code 1` or `Claude was killed by SIGKILL`, unless
+    /// it exited successfully.
+    pub fn exit_message(status: WaitStatus) -> Option<String> {
+        match status {
+            WaitStatus::Exited(_, 0) => None,
+            WaitStatus::Exited(_, code) => Some(format!("Claude exited with code {code}")),
+            WaitStatus::Signaled(_, signal, _) => {
+                Some(format!("Claude was killed by {}", signal.as_str()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::signal::Signal;
+    use nix::unistd::Pid;
+
+    #[test]
+    fn exit_message() {
+        let pid = Pid::from_raw(1);
+        assert_eq!(Notification::exit_message(WaitStatus::Exited(pid, 0)), None);
+        assert_eq!(
+            Notification::exit_message(WaitStatus::Exited(pid, 1)).as_deref(),
+            Some("Claude exited with code 1")
+        );
+        assert_eq!(
+            Notification::exit_message(WaitStatus::Signaled(pid, Signal::SIGKILL, false))
+                .as_deref(),
+            Some("Claude was killed by SIGKILL")
+        );
+    }
+}
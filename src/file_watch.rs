@@ -0,0 +1,132 @@
+//! `--watch-files`: which of the files changed under the project directory
+//! are worth a notification, e.g. `Cargo.toml` or the migrations.
+
+use anyhow::{Context, bail};
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A glob matched against the path relative to the project directory, or,
+/// without a `/`, against the file name in any directory:
+///
+/// - `*` matches any characters but `/`, and `?` any one of them
+/// - `**/` matches any number of directories
+#[derive(Debug, Clone)]
+pub struct FileGlob {
+    source: String,
+    regex: Regex,
+}
+
+impl FileGlob {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let glob = s.trim_start_matches("./");
+        if glob.is_empty() || glob.ends_with('/') {
+            bail!("a glob must name files");
+        }
+        let mut pattern = String::from("^");
+        let mut rest = glob;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("**/") {
+                pattern.push_str("(?:[^/]*/)*");
+                rest = after;
+                continue;
+            }
+            match c {
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        pattern.push('$');
+        let regex = Regex::new(&pattern).context("invalid glob")?;
+        Ok(Self {
+            source: s.to_string(),
+            regex,
+        })
+    }
+
+    fn matches(&self, relative_path: &str) -> bool {
+        let name = match self.source.contains('/') {
+            true => relative_path,
+            false => relative_path.rsplit('/').next().unwrap_or(relative_path),
+        };
+        self.regex.is_match(name)
+    }
+}
+
+impl fmt::Display for FileGlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// The project directory and the globs of the files to notify about.
+pub struct FileMatcher {
+    root: PathBuf,
+    globs: Vec<FileGlob>,
+}
+
+impl FileMatcher {
+    /// `root` is resolved, since changes are reported with resolved paths
+    /// (e.g. `/private/tmp` rather than `/tmp`).
+    pub fn new(root: &Path, globs: Vec<FileGlob>) -> anyhow::Result<Self> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("failed to resolve {}", root.display()))?;
+        Ok(Self { root, globs })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `path` relative to the project directory, if it matches a glob.
+    pub fn relative_match(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?.to_str()?;
+        self.globs
+            .iter()
+            .any(|glob| glob.matches(relative))
+            .then(|| relative.to_string())
+    }
+}
+
+/// `Modified Cargo.toml`, or `Modified Cargo.toml and 2 other files`.
+pub fn message(paths: &[String]) -> String {
+    match paths {
+        [] => "Modified no files".to_string(),
+        [path] => format!("Modified {path}"),
+        [path, _] => format!("Modified {path} and 1 other file"),
+        [path, rest @ ..] => format!("Modified {path} and {} other files", rest.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        let glob = |s| FileGlob::parse(s).unwrap();
+        assert!(glob("Cargo.toml").matches("Cargo.toml"));
+        assert!(glob("Cargo.toml").matches("crates/core/Cargo.toml"));
+        assert!(!glob("Cargo.toml").matches("Cargo.toml.orig"));
+        assert!(glob("*.sql").matches("db/migrations/001_init.sql"));
+        assert!(glob("migrations/*.sql").matches("migrations/001_init.sql"));
+        assert!(!glob("migrations/*.sql").matches("db/migrations/001_init.sql"));
+        assert!(glob("db/**/*.sql").matches("db/001_init.sql"));
+        assert!(glob("db/**/*.sql").matches("db/migrations/v1/001_init.sql"));
+        assert!(glob("./src/?.rs").matches("src/a.rs"));
+        assert!(!glob("src/?.rs").matches("src/ab.rs"));
+        assert!(FileGlob::parse("migrations/").is_err());
+        assert_eq!(glob("db/**/*.sql").to_string(), "db/**/*.sql");
+    }
+
+    #[test]
+    fn messages() {
+        let paths = |n| (0..n).map(|i| format!("{i}.sql")).collect::<Vec<_>>();
+        assert_eq!(message(&paths(1)), "Modified 0.sql");
+        assert_eq!(message(&paths(2)), "Modified 0.sql and 1 other file");
+        assert_eq!(message(&paths(3)), "Modified 0.sql and 2 other files");
+    }
+}
@@ -0,0 +1,189 @@
+//! `caloud filter`: run a byte stream from stdin through the output
+//! processing of a session to stdout, delivering and speaking the
+//! notifications posted in it, e.g. to replay a `script` recording or to pipe
+//! a non-interactive tool through caloud.  With `--bypass-filter`, the same
+//! is done for the output of a child that is run without a PTY, e.g.
+//! `claude -p`.
+//!
+//! `caloud reformat` only adjusts the line wrapping of a stream, e.g. to
+//! post-process a saved capture or to generate a snapshot fixture.
+//!
+//! Both take a recording of `--record` as well as a raw stream, and `caloud
+//! decode` turns one back into the raw stream it was made from.
+
+use crate::DEFAULT_NOTIFICATION_TITLE;
+use crate::args::{self, Arguments};
+use crate::claude::ChildCommand;
+use crate::event::Notification;
+#[cfg(target_os = "macos")]
+use crate::macos::notification::set_global_delegate;
+use crate::normalize;
+use crate::platform::{Native, Platform};
+use crate::recording;
+use crate::tty_text::fragment::{EscapeSequence, notification_parameters};
+use crate::tty_text::reformat::{LineWrapMode, Reformatter};
+use crate::tty_text::reformat_writer::ReformatWriter;
+use anyhow::{Context, bail};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::sync::mpsc;
+use std::thread;
+
+pub fn run(arguments: &Arguments) -> anyhow::Result<()> {
+    let input = recording::open(io::stdin().lock()).context("failed to read stdin")?;
+    filter(arguments, input).context("failed to filter stdin")
+}
+
+/// Run the file given, or stdin, through `--line-wrap=adjust` at `--width`,
+/// or else the width of the terminal on stdout, to stdout.
+pub fn reformat(arguments: &Arguments) -> anyhow::Result<()> {
+    let mut input = open_input(arguments, "reformat")?;
+    let width = arguments
+        .width()
+        .or_else(|| {
+            crate::get_winsize(&io::stdout())
+                .ok()
+                .map(|winsize| winsize.ws_col)
+                .filter(|&columns| columns > 0)
+        })
+        .unwrap_or(args::DEFAULT_WIDTH);
+    let reformatter =
+        Reformatter::new(width, LineWrapMode::Adjust).with_wrap_log(arguments.wrap_log()?);
+    let mut writer = ReformatWriter::new(
+        io::stdout().lock(),
+        reformatter,
+        |_: &EscapeSequence<'_>| {},
+    );
+    io::copy(&mut input, &mut writer).context("failed to reformat")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the raw output in the recording given, or on stdin, to stdout, as
+/// it was read from the PTY, with no processing at all.
+pub fn decode(arguments: &Arguments) -> anyhow::Result<()> {
+    let mut input = open_input(arguments, "decode")?;
+    let mut stdout = io::stdout().lock();
+    io::copy(&mut input, &mut stdout).context("failed to decode")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The file given to `command`, or stdin, read through a recording if it is one.
+fn open_input(arguments: &Arguments, command: &str) -> anyhow::Result<Box<dyn Read>> {
+    let input: Box<dyn Read> = match arguments.subcommand_args() {
+        [] => Box::new(io::stdin().lock()),
+        [path] => Box::new(
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+        ),
+        _ => bail!("{command} takes at most one file"),
+    };
+    recording::open(input).context("failed to read input")
+}
+
+/// Run `claude_command` with its stdout filtered, and return its exit code.
+pub fn run_bypassed(arguments: &Arguments, claude_command: &ChildCommand) -> anyhow::Result<i32> {
+    let mut child = claude_command.spawn_with_piped_stdout()?;
+    let stdout = child.stdout.take().context("stdout is not piped")?;
+    let filtered = filter(arguments, stdout)
+        .with_context(|| format!("failed to filter the output of {}", claude_command.tool()));
+    // Even if filtering failed, so as not to leave it behind.
+    let status = child.wait()?;
+    filtered?;
+    Ok(match status.signal() {
+        Some(signal) => 128 + signal,
+        None => status.code().unwrap_or(1),
+    })
+}
+
+fn filter(arguments: &Arguments, mut input: impl Read) -> anyhow::Result<()> {
+    let say_command = arguments.try_build_say_command().transpose()?;
+    let redactor = arguments.redactor();
+    let length_limits = arguments.length_limits();
+    let notification_center_delivery_enabled =
+        arguments.notification_center_delivery_enabled() && !arguments.notify_disabled();
+    #[cfg(target_os = "macos")]
+    if notification_center_delivery_enabled {
+        set_global_delegate().context("set_global_delegate")?;
+    }
+    // The width of the terminal showing the output, if it goes to one, else
+    // `--width` or `COLUMNS`, else unknown.
+    let width = crate::get_winsize(&io::stdout())
+        .ok()
+        .map(|winsize| winsize.ws_col)
+        .filter(|&columns| columns > 0)
+        .or_else(|| arguments.width())
+        .unwrap_or(0);
+    let reformatter =
+        Reformatter::new(width, arguments.line_wrap_mode()).with_wrap_log(arguments.wrap_log()?);
+
+    // Notifications are delivered and spoken one at a time, while the stream
+    // keeps flowing.
+    let (notification_tx, notification_rx) = mpsc::channel::<Notification>();
+    let delivery_thread = thread::spawn(move || {
+        for notification in notification_rx {
+            let notification = normalize::notification(notification);
+            let notification = match &redactor {
+                Some(redactor) => redactor.notification(notification),
+                None => notification,
+            };
+            if notification_center_delivery_enabled
+                && let Err(e) = Native::deliver(&length_limits.apply(&notification))
+            {
+                eprintln!("caloud: {}: {e:#}", Native::NOTIFIER);
+            }
+            if let Some(say_command) = &say_command {
+                let spoken = say_command
+                    .spawn(&notification.message)
+                    .and_then(|mut child| Ok(child.wait()?));
+                if let Err(e) = spoken {
+                    eprintln!("caloud: say: {e:#}");
+                }
+            }
+        }
+    });
+
+    let mut title = String::new();
+    let mut writer = ReformatWriter::new(
+        io::stdout().lock(),
+        reformatter,
+        |escape_sequence: &EscapeSequence<'_>| {
+            let (posted_title, message, parameters) = match *escape_sequence {
+                EscapeSequence::SetWindowAndIconTitle(new_title) => {
+                    title = String::from_utf8_lossy(new_title.trim_ascii()).into_owned();
+                    return;
+                }
+                EscapeSequence::PostNotification(message) => (None, message, &b""[..]),
+                EscapeSequence::PostTitledNotification {
+                    title,
+                    message,
+                    parameters,
+                } => (Some(title), message, parameters),
+                _ => return,
+            };
+            let message = String::from_utf8_lossy(message.trim_ascii()).into_owned();
+            let title = match posted_title {
+                Some(posted_title) => {
+                    String::from_utf8_lossy(posted_title.trim_ascii()).into_owned()
+                }
+                None if title.is_empty() => DEFAULT_NOTIFICATION_TITLE.to_string(),
+                None => title.clone(),
+            };
+            // The delivery thread only stops once the sender is dropped.
+            let _ = notification_tx.send(Notification {
+                title,
+                is_urgent: Notification::is_permission_request(&message),
+                parameters: notification_parameters(parameters),
+                message,
+                ..Default::default()
+            });
+        },
+    );
+    io::copy(&mut input, &mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    drop(notification_tx);
+    let _ = delivery_thread.join();
+    Ok(())
+}
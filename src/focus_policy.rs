@@ -0,0 +1,173 @@
+//! `--focus-policy`: deliver, hold back, or drop notifications depending on
+//! the macOS Focus that is on, e.g. deliver them during Work but hold them
+//! back during Personal until it is turned off.
+
+use anyhow::bail;
+use std::fmt;
+
+/// A Focus, as read by [`crate::macos::focus`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Focus {
+    /// `com.apple.focus.work`, or a UUID for a Focus the user made.
+    pub identifier: String,
+    /// `Work`, as shown in Control Center, if it could be found.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusPolicy {
+    Deliver,
+    /// Hold back until a Focus that delivers them is on, or none is.
+    Queue,
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusRule {
+    focus: String,
+    policy: FocusPolicy,
+}
+
+impl FocusRule {
+    /// Parse `FOCUS:POLICY`, where `FOCUS` is the name of a Focus, e.g.
+    /// `Work`, or its identifier, e.g. `com.apple.focus.work`.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let Some((focus, policy)) = s.rsplit_once(':') else {
+            bail!("expected FOCUS:POLICY");
+        };
+        if focus.is_empty() {
+            bail!("empty FOCUS");
+        }
+        let policy = match policy {
+            "deliver" => FocusPolicy::Deliver,
+            "queue" => FocusPolicy::Queue,
+            "drop" => FocusPolicy::Drop,
+            _ => bail!("unknown policy: {policy} (expected deliver, queue, drop)"),
+        };
+        Ok(Self {
+            focus: focus.to_string(),
+            policy,
+        })
+    }
+
+    fn matches(&self, focus: &Focus) -> bool {
+        focus.identifier == self.focus
+            || focus
+                .name
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(&self.focus))
+    }
+}
+
+/// Formats the rule in the form accepted by [`FocusRule::parse`].
+impl fmt::Display for FocusRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = match self.policy {
+            FocusPolicy::Deliver => "deliver",
+            FocusPolicy::Queue => "queue",
+            FocusPolicy::Drop => "drop",
+        };
+        write!(f, "{}:{policy}", self.focus)
+    }
+}
+
+/// Applies the rules to notifications, holding back those queued.
+pub struct FocusFilter<T> {
+    rules: Vec<FocusRule>,
+    queued: Vec<T>,
+}
+
+impl<T> FocusFilter<T> {
+    pub fn new(rules: Vec<FocusRule>) -> Self {
+        Self {
+            rules,
+            queued: Vec::new(),
+        }
+    }
+
+    /// The policy of the first rule for `focus`; notifications are delivered
+    /// without a Focus or a rule for it.
+    pub fn policy(&self, focus: Option<&Focus>) -> FocusPolicy {
+        focus
+            .and_then(|focus| self.rules.iter().find(|rule| rule.matches(focus)))
+            .map_or(FocusPolicy::Deliver, |rule| rule.policy)
+    }
+
+    /// What to deliver now that `item` has come in under `focus`: those held
+    /// back so far, in order, and then `item`.
+    pub fn push(&mut self, item: T, focus: Option<&Focus>) -> Vec<T> {
+        match self.policy(focus) {
+            FocusPolicy::Deliver => {
+                let mut items = std::mem::take(&mut self.queued);
+                items.push(item);
+                items
+            }
+            FocusPolicy::Queue => {
+                self.queued.push(item);
+                Vec::new()
+            }
+            FocusPolicy::Drop => Vec::new(),
+        }
+    }
+
+    /// Those held back, if `focus` lets them be delivered now.
+    pub fn release(&mut self, focus: Option<&Focus>) -> Vec<T> {
+        match self.policy(focus) {
+            FocusPolicy::Deliver => std::mem::take(&mut self.queued),
+            FocusPolicy::Queue | FocusPolicy::Drop => Vec::new(),
+        }
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focus(identifier: &str, name: &str) -> Option<Focus> {
+        Some(Focus {
+            identifier: identifier.to_string(),
+            name: Some(name.to_string()),
+        })
+    }
+
+    #[test]
+    fn parse() {
+        let rule = FocusRule::parse("Personal:queue").unwrap();
+        assert_eq!(rule.policy, FocusPolicy::Queue);
+        assert_eq!(rule.to_string(), "Personal:queue");
+        assert!(FocusRule::parse("Work").is_err());
+        assert!(FocusRule::parse(":drop").is_err());
+        assert!(FocusRule::parse("Work:later").is_err());
+    }
+
+    #[test]
+    fn queue_until_delivered() {
+        let rules = [
+            "Work:deliver",
+            "personal:queue",
+            "com.apple.sleep.sleep-mode:drop",
+        ]
+        .map(|rule| FocusRule::parse(rule).unwrap())
+        .to_vec();
+        let mut filter = FocusFilter::new(rules);
+        let work = focus("com.apple.focus.work", "Work");
+        let personal = focus("com.apple.focus.personal-time", "Personal");
+        let sleep = focus("com.apple.sleep.sleep-mode", "Sleep");
+
+        assert_eq!(filter.push(1, work.as_ref()), [1]);
+        assert!(filter.push(2, personal.as_ref()).is_empty());
+        assert!(filter.push(3, sleep.as_ref()).is_empty());
+        assert!(filter.release(sleep.as_ref()).is_empty());
+        assert_eq!(filter.queued(), 1);
+        assert_eq!(filter.release(None), [2]);
+        assert!(filter.push(4, personal.as_ref()).is_empty());
+        assert_eq!(filter.push(5, work.as_ref()), [4, 5]);
+        // A Focus without a rule delivers.
+        let gaming = focus("com.apple.focus.gaming", "Gaming");
+        assert_eq!(filter.policy(gaming.as_ref()), FocusPolicy::Deliver);
+    }
+}
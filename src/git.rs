@@ -0,0 +1,102 @@
+//! `--git-context`: the repository and branch `claude` works in, so that
+//! notifications from sessions in several repositories can be told apart.
+
+use std::path::{Path, PathBuf};
+
+/// A Git working tree, found by looking for `.git` in a directory and its
+/// parents.  The branch is read from `HEAD` each time it is asked for, so
+/// that a checkout during the session is picked up.
+#[derive(Debug, Clone)]
+pub struct GitRepository {
+    name: String,
+    head: PathBuf,
+}
+
+impl GitRepository {
+    /// The repository containing `dir`, if any.
+    pub fn find(dir: &Path) -> Option<Self> {
+        let (top, dot_git) = dir.ancestors().find_map(|top| {
+            let dot_git = top.join(".git");
+            dot_git.exists().then_some((top, dot_git))
+        })?;
+        // In a linked worktree or a submodule, `.git` is a file pointing to
+        // the Git directory: `gitdir: ../.git/worktrees/feature`.
+        let git_dir = match std::fs::read_to_string(&dot_git) {
+            Ok(contents) => top.join(contents.strip_prefix("gitdir:")?.trim()),
+            Err(_) => dot_git,
+        };
+        Some(Self {
+            name: top.file_name()?.to_string_lossy().into_owned(),
+            head: git_dir.join("HEAD"),
+        })
+    }
+
+    /// The name of the top directory of the working tree.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The branch checked out, or the abbreviated commit on a detached `HEAD`.
+    pub fn branch(&self) -> Option<String> {
+        let head = std::fs::read_to_string(&self.head).ok()?;
+        let head = head.trim();
+        match head.strip_prefix("ref:") {
+            Some(reference) => {
+                let reference = reference.trim();
+                Some(
+                    reference
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(reference)
+                        .to_string(),
+                )
+            }
+            None => head.get(..7).map(str::to_string),
+        }
+    }
+
+    /// `caloud (main)`, or the name alone if `HEAD` cannot be read.
+    pub fn session_name(&self) -> String {
+        match self.branch() {
+            Some(branch) => format!("{} ({branch})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn branch_of_repository_and_worktree() {
+        let dir = std::env::temp_dir().join(format!("caloud-git-{}", std::process::id()));
+        let main = dir.join("project");
+        let worktree = dir.join("project-feature");
+        fs::create_dir_all(main.join(".git/worktrees/feature")).unwrap();
+        fs::create_dir_all(main.join("src")).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(main.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(
+            main.join(".git/worktrees/feature/HEAD"),
+            "2f1e6a9c0d3b4e5f60718293a4b5c6d7e8f90a1b\n",
+        )
+        .unwrap();
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../project/.git/worktrees/feature\n",
+        )
+        .unwrap();
+
+        let repository = GitRepository::find(&main.join("src")).unwrap();
+        assert_eq!(repository.name(), "project");
+        assert_eq!(repository.session_name(), "project (main)");
+        fs::write(main.join(".git/HEAD"), "ref: refs/heads/fix/login\n").unwrap();
+        assert_eq!(repository.branch().as_deref(), Some("fix/login"));
+
+        let repository = GitRepository::find(&worktree).unwrap();
+        assert_eq!(repository.session_name(), "project-feature (2f1e6a9)");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
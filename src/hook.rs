@@ -0,0 +1,99 @@
+//! `--on-start` and `--on-exit`: commands run when a session begins and
+//! ends, e.g. to start and stop time tracking or to rename a tmux window.
+
+use crate::shell_command::ShellCommand;
+use anyhow::{Context, bail};
+use nix::unistd::Pid;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// A hook still running after this long is left to finish on its own, so
+/// that a hung one holds up neither the session nor the exit of caloud.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Start,
+    Exit { exit_code: i32, elapsed: Duration },
+}
+
+/// Run `command` in `cwd` with the session described in `env`, and wait for
+/// it to succeed.  Its output is discarded, since it would land in the middle
+/// of the TUI.
+pub fn run(command: &ShellCommand, cwd: &Path, env: &[(&str, String)]) -> anyhow::Result<()> {
+    let mut child = command
+        .command()
+        .current_dir(cwd)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {}", command.program()))?;
+    if !command.wait(&mut child, HOOK_TIMEOUT)? {
+        bail!("still running after {} seconds", HOOK_TIMEOUT.as_secs());
+    }
+    Ok(())
+}
+
+/// The variables a hook gets for `event` of the session running `claude` as
+/// `pid`:
+///
+/// - `CALOUD_HOOK_EVENT`: `start` or `exit`
+/// - `CALOUD_HOOK_PID`: the process ID of `claude`
+/// - `CALOUD_HOOK_SESSION`: the session name, if there is one
+/// - `CALOUD_HOOK_CLAUDE_SESSION`: the session ID of Claude Code, if known
+/// - `CALOUD_HOOK_EXIT_CODE` and `CALOUD_HOOK_ELAPSED_SECS` on `exit`
+pub fn environment(
+    event: HookEvent,
+    pid: Pid,
+    session_name: Option<&str>,
+    claude_session_id: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let name = match event {
+        HookEvent::Start => "start",
+        HookEvent::Exit { .. } => "exit",
+    };
+    let mut env = vec![
+        ("CALOUD_HOOK_EVENT", name.to_string()),
+        ("CALOUD_HOOK_PID", pid.to_string()),
+    ];
+    env.extend(session_name.map(|name| ("CALOUD_HOOK_SESSION", name.to_string())));
+    env.extend(claude_session_id.map(|id| ("CALOUD_HOOK_CLAUDE_SESSION", id.to_string())));
+    if let HookEvent::Exit { exit_code, elapsed } = event {
+        env.push(("CALOUD_HOOK_EXIT_CODE", exit_code.to_string()));
+        env.push(("CALOUD_HOOK_ELAPSED_SECS", elapsed.as_secs().to_string()));
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks() {
+        let event = HookEvent::Exit {
+            exit_code: 1,
+            elapsed: Duration::from_secs(63),
+        };
+        let env = environment(event, Pid::from_raw(42), Some("caloud"), Some("0b6f3c1e"));
+        assert_eq!(
+            env,
+            [
+                ("CALOUD_HOOK_EVENT", "exit".to_string()),
+                ("CALOUD_HOOK_PID", "42".to_string()),
+                ("CALOUD_HOOK_SESSION", "caloud".to_string()),
+                ("CALOUD_HOOK_CLAUDE_SESSION", "0b6f3c1e".to_string()),
+                ("CALOUD_HOOK_EXIT_CODE", "1".to_string()),
+                ("CALOUD_HOOK_ELAPSED_SECS", "63".to_string()),
+            ]
+        );
+
+        let hook = ShellCommand::parse(r#"sh -c 'test "$CALOUD_HOOK_EXIT_CODE" = 1'"#).unwrap();
+        assert!(run(&hook, Path::new("/"), &env).is_ok());
+        let env = environment(HookEvent::Start, Pid::from_raw(42), None, None);
+        assert!(run(&hook, Path::new("/"), &env).is_err());
+    }
+}
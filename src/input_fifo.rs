@@ -0,0 +1,133 @@
+//! `--input-fifo`: a named pipe whose contents are typed into `claude`, so
+//! that scripts or another terminal can send prompts to a running session.
+
+use crate::input_rewrite::pipeline::InputPipeline;
+use anyhow::{Context, bail};
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::stat::Mode;
+use nix::unistd::getuid;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
+use std::path::PathBuf;
+use std::thread;
+
+pub struct InputFifo {
+    file: File,
+    path: PathBuf,
+}
+
+impl InputFifo {
+    /// Create the FIFO at `path`, or reuse one left there by an earlier
+    /// session, as long as nobody else could have created it or write to it.
+    pub fn create(path: PathBuf) -> anyhow::Result<Self> {
+        match nix::unistd::mkfifo(&path, Mode::S_IRUSR | Mode::S_IWUSR) {
+            Ok(()) | Err(Errno::EEXIST) => {}
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("failed to create --input-fifo: {}", path.display()));
+            }
+        }
+        // Also opened for writing, so that a writer closing it is not an EOF
+        // that would end the input.  Checked once open, so that the file
+        // cannot be swapped in between.
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&path)
+            .with_context(|| format!("failed to open --input-fifo: {}", path.display()))?;
+        let metadata = file
+            .metadata()
+            .with_context(|| format!("failed to stat --input-fifo: {}", path.display()))?;
+        if !metadata.file_type().is_fifo() {
+            bail!("--input-fifo is not a FIFO: {}", path.display());
+        }
+        if metadata.uid() != getuid().as_raw() || metadata.mode() & 0o077 != 0 {
+            bail!(
+                "--input-fifo is not private to the current user: {}",
+                path.display()
+            );
+        }
+        Ok(Self { file, path })
+    }
+
+    /// Type what is written to the FIFO into `pty` through `pipeline`, on a
+    /// background thread.  The FIFO is removed when the returned guard is
+    /// dropped.
+    pub fn spawn(self, pipeline: InputPipeline, pty: File) -> FifoFileGuard {
+        let guard = FifoFileGuard(self.path);
+        let file = self.file;
+        thread::spawn(move || {
+            let _ = pipeline.run(file, pty);
+        });
+        guard
+    }
+}
+
+pub struct FifoFileGuard(PathBuf);
+
+impl Drop for FifoFileGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_rewrite::rewriter::InputRewriter;
+    use crate::input_rewrite::rule::RewriteRule;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn contents_are_rewritten_and_typed() {
+        let path = std::env::temp_dir().join(format!("caloud-input-fifo-{}", std::process::id()));
+        let fifo = InputFifo::create(path.clone()).unwrap();
+        let (output_read, output_write) = nix::unistd::pipe().unwrap();
+        let rewriter = InputRewriter::new(vec![RewriteRule::parse(r"\n:\r").unwrap()]);
+        let guard = fifo.spawn(
+            InputPipeline::new(rewriter, false),
+            File::from(output_write),
+        );
+
+        // Each writer closing the FIFO leaves it open for the next.
+        for prompt in ["continue\n", "yes\n"] {
+            OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .write_all(prompt.as_bytes())
+                .unwrap();
+        }
+        let mut output = [0; 13];
+        File::from(output_read).read_exact(&mut output).unwrap();
+        assert_eq!(&output, b"continue\ryes\r");
+
+        drop(guard);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let path = std::env::temp_dir().join(format!("caloud-input-file-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        assert!(InputFifo::create(path.clone()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn fifos_others_can_write_are_rejected() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("caloud-input-shared-{}", std::process::id()));
+        nix::unistd::mkfifo(&path, Mode::S_IRUSR | Mode::S_IWUSR).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o622)).unwrap();
+        let error = InputFifo::create(path.clone()).err().unwrap();
+        assert!(format!("{error:#}").contains("not private"));
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        drop(InputFifo::create(path.clone()).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+}
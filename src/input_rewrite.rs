@@ -1,4 +1,7 @@
 pub mod escape;
+pub mod pipeline;
+pub mod presets;
 pub mod rewriter;
 pub mod rule;
+pub mod rules_file;
 pub mod zwsp_inserter;
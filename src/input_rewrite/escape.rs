@@ -50,6 +50,25 @@ pub fn parse_escaped_str(s: &str) -> Result<Vec<u8>, EscapeError> {
     Ok(result)
 }
 
+/// Inverse of [`parse_escaped_str`].  Printable ASCII is kept as-is, except
+/// for `\\` and `:`, which would be ambiguous in a `FROM:TO` rule.
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    for &b in bytes {
+        match b {
+            b'\\' => result.push_str(r"\\"),
+            0x1b => result.push_str(r"\e"),
+            b'\n' => result.push_str(r"\n"),
+            b'\r' => result.push_str(r"\r"),
+            b'\t' => result.push_str(r"\t"),
+            b':' => result.push_str(r"\x3a"),
+            0x20..=0x7e => result.push(char::from(b)),
+            _ => result.push_str(&format!(r"\x{b:02x}")),
+        }
+    }
+    result
+}
+
 #[derive(Debug)]
 pub enum EscapeError {
     InvalidHex { pos: usize },
@@ -88,6 +107,11 @@ pub(super) mod tests {
         prop_assert_eq!(parse_escaped_str(&escaped).unwrap(), expected);
     }
 
+    #[property_test]
+    fn escape_round_trips(bytes: Vec<u8>) {
+        prop_assert_eq!(parse_escaped_str(&escape_bytes(&bytes)).unwrap(), bytes);
+    }
+
     #[property_test]
     fn parse_rejects_invalid_escape(#[strategy = arb_invalid_escaped_string()] invalid: String) {
         prop_assert!(parse_escaped_str(&invalid).is_err());
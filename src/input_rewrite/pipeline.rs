@@ -0,0 +1,132 @@
+use super::rewriter::InputRewriter;
+use super::zwsp_inserter::ZwspInserter;
+use nix::errno::Errno;
+use nix::sys::termios::{LocalFlags, SpecialCharacterIndices, tcgetattr};
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::AsFd;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
+/// Input path from the user's terminal to the PTY master:
+/// raw read → [`InputRewriter`] → [`ZwspInserter`] (optional) → PTY write.
+pub struct InputPipeline {
+    rewriter: InputRewriter,
+    zwsp_after_updown_arrow: bool,
+}
+
+impl InputPipeline {
+    pub fn new(rewriter: InputRewriter, zwsp_after_updown_arrow: bool) -> Self {
+        Self {
+            rewriter,
+            zwsp_after_updown_arrow,
+        }
+    }
+
+    /// See [`InputRewriter::add_hotkey`].
+    pub fn add_hotkey(&mut self, key: Vec<u8>, action: impl FnMut() + Send + 'static) {
+        self.rewriter.add_hotkey(key, action);
+    }
+
+    /// See [`InputRewriter::set_interceptor`].
+    pub fn set_interceptor(&mut self, interceptor: impl FnMut(&[u8]) -> bool + Send + 'static) {
+        self.rewriter.set_interceptor(interceptor);
+    }
+
+    /// See [`InputRewriter::duplicate`].
+    pub fn duplicate(&self) -> Self {
+        Self {
+            rewriter: self.rewriter.duplicate(),
+            zwsp_after_updown_arrow: self.zwsp_after_updown_arrow,
+        }
+    }
+
+    /// See [`InputRewriter::input_count`].
+    pub fn input_count(&self) -> Arc<AtomicUsize> {
+        self.rewriter.input_count()
+    }
+
+    /// Pump `input` into `pty` until `input` reaches EOF.
+    ///
+    /// On EOF, pending bytes are flushed and the EOF is forwarded to the slave
+    /// side (see [`send_eof`]) before `pty` is closed.
+    pub fn run(mut self, input: impl AsFd, mut pty: File) -> io::Result<()> {
+        if self.zwsp_after_updown_arrow {
+            let mut writer = ZwspInserter::new(&mut pty);
+            self.rewriter.rewrite(input, &mut writer)?;
+        } else {
+            self.rewriter.rewrite(input, &mut pty)?;
+        }
+        send_eof(&mut pty)
+    }
+}
+
+/// Forward EOF to the slave side of the PTY.
+///
+/// Closing our handle alone is not enough because the master stays open for
+/// output, so the slave would never see EOF.  In canonical mode the VEOF
+/// character makes a pending `read(2)` on the slave return 0; in raw mode
+/// there is no in-band EOF, so nothing is sent.  Non-terminal writers (e.g.
+/// pipes in tests) are left alone.
+fn send_eof(pty: &mut File) -> io::Result<()> {
+    let termios = match tcgetattr(pty.as_fd()) {
+        Ok(termios) => termios,
+        Err(Errno::ENOTTY | Errno::EINVAL) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if termios.local_flags.contains(LocalFlags::ICANON) {
+        let veof = termios.control_chars[SpecialCharacterIndices::VEOF as usize];
+        pty.write_all(&[veof])?;
+        pty.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_rewrite::rule::RewriteRule;
+    use std::io::Read;
+
+    #[test]
+    fn rewrite_and_zwsp_compose() {
+        let rewriter = InputRewriter::new(vec![RewriteRule::parse(r"\x10:\e[A").unwrap()]);
+        let output = run(InputPipeline::new(rewriter, true), b"\x105");
+        assert_eq!(output, b"\x1b[A\xe2\x80\x8b5");
+    }
+
+    #[test]
+    fn zwsp_disabled() {
+        let rewriter = InputRewriter::new(vec![RewriteRule::parse(r"\x10:\e[A").unwrap()]);
+        let output = run(InputPipeline::new(rewriter, false), b"\x105");
+        assert_eq!(output, b"\x1b[A5");
+    }
+
+    #[test]
+    fn duplicate_keeps_rules_but_not_hotkeys() {
+        let rewriter = InputRewriter::new(vec![RewriteRule::parse(r"\x10:\e[A").unwrap()]);
+        let mut pipeline = InputPipeline::new(rewriter, false);
+        pipeline.add_hotkey(b"\x0f".to_vec(), || {});
+        assert_eq!(run(pipeline.duplicate(), b"\x10\x0f"), b"\x1b[A\x0f");
+    }
+
+    #[test]
+    fn eof_closes_writer() {
+        let rewriter = InputRewriter::new(vec![]);
+        assert_eq!(run(InputPipeline::new(rewriter, false), b"abc"), b"abc");
+    }
+
+    /// Feed `input` through `pipeline` using pipes on both sides; reading the
+    /// output to the end also checks that the pipeline closed its writer.
+    fn run(pipeline: InputPipeline, input: &[u8]) -> Vec<u8> {
+        let (input_read, input_write) = nix::unistd::pipe().unwrap();
+        let (output_read, output_write) = nix::unistd::pipe().unwrap();
+        File::from(input_write).write_all(input).unwrap();
+
+        pipeline.run(input_read, File::from(output_write)).unwrap();
+
+        let mut output = Vec::new();
+        File::from(output_read).read_to_end(&mut output).unwrap();
+        output
+    }
+}
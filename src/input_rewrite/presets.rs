@@ -0,0 +1,92 @@
+//! `--keys`: named sets of rewrite rules for common keybindings, so that they
+//! need not be written as escapes.
+
+use super::rule::RewriteRule;
+use anyhow::bail;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPreset {
+    /// <kbd>Ctrl+B</kbd>/<kbd>Ctrl+F</kbd>/<kbd>Ctrl+P</kbd>/<kbd>Ctrl+N</kbd>
+    /// as arrows.
+    Emacs,
+    /// <kbd>Option+Left</kbd>/<kbd>Option+Right</kbd> as the word movement of
+    /// `claude`, and <kbd>Shift+Enter</kbd> as a newline, from terminals that
+    /// send them as modified keys.
+    MacOptionArrows,
+}
+
+const PRESETS: &[(&str, KeyPreset)] = &[
+    ("emacs", KeyPreset::Emacs),
+    ("mac-option-arrows", KeyPreset::MacOptionArrows),
+];
+
+impl KeyPreset {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match PRESETS.iter().find(|(name, _)| *name == s) {
+            Some((_, preset)) => Ok(*preset),
+            None => bail!(
+                "unknown preset: {s} (expected {})",
+                PRESETS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// In the form of `--input-rewrite`.
+    fn escaped_rules(self) -> &'static [&'static str] {
+        match self {
+            Self::Emacs => &[r"\x02:\e[D", r"\x06:\e[C", r"\x10:\e[A", r"\x0e:\e[B"],
+            Self::MacOptionArrows => &[
+                // xterm-style modifiers, as iTerm2 and Ghostty send them.
+                r"\e[1;3D:\eb",
+                r"\e[1;3C:\ef",
+                // CSI u and modifyOtherKeys.
+                r"\e[13;2u:\e\r",
+                r"\e[27;2;13~:\e\r",
+            ],
+        }
+    }
+
+    pub fn rules(self) -> Vec<RewriteRule> {
+        self.escaped_rules()
+            .iter()
+            .map(|rule| RewriteRule::parse(rule).expect("presets are valid"))
+            .collect()
+    }
+}
+
+impl fmt::Display for KeyPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = PRESETS
+            .iter()
+            .find(|(_, preset)| preset == self)
+            .expect("every preset has a name");
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for (name, preset) in PRESETS {
+            assert_eq!(KeyPreset::parse(name).unwrap(), *preset);
+            assert_eq!(preset.to_string(), *name);
+            assert_eq!(preset.rules().len(), preset.escaped_rules().len());
+        }
+        let rules = KeyPreset::MacOptionArrows.rules();
+        assert_eq!(rules[0].from(), b"\x1b[1;3D");
+        assert_eq!(rules[0].to(), b"\x1bb");
+        let err = KeyPreset::parse("vi").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown preset: vi (expected emacs, mac-option-arrows)"
+        );
+    }
+}
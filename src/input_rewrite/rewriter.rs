@@ -4,15 +4,26 @@ use nix::poll::{PollFd, PollFlags};
 use std::collections::HashSet;
 use std::io::{self, Write};
 use std::os::fd::AsFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 /// Default timeout for flushing pending prefix bytes (ESC ambiguity resolution)
-const DEFAULT_PENDING_TIMEOUT: Duration = Duration::from_millis(10);
+pub const DEFAULT_PENDING_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Runs when its key is typed; see [`InputRewriter::add_hotkey`].
+type HotkeyAction = Box<dyn FnMut() + Send>;
+
+/// Sees each read first; see [`InputRewriter::set_interceptor`].
+type Interceptor = Box<dyn FnMut(&[u8]) -> bool + Send>;
 
 pub struct InputRewriter {
     rules: Vec<RewriteRule>,
+    hotkeys: Vec<(Vec<u8>, HotkeyAction)>,
+    interceptor: Option<Interceptor>,
     buffer: Vec<u8>,
     pending_timeout: Duration,
+    input_count: Arc<AtomicUsize>,
 }
 
 impl InputRewriter {
@@ -29,11 +40,61 @@ impl InputRewriter {
 
         InputRewriter {
             rules,
+            hotkeys: Vec::new(),
+            interceptor: None,
             buffer: Vec::new(),
             pending_timeout: DEFAULT_PENDING_TIMEOUT,
+            input_count: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Run `action` instead of forwarding `key` when it is typed.  A hotkey
+    /// takes precedence over a rule with the same FROM.
+    pub fn add_hotkey(&mut self, key: Vec<u8>, action: impl FnMut() + Send + 'static) {
+        self.rules.retain(|rule| rule.from() != key);
+        let position = self
+            .rules
+            .partition_point(|rule| rule.from().len() >= key.len());
+        self.rules.insert(
+            position,
+            RewriteRule::new_unchecked(key.clone(), Vec::new()),
+        );
+        self.hotkeys.push((key, Box::new(action)));
+    }
+
+    /// Pass each read to `interceptor` before rewriting it, which returns
+    /// whether it has taken the input, so that it is not forwarded, e.g.
+    /// while a hotkey has a menu open.
+    pub fn set_interceptor(&mut self, interceptor: impl FnMut(&[u8]) -> bool + Send + 'static) {
+        self.interceptor = Some(Box::new(interceptor));
+    }
+
+    /// A rewriter for another source of input, with the same rules and input
+    /// count but without the hotkeys or the interceptor, which are for the
+    /// terminal only.
+    pub fn duplicate(&self) -> Self {
+        let is_hotkey = |rule: &RewriteRule| self.hotkeys.iter().any(|(key, _)| key == rule.from());
+        InputRewriter {
+            rules: self
+                .rules
+                .iter()
+                .filter(|r| !is_hotkey(r))
+                .cloned()
+                .collect(),
+            hotkeys: Vec::new(),
+            interceptor: None,
+            buffer: Vec::new(),
+            pending_timeout: self.pending_timeout,
+            input_count: Arc::clone(&self.input_count),
+        }
+    }
+
+    /// Counts the reads from the terminal, so that other threads can tell
+    /// whether the user has typed anything since.
+    pub fn input_count(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.input_count)
+    }
+
     /// Read from `fd` and write rewritten output to `writer`, using poll(2)
     /// to resolve prefix ambiguity via timeout.
     ///
@@ -68,6 +129,12 @@ impl InputRewriter {
                     return Ok(());
                 }
                 Ok(n) => {
+                    self.input_count.fetch_add(1, Ordering::Relaxed);
+                    if let Some(intercept) = &mut self.interceptor
+                        && intercept(&buf[..n])
+                    {
+                        continue;
+                    }
                     self.push(&buf[..n]);
                     self.drain(writer, false)?;
                     writer.flush()?;
@@ -78,8 +145,10 @@ impl InputRewriter {
         }
     }
 
-    #[cfg(test)]
-    fn with_pending_timeout(mut self, timeout: Duration) -> Self {
+    /// How long to wait for the rest of a key whose bytes are a prefix of a
+    /// rule's FROM, e.g. ESC alone, before forwarding them as they are.
+    #[must_use]
+    pub fn with_pending_timeout(mut self, timeout: Duration) -> Self {
         self.pending_timeout = timeout;
         self
     }
@@ -119,6 +188,11 @@ impl InputRewriter {
                     if !rule.to().is_empty() {
                         writer.write_all(rule.to())?;
                     }
+                    if let Some((_, action)) =
+                        self.hotkeys.iter_mut().find(|(key, _)| key == rule.from())
+                    {
+                        action();
+                    }
                     i += rule.from().len();
                     passthrough_from = i;
                     matched = true;
@@ -148,6 +222,8 @@ mod tests {
     use proptest::property_test;
     use std::collections::HashSet;
     use std::os::fd::{AsFd, OwnedFd};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread;
     use std::time::Duration;
 
@@ -179,6 +255,29 @@ mod tests {
         assert_eq!(rewrite_bytes(&mut rewriter, b"ab"), b"long");
     }
 
+    #[test]
+    fn hotkey_runs_instead_of_rule() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut rewriter = InputRewriter::new(vec![
+            RewriteRule::parse(r"\x1bc:x").unwrap(),
+            RewriteRule::parse(r"\x1b:y").unwrap(),
+        ]);
+        let counter = Arc::clone(&count);
+        rewriter.add_hotkey(b"\x1bc".to_vec(), move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(rewrite_bytes(&mut rewriter, b"a\x1bcb\x1bc\x1b"), b"aby");
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn interceptor_takes_input() {
+        let mut rewriter = InputRewriter::new(vec![RewriteRule::parse("a:b").unwrap()]);
+        rewriter.set_interceptor(|input| input.starts_with(b"x"));
+        assert_eq!(rewrite_bytes(&mut rewriter, b"xa"), b"");
+        assert_eq!(rewrite_bytes(&mut rewriter, b"ax"), b"bx");
+    }
+
     #[test]
     fn eof_flushes_unmatched_buffer_as_raw() {
         let mut rewriter = InputRewriter::new(vec![RewriteRule::parse("abc:x").unwrap()]);
@@ -1,4 +1,4 @@
-use super::escape::{EscapeError, parse_escaped_str};
+use super::escape::{EscapeError, escape_bytes, parse_escaped_str};
 use std::fmt;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -23,6 +23,11 @@ impl RewriteRule {
         Ok(RewriteRule { from, to })
     }
 
+    pub fn new_unchecked(from: Vec<u8>, to: Vec<u8>) -> Self {
+        debug_assert!(!from.is_empty(), "FROM must be non-empty");
+        RewriteRule { from, to }
+    }
+
     pub fn from(&self) -> &[u8] {
         &self.from
     }
@@ -32,6 +37,13 @@ impl RewriteRule {
     }
 }
 
+/// Formats the rule in the form accepted by [`RewriteRule::parse`].
+impl fmt::Display for RewriteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", escape_bytes(&self.from), escape_bytes(&self.to))
+    }
+}
+
 #[derive(Debug)]
 pub enum ParseError {
     MissingColon,
@@ -72,15 +84,13 @@ pub(super) mod tests {
     }
 
     #[property_test]
-    fn parse_rejects_invalid(#[strategy = arb_invalid_rule_string()] invalid: String) {
-        prop_assert!(RewriteRule::parse(&invalid).is_err());
+    fn display_round_trips(#[strategy = arb_rule()] (rule, _): (RewriteRule, String)) {
+        prop_assert_eq!(RewriteRule::parse(&rule.to_string()).unwrap(), rule);
     }
 
-    impl RewriteRule {
-        pub fn new_unchecked(from: Vec<u8>, to: Vec<u8>) -> Self {
-            debug_assert!(!from.is_empty(), "FROM must be non-empty");
-            RewriteRule { from, to }
-        }
+    #[property_test]
+    fn parse_rejects_invalid(#[strategy = arb_invalid_rule_string()] invalid: String) {
+        prop_assert!(RewriteRule::parse(&invalid).is_err());
     }
 
     pub fn arb_rule() -> impl Strategy<Value = (RewriteRule, String)> {
@@ -0,0 +1,74 @@
+//! Parser for `--rewrite-file`.
+//!
+//! Format: one `FROM:TO` rule per line, as accepted by `--input-rewrite`.
+//! Blank lines and lines starting with `#` are ignored, and surrounding
+//! whitespace is trimmed, so a space or `#` at either end of a rule is written
+//! as `\x20` or `\x23`.
+
+use super::rule::{self, RewriteRule};
+use std::fmt;
+
+pub fn parse(s: &str) -> Result<Vec<RewriteRule>, ParseError> {
+    s.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            (!line.is_empty() && !line.starts_with('#')).then_some((i + 1, line))
+        })
+        .map(|(line_number, line)| {
+            RewriteRule::parse(line).map_err(|kind| ParseError { line_number, kind })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    line_number: usize,
+    kind: rule::ParseError,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse_file() {
+        let rules = parse(indoc! {r"
+            # Emacs-style cursor movement
+            \x02:\e[D
+            \x06:\e[C
+
+              \x1bb:\x02
+        "})
+        .unwrap();
+        assert_eq!(
+            rules,
+            [
+                RewriteRule::new_unchecked(b"\x02".to_vec(), b"\x1b[D".to_vec()),
+                RewriteRule::new_unchecked(b"\x06".to_vec(), b"\x1b[C".to_vec()),
+                RewriteRule::new_unchecked(b"\x1bb".to_vec(), b"\x02".to_vec()),
+            ]
+        );
+        assert!(parse("# nothing\n\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let err = parse("\\x02:\\e[D\n# comment\n\\x06\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: missing ':' separator in rewrite rule"
+        );
+        let err = parse(":\\e[D").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: FROM pattern cannot be empty");
+    }
+}
@@ -0,0 +1,74 @@
+//! Suspending caloud together with `claude`, as a shell would suspend
+//! `claude` run without caloud.
+//!
+//! The terminal is raw, so <kbd>Ctrl+Z</kbd> reaches `claude` as input, and
+//! it stops itself; SIGTSTP sent to caloud, e.g. by `kill -TSTP`, is passed
+//! on to it.  Either way caloud follows once `claude` has stopped: it
+//! restores the terminal and stops itself, and when continued, e.g. by `fg`,
+//! makes the terminal raw again and continues `claude`, which is sent the
+//! window size as on SIGWINCH.
+
+use crate::debug_log::DebugLog;
+use anyhow::Context;
+use nix::sys::signal::{SigSet, Signal, kill, killpg};
+use nix::sys::termios::{SetArg, Termios, cfmakeraw, tcsetattr};
+use nix::unistd::Pid;
+use std::io;
+use std::thread;
+
+pub struct JobControl {
+    child: Pid,
+    /// The terminal as caloud found it.
+    termios: Termios,
+    debug_log: DebugLog,
+}
+
+impl JobControl {
+    pub fn new(child: Pid, termios: Termios, debug_log: DebugLog) -> Self {
+        Self {
+            child,
+            termios,
+            debug_log,
+        }
+    }
+
+    /// Once `claude` has stopped: stop caloud until it is continued, then
+    /// continue `claude`.
+    pub fn suspend(&self) {
+        if let Err(e) = self.try_suspend() {
+            self.debug_log.log(format_args!("job control: {e:#}"));
+        }
+    }
+
+    fn try_suspend(&self) -> anyhow::Result<()> {
+        tcsetattr(io::stdin(), SetArg::TCSANOW, &self.termios).context("tcsetattr() failed")?;
+        // Unlike SIGTSTP, SIGSTOP cannot be blocked; this returns once
+        // continued.
+        kill(Pid::this(), Signal::SIGSTOP).context("failed to stop")?;
+        let mut raw = self.termios.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(io::stdin(), SetArg::TCSANOW, &raw).context("tcsetattr() failed")?;
+        // `claude` redraws on SIGWINCH.  The window size, which may have
+        // changed meanwhile, is propagated on SIGCONT by the winsize updater.
+        killpg(self.child, Signal::SIGWINCH).context("failed to signal claude")?;
+        killpg(self.child, Signal::SIGCONT).context("failed to continue claude")?;
+        Ok(())
+    }
+}
+
+/// Pass SIGTSTP sent to caloud on to `claude`, which caloud follows when it
+/// stops.  Call before spawning any other thread, so that none of them takes
+/// SIGTSTP and stops caloud with the terminal left raw.
+pub fn forward_sigtstp(child: Pid) -> anyhow::Result<()> {
+    let mut sigset = SigSet::empty();
+    sigset.add(Signal::SIGTSTP);
+    sigset.thread_block().context("failed to block SIGTSTP")?;
+    thread::spawn(move || {
+        while let Ok(signal) = sigset.wait() {
+            if signal == Signal::SIGTSTP && killpg(child, Signal::SIGTSTP).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
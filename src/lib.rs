@@ -0,0 +1,8 @@
+//! The output processing of caloud, for other programs to embed: splitting
+//! terminal output into fragments, reformatting it, and finding the
+//! notifications, questions, and messages of `claude` in it.
+//!
+//! [`ReformatWriter`](tty_text::reformat_writer::ReformatWriter) runs any
+//! byte stream through it.
+
+pub mod tty_text;
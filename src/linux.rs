@@ -0,0 +1,3 @@
+pub mod notify_send;
+pub mod platform;
+pub mod tts;
@@ -0,0 +1,73 @@
+//! Notifications through `notify-send`, which hands them to the
+//! `org.freedesktop.Notifications` service of the desktop.
+
+use crate::event::Notification;
+use anyhow::{Context, bail};
+use std::process::{Command, Stdio};
+
+pub fn deliver(notification: &Notification) -> anyhow::Result<()> {
+    let output = Command::new("notify-send")
+        .args(arguments(notification))
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to run notify-send")?;
+    if !output.status.success() {
+        bail!(
+            "notify-send failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// The freedesktop specification has no subtitle, so it heads the body.  A
+/// permission request is critical, which keeps it on screen until dismissed.
+fn arguments(notification: &Notification) -> Vec<String> {
+    let mut args = vec!["--app-name=caloud".to_string()];
+    if notification.is_urgent {
+        args.push("--urgency=critical".to_string());
+    }
+    let body = match &notification.subtitle {
+        Some(subtitle) => format!("{subtitle}\n{}", notification.message),
+        None => notification.message.clone(),
+    };
+    // Neither may be taken for an option.
+    args.extend(["--".to_string(), notification.title.clone(), body]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_send_arguments() {
+        let notification = Notification {
+            title: "Claude Code".to_string(),
+            subtitle: Some("caloud".to_string()),
+            message: "Claude needs your permission to use Bash".to_string(),
+            is_urgent: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            arguments(&notification),
+            [
+                "--app-name=caloud",
+                "--urgency=critical",
+                "--",
+                "Claude Code",
+                "caloud\nClaude needs your permission to use Bash",
+            ]
+        );
+        let notification = Notification {
+            subtitle: None,
+            message: "-done".to_string(),
+            is_urgent: false,
+            ..notification
+        };
+        assert_eq!(
+            arguments(&notification),
+            ["--app-name=caloud", "--", "Claude Code", "-done"]
+        );
+    }
+}
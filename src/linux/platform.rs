@@ -0,0 +1,52 @@
+//! The [`Platform`] of Linux desktops.
+
+use crate::event::Notification;
+use crate::linux::{notify_send, tts};
+use crate::macos::say::Voice;
+use crate::platform::Platform;
+use anyhow::bail;
+use std::process::Child;
+
+pub struct Linux;
+
+impl Platform for Linux {
+    const NOTIFIER: &str = "notify-send";
+    const FALLBACK_NOTIFIER: &str = "nothing";
+    const SOUND_PLAYER: &str = "paplay";
+
+    fn deliver(notification: &Notification) -> anyhow::Result<bool> {
+        notify_send::deliver(notification)?;
+        Ok(true)
+    }
+
+    fn deliver_fallback(_notification: &Notification) -> anyhow::Result<()> {
+        bail!("no other way to show notifications")
+    }
+
+    fn speak(args: &[String], text: &str) -> anyhow::Result<Child> {
+        tts::spawn(args, text)
+    }
+
+    /// TTS commands have no dry run in common, so theirs are taken as given.
+    fn validate_speech(_args: &[String]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn list_voices() -> anyhow::Result<Vec<Voice>> {
+        bail!("the TTS command lists its voices, e.g. `espeak-ng --voices`")
+    }
+
+    /// Terminals are not told apart, so none is known to show OSC 9 or to be
+    /// in sight.
+    fn host_terminal() -> Option<String> {
+        None
+    }
+
+    fn is_osc9_supported() -> bool {
+        false
+    }
+
+    fn is_host_terminal_active() -> bool {
+        false
+    }
+}
@@ -0,0 +1,76 @@
+//! Speech through a TTS command that reads the text on stdin: `espeak-ng`
+//! by default, or the command `--say` names, e.g. `--say='piper --model
+//! en_US-amy-medium.onnx --output-raw'`.
+
+use anyhow::Context;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+const DEFAULT_COMMAND: &str = "espeak-ng";
+
+/// Start speaking `text`; the caller waits for the child, or kills it to stop
+/// in the middle.
+pub fn spawn(args: &[String], text: &str) -> anyhow::Result<Child> {
+    let (program, args) = command(args);
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {program}"))?;
+    let mut stdin = child.stdin.take().context("no stdin")?;
+    let text = text.to_string();
+    // Not to block on a long text while the command is still starting.
+    thread::spawn(move || {
+        let _ = stdin.write_all(text.as_bytes());
+    });
+    Ok(child)
+}
+
+/// Arguments that start with an option, e.g. `-v en-us` as for `say`, are
+/// for the default command; otherwise the first names the command.
+fn command(args: &[String]) -> (&str, &[String]) {
+    match args.split_first() {
+        Some((program, args)) if !program.starts_with('-') => (program, args),
+        _ => (DEFAULT_COMMAND, args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(command(&[]), (DEFAULT_COMMAND, &[][..]));
+        let args = strings(&["-v", "en-us"]);
+        assert_eq!(command(&args), (DEFAULT_COMMAND, &args[..]));
+        let args = strings(&["piper", "--model", "amy.onnx"]);
+        assert_eq!(command(&args), ("piper", &args[1..]));
+    }
+
+    #[test]
+    fn text_is_given_on_stdin() {
+        let path = std::env::temp_dir().join(format!("caloud-tts-{}", std::process::id()));
+        let args = strings(&["sh", "-c", &format!("cat > {}", path.display())]);
+        assert!(
+            spawn(&args, "Build finished")
+                .unwrap()
+                .wait()
+                .unwrap()
+                .success()
+        );
+        let mut spoken = String::new();
+        std::fs::File::open(&path)
+            .unwrap()
+            .read_to_string(&mut spoken)
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(spoken, "Build finished");
+    }
+}
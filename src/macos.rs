@@ -1,4 +1,26 @@
+#[cfg(target_os = "macos")]
+pub mod accessibility;
+pub mod appearance;
+#[cfg(target_os = "macos")]
 pub mod application;
+pub mod dialog;
+#[cfg(target_os = "macos")]
+pub mod distributed_notification;
+#[cfg(target_os = "macos")]
+pub mod focus;
+#[cfg(target_os = "macos")]
+pub mod fs_events;
+#[cfg(target_os = "macos")]
 pub mod notification;
+pub mod pasteboard;
+#[cfg(target_os = "macos")]
+pub mod platform;
+pub mod resource_usage;
+#[cfg(target_os = "macos")]
+pub mod run_loop;
 pub mod say;
+pub mod sound;
+#[cfg(target_os = "macos")]
 pub mod sys_proc_info;
+#[cfg(target_os = "macos")]
+pub mod unicode;
@@ -0,0 +1,29 @@
+//! VoiceOver announcements.
+//!
+//! Unlike `say`, an announcement is spoken by VoiceOver itself, so it follows
+//! the user's VoiceOver voice, verbosity, and braille display settings and
+//! does not talk over VoiceOver.  Nothing is announced while VoiceOver is off.
+
+use objc2::runtime::AnyObject;
+use objc2_app_kit::{
+    NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+    NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityKey,
+    NSAccessibilityPriorityLevel, NSApplication,
+};
+use objc2_foundation::{MainThreadMarker, NSDictionary, NSNumber, NSString};
+
+/// Ask VoiceOver to read `message` out, interrupting less important speech.
+pub fn announce(main_thread_marker: MainThreadMarker, message: &str) {
+    let application = NSApplication::sharedApplication(main_thread_marker);
+    let message = NSString::from_str(message);
+    let priority = NSNumber::new_isize(NSAccessibilityPriorityLevel::High.0);
+    let keys = unsafe { [NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey] };
+    let user_info = NSDictionary::<NSString, AnyObject>::from_slices(&keys, &[&message, &priority]);
+    unsafe {
+        NSAccessibilityPostNotificationWithUserInfo(
+            &application,
+            NSAccessibilityAnnouncementRequestedNotification,
+            Some(&user_info),
+        );
+    }
+}
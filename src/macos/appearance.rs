@@ -0,0 +1,137 @@
+//! `--appearance`: tell `claude` whether the system is in dark or light mode
+//! when the host terminal does not answer its color queries.
+
+use anyhow::{Context, bail};
+use std::fmt;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppearanceMode {
+    /// Follow the macOS appearance, including changes while `claude` runs.
+    Auto,
+    Fixed(Appearance),
+}
+
+impl AppearanceMode {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "dark" => Ok(Self::Fixed(Appearance::Dark)),
+            "light" => Ok(Self::Fixed(Appearance::Light)),
+            _ => bail!("unknown appearance: {s} (expected auto, dark, light)"),
+        }
+    }
+
+    /// The appearance now; `Auto` falls back to light, the macOS default.
+    pub fn resolve(self) -> Appearance {
+        match self {
+            Self::Auto => detect().unwrap_or(Appearance::Light),
+            Self::Fixed(appearance) => appearance,
+        }
+    }
+}
+
+impl fmt::Display for AppearanceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Fixed(Appearance::Dark) => "dark",
+            Self::Fixed(Appearance::Light) => "light",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Dark,
+    Light,
+}
+
+impl Appearance {
+    /// `COLORFGBG`: the foreground and background as ANSI color numbers.
+    pub fn colorfgbg(self) -> &'static str {
+        match self {
+            Self::Dark => "15;0",
+            Self::Light => "0;15",
+        }
+    }
+
+    /// The answer to `OSC 10 ; ? ST` (foreground) or `OSC 11 ; ? ST`
+    /// (background), with the terminator of the query.
+    pub fn color_report(self, ps: &[u8], terminator: &[u8]) -> Option<Vec<u8>> {
+        let (foreground, background) = match self {
+            Self::Dark => ("ffff/ffff/ffff", "0000/0000/0000"),
+            Self::Light => ("0000/0000/0000", "ffff/ffff/ffff"),
+        };
+        let color = match ps {
+            b"10" => foreground,
+            b"11" => background,
+            _ => return None,
+        };
+        let mut report = b"\x1b]".to_vec();
+        report.extend_from_slice(ps);
+        report.extend_from_slice(format!(";rgb:{color}").as_bytes());
+        report.extend_from_slice(terminator);
+        Some(report)
+    }
+
+    /// The unsolicited report of mode 2031 (color palette updates):
+    /// `CSI ? 997 ; 1 n` for dark and `CSI ? 997 ; 2 n` for light.
+    ///
+    /// <https://contour-terminal.org/vt-extensions/color-palette-update-notifications/>
+    pub fn color_scheme_report(self) -> &'static [u8] {
+        match self {
+            Self::Dark => b"\x1b[?997;1n",
+            Self::Light => b"\x1b[?997;2n",
+        }
+    }
+}
+
+/// The current macOS appearance, as set in System Settings > Appearance.
+pub fn detect() -> anyhow::Result<Appearance> {
+    // The key is absent in light mode, which `defaults` reports as an error.
+    let output = Command::new("defaults")
+        .args(["read", "-g", "AppleInterfaceStyle"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .context("failed to run defaults")?;
+    Ok(parse_interface_style(
+        output.status.success(),
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+fn parse_interface_style(is_set: bool, style: &str) -> Appearance {
+    match is_set && style.trim() == "Dark" {
+        true => Appearance::Dark,
+        false => Appearance::Light,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(AppearanceMode::parse("auto").unwrap(), AppearanceMode::Auto);
+        assert_eq!(AppearanceMode::parse("dark").unwrap().to_string(), "dark");
+        assert!(AppearanceMode::parse("sepia").is_err());
+        assert_eq!(parse_interface_style(true, "Dark\n"), Appearance::Dark);
+        assert_eq!(parse_interface_style(false, ""), Appearance::Light);
+    }
+
+    #[test]
+    fn color_reports() {
+        assert_eq!(
+            Appearance::Dark.color_report(b"11", b"\x1b\\").unwrap(),
+            b"\x1b]11;rgb:0000/0000/0000\x1b\\"
+        );
+        assert_eq!(
+            Appearance::Light.color_report(b"10", b"\x07").unwrap(),
+            b"\x1b]10;rgb:0000/0000/0000\x07"
+        );
+        assert_eq!(Appearance::Dark.color_report(b"12", b"\x07"), None);
+    }
+}
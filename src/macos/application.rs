@@ -20,6 +20,11 @@ pub fn activate_host_application() -> bool {
         .unwrap_or_default()
 }
 
+/// Whether the terminal is the frontmost app, i.e. the user is looking at it.
+pub fn is_host_application_active() -> bool {
+    find_host_application().is_some_and(|app| app.isActive())
+}
+
 pub fn find_host_application() -> Option<Retained<NSRunningApplication>> {
     iterate_ancestor_pids().find_map(|pid| {
         NSRunningApplication::runningApplicationWithProcessIdentifier(pid)
@@ -0,0 +1,32 @@
+//! Asking the user through a dialog, with `osascript`.
+
+use anyhow::Context;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// The button that [`confirm`] returns `true` for.
+const CONFIRM_BUTTON: &str = "Answer";
+
+/// Show `message` with Skip and Answer buttons, returning whether Answer was
+/// clicked.  Skip is the default, so that a stray Return does not answer, and
+/// the dialog closes as skipped after `timeout`.
+pub fn confirm(title: &str, message: &str, timeout: Duration) -> anyhow::Result<bool> {
+    // The texts are passed as arguments, so they need no AppleScript quoting.
+    let script = format!(
+        "on run argv
+            display dialog (item 2 of argv) with title (item 1 of argv) \
+                buttons {{\"Skip\", \"{CONFIRM_BUTTON}\"}} default button \"Skip\" \
+                giving up after {}
+            return button returned of result
+        end run",
+        timeout.as_secs().max(1)
+    );
+    let output = Command::new("osascript")
+        .args(["-e", &script, title, message])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .context("failed to run osascript")?;
+    // Giving up reports an empty button.
+    Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == CONFIRM_BUTTON)
+}
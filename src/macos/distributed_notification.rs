@@ -0,0 +1,33 @@
+//! Posting to the distributed notification center, which every process of the
+//! user can observe.
+
+use objc2::rc::Retained;
+use objc2_foundation::{NSDictionary, NSDistributedNotificationCenter, NSString};
+
+/// Post `name` with string values in its user info.
+pub fn post(name: &str, user_info: &[(&str, String)]) {
+    let keys: Vec<_> = user_info
+        .iter()
+        .map(|(key, _)| NSString::from_str(key))
+        .collect();
+    let values: Vec<_> = user_info
+        .iter()
+        .map(|(_, value)| NSString::from_str(value))
+        .collect();
+    let keys: Vec<&NSString> = keys.iter().map(|key| &**key).collect();
+    let values: Vec<&NSString> = values.iter().map(|value| &**value).collect();
+    let user_info = NSDictionary::<NSString, NSString>::from_slices(&keys, &values);
+    // The method takes the dictionary with its type parameters erased.
+    let user_info = unsafe { Retained::cast_unchecked::<NSDictionary>(user_info) };
+    let center = NSDistributedNotificationCenter::defaultCenter();
+    // Delivered right away even to observers that are suspended, e.g. in the
+    // background.
+    unsafe {
+        center.postNotificationName_object_userInfo_deliverImmediately(
+            &NSString::from_str(name),
+            None,
+            Some(&user_info),
+            true,
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! The Focus that is on, e.g. Work or Personal, as recorded by macOS in
+//! `~/Library/DoNotDisturb/DB`.  Reading it needs Full Disk Access for the
+//! terminal on recent versions of macOS.
+
+use crate::focus_policy::Focus;
+use anyhow::Context;
+use regex::Regex;
+use std::path::PathBuf;
+
+/// The Focus that is on, or `None` if none is.
+pub fn current() -> anyhow::Result<Option<Focus>> {
+    let directory = std::env::home_dir()
+        .context("no home directory")?
+        .join("Library/DoNotDisturb/DB");
+    let assertions = read(directory.join("Assertions.json"))?;
+    let Some(identifier) = parse_mode_identifier(&assertions) else {
+        return Ok(None);
+    };
+    let name = read(directory.join("ModeConfigurations.json"))
+        .ok()
+        .and_then(|configurations| parse_mode_name(&configurations, &identifier));
+    Ok(Some(Focus { identifier, name }))
+}
+
+fn read(path: PathBuf) -> anyhow::Result<String> {
+    std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// The mode of the first assertion, i.e. of the Focus turned on by hand.
+fn parse_mode_identifier(assertions: &str) -> Option<String> {
+    let regex = Regex::new(r#""assertionDetailsModeIdentifier"\s*:\s*"([^"]+)""#)
+        .expect("the pattern must be valid");
+    Some(regex.captures(assertions)?[1].to_string())
+}
+
+/// The name in the configuration of the mode `identifier`, whose object has
+/// no nested objects.
+fn parse_mode_name(configurations: &str, identifier: &str) -> Option<String> {
+    let mode = Regex::new(&format!(
+        r#"\{{[^{{}}]*"modeIdentifier"\s*:\s*"{}"[^{{}}]*\}}"#,
+        regex::escape(identifier)
+    ))
+    .expect("the pattern must be valid");
+    let name =
+        Regex::new(r#""name"\s*:\s*"((?:[^"\\]|\\.)*)""#).expect("the pattern must be valid");
+    let mode = mode.find(configurations)?.as_str();
+    Some(name.captures(mode)?[1].replace(r#"\""#, "\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse() {
+        let assertions = indoc! {r#"
+            {"data":[{"storeAssertionRecords":[{"assertionUUID":"A1",
+            "assertionDetails":{"assertionDetailsReason":"user-action",
+            "assertionDetailsModeIdentifier":"com.apple.focus.work",
+            "assertionDetailsIdentifier":"com.apple.controlcenter.dnd"}}]}]}
+        "#};
+        let configurations = indoc! {r#"
+            {"data":[{"modeConfigurations":{
+            "com.apple.focus.personal-time":{"mode":{"name":"Personal",
+            "modeIdentifier":"com.apple.focus.personal-time"}},
+            "com.apple.focus.work":{"mode":{"name":"Work",
+            "modeIdentifier":"com.apple.focus.work","semanticType":1}}}}]}
+        "#};
+        let identifier = parse_mode_identifier(assertions).unwrap();
+        assert_eq!(identifier, "com.apple.focus.work");
+        assert_eq!(
+            parse_mode_name(configurations, &identifier).as_deref(),
+            Some("Work")
+        );
+        assert_eq!(
+            parse_mode_name(configurations, "com.apple.focus.gaming"),
+            None
+        );
+        assert_eq!(
+            parse_mode_identifier(r#"{"data":[{"storeAssertionRecords":[]}]}"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Watch a directory tree for changed files with FSEvents.
+//!
+//! <https://developer.apple.com/documentation/coreservices/file_system_events>
+
+use anyhow::bail;
+use objc2::rc::Retained;
+use objc2_foundation::{NSArray, NSString};
+use std::ffi::{CStr, c_char, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Changes within this many seconds are reported together.
+const LATENCY: f64 = 1.0;
+
+const EVENT_ID_SINCE_NOW: u64 = u64::MAX;
+/// Report each file rather than only the directories containing changes.
+const CREATE_FLAG_FILE_EVENTS: u32 = 0x10;
+
+const ITEM_CREATED: u32 = 0x100;
+const ITEM_REMOVED: u32 = 0x200;
+const ITEM_RENAMED: u32 = 0x800;
+const ITEM_MODIFIED: u32 = 0x1000;
+const ITEM_IS_FILE: u32 = 0x10000;
+
+type Callback = Box<dyn Fn(&[PathBuf]) + Send + Sync>;
+
+#[repr(C)]
+struct FSEventStreamContext {
+    version: isize,
+    info: *mut c_void,
+    retain: Option<extern "C" fn(*const c_void) -> *const c_void>,
+    release: Option<extern "C" fn(*const c_void)>,
+    copy_description: Option<extern "C" fn(*const c_void) -> *const c_void>,
+}
+
+type FSEventStreamCallback = extern "C" fn(
+    stream: *const c_void,
+    info: *mut c_void,
+    num_events: usize,
+    event_paths: *mut c_void,
+    event_flags: *const u32,
+    event_ids: *const u64,
+);
+
+#[link(name = "CoreServices", kind = "framework")]
+unsafe extern "C" {
+    fn FSEventStreamCreate(
+        allocator: *const c_void,
+        callback: FSEventStreamCallback,
+        context: *const FSEventStreamContext,
+        paths_to_watch: *const c_void,
+        since_when: u64,
+        latency: f64,
+        flags: u32,
+    ) -> *mut c_void;
+    fn FSEventStreamSetDispatchQueue(stream: *mut c_void, queue: *mut c_void);
+    fn FSEventStreamStart(stream: *mut c_void) -> u8;
+}
+
+unsafe extern "C" {
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+}
+
+/// Call `on_change` on a background queue with the files created, modified,
+/// renamed, or removed under `dir`, a batch at a time, for the rest of the
+/// process.
+pub fn watch(
+    dir: &Path,
+    on_change: impl Fn(&[PathBuf]) + Send + Sync + 'static,
+) -> anyhow::Result<()> {
+    let Some(dir) = dir.to_str() else {
+        bail!("not valid UTF-8: {}", dir.display());
+    };
+    let paths: Retained<NSArray<NSString>> =
+        NSArray::from_retained_slice(&[NSString::from_str(dir)]);
+    // The stream is never stopped, so neither it nor the callback is freed.
+    let callback: &'static Callback = Box::leak(Box::new(Box::new(on_change) as Callback));
+    let context = FSEventStreamContext {
+        version: 0,
+        info: std::ptr::from_ref(callback).cast_mut().cast(),
+        retain: None,
+        release: None,
+        copy_description: None,
+    };
+    unsafe {
+        // NSArray is toll-free bridged to CFArray.
+        let stream = FSEventStreamCreate(
+            std::ptr::null(),
+            on_events,
+            &context,
+            Retained::as_ptr(&paths).cast(),
+            EVENT_ID_SINCE_NOW,
+            LATENCY,
+            CREATE_FLAG_FILE_EVENTS,
+        );
+        if stream.is_null() {
+            bail!("FSEventStreamCreate failed for {dir}");
+        }
+        let queue = dispatch_queue_create(c"caloud.fs-events".as_ptr(), std::ptr::null());
+        FSEventStreamSetDispatchQueue(stream, queue);
+        if FSEventStreamStart(stream) == 0 {
+            bail!("FSEventStreamStart failed for {dir}");
+        }
+    }
+    Ok(())
+}
+
+extern "C" fn on_events(
+    _stream: *const c_void,
+    info: *mut c_void,
+    num_events: usize,
+    event_paths: *mut c_void,
+    event_flags: *const u32,
+    _event_ids: *const u64,
+) {
+    // Without kFSEventStreamCreateFlagUseCFTypes, the paths are C strings.
+    let (callback, paths, flags) = unsafe {
+        (
+            &*info.cast::<Callback>(),
+            std::slice::from_raw_parts(event_paths.cast::<*const c_char>(), num_events),
+            std::slice::from_raw_parts(event_flags, num_events),
+        )
+    };
+    let changed = ITEM_CREATED | ITEM_REMOVED | ITEM_RENAMED | ITEM_MODIFIED;
+    let mut files: Vec<PathBuf> = Vec::new();
+    for (&path, &flags) in paths.iter().zip(flags) {
+        if flags & ITEM_IS_FILE == 0 || flags & changed == 0 {
+            continue;
+        }
+        let path = Path::new(std::ffi::OsStr::from_bytes(
+            unsafe { CStr::from_ptr(path) }.to_bytes(),
+        ));
+        if !files.iter().any(|file| file == path) {
+            files.push(path.to_path_buf());
+        }
+    }
+    if !files.is_empty() {
+        callback(&files);
+    }
+}
@@ -9,18 +9,36 @@
 //! activated using the functionality from the [`application`](super::application) module.
 
 use super::application::{activate_host_application, find_host_application};
-use anyhow::bail;
+use crate::event::Notification;
+use anyhow::{Context, bail};
 use objc2::ffi::{class_getInstanceMethod, method_exchangeImplementations};
 use objc2::rc::Retained;
 use objc2::runtime::{NSObject, ProtocolObject};
 use objc2::{ClassType, MainThreadOnly, class, define_class, msg_send, sel};
+use objc2_app_kit::NSBeep;
 use objc2_foundation::{
-    MainThreadMarker, NSObjectProtocol, NSString, NSUserNotificationCenterDelegate, ns_string,
+    MainThreadMarker, NSBundle, NSObjectProtocol, NSString, NSUserNotificationCenterDelegate,
+    ns_string,
 };
 #[expect(deprecated)]
-use objc2_foundation::{NSUserNotification, NSUserNotificationCenter};
+use objc2_foundation::{
+    NSUserNotification, NSUserNotificationCenter, NSUserNotificationDefaultSoundName,
+};
 use std::mem;
+use std::process::{Command, Stdio};
 use std::sync::Once;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bundle identifier that notifications are delivered as.
+const TERMINAL_BUNDLE_ID: &str = "com.apple.Terminal";
+
+/// Clicks on the notifications delivered, for `caloud notify` to report.
+static ACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// How many of the notifications delivered have been clicked so far.
+pub fn activation_count() -> usize {
+    ACTIVATIONS.load(Ordering::Relaxed)
+}
 
 pub fn set_global_delegate() -> anyhow::Result<()> {
     let Some(main_thread_marker) = MainThreadMarker::new() else {
@@ -43,6 +61,7 @@ pub fn set_global_delegate() -> anyhow::Result<()> {
                 center: &NSUserNotificationCenter,
                 notification: &NSUserNotification,
             ) {
+                ACTIVATIONS.fetch_add(1, Ordering::Relaxed);
                 let _ = activate_host_application();
                 center.removeDeliveredNotification(notification);
             }
@@ -67,23 +86,90 @@ pub fn set_global_delegate() -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn deliver_if_osc9_unsupported(title: &str, message: &str) -> anyhow::Result<bool> {
+pub fn deliver_if_osc9_unsupported(notification: &Notification) -> anyhow::Result<bool> {
     if is_osc9_supported() {
         return Ok(false);
     }
 
+    // Without the swizzle, Notification Center ignores the notifications of
+    // an executable outside an app bundle.
+    let bundle_identifier = NSBundle::mainBundle().bundleIdentifier();
+    if bundle_identifier.is_none_or(|identifier| identifier.to_string() != TERMINAL_BUNDLE_ID) {
+        bail!("bundleIdentifier is not swizzled");
+    }
+
     #[expect(deprecated)]
     {
-        let notification = NSUserNotification::new();
-        notification.setTitle(Some(&NSString::from_str(title)));
-        notification.setInformativeText(Some(&NSString::from_str(message)));
-        NSUserNotificationCenter::defaultUserNotificationCenter()
-            .deliverNotification(&notification);
+        let center: Option<Retained<NSUserNotificationCenter>> = unsafe {
+            msg_send![
+                NSUserNotificationCenter::class(),
+                defaultUserNotificationCenter
+            ]
+        };
+        let Some(center) = center else {
+            bail!("no default user notification center");
+        };
+        let user_notification = NSUserNotification::new();
+        user_notification.setTitle(Some(&NSString::from_str(&notification.title)));
+        if let Some(subtitle) = &notification.subtitle {
+            user_notification.setSubtitle(Some(&NSString::from_str(subtitle)));
+        }
+        user_notification.setInformativeText(Some(&NSString::from_str(&notification.message)));
+        // `sound=Glass` names a sound in /System/Library/Sounds, and a later
+        // notification with the same `id` replaces this one.
+        match notification.parameter("sound") {
+            Some(sound) => user_notification.setSoundName(Some(&NSString::from_str(sound))),
+            None if notification.is_urgent => {
+                user_notification.setSoundName(Some(unsafe { NSUserNotificationDefaultSoundName }))
+            }
+            None => {}
+        }
+        if let Some(id) = notification.parameter("id") {
+            user_notification.setIdentifier(Some(&NSString::from_str(id)));
+        }
+        center.deliverNotification(&user_notification);
+        if user_notification.deliveryDate().is_none() {
+            bail!("not delivered; notifications may not be allowed for Terminal");
+        }
     }
 
     Ok(true)
 }
 
+/// Deliver `notification` with AppleScript's `display notification`, which
+/// needs no swizzle but shows it as from Script Editor.  Tried when
+/// Notification Center delivery fails.
+pub fn deliver_with_osascript(notification: &Notification) -> anyhow::Result<()> {
+    let output = Command::new("osascript")
+        .args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 3 of argv) with title (item 1 of argv) subtitle (item 2 of argv)",
+            "-e",
+            "end run",
+            &notification.title,
+            notification.subtitle.as_deref().unwrap_or_default(),
+            &notification.message,
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to run osascript")?;
+    if !output.status.success() {
+        bail!(
+            "osascript failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Play the alert sound chosen in System Settings, in place of a
+/// notification that cannot be shown.
+pub fn beep() {
+    NSBeep();
+}
+
 fn swizzle_bundle_identifier() {
     define_class!(
         #[unsafe(super(NSObject))]
@@ -93,7 +179,7 @@ fn swizzle_bundle_identifier() {
         impl FakeBundle {
             #[unsafe(method(bundleIdentifier))]
             fn bundle_identifier(&self) -> &NSString {
-                ns_string!("com.apple.Terminal")
+                ns_string!(TERMINAL_BUNDLE_ID)
             }
         }
     );
@@ -106,7 +192,7 @@ fn swizzle_bundle_identifier() {
     }
 }
 
-fn is_osc9_supported() -> bool {
+pub fn is_osc9_supported() -> bool {
     const GHOSTTY: &str = "com.mitchellh.ghostty"; // https://ghostty.org/docs/config/reference#desktop-notifications
     const ITERM2: &str = "com.googlecode.iterm2"; // https://iterm2.com/documentation-escape-codes.html
 
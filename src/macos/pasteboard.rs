@@ -0,0 +1,36 @@
+//! Copying text to the general pasteboard.
+
+#[cfg(target_os = "macos")]
+use anyhow::{Context, ensure};
+#[cfg(target_os = "macos")]
+use std::io::Write;
+#[cfg(target_os = "macos")]
+use std::process::{Command, Stdio};
+
+/// Replace the contents of the pasteboard with `text`, as `pbcopy` does.
+#[cfg(target_os = "macos")]
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new("pbcopy")
+        // pbcopy decodes its input by the locale, which may not be UTF-8
+        // when caloud is started outside a login shell.
+        .env("LC_CTYPE", "UTF-8")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run pbcopy")?;
+    let mut stdin = child.stdin.take().context("pbcopy has no stdin")?;
+    stdin
+        .write_all(text.as_bytes())
+        .context("failed to write to pbcopy")?;
+    drop(stdin);
+    let status = child.wait().context("failed to wait for pbcopy")?;
+    ensure!(status.success(), "pbcopy failed: {status}");
+    Ok(())
+}
+
+/// There is no one pasteboard, or command for it, on other platforms.
+#[cfg(not(target_os = "macos"))]
+pub fn copy(_text: &str) -> anyhow::Result<()> {
+    anyhow::bail!("the pasteboard is only supported on macOS")
+}
@@ -0,0 +1,72 @@
+//! The [`Platform`] of macOS.
+
+use crate::event::Notification;
+use crate::macos::application::{find_host_application, is_host_application_active};
+use crate::macos::notification::{self, deliver_if_osc9_unsupported, deliver_with_osascript};
+use crate::macos::say::{self, Voice};
+use crate::platform::Platform;
+use anyhow::{Context, bail};
+use std::process::{Child, Command, Stdio};
+
+pub struct MacOs;
+
+impl Platform for MacOs {
+    const NOTIFIER: &str = "Notification Center";
+    const FALLBACK_NOTIFIER: &str = "osascript";
+    const SOUND_PLAYER: &str = "afplay";
+
+    fn deliver(notification: &Notification) -> anyhow::Result<bool> {
+        deliver_if_osc9_unsupported(notification)
+    }
+
+    fn deliver_fallback(notification: &Notification) -> anyhow::Result<()> {
+        deliver_with_osascript(notification)
+    }
+
+    fn speak(args: &[String], text: &str) -> anyhow::Result<Child> {
+        Command::new("say")
+            .args(args)
+            .arg(text)
+            .spawn()
+            .context("Command::spawn() failed")
+    }
+
+    /// Speak nothing with the arguments.
+    fn validate_speech(args: &[String]) -> anyhow::Result<()> {
+        let output = Command::new("say")
+            .args(args)
+            .arg("")
+            .stdin(Stdio::null())
+            .output()
+            .context("Command::output() failed")?;
+        // `say` reports an unknown voice on stderr, with or without failing.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim();
+        if output.status.success() && message.is_empty() {
+            return Ok(());
+        }
+        let reason = match message {
+            "" => output.status.to_string(),
+            message => message.to_string(),
+        };
+        bail!("say {} failed: {reason}", args.join(" "))
+    }
+
+    fn list_voices() -> anyhow::Result<Vec<Voice>> {
+        say::list_voices()
+    }
+
+    fn host_terminal() -> Option<String> {
+        find_host_application()
+            .and_then(|app| app.bundleIdentifier())
+            .map(|bundle_identifier| bundle_identifier.to_string())
+    }
+
+    fn is_osc9_supported() -> bool {
+        notification::is_osc9_supported()
+    }
+
+    fn is_host_terminal_active() -> bool {
+        is_host_application_active()
+    }
+}
@@ -0,0 +1,138 @@
+//! CPU time and memory usage of the `claude` process.
+
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::wait::WaitStatus;
+use nix::unistd::Pid;
+use std::time::Duration;
+use std::{fmt, io, mem};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    pub peak_memory: PeakMemory,
+}
+
+/// The most memory a process has used, by either of the measures macOS
+/// reports, which differ by as much as the memory that is compressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeakMemory {
+    /// `ru_maxrss` from `wait4()`: the resident set size.
+    Rss(u64),
+    /// `ri_lifetime_max_phys_footprint` from `proc_pid_rusage()`: the
+    /// physical footprint, as in Activity Monitor.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))] // macOS only
+    Footprint(u64),
+}
+
+impl ResourceUsage {
+    /// Usage of a running process, with CPU time including the children it has reaped.
+    #[cfg(target_os = "macos")]
+    pub fn of_process(pid: Pid) -> io::Result<Self> {
+        let mut info = unsafe { mem::zeroed::<libc::rusage_info_v4>() };
+        let result = unsafe {
+            libc::proc_pid_rusage(
+                pid.as_raw(),
+                libc::RUSAGE_INFO_V4,
+                (&raw mut info).cast::<libc::rusage_info_t>(),
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            user_time: mach_time_to_duration(info.ri_user_time + info.ri_child_user_time),
+            system_time: mach_time_to_duration(info.ri_system_time + info.ri_child_system_time),
+            peak_memory: PeakMemory::Footprint(info.ri_lifetime_max_phys_footprint),
+        })
+    }
+
+    /// Only known on macOS.
+    #[cfg(not(target_os = "macos"))]
+    pub fn of_process(_pid: Pid) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn from_rusage(rusage: &libc::rusage) -> Self {
+        let duration = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+        // Bytes on macOS, but kilobytes on Linux.
+        let unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+        Self {
+            user_time: duration(rusage.ru_utime),
+            system_time: duration(rusage.ru_stime),
+            peak_memory: PeakMemory::Rss(rusage.ru_maxrss as u64 * unit),
+        }
+    }
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, bytes) = match self.peak_memory {
+            PeakMemory::Rss(bytes) => ("RSS", bytes),
+            PeakMemory::Footprint(bytes) => ("footprint", bytes),
+        };
+        write!(
+            f,
+            "cpu {:.2}s user + {:.2}s sys, max {name} {:.1} MiB",
+            self.user_time.as_secs_f64(),
+            self.system_time.as_secs_f64(),
+            bytes as f64 / (1024.0 * 1024.0),
+        )
+    }
+}
+
+/// Blocking `waitpid()` that also returns the resource usage of the terminated child.
+/// A stopped child is reported too, for job control.
+pub fn wait_with_usage(pid: Pid) -> nix::Result<(WaitStatus, ResourceUsage)> {
+    let mut status = 0;
+    let mut rusage = unsafe { mem::zeroed::<libc::rusage>() };
+    let pid = Errno::result(unsafe {
+        libc::wait4(pid.as_raw(), &mut status, libc::WUNTRACED, &mut rusage)
+    })?;
+    Ok((
+        WaitStatus::from_raw(Pid::from_raw(pid), status)?,
+        ResourceUsage::from_rusage(&rusage),
+    ))
+}
+
+/// `rusage_info` times are in Mach absolute time units, which are not
+/// nanoseconds on Apple silicon.
+#[cfg(target_os = "macos")]
+#[expect(deprecated)] // libc defers to the mach2 crate for Mach types
+fn mach_time_to_duration(ticks: u64) -> Duration {
+    let mut timebase = libc::mach_timebase_info { numer: 0, denom: 0 };
+    if unsafe { libc::mach_timebase_info(&mut timebase) } != 0 || timebase.denom == 0 {
+        return Duration::from_nanos(ticks);
+    }
+    let nanos = u128::from(ticks) * u128::from(timebase.numer) / u128::from(timebase.denom);
+    Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let usage = ResourceUsage {
+            user_time: Duration::from_millis(12_346),
+            system_time: Duration::from_millis(1_200),
+            peak_memory: PeakMemory::Rss(210 * 1024 * 1024 + 300 * 1024),
+        };
+        assert_eq!(
+            usage.to_string(),
+            "cpu 12.35s user + 1.20s sys, max RSS 210.3 MiB"
+        );
+        let usage = ResourceUsage {
+            peak_memory: PeakMemory::Footprint(64 * 1024 * 1024),
+            ..usage
+        };
+        assert_eq!(
+            usage.to_string(),
+            "cpu 12.35s user + 1.20s sys, max footprint 64.0 MiB"
+        );
+    }
+}
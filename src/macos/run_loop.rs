@@ -0,0 +1,58 @@
+//! Main run loop driven by events from other threads.
+//!
+//! Notification Center delivers clicks through the main run loop, so the main
+//! thread sits in it with no timeout.  Other threads call
+//! [`wake_main_run_loop`] after queuing work for it, which returns the main
+//! thread from [`MainRunLoop::run_once`] right away.
+
+use objc2::rc::{Retained, autoreleasepool};
+use objc2::runtime::NSObject;
+use objc2::sel;
+use objc2_foundation::{
+    MainThreadMarker, NSDate, NSDefaultRunLoopMode, NSObjectNSThreadPerformAdditions, NSPort,
+    NSRunLoop,
+};
+use std::time::Instant;
+
+pub struct MainRunLoop {
+    run_loop: Retained<NSRunLoop>,
+}
+
+impl MainRunLoop {
+    pub fn new(_: MainThreadMarker) -> Self {
+        let run_loop = NSRunLoop::mainRunLoop();
+        // A run loop without input sources returns immediately instead of
+        // waiting; keep one attached so that `run_once` actually blocks.
+        unsafe { run_loop.addPort_forMode(&NSPort::port(), NSDefaultRunLoopMode) };
+        Self { run_loop }
+    }
+
+    /// Handle one input source, or give up at `deadline`.
+    pub fn run_once(&self, deadline: Option<Instant>) {
+        let date = match deadline {
+            Some(deadline) => NSDate::dateWithTimeIntervalSinceNow(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64(),
+            ),
+            None => NSDate::distantFuture(),
+        };
+        unsafe {
+            self.run_loop
+                .runMode_beforeDate(NSDefaultRunLoopMode, &date)
+        };
+    }
+}
+
+/// Make a pending or upcoming [`MainRunLoop::run_once`] return.  Callable from any thread.
+pub fn wake_main_run_loop() {
+    autoreleasepool(|_| {
+        // Performing a no-op selector queues a run loop source on the main
+        // thread; unlike a bare wakeup it is not lost if the main thread is
+        // not inside the run loop yet.
+        let object = NSObject::new();
+        unsafe {
+            object.performSelectorOnMainThread_withObject_waitUntilDone(sel!(self), None, false)
+        };
+    });
+}
@@ -1,5 +1,40 @@
+use crate::platform::{Native, Platform};
+#[cfg(target_os = "macos")]
 use anyhow::Context;
-use std::process::{Command, ExitStatus};
+use anyhow::bail;
+use std::fmt;
+use std::process::Child;
+#[cfg(target_os = "macos")]
+use std::process::Command;
+
+/// When `--say` speaks notifications.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SayWhen {
+    #[default]
+    Always,
+    /// Only while the terminal is not the frontmost app, as the notification
+    /// is in sight otherwise.
+    Background,
+}
+
+impl SayWhen {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "always" => Ok(Self::Always),
+            "background" => Ok(Self::Background),
+            _ => bail!("unknown condition: {s} (expected always, background)"),
+        }
+    }
+}
+
+impl fmt::Display for SayWhen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Always => "always",
+            Self::Background => "background",
+        })
+    }
+}
 
 #[derive(Debug)]
 pub struct SayCommand {
@@ -12,11 +47,198 @@ impl SayCommand {
         Self { args }
     }
 
-    pub fn run(&self, message: &str) -> anyhow::Result<ExitStatus> {
-        let mut cmd = Command::new("say");
-        cmd.args(&self.args);
-        cmd.arg(message)
-            .status()
-            .context("Command::status() failed")
+    /// Start speaking `message`; the caller waits for the child, or kills it
+    /// to stop in the middle.
+    pub fn spawn(&self, message: &str) -> anyhow::Result<Child> {
+        Native::speak(&self.args, message)
+    }
+
+    /// Find out at startup rather than on every notification whether the
+    /// arguments are turned down, e.g. an unknown voice or option.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        Native::validate_speech(&self.args)
+    }
+
+    /// Speak in `voice` unless the arguments select one.
+    #[must_use]
+    pub fn with_default_voice(mut self, voice: &str) -> Self {
+        if self.voice().is_none() {
+            self.args.extend(["-v".to_string(), voice.to_string()]);
+        }
+        self
+    }
+
+    /// Speak in `voice`, in place of the one the arguments select.
+    #[must_use]
+    pub fn with_voice(mut self, voice: &str) -> Self {
+        let mut args = std::mem::take(&mut self.args).into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-v" || arg == "--voice" {
+                args.next();
+            } else if !arg.starts_with("--voice=") && !arg.starts_with("-v") {
+                self.args.push(arg);
+            }
+        }
+        self.with_default_voice(voice)
+    }
+
+    /// Whether `args` only select a voice and a rate, as with `-v VOICE -r
+    /// RATE`, rather than e.g. write to a file with `-o`.
+    pub fn selects_voice_only(args: &[String]) -> bool {
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-v" | "--voice" | "-r" | "--rate" => {
+                    if args.next().is_none() {
+                        return false;
+                    }
+                }
+                arg if ["--voice=", "--rate=", "-v", "-r"]
+                    .iter()
+                    .any(|prefix| arg.starts_with(prefix)) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// The voice selected by `-v VOICE`, `-vVOICE`, or `--voice=VOICE`, if any.
+    pub fn voice(&self) -> Option<&str> {
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-v" || arg == "--voice" {
+                return args.next().map(String::as_str);
+            }
+            if let Some(voice) = arg
+                .strip_prefix("--voice=")
+                .or_else(|| arg.strip_prefix("-v"))
+            {
+                return Some(voice);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Voice {
+    pub name: String,
+    pub locale: String,
+}
+
+/// List installed voices as reported by `say -v ?`.
+#[cfg(target_os = "macos")]
+pub fn list_voices() -> anyhow::Result<Vec<Voice>> {
+    let output = Command::new("say")
+        .args(["-v", "?"])
+        .output()
+        .context("Command::output() failed")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "say -v ? failed: {}",
+        output.status
+    );
+    Ok(parse_voice_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse lines of the form `NAME  LOCALE  # SAMPLE`, where NAME may contain spaces.
+#[cfg(target_os = "macos")]
+fn parse_voice_list(s: &str) -> Vec<Voice> {
+    s.lines()
+        .filter_map(|line| {
+            let (voice, _sample) = line.split_once('#')?;
+            let (name, locale) = voice.trim_end().rsplit_once(char::is_whitespace)?;
+            Some(Voice {
+                name: name.trim_end().to_string(),
+                locale: locale.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_os = "macos")]
+    use indoc::indoc;
+
+    #[test]
+    fn voice_option() {
+        let voice = |args: &[&str]| {
+            SayCommand::new(args.iter().map(|s| s.to_string()).collect())
+                .voice()
+                .map(str::to_string)
+        };
+        assert_eq!(voice(&["-v", "Samantha"]).as_deref(), Some("Samantha"));
+        assert_eq!(voice(&["-vSamantha"]).as_deref(), Some("Samantha"));
+        assert_eq!(
+            voice(&["-r", "200", "--voice=Bad News"]).as_deref(),
+            Some("Bad News")
+        );
+        assert_eq!(voice(&["-r", "200"]), None);
+
+        let with_default = |args: &[&str]| {
+            SayCommand::new(args.iter().map(|s| s.to_string()).collect())
+                .with_default_voice("Daniel")
+                .voice()
+                .map(str::to_string)
+        };
+        assert_eq!(with_default(&["-r", "200"]).as_deref(), Some("Daniel"));
+        assert_eq!(with_default(&["-vSamantha"]).as_deref(), Some("Samantha"));
+
+        let with_voice = |args: &[&str]| {
+            SayCommand::new(args.iter().map(|s| s.to_string()).collect())
+                .with_voice("Kyoko")
+                .args
+        };
+        assert_eq!(
+            with_voice(&["-r", "200", "-v", "Samantha"]),
+            ["-r", "200", "-v", "Kyoko"]
+        );
+        assert_eq!(
+            with_voice(&["--voice=Bad News", "-r200"]),
+            ["-r200", "-v", "Kyoko"]
+        );
+    }
+
+    #[test]
+    fn voice_only_arguments() {
+        let voice_only = |args: &[&str]| {
+            SayCommand::selects_voice_only(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+        };
+        assert!(voice_only(&["-v", "Bad News", "-r200"]));
+        assert!(voice_only(&["--voice=Kyoko", "--rate", "180"]));
+        assert!(!voice_only(&["-v", "Kyoko", "-o", "out.aiff"]));
+        assert!(!voice_only(&["-f", "notes.txt"]));
+        assert!(!voice_only(&["-v"]));
+    }
+
+    #[test]
+    fn say_when() {
+        assert_eq!(SayWhen::parse("background").unwrap(), SayWhen::Background);
+        assert_eq!(SayWhen::Always.to_string(), "always");
+        assert!(SayWhen::parse("foreground").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn voice_list() {
+        let voices = parse_voice_list(indoc! {"
+            Albert              en_US    # Hello! My name is Albert.
+            Bad News            en_US    # Hello! My name is Bad News.
+            Eddy (English (UK)) en_GB    # Hello! My name is Eddy.
+        "});
+        assert_eq!(
+            voices,
+            [
+                ("Albert", "en_US"),
+                ("Bad News", "en_US"),
+                ("Eddy (English (UK))", "en_GB"),
+            ]
+            .map(|(name, locale)| Voice {
+                name: name.to_string(),
+                locale: locale.to_string(),
+            }),
+        );
     }
 }
@@ -0,0 +1,164 @@
+//! `--sound`: audio files played with `afplay` (`paplay` on Linux) on
+//! session events.
+
+use crate::platform::{Native, Platform};
+use anyhow::{Context, bail};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    /// `claude` asks a question with numbered options.
+    Question,
+    /// `claude` asks for permission; also played for reminders.  The
+    /// `question` sound is played instead if this one is not set.
+    Permission,
+    /// `claude` posts a notification, e.g. when it finishes or waits for input.
+    Completion,
+    /// `claude` exits with a non-zero code.
+    Error,
+    /// `claude` exits; also played on errors if no `error` sound is set.
+    Exit,
+}
+
+impl SoundEvent {
+    const NAMES: &[(&str, SoundEvent)] = &[
+        ("question", SoundEvent::Question),
+        ("permission", SoundEvent::Permission),
+        ("completion", SoundEvent::Completion),
+        ("error", SoundEvent::Error),
+        ("exit", SoundEvent::Exit),
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find_map(|&(name, event)| (event == self).then_some(name))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SoundTheme {
+    sounds: Vec<(SoundEvent, PathBuf)>,
+}
+
+impl SoundTheme {
+    pub fn new(sounds: Vec<(SoundEvent, PathBuf)>) -> Self {
+        Self { sounds }
+    }
+
+    /// `Error` for a failure if it has a sound, `Exit` otherwise.
+    pub fn exit_event(&self, exit_code: i32) -> SoundEvent {
+        if exit_code != 0 && self.sound_for(SoundEvent::Error).is_some() {
+            SoundEvent::Error
+        } else {
+            SoundEvent::Exit
+        }
+    }
+
+    /// `Permission` for a permission prompt if it has a sound, `Question` otherwise.
+    pub fn question_event(&self, is_permission_prompt: bool) -> SoundEvent {
+        if is_permission_prompt && self.sound_for(SoundEvent::Permission).is_some() {
+            SoundEvent::Permission
+        } else {
+            SoundEvent::Question
+        }
+    }
+
+    /// The file for `event`; the last one given wins.
+    pub fn sound_for(&self, event: SoundEvent) -> Option<&Path> {
+        self.sounds
+            .iter()
+            .rev()
+            .find_map(|(e, path)| (*e == event).then_some(path.as_path()))
+    }
+
+    /// Start playing the sound for `event` without waiting for it to finish.
+    /// The player keeps playing even if caloud exits first.
+    pub fn play(&self, event: SoundEvent) -> anyhow::Result<()> {
+        let Some(path) = self.sound_for(event) else {
+            return Ok(());
+        };
+        let mut child = Command::new(Native::SOUND_PLAYER)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to run {}", Native::SOUND_PLAYER))?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// `EVENT=PATH`, e.g. `question=/System/Library/Sounds/Ping.aiff`.
+pub fn parse_sound(s: &str) -> anyhow::Result<(SoundEvent, PathBuf)> {
+    let Some((name, path)) = s.split_once('=') else {
+        bail!("expected EVENT=PATH");
+    };
+    let Some(&(_, event)) = SoundEvent::NAMES.iter().find(|(n, _)| *n == name) else {
+        let names: Vec<_> = SoundEvent::NAMES.iter().map(|(n, _)| *n).collect();
+        bail!("unknown event: {name} (expected {})", names.join(", "));
+    };
+    if path.is_empty() {
+        bail!("empty path for {name}");
+    }
+    Ok((event, PathBuf::from(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_sound("question=/System/Library/Sounds/Ping.aiff").unwrap(),
+            (
+                SoundEvent::Question,
+                PathBuf::from("/System/Library/Sounds/Ping.aiff")
+            )
+        );
+        assert_eq!(
+            parse_sound("exit=a=b.wav").unwrap(),
+            (SoundEvent::Exit, PathBuf::from("a=b.wav"))
+        );
+        assert_eq!(
+            parse_sound("done=x.wav").unwrap_err().to_string(),
+            "unknown event: done (expected question, permission, completion, error, exit)"
+        );
+        assert!(parse_sound("question").is_err());
+        assert!(parse_sound("question=").is_err());
+    }
+
+    #[test]
+    fn last_sound_wins() {
+        let theme = SoundTheme::new(vec![
+            (SoundEvent::Exit, PathBuf::from("a.wav")),
+            (SoundEvent::Exit, PathBuf::from("b.wav")),
+        ]);
+        assert_eq!(theme.sound_for(SoundEvent::Exit), Some(Path::new("b.wav")));
+        assert_eq!(theme.sound_for(SoundEvent::Error), None);
+        assert_eq!(theme.exit_event(1), SoundEvent::Exit);
+        assert_eq!(theme.question_event(true), SoundEvent::Question);
+    }
+
+    #[test]
+    fn error_sound_replaces_exit_sound() {
+        let theme = SoundTheme::new(vec![
+            (SoundEvent::Error, PathBuf::from("error.wav")),
+            (SoundEvent::Exit, PathBuf::from("exit.wav")),
+        ]);
+        assert_eq!(theme.exit_event(0), SoundEvent::Exit);
+        assert_eq!(theme.exit_event(1), SoundEvent::Error);
+    }
+
+    #[test]
+    fn permission_sound_replaces_question_sound() {
+        let theme = SoundTheme::new(vec![(SoundEvent::Permission, PathBuf::from("alert.wav"))]);
+        assert_eq!(theme.question_event(false), SoundEvent::Question);
+        assert_eq!(theme.question_event(true), SoundEvent::Permission);
+    }
+}
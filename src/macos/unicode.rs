@@ -0,0 +1,14 @@
+//! Unicode normalization with Foundation.
+
+use objc2_foundation::NSString;
+
+/// `text` in Normalization Form C, with combining sequences such as `e` +
+/// U+0301 composed into `é`, as Notification Center expects.
+pub fn precompose(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    NSString::from_str(text)
+        .precomposedStringWithCanonicalMapping()
+        .to_string()
+}
@@ -1,139 +1,1708 @@
+use crate::abbreviation::Abbreviator;
+use crate::auto_respond::{AutoRespondMode, AutoResponder, Response};
+use crate::broadcast::{BroadcastEvent, Broadcaster};
+use crate::claude::ExecHandshake;
+use crate::control::{ControlServer, Session, copy_last_message};
+use crate::debug_log::DebugLog;
+use crate::digest::Digest;
+use crate::event::Notification;
+use crate::focus_policy::{Focus, FocusFilter};
+use crate::hook::HookEvent;
+use crate::input_fifo::InputFifo;
+use crate::input_rewrite::escape::escape_bytes;
+use crate::job_control::JobControl;
+use crate::mirror::{MirrorReceiver, MirrorSender};
+use crate::normalize::normalize;
+use crate::parent_watchdog::ParentWatchdog;
+use crate::platform::{Native, Platform};
+use crate::progress::ProgressReminder;
+use crate::progress_state::ProgressState;
+use crate::pty::PacketReader;
+use crate::recording::Recorder;
+use crate::reminder::Reminder;
+use crate::retry_queue::RetryQueue;
+use crate::review::{Review, ReviewGate};
 use crate::runtime::Runtime;
+use crate::speech_queue::{SpeechPriority, SpeechQueue, Utterance};
+use crate::stall::StallWatch;
+use crate::supervisor::{ClaudePid, PidReport};
+use crate::title_flash::TitleFlash;
+use crate::title_history::TitleHistory;
+use crate::tty_text::alert::AlertDetector;
 use crate::tty_text::buffer::Buffer;
-use crate::tty_text::fragment::EscapeSequence;
+use crate::tty_text::code_block::CodeBlockDetector;
+use crate::tty_text::fragment::{EscapeSequence, notification_parameters};
+use crate::tty_text::message::{LastMessage, MessageExtractor};
+use crate::tty_text::osc_scanner::OscScanner;
+use crate::tty_text::question::{Question, QuestionDetector};
+use crate::tty_text::spinner::SpinnerDetector;
+use crate::tty_text::tool_call::ToolCallDetector;
+use crate::tty_text::transcript::Transcript;
+use crate::viewer::{OutputTap, TeeWriter};
 use anyhow::Context;
-use input_rewrite::zwsp_inserter::ZwspInserter;
-use macos::notification::{deliver_if_osc9_unsupported, set_global_delegate};
-use nix::pty::{ForkptyResult, forkpty};
+use caloud::tty_text;
+#[cfg(target_os = "macos")]
+use macos::accessibility::announce;
+use macos::appearance::{self, AppearanceMode};
+use macos::dialog;
+#[cfg(target_os = "macos")]
+use macos::focus;
+#[cfg(target_os = "macos")]
+use macos::fs_events;
+#[cfg(target_os = "macos")]
+use macos::notification::{beep, set_global_delegate};
+use macos::resource_usage::{ResourceUsage, wait_with_usage};
+#[cfg(target_os = "macos")]
+use macos::run_loop::{MainRunLoop, wake_main_run_loop};
+use macos::say::{SayCommand, SayWhen};
+use macos::sound::SoundEvent;
+use nix::errno::Errno;
+use nix::pty::{ForkptyResult, Winsize, forkpty};
 use nix::sys::signal::{SigHandler, SigSet, Signal, signal};
 use nix::sys::termios::{SetArg, Termios, cfmakeraw, tcgetattr, tcsetattr};
-use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+use nix::sys::wait::{WaitStatus, waitpid};
 use nix::unistd::Pid;
 use nix::{ioctl_read_bad, ioctl_write_ptr_bad};
-use objc2_foundation::{NSDate, NSDefaultRunLoopMode, NSRunLoop};
+#[cfg(target_os = "macos")]
+use objc2_foundation::MainThreadMarker;
 use std::convert::Infallible;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::os::fd::{AsFd, AsRawFd, OwnedFd};
-use std::sync::atomic::AtomicU16;
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+mod abbreviation;
 mod args;
+mod auto_respond;
+mod broadcast;
+mod capabilities;
 mod claude;
+mod claude_session;
+mod code_block_destination;
+mod config;
+mod control;
+mod debug_log;
+mod digest;
+mod doctor;
+mod emoji;
+mod env_file;
+mod event;
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))] // macOS only
+mod file_watch;
+mod filter;
+mod focus_policy;
+mod git;
+mod hook;
+mod input_fifo;
 mod input_rewrite;
+mod job_control;
+#[cfg(target_os = "linux")]
+mod linux;
 mod macos;
+mod mirror;
+mod normalize;
+mod notify;
+mod parent_watchdog;
+mod platform;
+mod progress;
+mod progress_state;
+mod pty;
+mod recording;
+mod redact;
+mod reminder;
+mod retry_queue;
+mod review;
 mod runtime;
-mod tty_text;
+mod send;
+mod shell_command;
+mod speech_queue;
+mod stall;
+mod supervisor;
+mod terminfo;
+mod title_flash;
+mod title_history;
+mod tool;
+mod translate;
+mod truncation;
+mod viewer;
+mod voice_pool;
+mod voices;
 
 const DEFAULT_NOTIFICATION_TITLE: &str = "Claude Code";
 
-static TERMINAL_WIDTH: AtomicU16 = AtomicU16::new(0);
+/// Posted by `--copy-code-blocks-notify`.
+const CODE_BLOCK_COPIED_MESSAGE: &str = "Code block copied";
+
+/// How often a running `say` is checked for an interruption.
+const SPEECH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Posted by `--permission-reminder` while a permission prompt is unanswered.
+const PERMISSION_REMINDER_MESSAGE: &str = "Claude is still waiting for your permission";
+
+/// How long an `--auto-respond` confirmation waits before it is skipped.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often `--appearance=auto` checks for a switch between dark and light mode.
+const APPEARANCE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a notification that cannot be shown stays in the terminal title,
+/// unless `--title-flash` says otherwise.
+const FALLBACK_TITLE_FLASH: Duration = Duration::from_secs(10);
+
+/// Spoken once when notifications cannot be shown.
+const NOTIFICATIONS_UNAVAILABLE_MESSAGE: &str =
+    "Desktop notifications are unavailable. Alerting with a sound and the terminal title instead";
+
+/// How often the Focus is checked while `--focus-policy` holds notifications back.
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Upper bound on the time spent draining output and notifications after the child exits.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A window is taken to be resized once no SIGWINCH has come for this long.
+const WINSIZE_SETTLE_TIME: Duration = Duration::from_millis(75);
+
+/// How often to check whether the machine has slept.
+const WAKE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What worker threads report to the main thread, which otherwise sleeps in the run loop.
+enum MainEvent {
+    ChildExited(nix::Result<(WaitStatus, ResourceUsage)>),
+    /// A message for VoiceOver, which must be posted from the main thread.
+    #[cfg_attr(not(target_os = "macos"), expect(dead_code))] // macOS only
+    Announce(String),
+    ThreadFinished,
+}
 
 fn main() -> anyhow::Result<Infallible> {
-    let runtime = args::Arguments::parse()?.try_into_runtime()?;
+    let arguments = args::Arguments::parse()?;
+    match arguments.command() {
+        args::Command::Run => {}
+        args::Command::Doctor => std::process::exit(if doctor::run(&arguments) { 0 } else { 1 }),
+        args::Command::Capabilities => {
+            capabilities::run();
+            std::process::exit(0);
+        }
+        args::Command::Config => {
+            print!("{}", arguments.effective_config());
+            std::process::exit(0);
+        }
+        args::Command::ConfigValidate => {
+            let errors = arguments.config_errors();
+            for error in errors {
+                eprintln!("caloud: {error:#}");
+            }
+            print!("{}", arguments.effective_config());
+            std::process::exit(if errors.is_empty() { 0 } else { 1 });
+        }
+        args::Command::Filter => {
+            filter::run(&arguments)?;
+            std::process::exit(0);
+        }
+        args::Command::Reformat => {
+            filter::reformat(&arguments)?;
+            std::process::exit(0);
+        }
+        args::Command::Decode => {
+            filter::decode(&arguments)?;
+            std::process::exit(0);
+        }
+        args::Command::Send => {
+            send::run(arguments.subcommand_args())?;
+            std::process::exit(0);
+        }
+        args::Command::Notify => {
+            let delivered = notify::run(arguments.subcommand_args())?;
+            std::process::exit(if delivered { 0 } else { 1 });
+        }
+        args::Command::ListVoices => {
+            voices::list(arguments.subcommand_args())?;
+            std::process::exit(0);
+        }
+        args::Command::Say => {
+            voices::say(&arguments, arguments.subcommand_args())?;
+            std::process::exit(0);
+        }
+        args::Command::Mirror => {
+            viewer::run(arguments.subcommand_args())?;
+            std::process::exit(0);
+        }
+        args::Command::Version => {
+            print_version();
+            std::process::exit(0);
+        }
+        args::Command::Help => {
+            print!("{}", args::help());
+            std::process::exit(0);
+        }
+    }
+    // Before the runtime, which would set up what a PTY needs.
+    if arguments.bypass_filter_enabled() {
+        let claude_command = arguments.try_build_claude_command()?;
+        if claude_command.should_bypass_pty() {
+            std::process::exit(filter::run_bypassed(&arguments, &claude_command)?);
+        }
+    }
+    let mut runtime = arguments.try_into_runtime()?;
 
     if runtime.claude_command.should_bypass_pty() {
         runtime.claude_command.exec()?;
     }
 
-    match unsafe { forkpty(None, None) }.context("forkpty() failed")? {
-        ForkptyResult::Child => runtime.claude_command.exec(),
+    // Bind before forking so that a bad path is reported before claude starts.
+    let control_server = runtime
+        .control_socket
+        .take()
+        .map(ControlServer::bind)
+        .transpose()?;
+    let input_fifo = runtime
+        .input_fifo
+        .take()
+        .map(InputFifo::create)
+        .transpose()?;
+
+    // Size the PTY before forking so that the child never observes a 0x0 window.
+    let winsize = stdin_winsize(runtime.fallback_width);
+    runtime.reformatter.set_terminal_width(winsize.ws_col);
+
+    let exec_handshake = ExecHandshake::new()?;
+    let pid_report = runtime
+        .restart_policy
+        .is_some()
+        .then(PidReport::new)
+        .transpose()?;
+    match unsafe { forkpty(Some(&winsize), None) }.context("forkpty() failed")? {
+        ForkptyResult::Child => match (&runtime.restart_policy, pid_report) {
+            (Some(policy), Some(pid_report)) => {
+                supervisor::run(exec_handshake, pid_report, &runtime.claude_command, policy)
+            }
+            _ => exec_handshake.exec(&runtime.claude_command),
+        },
         ForkptyResult::Parent { child, master } => {
-            std::process::exit(intercept(child, master, runtime)?)
+            // Before the terminal is made raw, so that the report reads well.
+            if let Some(failure) = exec_handshake.wait().context("exec handshake")? {
+                eprint!("{}", failure.report(&runtime.claude_command));
+                let _ = waitpid(child, None);
+                std::process::exit(127);
+            }
+            let claude_pid = match pid_report {
+                Some(pid_report) => pid_report.follow(child),
+                None => ClaudePid::new(child),
+            };
+            let parent_watchdog = ParentWatchdog::spawn(child).context("parent watchdog")?;
+            let summary_enabled = runtime.summary_enabled;
+            let on_exit = runtime.on_exit.take();
+            let cwd = runtime.cwd.clone();
+            let session_name = runtime.session_name.clone();
+            let claude_session = Arc::clone(&runtime.claude_session);
+            // The terminal mode is restored by then, but not the screen.
+            let report = intercept(
+                child,
+                claude_pid.clone(),
+                master,
+                runtime,
+                control_server,
+                input_fifo,
+            )
+            .inspect_err(|_| reset_screen())?;
+            parent_watchdog.stand_down();
+            if summary_enabled {
+                eprintln!("caloud: {report}");
+            }
+            if let Some(hook) = on_exit {
+                let event = HookEvent::Exit {
+                    exit_code: report.exit_code,
+                    elapsed: report.elapsed,
+                };
+                let env = hook::environment(
+                    event,
+                    claude_pid.get(),
+                    session_name.as_deref(),
+                    claude_session.id(),
+                );
+                if let Err(e) = hook::run(&hook, &cwd, &env) {
+                    eprintln!("caloud: --on-exit: {e:#}");
+                }
+            }
+            std::process::exit(report.exit_code)
         }
     }
 }
 
-fn intercept(child: Pid, master: OwnedFd, mut runtime: Runtime) -> anyhow::Result<i32> {
-    let _termios = try_make_raw(io::stdin()).context("try_make_raw")?;
-    let mut reader = File::from(master.try_clone()?);
-    let mut writer = File::from(master.try_clone()?);
+fn print_version() {
+    println!("caloud {}", env!("CARGO_PKG_VERSION"));
+    println!("target: {}", env!("CALOUD_BUILD_TARGET"));
+    println!("profile: {}", env!("CALOUD_BUILD_PROFILE"));
+}
+
+/// How the session ended, for `--summary`.
+struct ExitReport {
+    exit_code: i32,
+    elapsed: Duration,
+    usage: ResourceUsage,
+}
+
+impl fmt::Display for ExitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs();
+        write!(
+            f,
+            "claude exited with code {} after {}h{:02}m{:02}s ({})",
+            self.exit_code,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            self.usage,
+        )
+    }
+}
+
+/// `child` is the process forked onto the PTY, and `claude_pid` the
+/// `claude` it runs, the same but under `--restart-on-crash`.
+fn intercept(
+    child: Pid,
+    claude_pid: ClaudePid,
+    master: OwnedFd,
+    mut runtime: Runtime,
+    control_server: Option<ControlServer>,
+    input_fifo: Option<InputFifo>,
+) -> anyhow::Result<ExitReport> {
+    let started_at = Instant::now();
+    // Signals are blocked before any other thread is spawned, so that none of
+    // them takes one.
+    job_control::forward_sigtstp(child).context("forward_sigtstp")?;
+    let (width_tx, width_rx) = mpsc::channel::<u16>();
+    spawn_winsize_updater(master.try_clone()?, runtime.fallback_width, width_tx)
+        .context("spawn_winsize_updater")?;
+    if let Some(hook) = runtime.on_start.take() {
+        let env = hook::environment(
+            HookEvent::Start,
+            claude_pid.get(),
+            runtime.session_name.as_deref(),
+            runtime.claude_session.id(),
+        );
+        let cwd = runtime.cwd.clone();
+        let debug_log = runtime.debug_log.clone();
+        // Alongside the session rather than before it, so as not to delay it.
+        thread::spawn(move || {
+            if let Err(e) = hook::run(&hook, &cwd, &env) {
+                debug_log.log(format_args!("--on-start: {e:#}"));
+            }
+        });
+    }
+    // Input piped in, as in CI, is passed on as it is.
+    let termios = io::stdin()
+        .is_terminal()
+        .then(|| try_make_raw(io::stdin()))
+        .transpose()
+        .context("try_make_raw")?;
+    let job_control = termios.as_ref().map(|termios| {
+        install_panic_hook(termios.1.clone());
+        JobControl::new(child, termios.1.clone(), runtime.debug_log.clone())
+    });
+    pty::enable_packet_mode(&master).context("enable_packet_mode")?;
+    let mut reader = PacketReader::new(File::from(master.try_clone()?));
+    let writer = File::from(master.try_clone()?);
+    let response_writer = if runtime.auto_responder.is_empty() {
+        None
+    } else {
+        Some(Arc::new(File::from(master.try_clone()?)))
+    };
+
+    // Color queries are answered, and changes between dark and light mode
+    // reported, by writing to `claude`'s input.
+    let appearance_writer = match runtime.appearance {
+        Some(_) => Some(Arc::new(File::from(master.try_clone()?))),
+        None => None,
+    };
+    let color_scheme_updates = Arc::new(AtomicBool::new(false));
+    if let (Some(AppearanceMode::Auto), Some(writer)) = (runtime.appearance, &appearance_writer) {
+        spawn_appearance_watcher(
+            Arc::clone(writer),
+            Arc::clone(&color_scheme_updates),
+            runtime.debug_log.clone(),
+        );
+    }
 
+    #[cfg(target_os = "macos")]
     set_global_delegate().context("set_global_delegate")?;
-    spawn_winsize_updater(master).context("spawn_winsize_updater")?;
-    let mut input_rewriter = runtime.input_rewriter;
-    let zwsp_after_updown_arrow = runtime.zwsp_after_updown_arrow;
+    #[cfg(target_os = "macos")]
+    let main_thread_marker = MainThreadMarker::new().context("not on the main thread")?;
+    #[cfg(target_os = "macos")]
+    let main_run_loop = MainRunLoop::new(main_thread_marker);
+    let (event_tx, event_rx) = mpsc::channel::<MainEvent>();
+    // Messages are only followed if something reads them.
+    let last_message = (runtime.speak_output_enabled
+        || runtime.speak_final_enabled
+        || runtime.copy_key.is_some()
+        || control_server.is_some())
+    .then(LastMessage::default);
+    let title_history = control_server.is_some().then(TitleHistory::default);
+    let output_tap = control_server.is_some().then(OutputTap::default);
+    let _control_socket = match control_server {
+        Some(server) => Some(server.spawn(Session {
+            pid: claude_pid.clone(),
+            pty: master.try_clone()?,
+            started_at,
+            last_message: last_message.clone().unwrap_or_default(),
+            title_history: title_history.clone().unwrap_or_default(),
+            git_repository: runtime.git_repository.clone(),
+            output_tap: output_tap.clone().unwrap_or_default(),
+            claude_session: Arc::clone(&runtime.claude_session),
+        })),
+        None => None,
+    };
+    let mut input_pipeline = runtime.input_pipeline;
+    let input_count = input_pipeline.input_count();
+    // Typed like the user's input, but without the hotkeys added below.
+    let _input_fifo = match input_fifo {
+        Some(fifo) => Some(fifo.spawn(input_pipeline.duplicate(), writer.try_clone()?)),
+        None => None,
+    };
+    // Notifications and output are spoken one at a time, in order of
+    // priority; see `speech_queue`.
+    let (speech_tx, speech_rx) = mpsc::channel::<Utterance>();
+    // Notifications can be spoken again from the menu until `claude` exits.
+    let review_speech_tx = Arc::new(Mutex::new(
+        runtime.review_key.is_some().then(|| speech_tx.clone()),
+    ));
+    let review = runtime.review_key.take().map(|key| {
+        let review = Review::new(key.clone());
+        let opener = review.clone();
+        let debug_log = runtime.debug_log.clone();
+        input_pipeline.add_hotkey(key, move || {
+            if let Err(e) = opener.open(&mut io::stdout()) {
+                debug_log.log(format_args!("review: {e:#}"));
+            }
+        });
+        // While the menu is open, what is typed goes to it, not to `claude`.
+        let menu = review.clone();
+        let speech_tx = Arc::clone(&review_speech_tx);
+        let debug_log = runtime.debug_log.clone();
+        input_pipeline.set_interceptor(move |input| {
+            if !menu.is_open() {
+                return false;
+            }
+            match menu.key(input, &mut io::stdout()) {
+                Ok(Some(message)) => {
+                    if let Some(tx) = &*speech_tx.lock().unwrap_or_else(PoisonError::into_inner) {
+                        let _ = tx.send(Utterance::new(message, SpeechPriority::Urgent));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => debug_log.log(format_args!("review: {e:#}")),
+            }
+            true
+        });
+        review
+    });
+    if let (Some(key), Some(last_message)) = (runtime.copy_key.take(), last_message.clone()) {
+        let debug_log = runtime.debug_log.clone();
+        input_pipeline.add_hotkey(key, move || match copy_last_message(&last_message) {
+            Ok(chars) => debug_log.log(format_args!("copy: {chars} characters")),
+            Err(e) => debug_log.log(format_args!("copy: {e:#}")),
+        });
+    }
     thread::spawn(move || {
-        if zwsp_after_updown_arrow {
-            let _ = input_rewriter.rewrite(io::stdin(), &mut ZwspInserter::new(writer));
-        } else {
-            let _ = input_rewriter.rewrite(io::stdin(), &mut writer);
-        }
+        let _ = input_pipeline.run(io::stdin(), writer);
     });
 
-    debug_assert!(TERMINAL_WIDTH.load(std::sync::atomic::Ordering::Relaxed) > 0);
+    let (notification_tx, notification_rx) = std::sync::mpsc::sync_channel::<Notification>(10);
+    let speech_interrupt = Arc::new(AtomicBool::new(false));
+    let speaks_notifications = runtime.say_command.is_some();
+    let say_when = runtime.say_when;
+    let speaks_final_only = runtime.speak_final_enabled;
+    // Without --say, output and questions are spoken in the system default voice.
+    let mut say_command = runtime
+        .say_command
+        .take()
+        .unwrap_or_else(|| SayCommand::new(Vec::new()));
+    match voice_pool::assign(
+        &runtime.voice_pool,
+        runtime.voice_assignment,
+        runtime.session_name.as_deref(),
+    ) {
+        Ok(Some(voice)) => {
+            runtime
+                .debug_log
+                .log(format_args!("voice pool: {voice} for this session"));
+            say_command = say_command.with_default_voice(voice);
+        }
+        Ok(None) => {}
+        Err(e) => runtime.debug_log.log(format_args!("voice pool: {e:#}")),
+    }
+    let abbreviator = Arc::new(Abbreviator::new(runtime.speech_abbreviations));
+    let speech_abbreviator = Arc::clone(&abbreviator);
+    let speech_emoji = runtime.speech_emoji;
+    let speech_redactor = runtime.redactor.clone();
+    let debug_log = runtime.debug_log.clone();
+    let interrupt = Arc::clone(&speech_interrupt);
+    let finished = FinishNotifier(event_tx.clone());
+    let speech_thread = thread::spawn(move || {
+        let _finished = finished;
+        let mut queue = SpeechQueue::default();
+        let enqueue = |queue: &mut SpeechQueue, utterance| {
+            if !queue.push(utterance) {
+                debug_log.log(format_args!("say: dropped, speech is behind"));
+            }
+        };
+        loop {
+            if queue.is_empty() {
+                let Ok(utterance) = speech_rx.recv() else {
+                    break;
+                };
+                enqueue(&mut queue, utterance);
+            }
+            for utterance in speech_rx.try_iter() {
+                enqueue(&mut queue, utterance);
+            }
+            if interrupt.swap(false, Ordering::Relaxed) {
+                queue.drop_low();
+            }
+            let Some(utterance) = queue.pop() else {
+                continue;
+            };
+            // Secrets go before they can be mangled by abbreviation.
+            let text = match &speech_redactor {
+                Some(redactor) => redactor.apply(&utterance.text),
+                None => utterance.text.as_str().into(),
+            };
+            let text = speech_emoji.apply(&text);
+            let text = speech_abbreviator.apply(&text);
+            let is_urgent = utterance.priority == SpeechPriority::Urgent;
+            // A permission prompt that is not spoken cuts off what is being
+            // said for good, to be heard.
+            let mut is_cut_off = false;
+            let spoken = speak(&say_command, &text, || {
+                for utterance in speech_rx.try_iter() {
+                    enqueue(&mut queue, utterance);
+                }
+                is_cut_off = interrupt.swap(false, Ordering::Relaxed);
+                is_cut_off || (!is_urgent && queue.has_urgent())
+            });
+            match spoken {
+                Ok(true) => {}
+                Ok(false) if is_cut_off => {
+                    let dropped = queue.drop_low();
+                    debug_log.log(format_args!("say: cut off, {dropped} dropped"));
+                }
+                Ok(false) => {
+                    let dropped = queue.interrupted(utterance);
+                    debug_log.log(format_args!("say: interrupted, {dropped} dropped"));
+                }
+                Err(e) => debug_log.log(format_args!("say: {e:#}")),
+            }
+        }
+    });
 
-    let (notification_tx, notification_rx) = std::sync::mpsc::sync_channel::<(String, String)>(10);
+    let sound_theme = Arc::new(std::mem::take(&mut runtime.sound_theme));
+    // Held by the main thread until `claude` exits, to report its death.
+    let mut exit_notification_tx = runtime.exit_notify_enabled.then(|| notification_tx.clone());
+    let exit_subtitle = runtime.session_name.clone();
+    let osc9_passthrough_enabled = runtime.osc9_passthrough_enabled;
+    let broadcaster = runtime.broadcast_enabled.then(|| {
+        Broadcaster::new(
+            claude_pid.get(),
+            runtime.session_name.clone(),
+            Arc::clone(&runtime.claude_session),
+        )
+    });
+    if let Some(broadcaster) = &broadcaster {
+        broadcaster.post(BroadcastEvent::Started);
+    }
+    // Notifications from other sessions are also taken until `claude` exits.
+    let mirror_notification_tx = Arc::new(Mutex::new(
+        runtime
+            .mirror_receive
+            .is_some()
+            .then(|| notification_tx.clone()),
+    ));
+    let mirror_name = mirror::instance_name();
+    let _mirror_advertisement = match runtime.mirror_receive.take() {
+        Some(key) => {
+            let tx = Arc::clone(&mirror_notification_tx);
+            let deliver = move |notification| {
+                if let Some(tx) = &*tx.lock().unwrap_or_else(PoisonError::into_inner) {
+                    let _ = tx.try_send(notification);
+                }
+            };
+            Some(MirrorReceiver::bind(key)?.spawn(
+                &mirror_name,
+                deliver,
+                runtime.debug_log.clone(),
+            )?)
+        }
+        None => None,
+    };
+    let mirror_sender = runtime
+        .mirror_send
+        .take()
+        .map(|key| MirrorSender::start(mirror_name, key, runtime.debug_log.clone()))
+        .transpose()?;
+    // Changed files are also notified about until `claude` exits.
+    let file_notification_tx = Arc::new(Mutex::new(
+        runtime
+            .file_matcher
+            .is_some()
+            .then(|| notification_tx.clone()),
+    ));
+    // `--watch-files` is turned down on other platforms.
+    #[cfg(target_os = "macos")]
+    if let Some(file_matcher) = runtime.file_matcher.take() {
+        let tx = Arc::clone(&file_notification_tx);
+        let subtitle = runtime.session_name.clone();
+        let debug_log = runtime.debug_log.clone();
+        let root = file_matcher.root().to_path_buf();
+        fs_events::watch(&root, move |paths| {
+            let changed: Vec<String> = paths
+                .iter()
+                .filter_map(|path| file_matcher.relative_match(path))
+                .collect();
+            if changed.is_empty() {
+                return;
+            }
+            debug_log.log(format_args!("watch-files: {changed:?}"));
+            if let Some(tx) = &*tx.lock().unwrap_or_else(PoisonError::into_inner) {
+                let _ = tx.try_send(Notification {
+                    title: DEFAULT_NOTIFICATION_TITLE.to_string(),
+                    subtitle: subtitle.clone(),
+                    message: file_watch::message(&changed),
+                    ..Default::default()
+                });
+            }
+        })
+        .with_context(|| format!("failed to watch {}", root.display()))?;
+    }
 
-    thread::spawn(move || {
-        let mut stdout = io::stdout().lock();
+    // Also flashed when a notification cannot be shown at all, but only made
+    // then, so that the output keeps the fast path until a title is flashed.
+    let title_flash = Arc::new(OnceLock::<Mutex<TitleFlash>>::new());
+    if runtime.title_flash.is_some() {
+        title_flash.get_or_init(Mutex::default);
+    }
+    let notification_title_flash = title_flash.clone();
+    let debug_log = runtime.debug_log.clone();
+    let output_speech_tx = speech_tx.clone();
+    let output_broadcaster = broadcaster.clone();
+    let question_redactor = runtime.redactor.clone();
+    let output_sound_theme = Arc::clone(&sound_theme);
+    let output_review = review.clone();
+    let finished = FinishNotifier(event_tx.clone());
+    let output_thread = thread::spawn(move || {
+        let _finished = finished;
+        // Not locked for good: `--title-flash` restores the title from another thread.
+        let mut stdout = TeeWriter::new(
+            ReviewGate::new(io::stdout(), output_review.clone()),
+            output_tap,
+        );
         let mut title = DEFAULT_NOTIFICATION_TITLE.to_string();
-        let mut buffer = Buffer::<8192>::new();
+        let mut buffer = Buffer::new(runtime.buffer_size);
+        let mut osc_scanner = OscScanner::new();
+        let session_name = runtime.session_name;
+        let debug_log = runtime.debug_log;
+        let code_block_destination = runtime.code_block_destination;
+        let mut code_blocks = code_block_destination
+            .as_ref()
+            .map(|_| CodeBlockDetector::new(runtime.code_block_min_lines));
+        let mut messages =
+            (last_message.is_some() || code_blocks.is_some()).then(MessageExtractor::new);
+        let mut transcript = runtime.screen_reader_enabled.then(Transcript::new);
+        let mut questions = (runtime.speak_questions_enabled
+            || output_sound_theme.sound_for(SoundEvent::Question).is_some()
+            || output_sound_theme
+                .sound_for(SoundEvent::Permission)
+                .is_some()
+            || runtime.permission_reminder.is_some()
+            || response_writer.is_some())
+        .then(QuestionDetector::new);
+        // The number of the question shown, or 0, for confirmations to check.
+        let shown_question = Arc::new(AtomicU64::new(0));
+        let mut tool_calls = runtime.speak_tools_enabled.then(ToolCallDetector::new);
+        let mut permission_reminder = Reminder::default();
+        let mut spinners = (runtime.progress_after.is_some()
+            || runtime.stall_timeout.is_some()
+            || runtime.progress_state_enabled
+            || runtime.speak_final_enabled)
+            .then(SpinnerDetector::new);
+        let progress_state = runtime.progress_state_enabled.then(|| {
+            ProgressState::new(|sequence| {
+                let mut stdout = io::stdout();
+                let _ = stdout.write_all(sequence);
+                let _ = stdout.flush();
+            })
+        });
+        // --speak-final: the last message of a turn, once `claude` is waiting
+        // again, unless nothing new has been said since the last turn.
+        let final_message = last_message
+            .clone()
+            .filter(|_| runtime.speak_final_enabled)
+            .map(|last_message| {
+                let speech_tx = output_speech_tx.clone();
+                let debug_log = debug_log.clone();
+                let spoken = Mutex::new(None);
+                ProgressState::new(move |sequence| {
+                    if sequence != progress_state::WAITING {
+                        return;
+                    }
+                    let message = last_message.get();
+                    let mut spoken = spoken.lock().unwrap_or_else(PoisonError::into_inner);
+                    if message.is_none() || *spoken == message {
+                        debug_log.log(format_args!("final message: nothing new"));
+                        return;
+                    }
+                    if let Some(message) = &message {
+                        let _ = speech_tx.send(Utterance::new(message, SpeechPriority::Normal));
+                    }
+                    *spoken = message;
+                })
+            });
+        let mut progress_reminder = runtime.progress_after.map(ProgressReminder::new);
+        let stall_watch = runtime.stall_timeout.map(|timeout| {
+            let notification_tx = notification_tx.clone();
+            let subtitle = session_name.clone();
+            let debug_log = debug_log.clone();
+            StallWatch::new(timeout, move || {
+                debug_log.log(format_args!("stall: no output for {timeout:?}"));
+                let _ = notification_tx.try_send(Notification {
+                    title: DEFAULT_NOTIFICATION_TITLE.to_string(),
+                    subtitle: subtitle.clone(),
+                    message: stall::message(timeout),
+                    ..Default::default()
+                });
+            })
+        });
+        let mut alerts = (!runtime.alert_kinds.is_empty())
+            .then(|| AlertDetector::new(&runtime.alert_kinds, &runtime.alert_patterns));
+        let mut title_restore = Reminder::default();
 
-        while let Ok(n) = buffer.extend_from_read(&mut reader) {
-            if n == 0 {
-                break;
+        loop {
+            let held = buffer.pending_len();
+            let read = match &mut runtime.recorder {
+                Some(recorder) => buffer.extend_from_read(recorder.tee(&mut reader)),
+                None => buffer.extend_from_read(&mut reader),
+            };
+            if let Some(e) = runtime.recorder.as_mut().and_then(Recorder::take_error) {
+                debug_log.log(format_args!("--record: {e}"));
+            }
+            match read {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    let status = reader.take_status();
+                    if status.flush_write() {
+                        // The slave discarded its queued output; drop ours too.
+                        buffer.clear();
+                    }
+                    if status.stop() {
+                        // Output is paused by XOFF: show what is being held back
+                        // instead of waiting for data that may not come soon,
+                        // but not half an escape sequence, whose rest would not
+                        // be recognized, e.g. as a notification.
+                        if stdout.write_all(buffer.take_complete()).is_err()
+                            || stdout.flush().is_err()
+                        {
+                            return;
+                        }
+                    }
+                    continue;
+                }
+                Err(_) => break,
             }
 
-            runtime
-                .reformatter
-                .set_terminal_width(TERMINAL_WIDTH.load(std::sync::atomic::Ordering::Relaxed));
-            for fragment in buffer.read_fragments(&runtime.reformatter) {
-                if stdout.write_all(fragment.data()).is_err() {
+            // Checked once the read returns, as a resize usually comes while
+            // it blocks, so that what was just read is laid out at the new width.
+            if let Some(width) = width_rx.try_iter().last() {
+                // Lines held back for URL unwrapping were laid out for the old
+                // width and cannot be joined reliably at the new one.
+                if stdout.write_all(buffer.take_pending_prefix(held)).is_err() {
                     return;
                 }
-                match fragment.escape_sequence() {
-                    Some(EscapeSequence::SetWindowAndIconTitle(new_title)) => {
-                        title.replace_range(.., &String::from_utf8_lossy(new_title.trim_ascii()));
+                runtime.reformatter.set_terminal_width(width);
+                debug_log.log(format_args!(
+                    "terminal width: {width} (passthrough: {})",
+                    runtime.reformatter.is_passthrough()
+                ));
+            }
+
+            let mut on_escape_sequence = |escape_sequence: &EscapeSequence| match escape_sequence {
+                EscapeSequence::SetWindowAndIconTitle(new_title) => {
+                    title.replace_range(.., &String::from_utf8_lossy(new_title.trim_ascii()));
+                    debug_log.log(format_args!("OSC 0 title: {title:?}"));
+                    if let Some(title_history) = &title_history {
+                        title_history.push(&title);
+                    }
+                }
+                EscapeSequence::PostNotification(message)
+                | EscapeSequence::PostTitledNotification { message, .. } => {
+                    let message = normalize(&String::from_utf8_lossy(message.trim_ascii()));
+                    debug_log.log(format_args!("OSC 9 notification: {message:?}"));
+                    // `caloud send` gives a title; `claude` leaves it to OSC 0.
+                    let (title, parameters) = match escape_sequence {
+                        EscapeSequence::PostTitledNotification {
+                            title, parameters, ..
+                        } => (
+                            normalize(&String::from_utf8_lossy(title.trim_ascii())),
+                            notification_parameters(parameters),
+                        ),
+                        _ => (title.clone(), Vec::new()),
+                    };
+                    if let Some(duration) = runtime.title_flash {
+                        flash_title(&title_flash, &message, duration, &mut title_restore);
+                    }
+                    if let Err(e) = notification_tx.try_send(Notification {
+                        title,
+                        subtitle: session_name.clone(),
+                        is_urgent: Notification::is_permission_request(&message),
+                        parameters,
+                        message,
+                        ..Default::default()
+                    }) {
+                        debug_log.log(format_args!("notification dropped: {e}"));
+                    }
+                }
+                EscapeSequence::QueryColor { ps, terminator } => {
+                    let (Some(mode), Some(writer)) = (runtime.appearance, &appearance_writer)
+                    else {
+                        return;
+                    };
+                    let appearance = mode.resolve();
+                    if let Some(report) = appearance.color_report(ps, terminator) {
+                        debug_log.log(format_args!("color query: answered {appearance:?}"));
+                        let _ = (&**writer).write_all(&report);
+                    }
+                }
+                EscapeSequence::ColorSchemeUpdates(enabled) => {
+                    color_scheme_updates.store(*enabled, Ordering::Relaxed);
+                }
+                EscapeSequence::EndSynchronizedUpdate
+                | EscapeSequence::ShowCursor
+                | EscapeSequence::Incomplete
+                | EscapeSequence::Other => {}
+                EscapeSequence::Osc777(command) => {
+                    debug_log.log(format_args!(
+                        "OSC 777: ignored {:?}",
+                        String::from_utf8_lossy(command)
+                    ));
+                }
+            };
+
+            let mut spinner_in_output = false;
+            if runtime.reformatter.is_passthrough()
+                && runtime.osc9_passthrough_enabled
+                && messages.is_none()
+                && questions.is_none()
+                && tool_calls.is_none()
+                && transcript.is_none()
+                && alerts.is_none()
+                && spinners.is_none()
+                && appearance_writer.is_none()
+                && title_flash.get().is_none()
+            {
+                // Fast path: forward the bytes as read and only look for OSC sequences.
+                let data = buffer.take_pending();
+                debug_log.log(format_args!("passthrough: {} bytes", data.len()));
+                osc_scanner.scan(data, &mut on_escape_sequence);
+                if stdout.write_all(data).is_err() {
+                    return;
+                }
+            } else {
+                for fragment in buffer.read_fragments(&runtime.reformatter) {
+                    debug_log.log(format_args!("fragment: {fragment:?}"));
+                    // With --no-notify, keep the host terminal from showing it.
+                    let is_suppressed = !runtime.osc9_passthrough_enabled
+                        && matches!(
+                            fragment.escape_sequence(),
+                            Some(
+                                EscapeSequence::PostNotification(_)
+                                    | EscapeSequence::PostTitledNotification { .. }
+                            )
+                        );
+                    if is_suppressed {
+                        debug_log.log(format_args!(
+                            "OSC 9 not forwarded to the terminal: --no-notify"
+                        ));
+                    }
+                    // With --appearance, caloud answers instead of the host terminal.
+                    let is_answered = appearance_writer.is_some()
+                        && matches!(
+                            fragment.escape_sequence(),
+                            Some(EscapeSequence::QueryColor { .. })
+                        );
+                    // With --title-flash, a new title waits until the notification
+                    // has been shown long enough.
+                    let is_held_back = match (title_flash.get(), fragment.escape_sequence()) {
+                        (Some(title_flash), Some(EscapeSequence::SetWindowAndIconTitle(_))) => {
+                            !title_flash
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .set(fragment.data())
+                        }
+                        _ => false,
+                    };
+                    if is_held_back {
+                        debug_log.log(format_args!("OSC 0 held back: --title-flash"));
+                    }
+                    if !is_suppressed && !is_answered && !is_held_back {
+                        let written = match &mut transcript {
+                            Some(transcript) => stdout.write_all(&transcript.push(&fragment)),
+                            None => stdout.write_all(fragment.data()),
+                        };
+                        if written.is_err() {
+                            return;
+                        }
                     }
-                    Some(EscapeSequence::PostNotification(message)) => {
-                        let message = String::from_utf8_lossy(message.trim_ascii()).into_owned();
-                        let _ = notification_tx.try_send((title.clone(), message));
+                    if let Some(escape_sequence) = fragment.escape_sequence() {
+                        on_escape_sequence(escape_sequence);
+                    }
+                    if let Some(name) = tool_calls.as_mut().and_then(|t| t.push(&fragment)) {
+                        debug_log.log(format_args!("tool call: {name}"));
+                        let _ = output_speech_tx.send(Utterance::new(name, SpeechPriority::Low));
+                    }
+                    if let Some(line) = messages.as_mut().and_then(|m| m.push(&fragment)) {
+                        if let Some(last_message) = &last_message {
+                            last_message.push(&line);
+                        }
+                        let text = line.text.trim();
+                        if runtime.speak_output_enabled && !text.is_empty() {
+                            let _ =
+                                output_speech_tx.send(Utterance::new(text, SpeechPriority::Low));
+                        }
+                        if let (Some(block), Some(destination)) = (
+                            code_blocks.as_mut().and_then(|c| c.push(&line)),
+                            &code_block_destination,
+                        ) {
+                            match destination.save(&block) {
+                                Ok(()) => {
+                                    debug_log.log(format_args!(
+                                        "code block: {} lines copied to {destination}",
+                                        block.lines().count()
+                                    ));
+                                    if runtime.code_block_notify_enabled {
+                                        post_notification(
+                                            &mut stdout,
+                                            runtime.osc9_passthrough_enabled,
+                                            CODE_BLOCK_COPIED_MESSAGE,
+                                            &mut on_escape_sequence,
+                                        );
+                                    }
+                                }
+                                Err(e) => debug_log.log(format_args!("code block: {e:#}")),
+                            }
+                        }
+                    }
+                    if let Some(alert) = alerts.as_mut().and_then(|a| a.push(&fragment)) {
+                        debug_log.log(format_args!("alert: {alert:?}"));
+                        post_notification(
+                            &mut stdout,
+                            runtime.osc9_passthrough_enabled,
+                            &alert.message(),
+                            &mut on_escape_sequence,
+                        );
+                    }
+                    let spinner_drawn = spinners.as_mut().is_some_and(|s| s.push(&fragment));
+                    spinner_in_output |= spinner_drawn;
+                    if let Some(progress_state) = progress_state.as_ref().filter(|_| spinner_drawn)
+                    {
+                        progress_state.spinner_drawn();
+                    }
+                    if let Some(final_message) = final_message.as_ref().filter(|_| spinner_drawn) {
+                        final_message.spinner_drawn();
+                    }
+                    if let Some(progress_reminder) =
+                        progress_reminder.as_mut().filter(|_| spinner_drawn)
+                    {
+                        let notification_tx = notification_tx.clone();
+                        let subtitle = session_name.clone();
+                        let started = progress_reminder.spinner_drawn(move |elapsed| {
+                            let _ = notification_tx.try_send(Notification {
+                                title: DEFAULT_NOTIFICATION_TITLE.to_string(),
+                                subtitle: subtitle.clone(),
+                                message: progress::message(elapsed),
+                                ..Default::default()
+                            });
+                        });
+                        if started {
+                            debug_log.log(format_args!("progress: claude is working"));
+                        }
+                    }
+                    let question = questions.as_mut().and_then(|q| q.push(&fragment));
+                    if let Some(detector) = &questions {
+                        shown_question.store(detector.pending().unwrap_or(0), Ordering::Relaxed);
+                    }
+                    if let Some(question) = question {
+                        debug_log.log(format_args!("question: {question:?}"));
+                        let Question {
+                            text,
+                            is_permission_prompt,
+                        } = question;
+                        if let Some(broadcaster) = &output_broadcaster {
+                            let text = match &question_redactor {
+                                Some(redactor) => redactor.apply(&text),
+                                None => text.as_str().into(),
+                            };
+                            broadcaster.post(BroadcastEvent::Question {
+                                text: &text,
+                                is_permission_prompt,
+                            });
+                        }
+                        let sound = output_sound_theme.question_event(is_permission_prompt);
+                        if let Err(e) = output_sound_theme.play(sound) {
+                            debug_log.log(format_args!("sound: {e:#}"));
+                        }
+                        // A spoken one interrupts by itself.
+                        if is_permission_prompt && !runtime.speak_questions_enabled {
+                            speech_interrupt.store(true, Ordering::Relaxed);
+                        }
+                        if let (Some(writer), Some(detector)) = (&response_writer, &questions) {
+                            auto_respond(
+                                &runtime.auto_responder,
+                                &text,
+                                detector.context(),
+                                writer,
+                                &input_count,
+                                &shown_question,
+                                &debug_log,
+                            );
+                        }
+                        if let Some(interval) =
+                            runtime.permission_reminder.filter(|_| is_permission_prompt)
+                        {
+                            let input_count = Arc::clone(&input_count);
+                            let asked_at = input_count.load(Ordering::Relaxed);
+                            let notification_tx = notification_tx.clone();
+                            let subtitle = session_name.clone();
+                            permission_reminder.start(interval, move |_| {
+                                if input_count.load(Ordering::Relaxed) != asked_at {
+                                    return false;
+                                }
+                                let _ = notification_tx.try_send(Notification {
+                                    title: DEFAULT_NOTIFICATION_TITLE.to_string(),
+                                    subtitle: subtitle.clone(),
+                                    message: PERMISSION_REMINDER_MESSAGE.to_string(),
+                                    is_urgent: true,
+                                    ..Default::default()
+                                });
+                                true
+                            });
+                        }
+                        if runtime.speak_questions_enabled {
+                            let priority = match is_permission_prompt {
+                                true => SpeechPriority::Urgent,
+                                false => SpeechPriority::Normal,
+                            };
+                            let _ = output_speech_tx.send(Utterance::new(text, priority));
+                        }
                     }
-                    Some(
-                        EscapeSequence::EndSynchronizedUpdate
-                        | EscapeSequence::ShowCursor
-                        | EscapeSequence::Incomplete
-                        | EscapeSequence::Other,
-                    )
-                    | None => {}
                 }
             }
+            if let Some(stall_watch) = &stall_watch
+                && stall_watch.output(spinner_in_output)
+            {
+                debug_log.log(format_args!("stall: output resumed"));
+                post_notification(
+                    &mut stdout,
+                    runtime.osc9_passthrough_enabled,
+                    stall::RESUMED_MESSAGE,
+                    &mut on_escape_sequence,
+                );
+            }
 
             if stdout.flush().is_err() {
                 break;
             }
         }
+
+        if let Some(review) = &output_review
+            && let Err(e) = review.close(&mut io::stdout())
+        {
+            debug_log.log(format_args!("review: {e:#}"));
+        }
+        // Emit what the reformatter was still holding back (e.g. a pending URL
+        // line in Adjust mode); there is nothing left to join it with.
+        if let Some(transcript) = &mut transcript {
+            let _ = stdout.write_all(&transcript.finish());
+        }
+        let _ = stdout.write_all(buffer.take_pending());
+        let _ = stdout.flush();
+        if let Some(title_flash) = title_flash.get() {
+            restore_title(title_flash);
+        }
+        if let Some(progress_state) = &progress_state {
+            progress_state.clear();
+        }
     });
 
     let notification_center_delivery_enabled = runtime.notification_center_delivery_enabled;
-    let say_command = runtime.say_command;
+    let fallback_flash_duration = runtime.title_flash.unwrap_or(FALLBACK_TITLE_FLASH);
+    let bell_enabled = runtime.bell_enabled;
+    let voiceover_enabled = runtime.voiceover_enabled;
+    let translate_command = runtime.translate_command.take();
+    let focus_rules = std::mem::take(&mut runtime.focus_rules);
+    let git_repository = runtime.git_repository.take();
+    let digest_interval = runtime.digest;
+    let redactor = runtime.redactor.clone();
+    let length_limits = runtime.length_limits;
+    let notification_broadcaster = broadcaster.clone();
+    let announcement_tx = event_tx.clone();
+    let notification_sound_theme = Arc::clone(&sound_theme);
+    let notification_review = review;
+    let finished = FinishNotifier(event_tx.clone());
+    let notification_thread = thread::spawn(move || {
+        let _finished = finished;
+        // Notification Center, falling back to `osascript` when it fails, on
+        // macOS.  Returns whether the notification has been shown or needs no
+        // showing.
+        let show = |notification: &Notification| -> bool {
+            if !notification_center_delivery_enabled {
+                debug_log.log(format_args!(
+                    "Notification Center: skipped, --notification-center is not enabled"
+                ));
+                return true;
+            }
+            let notification = &*length_limits.apply(notification);
+            let (notifier, fallback) = (Native::NOTIFIER, Native::FALLBACK_NOTIFIER);
+            match Native::deliver(notification) {
+                Ok(true) => debug_log.log(format_args!("{notifier}: delivered")),
+                Ok(false) => debug_log.log(format_args!(
+                    "{notifier}: skipped, the host terminal shows OSC 9 itself"
+                )),
+                Err(e) => {
+                    debug_log.log(format_args!("{notifier}: {e:#}"));
+                    match Native::deliver_fallback(notification) {
+                        Ok(()) => debug_log.log(format_args!("{fallback}: delivered")),
+                        Err(e) => {
+                            debug_log.log(format_args!("{fallback}: {e:#}"));
+                            return false;
+                        }
+                    }
+                }
+            }
+            true
+        };
+        // The other backends get a notification only once; if it cannot be
+        // shown, only showing it is retried.
+        let deliver = |notification: &Notification| -> bool {
+            if let Some(review) = &notification_review {
+                review.record(notification);
+            }
+            if let Some(broadcaster) = &notification_broadcaster {
+                broadcaster.post(BroadcastEvent::Notification(notification));
+            }
+            if bell_enabled {
+                let mut stdout = io::stdout();
+                let _ = stdout.write_all(b"\x07");
+                let _ = stdout.flush();
+            }
+            // A permission request gets the permission sound, if there is one,
+            // and the death of `claude` only the exit sound.
+            if !notification.is_exit {
+                let sound = match notification_sound_theme.question_event(notification.is_urgent) {
+                    SoundEvent::Permission => SoundEvent::Permission,
+                    _ => SoundEvent::Completion,
+                };
+                if let Err(e) = notification_sound_theme.play(sound) {
+                    debug_log.log(format_args!("sound: {e:#}"));
+                }
+            }
+            let shown = show(notification);
+            // The notification is shown as posted, and only spoken translated.
+            let spoken = match &translate_command {
+                Some(command) if speaks_notifications || voiceover_enabled => {
+                    match translate::translate(command, &notification.message) {
+                        Ok(translation) => translation,
+                        Err(e) => {
+                            debug_log.log(format_args!("translate: {e:#}"));
+                            notification.message.clone()
+                        }
+                    }
+                }
+                _ => notification.message.clone(),
+            };
+            let is_in_sight = say_when == SayWhen::Background
+                && speaks_notifications
+                && Native::is_host_terminal_active();
+            if is_in_sight {
+                debug_log.log(format_args!("say: skipped, the terminal is frontmost"));
+            } else if speaks_notifications && speaks_final_only && !notification.is_urgent {
+                debug_log.log(format_args!("say: skipped, --speak-final"));
+            } else if speaks_notifications {
+                let priority = match notification.is_urgent {
+                    true => SpeechPriority::Urgent,
+                    false => SpeechPriority::Normal,
+                };
+                let utterance = Utterance::new(spoken.clone(), priority);
+                if speech_tx.send(utterance).is_err() {
+                    debug_log.log(format_args!("say: skipped, the speech thread has finished"));
+                }
+            } else {
+                debug_log.log(format_args!("say: skipped, --say is not enabled"));
+            }
+            if voiceover_enabled {
+                let spoken = abbreviator.apply(&speech_emoji.apply(&spoken)).into_owned();
+                send_to_main(&announcement_tx, MainEvent::Announce(spoken));
+            }
+            if let Some(mirror_sender) =
+                mirror_sender.as_ref().filter(|_| !notification.is_mirrored)
+            {
+                mirror_sender.send(notification);
+            }
+            shown
+        };
+        let retry = |retry_queue: &mut RetryQueue<Notification>,
+                     notification: Notification,
+                     retries: u32| {
+            if retry_queue.push(notification, retries, Instant::now()) {
+                debug_log.log(format_args!(
+                    "delivery: retrying in {:?}",
+                    retry_queue::backoff(retries)
+                ));
+            } else {
+                debug_log.log(format_args!("delivery: gave up after {retries} retries"));
+            }
+        };
+        // Without desktop notifications, the user is alerted with the system
+        // sound and the terminal title instead, and told why once.
+        let mut is_warned = false;
+        let mut title_restore = Reminder::default();
+        let mut alert_instead = |notification: &Notification| {
+            #[cfg(target_os = "macos")]
+            beep();
+            flash_title(
+                &notification_title_flash,
+                &notification.message,
+                fallback_flash_duration,
+                &mut title_restore,
+            );
+            if !std::mem::replace(&mut is_warned, true) {
+                debug_log.log(format_args!("delivery: notifications are unavailable"));
+                let utterance =
+                    Utterance::new(NOTIFICATIONS_UNAVAILABLE_MESSAGE, SpeechPriority::Normal);
+                if speech_tx.send(utterance).is_err() {
+                    debug_log.log(format_args!("say: skipped, the speech thread has finished"));
+                }
+            }
+        };
+        let is_focus_aware = !focus_rules.is_empty();
+        let mut focus_filter = FocusFilter::new(focus_rules);
+        let mut retry_queue = RetryQueue::default();
+        let mut digest = digest_interval.map(Digest::new);
+        loop {
+            // While notifications are held back, the Focus is checked again
+            // now and then to deliver them once it allows, and those that
+            // could not be shown are retried when due.
+            let focus_poll = (focus_filter.queued() > 0).then_some(FOCUS_POLL_INTERVAL);
+            let retry_wait = retry_queue
+                .next_due()
+                .into_iter()
+                .chain(digest.as_ref().and_then(Digest::next_due))
+                .min()
+                .map(|due| due.saturating_duration_since(Instant::now()));
+            let received = match focus_poll.into_iter().chain(retry_wait).min() {
+                None => notification_rx
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+                Some(timeout) => notification_rx.recv_timeout(timeout),
+            };
+            // The branch may have changed since the session started.  Secrets
+            // are masked here, before anything is delivered, spoken, or sent.
+            let received = received.map(|mut notification| {
+                if let Some(repository) = git_repository
+                    .as_ref()
+                    .filter(|_| !notification.is_mirrored)
+                {
+                    notification.subtitle = Some(repository.session_name());
+                }
+                let notification = normalize::notification(notification);
+                match &redactor {
+                    Some(redactor) => redactor.notification(notification),
+                    None => notification,
+                }
+            });
+            let notifications = match received {
+                Ok(notification) if is_focus_aware => {
+                    let focus = current_focus(&debug_log);
+                    let policy = focus_filter.policy(focus.as_ref());
+                    debug_log.log(format_args!("focus: {focus:?}, {policy:?}"));
+                    focus_filter.push(notification, focus.as_ref())
+                }
+                Ok(notification) => vec![notification],
+                Err(RecvTimeoutError::Timeout) if focus_filter.queued() > 0 => {
+                    focus_filter.release(current_focus(&debug_log).as_ref())
+                }
+                Err(RecvTimeoutError::Timeout) => Vec::new(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let notifications = match &mut digest {
+                Some(digest) => {
+                    let now = Instant::now();
+                    let mut notifications: Vec<_> = notifications
+                        .into_iter()
+                        .filter_map(|notification| digest.push(notification, now))
+                        .collect();
+                    notifications.extend(digest.take_due(now));
+                    notifications
+                }
+                None => notifications,
+            };
+            for notification in notifications {
+                if !deliver(&notification) {
+                    alert_instead(&notification);
+                    retry(&mut retry_queue, notification, 0);
+                }
+            }
+            for (notification, retries) in retry_queue.take_due(Instant::now()) {
+                debug_log.log(format_args!("delivery: retry {retries}"));
+                if !show(&notification) {
+                    retry(&mut retry_queue, notification, retries);
+                }
+            }
+        }
+        // What the digest still holds is delivered when `claude` exits.
+        if let Some(notification) = digest.as_mut().and_then(Digest::take)
+            && !deliver(&notification)
+        {
+            alert_instead(&notification);
+        }
+        if focus_filter.queued() > 0 {
+            debug_log.log(format_args!(
+                "focus: {} notifications never delivered",
+                focus_filter.queued()
+            ));
+        }
+        if !retry_queue.is_empty() {
+            debug_log.log(format_args!(
+                "delivery: {} notifications never shown",
+                retry_queue.len()
+            ));
+        }
+    });
+
     thread::spawn(move || {
-        while let Ok((title, message)) = notification_rx.recv() {
-            if notification_center_delivery_enabled {
-                let _ = deliver_if_osc9_unsupported(&title, &message);
+        let status = loop {
+            match wait_with_usage(child) {
+                Err(Errno::EINTR) => {}
+                Ok((WaitStatus::Stopped(..), _)) => {
+                    if let Some(job_control) = &job_control {
+                        job_control.suspend();
+                    }
+                }
+                result => break result,
             }
-            if let Some(say_command) = &say_command {
-                let _ = say_command.run(&message);
+        };
+        send_to_main(&event_tx, MainEvent::ChildExited(status));
+    });
+
+    // The main thread sleeps in the run loop, where Notification Center clicks
+    // are handled, and wakes up only for events from the other threads.
+    //
+    // Orderly shutdown: the output thread drains the last bytes written by the
+    // child and then drops the notification sender, which lets the notification
+    // thread finish delivering what is queued, and the speech thread finish
+    // speaking once both have dropped their speech senders.  The run loop
+    // keeps running meanwhile so that Notification Center stays responsive.
+    // A grandchild holding the PTY open would keep the output thread alive, so
+    // the wait is bounded.  The terminal is restored when `termios` drops.
+    let mut report = None;
+    let mut exit_message = None;
+    let mut running_threads = 3;
+    let mut deadline = None;
+    while report.is_none() || running_threads > 0 {
+        #[cfg(target_os = "macos")]
+        let event = recv_on_main_run_loop(&main_run_loop, &event_rx, deadline);
+        #[cfg(not(target_os = "macos"))]
+        let event = recv_until(&event_rx, deadline);
+        let Some(event) = event else {
+            break;
+        };
+        match event {
+            MainEvent::ChildExited(status) => {
+                let (status, usage) = status?;
+                let exit_code = match status {
+                    WaitStatus::Exited(_, code) => code,
+                    WaitStatus::Signaled(_, signal, _) => 128 + signal as i32,
+                    status => anyhow::bail!("unexpected status: {status:?}"),
+                };
+                let _ = sound_theme.play(sound_theme.exit_event(exit_code));
+                if let Some(broadcaster) = &broadcaster {
+                    broadcaster.post(BroadcastEvent::Exited { exit_code });
+                }
+                // Dropping the senders lets the notification thread finish,
+                // and then the speech thread.
+                review_speech_tx
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take();
+                mirror_notification_tx
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take();
+                file_notification_tx
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take();
+                if let Some(tx) = exit_notification_tx.take() {
+                    exit_message = Notification::exit_message(status);
+                    if let Some(message) = &exit_message {
+                        let _ = tx.try_send(Notification {
+                            title: DEFAULT_NOTIFICATION_TITLE.to_string(),
+                            subtitle: exit_subtitle.clone(),
+                            message: message.clone(),
+                            is_urgent: true,
+                            is_exit: true,
+                            ..Default::default()
+                        });
+                    }
+                }
+                report = Some(ExitReport {
+                    exit_code,
+                    elapsed: started_at.elapsed(),
+                    usage,
+                });
+                deadline = Some(Instant::now() + SHUTDOWN_TIMEOUT);
             }
+            #[cfg(target_os = "macos")]
+            MainEvent::Announce(message) => announce(main_thread_marker, &message),
+            // `--voiceover` is turned down on other platforms.
+            #[cfg(not(target_os = "macos"))]
+            MainEvent::Announce(_) => {}
+            MainEvent::ThreadFinished => running_threads -= 1,
+        }
+    }
+    // The host terminal shows it too, once the output thread has let go of stdout.
+    if let Some(message) =
+        exit_message.filter(|_| osc9_passthrough_enabled && output_thread.is_finished())
+    {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b]9;{message}\x07");
+        let _ = stdout.flush();
+    }
+    for thread in [output_thread, notification_thread, speech_thread] {
+        if thread.is_finished() {
+            let _ = thread.join();
+        }
+    }
+
+    report.context("the child exit status was not received")
+}
+
+/// Post `message` as if `claude` had, so that it is shown by the host
+/// terminal, delivered, spoken, and suppressed by `--no-notify` alike.
+fn post_notification(
+    stdout: &mut impl Write,
+    osc9_passthrough_enabled: bool,
+    message: &str,
+    on_escape_sequence: &mut impl FnMut(&EscapeSequence),
+) {
+    if osc9_passthrough_enabled {
+        let _ = write!(stdout, "\x1b]9;{message}\x07");
+    }
+    on_escape_sequence(&EscapeSequence::PostNotification(message.as_bytes()));
+}
+
+/// Show `message` in the terminal title for `duration`, making `title_flash`
+/// first if no title has been flashed yet.
+fn flash_title(
+    title_flash: &Arc<OnceLock<Mutex<TitleFlash>>>,
+    message: &str,
+    duration: Duration,
+    title_restore: &mut Reminder,
+) {
+    let flash = title_flash
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .flash(message);
+    let _ = io::stdout().write_all(&flash);
+    let title_flash = Arc::clone(title_flash);
+    title_restore.start(duration, move |_| {
+        if let Some(title_flash) = title_flash.get() {
+            restore_title(title_flash);
         }
+        false
     });
+}
+
+/// Show the title of `claude` again after `--title-flash`.  The lock is held
+/// while writing so that a newer title is never overwritten by an older one.
+fn restore_title(title_flash: &Mutex<TitleFlash>) {
+    let mut title_flash = title_flash.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(sequence) = title_flash.restore() {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&sequence);
+        let _ = stdout.flush();
+    }
+}
+
+/// The Focus that is on, or `None` if none is or it cannot be read.
+#[cfg(target_os = "macos")]
+fn current_focus(debug_log: &DebugLog) -> Option<Focus> {
+    focus::current().unwrap_or_else(|e| {
+        debug_log.log(format_args!("focus: {e:#}"));
+        None
+    })
+}
+
+/// Focus is macOS only, and `--focus-policy` is turned down elsewhere.
+#[cfg(not(target_os = "macos"))]
+fn current_focus(_debug_log: &DebugLog) -> Option<Focus> {
+    None
+}
 
-    unsafe {
-        let run_loop = NSRunLoop::mainRunLoop();
+/// Report changes of the macOS appearance to `claude` while it has asked for
+/// them with mode 2031.
+fn spawn_appearance_watcher(writer: Arc<File>, subscribed: Arc<AtomicBool>, debug_log: DebugLog) {
+    thread::spawn(move || {
+        let mut last = appearance::detect().ok();
         loop {
-            match waitpid(child, Some(WaitPidFlag::WNOHANG)) {
-                Ok(WaitStatus::Exited(_, code)) => return Ok(code),
-                Ok(WaitStatus::Signaled(_, signal, _)) => return Ok(128 + signal as i32),
-                Ok(WaitStatus::StillAlive) => {}
-                Ok(status) => anyhow::bail!("unexpected status: {status:?}"),
-                Err(e) => anyhow::bail!(e),
-            }
-            run_loop.runMode_beforeDate(
-                NSDefaultRunLoopMode,
-                &NSDate::dateWithTimeIntervalSinceNow(0.1),
-            );
+            thread::sleep(APPEARANCE_POLL_INTERVAL);
+            let Ok(current) = appearance::detect() else {
+                continue;
+            };
+            if last.replace(current) == Some(current) {
+                continue;
+            }
+            debug_log.log(format_args!("appearance: {current:?}"));
+            if subscribed.load(Ordering::Relaxed)
+                && (&*writer).write_all(current.color_scheme_report()).is_err()
+            {
+                return;
+            }
         }
+    });
+}
+
+/// Speak `text`, stopping early if `interrupts` says so meanwhile.  Returns
+/// whether it was said to the end.
+fn speak(
+    say_command: &SayCommand,
+    text: &str,
+    mut interrupts: impl FnMut() -> bool,
+) -> anyhow::Result<bool> {
+    let mut child = say_command.spawn(text)?;
+    while child.try_wait()?.is_none() {
+        if interrupts() {
+            child.kill()?;
+            child.wait()?;
+            return Ok(false);
+        }
+        thread::sleep(SPEECH_POLL_INTERVAL);
+    }
+    Ok(true)
+}
+
+/// Answer `question` as the first matching `--auto-respond` rule says, if any.
+/// A `confirm` rule asks on another thread so that output keeps flowing, and
+/// answers only if `question` is still the one shown, by `shown_question`,
+/// and unanswered.
+fn auto_respond(
+    responder: &AutoResponder,
+    question: &str,
+    context: &[String],
+    writer: &Arc<File>,
+    input_count: &Arc<AtomicUsize>,
+    shown_question: &Arc<AtomicU64>,
+    debug_log: &DebugLog,
+) {
+    let rule = match responder.respond(question, context) {
+        Response::None => return,
+        Response::Unsafe { pattern } => {
+            debug_log.log(format_args!("auto-respond: skipped, matches {pattern:?}"));
+            return;
+        }
+        Response::Respond(rule) => rule,
+    };
+    debug_log.log(format_args!("auto-respond: {rule}"));
+    let keys = rule.keys.clone();
+    let writer = Arc::clone(writer);
+    let input_count = Arc::clone(input_count);
+    let shown_question = Arc::clone(shown_question);
+    let (asked, inputs_asked_at) = (
+        shown_question.load(Ordering::Relaxed),
+        input_count.load(Ordering::Relaxed),
+    );
+    let answer_log = debug_log.clone();
+    let answer = move || {
+        if shown_question.load(Ordering::Relaxed) != asked
+            || input_count.load(Ordering::Relaxed) != inputs_asked_at
+        {
+            answer_log.log(format_args!("auto-respond: skipped, no longer asked"));
+            return;
+        }
+        // Counted as input, like an answer the user typed.
+        match writer.as_ref().write_all(&keys) {
+            Ok(()) => {
+                input_count.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => answer_log.log(format_args!("auto-respond: {e}")),
+        }
+    };
+    match rule.mode {
+        AutoRespondMode::Auto => answer(),
+        AutoRespondMode::Confirm => {
+            let message = format!("{question}\n\nAnswer with {}?", escape_bytes(&rule.keys));
+            let debug_log = debug_log.clone();
+            thread::spawn(move || {
+                match dialog::confirm(DEFAULT_NOTIFICATION_TITLE, &message, CONFIRM_TIMEOUT) {
+                    Ok(true) => answer(),
+                    Ok(false) => debug_log.log(format_args!("auto-respond: skipped by the user")),
+                    Err(e) => debug_log.log(format_args!("auto-respond: {e:#}")),
+                }
+            });
+        }
+    }
+}
+
+/// Wait in the main run loop, handling its sources, until an event arrives or
+/// `deadline` passes.
+#[cfg(target_os = "macos")]
+fn recv_on_main_run_loop<T>(
+    main_run_loop: &MainRunLoop,
+    rx: &Receiver<T>,
+    deadline: Option<Instant>,
+) -> Option<T> {
+    loop {
+        match rx.try_recv() {
+            Ok(event) => return Some(event),
+            Err(mpsc::TryRecvError::Disconnected) => return None,
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+        if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            return None;
+        }
+        main_run_loop.run_once(deadline);
+    }
+}
+
+/// Wait until an event arrives or `deadline` passes, with no run loop to
+/// handle outside macOS.
+#[cfg(not(target_os = "macos"))]
+fn recv_until<T>(rx: &Receiver<T>, deadline: Option<Instant>) -> Option<T> {
+    match deadline {
+        Some(deadline) => rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok(),
+        None => rx.recv().ok(),
+    }
+}
+
+fn send_to_main(tx: &Sender<MainEvent>, event: MainEvent) {
+    let _ = tx.send(event);
+    #[cfg(target_os = "macos")]
+    wake_main_run_loop();
+}
+
+/// Reports [`MainEvent::ThreadFinished`] when dropped by the thread that owns it.
+struct FinishNotifier(Sender<MainEvent>);
+
+impl Drop for FinishNotifier {
+    fn drop(&mut self) {
+        send_to_main(&self.0, MainEvent::ThreadFinished);
     }
 }
 
@@ -145,6 +1714,30 @@ impl<Fd: AsFd> Drop for TermiosGuard<Fd> {
     }
 }
 
+/// Show the cursor and leave the alternate screen, as `claude` would have on
+/// exit, when caloud ends without it.
+const RESET_SCREEN: &[u8] = b"\x1b[?25h\x1b[?1049l";
+
+fn reset_screen() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(RESET_SCREEN).and_then(|()| stdout.flush());
+}
+
+/// Restore the terminal before a panic in any thread is printed, so that it
+/// can be read, and exit, since the session cannot go on in a terminal that
+/// is no longer raw.  `claude` is then terminated by the parent watchdog.
+fn install_panic_hook(termios: Termios) {
+    let termios = Mutex::new(termios);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let termios = termios.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = tcsetattr(io::stdin(), SetArg::TCSANOW, &termios);
+        reset_screen();
+        default_hook(info);
+        std::process::exit(101);
+    }));
+}
+
 fn try_make_raw<Fd: AsFd>(fd: Fd) -> anyhow::Result<TermiosGuard<Fd>> {
     let termios = tcgetattr(fd.as_fd()).context("tcgetattr() failed")?;
     let mut new_termios = termios.clone();
@@ -153,27 +1746,55 @@ fn try_make_raw<Fd: AsFd>(fd: Fd) -> anyhow::Result<TermiosGuard<Fd>> {
     Ok(TermiosGuard(fd, termios))
 }
 
-fn spawn_winsize_updater<Fd: AsRawFd + Send + Sync + 'static>(fd: Fd) -> anyhow::Result<()> {
-    update_winsize(&fd).context("update_winsize() failed")?;
+/// Propagate the window size of stdin to `fd` once it has settled after
+/// SIGWINCH, and report the new width to `width_tx`.  The size is also
+/// propagated when caloud is continued or the machine wakes from sleep, when
+/// it may have changed unnoticed.
+fn spawn_winsize_updater<Fd: AsRawFd + Send + Sync + 'static>(
+    fd: Fd,
+    fallback_width: u16,
+    width_tx: Sender<u16>,
+) -> anyhow::Result<()> {
+    update_winsize(&fd, fallback_width).context("update_winsize() failed")?;
 
     // On macOS, sigwait() requires signals to be "blocked, but not ignored" (man sigwait).
     // Setting an empty handler ensures the signal is not ignored and makes sigwait() happy.
     extern "C" fn noop(_: nix::libc::c_int) {}
-    unsafe { signal(Signal::SIGWINCH, SigHandler::Handler(noop)) }
-        .context("failed to set SIGWINCH handler")?;
-
     let mut sigset = SigSet::empty();
-    sigset.add(Signal::SIGWINCH);
-    sigset.thread_block().context("failed to block SIGWINCH")?;
+    for sig in [Signal::SIGWINCH, Signal::SIGCONT] {
+        unsafe { signal(sig, SigHandler::Handler(noop)) }
+            .with_context(|| format!("failed to set {sig} handler"))?;
+        sigset.add(sig);
+    }
+    sigset
+        .thread_block()
+        .context("failed to block SIGWINCH and SIGCONT")?;
 
+    let (resize_tx, resize_rx) = mpsc::channel::<()>();
+    let wake_tx = resize_tx.clone();
+    thread::spawn(move || {
+        while sigset.wait().is_ok() {
+            if resize_tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
     thread::spawn(move || {
         loop {
-            match sigset.wait() {
-                Ok(Signal::SIGWINCH) => {
-                    let _ = update_winsize(&fd);
-                }
-                Ok(_) => {}
-                Err(_) => break,
+            wait_for_wake();
+            if wake_tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    thread::spawn(move || {
+        while resize_rx.recv().is_ok() {
+            // Dragging the edge of a window sends a SIGWINCH for every step.
+            while resize_rx.recv_timeout(WINSIZE_SETTLE_TIME).is_ok() {}
+            if let Ok(winsize) = update_winsize(&fd, fallback_width)
+                && width_tx.send(winsize.ws_col).is_err()
+            {
+                break;
             }
         }
     });
@@ -181,23 +1802,59 @@ fn spawn_winsize_updater<Fd: AsRawFd + Send + Sync + 'static>(fd: Fd) -> anyhow:
     Ok(())
 }
 
-fn update_winsize<Fd: AsRawFd>(fd: &Fd) -> anyhow::Result<()> {
-    ioctl_read_bad!(get_winsize, nix::libc::TIOCGWINSZ, nix::libc::winsize);
-    ioctl_write_ptr_bad!(set_winsize, nix::libc::TIOCSWINSZ, nix::libc::winsize);
+/// Block until the machine has slept, as told by the wall clock running ahead
+/// of [`Instant`], which stops meanwhile.
+fn wait_for_wake() {
+    loop {
+        let (wall_clock, clock) = (SystemTime::now(), Instant::now());
+        thread::sleep(WAKE_CHECK_INTERVAL);
+        if wall_clock
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed > clock.elapsed() + WAKE_CHECK_INTERVAL)
+        {
+            return;
+        }
+    }
+}
+
+fn update_winsize<Fd: AsRawFd>(fd: &Fd, fallback_width: u16) -> anyhow::Result<Winsize> {
+    let winsize = stdin_winsize(fallback_width);
+    set_winsize(fd, &winsize).context("set_winsize() failed")?;
+
+    Ok(winsize)
+}
 
-    let stdin = io::stdin().as_fd().as_raw_fd();
-    let fd = fd.as_raw_fd();
-    let mut winsize = nix::libc::winsize {
+/// The window size of stdin, or `fallback_width` columns by 24 rows when it
+/// is not a terminal, or one that does not know its size, e.g. in CI.
+fn stdin_winsize(fallback_width: u16) -> Winsize {
+    match get_winsize(&io::stdin()) {
+        Ok(winsize) if winsize.ws_col > 0 => winsize,
+        _ => Winsize {
+            ws_row: 24,
+            ws_col: fallback_width,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        },
+    }
+}
+
+/// Read the full window size, including `ws_xpixel`/`ws_ypixel`, which
+/// image-capable programs use to size their output.
+fn get_winsize<Fd: AsFd>(fd: &Fd) -> nix::Result<Winsize> {
+    ioctl_read_bad!(get_winsize, nix::libc::TIOCGWINSZ, Winsize);
+
+    let mut winsize = Winsize {
         ws_row: 0,
         ws_col: 0,
         ws_xpixel: 0,
         ws_ypixel: 0,
     };
+    unsafe { get_winsize(fd.as_fd().as_raw_fd(), &mut winsize) }?;
+    Ok(winsize)
+}
 
-    unsafe { get_winsize(stdin, &mut winsize) }.context("get_winsize() failed")?;
-    unsafe { set_winsize(fd, &winsize) }.context("set_winsize() failed")?;
-
-    TERMINAL_WIDTH.store(winsize.ws_col, std::sync::atomic::Ordering::Relaxed);
+fn set_winsize<Fd: AsRawFd>(fd: &Fd, winsize: &Winsize) -> nix::Result<()> {
+    ioctl_write_ptr_bad!(set_winsize, nix::libc::TIOCSWINSZ, Winsize);
 
-    Ok(())
+    unsafe { set_winsize(fd.as_raw_fd(), winsize) }.map(drop)
 }
@@ -0,0 +1,523 @@
+//! `--mirror-send` and `--mirror-receive`: forward notifications to a session
+//! on another Mac on the LAN, so that a session running on one Mac can alert
+//! the one the user is sitting at.
+//!
+//! Receiving sessions are advertised and found with Bonjour through `dns-sd`.
+//! A notification is one line of tab-separated fields, escaped like
+//! `--input-rewrite` rules, sent over TCP sealed with the `--mirror-key` that
+//! both sessions share, so that only they can read or forge one.
+
+use crate::debug_log::DebugLog;
+use crate::event::Notification;
+use crate::input_rewrite::escape::{escape_bytes, parse_escaped_str};
+use anyhow::{Context, bail};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use nix::unistd::getuid;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SERVICE_TYPE: &str = "_caloud._tcp";
+
+/// How long a peer gets to be resolved, to accept a connection, or to send
+/// its notification.
+const PEER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Longer messages are cut, and fail to open.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// Older messages, or ones from further in the future, are taken for replays.
+const MAX_MESSAGE_AGE: Duration = Duration::from_secs(60);
+
+const NONCE_LEN: usize = 24;
+
+/// The key shared by the sessions that mirror notifications to each other.
+#[derive(Clone)]
+pub struct MirrorKey(Key);
+
+impl MirrorKey {
+    /// Read the key from `path`: 64 hex digits, e.g. from `openssl rand -hex
+    /// 32`, in a file that only the current user can read.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("failed to stat --mirror-key: {}", path.display()))?;
+        if metadata.uid() != getuid().as_raw() || metadata.mode() & 0o077 != 0 {
+            bail!(
+                "--mirror-key is not private to the current user: {}",
+                path.display()
+            );
+        }
+        let key = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read --mirror-key: {}", path.display()))?;
+        Self::parse(key.trim()).with_context(|| format!("invalid --mirror-key: {}", path.display()))
+    }
+
+    fn parse(hex: &str) -> anyhow::Result<Self> {
+        let digits = hex.as_bytes();
+        if digits.len() != 64 {
+            bail!("expected 64 hex digits, got {}", digits.len());
+        }
+        let mut key = [0; 32];
+        for (byte, pair) in key.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair)?;
+            *byte = u8::from_str_radix(pair, 16)
+                .with_context(|| format!("not a hex number: {pair}"))?;
+        }
+        Ok(Self(Key::from(key)))
+    }
+
+    /// `line` encrypted and authenticated, after a random nonce, with the
+    /// time it was sealed at.
+    fn seal(&self, line: &str) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut plaintext = unix_time().as_secs().to_be_bytes().to_vec();
+        plaintext.extend_from_slice(line.as_bytes());
+        let ciphertext = XChaCha20Poly1305::new(&self.0)
+            .encrypt(&nonce, plaintext.as_slice())
+            .expect("encryption failed");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    /// The line of a message sealed with the same key in the last
+    /// [`MAX_MESSAGE_AGE`], unless it is in `seen` already.
+    fn open(&self, message: &[u8], seen: &mut SeenNonces) -> anyhow::Result<String> {
+        let Some((nonce, ciphertext)) = message.split_at_checked(NONCE_LEN) else {
+            bail!("message too short");
+        };
+        let plaintext = XChaCha20Poly1305::new(&self.0)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .ok()
+            .context("not sealed with --mirror-key")?;
+        let Some((sealed_at, line)) = plaintext.split_first_chunk::<8>() else {
+            bail!("message too short");
+        };
+        let (sealed_at, now) = (u64::from_be_bytes(*sealed_at), unix_time().as_secs());
+        let age = now.abs_diff(sealed_at);
+        if age > MAX_MESSAGE_AGE.as_secs() {
+            bail!("message sealed {age}s away from now");
+        }
+        if !seen.insert(nonce, sealed_at, now) {
+            bail!("message received already");
+        }
+        Ok(String::from_utf8(line.to_vec())?)
+    }
+}
+
+impl fmt::Debug for MirrorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MirrorKey(..)")
+    }
+}
+
+/// The nonces of the messages opened in the last [`MAX_MESSAGE_AGE`], so that
+/// one sent again by someone who captured it is rejected; older ones are
+/// rejected for their age.
+#[derive(Debug, Default)]
+struct SeenNonces(HashMap<Vec<u8>, u64>);
+
+impl SeenNonces {
+    /// Record the nonce of a message sealed at `sealed_at`, forgetting those
+    /// too old to open by `now`; `false` if it has been recorded already.
+    fn insert(&mut self, nonce: &[u8], sealed_at: u64, now: u64) -> bool {
+        self.0
+            .retain(|_, sealed_at| now.abs_diff(*sealed_at) <= MAX_MESSAGE_AGE.as_secs());
+        self.0.insert(nonce.to_vec(), sealed_at).is_none()
+    }
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// The Bonjour name of this session, `caloud-<host>-<pid>`.
+pub fn instance_name() -> String {
+    let host = nix::unistd::gethostname()
+        .ok()
+        .and_then(|host| host.into_string().ok())
+        .unwrap_or_default();
+    let host = host.split('.').next().unwrap_or_default().replace(' ', "-");
+    format!("caloud-{host}-{}", std::process::id())
+}
+
+pub struct MirrorReceiver {
+    listener: TcpListener,
+    key: MirrorKey,
+}
+
+impl MirrorReceiver {
+    /// Listen for notifications sealed with `key`.
+    pub fn bind(key: MirrorKey) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .context("failed to listen for mirrored notifications")?;
+        Ok(Self { listener, key })
+    }
+
+    /// Advertise this session as `name`, and pass each notification received
+    /// to `deliver` on a background thread.  Each peer is read from on its
+    /// own thread, so that an idle one holds up no other.  The advertisement
+    /// is withdrawn when the returned `dns-sd` is dropped.
+    pub fn spawn(
+        self,
+        name: &str,
+        deliver: impl Fn(Notification) + Send + 'static,
+        debug_log: DebugLog,
+    ) -> anyhow::Result<DnsSd> {
+        let port = self.listener.local_addr()?.port().to_string();
+        let advertisement = DnsSd::spawn(&["-R", name, SERVICE_TYPE, "local", &port])?;
+        debug_log.log(format_args!("mirror: receiving as {name} on port {port}"));
+        let (listener, key) = (self.listener, Arc::new(self.key));
+        let seen = Arc::new(Mutex::new(SeenNonces::default()));
+        let (notification_tx, notification_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for notification in notification_rx {
+                deliver(notification);
+            }
+        });
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (key, seen) = (Arc::clone(&key), Arc::clone(&seen));
+                let notification_tx = notification_tx.clone();
+                let debug_log = debug_log.clone();
+                thread::spawn(move || match receive(&stream, &key, &seen) {
+                    Ok(notification) => {
+                        let _ = notification_tx.send(notification);
+                    }
+                    Err(e) => debug_log.log(format_args!("mirror: {e:#}")),
+                });
+            }
+        });
+        Ok(advertisement)
+    }
+}
+
+fn receive(
+    stream: &TcpStream,
+    key: &MirrorKey,
+    seen: &Mutex<SeenNonces>,
+) -> anyhow::Result<Notification> {
+    // A peer that never closes the connection is given up on.
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+    let mut message = Vec::new();
+    stream.take(MAX_MESSAGE_BYTES).read_to_end(&mut message)?;
+    let peer = stream.peer_addr()?;
+    let line = key
+        .open(
+            &message,
+            &mut seen.lock().unwrap_or_else(PoisonError::into_inner),
+        )
+        .with_context(|| format!("rejected notification from {peer}"))?;
+    decode(&line).with_context(|| format!("malformed notification from {peer}"))
+}
+
+/// Sends notifications to the receiving sessions found on the LAN.
+pub struct MirrorSender {
+    own_name: String,
+    /// Receiving sessions by name, with the number of interfaces they were
+    /// found on.
+    peers: Arc<Mutex<BTreeMap<String, usize>>>,
+    _browser: DnsSd,
+    key: MirrorKey,
+    debug_log: DebugLog,
+}
+
+impl MirrorSender {
+    /// Start looking for receiving sessions other than `own_name`, to send
+    /// them notifications sealed with `key`, which only those that share it
+    /// can read.
+    pub fn start(own_name: String, key: MirrorKey, debug_log: DebugLog) -> anyhow::Result<Self> {
+        let mut browser = DnsSd::spawn(&["-B", SERVICE_TYPE, "local"])?;
+        let stdout = browser.take_stdout()?;
+        let peers = Arc::new(Mutex::new(BTreeMap::<String, usize>::new()));
+        let found = Arc::clone(&peers);
+        let browse_log = debug_log.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let Some((is_added, name)) = parse_browse_line(&line) else {
+                    continue;
+                };
+                browse_log.log(format_args!(
+                    "mirror: {name} {}",
+                    if is_added { "found" } else { "gone" }
+                ));
+                let mut peers = found.lock().unwrap_or_else(PoisonError::into_inner);
+                let count = peers.entry(name.clone()).or_insert(0);
+                match is_added {
+                    true => *count += 1,
+                    false => *count = count.saturating_sub(1),
+                }
+                if *count == 0 {
+                    peers.remove(&name);
+                }
+            }
+        });
+        Ok(Self {
+            own_name,
+            peers,
+            _browser: browser,
+            key,
+            debug_log,
+        })
+    }
+
+    /// Send `notification` to each receiving session found so far, on a
+    /// background thread.
+    pub fn send(&self, notification: &Notification) {
+        let names: Vec<_> = self
+            .peers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .filter(|name| **name != self.own_name)
+            .cloned()
+            .collect();
+        if names.is_empty() {
+            self.debug_log
+                .log(format_args!("mirror: skipped, no receiving session found"));
+            return;
+        }
+        let message = self.key.seal(&encode(notification));
+        let debug_log = self.debug_log.clone();
+        thread::spawn(move || {
+            for name in names {
+                match send_to(&name, &message) {
+                    Ok(()) => debug_log.log(format_args!("mirror: sent to {name}")),
+                    Err(e) => debug_log.log(format_args!("mirror: {name}: {e:#}")),
+                }
+            }
+        });
+    }
+}
+
+fn send_to(name: &str, message: &[u8]) -> anyhow::Result<()> {
+    let (host, port) = resolve(name)?;
+    let address = (host.as_str(), port)
+        .to_socket_addrs()
+        .with_context(|| format!("failed to look up {host}"))?
+        .next()
+        .with_context(|| format!("no address for {host}"))?;
+    let mut stream = TcpStream::connect_timeout(&address, PEER_TIMEOUT)
+        .with_context(|| format!("failed to connect to {address}"))?;
+    stream.set_write_timeout(Some(PEER_TIMEOUT))?;
+    stream.write_all(message)?;
+    Ok(())
+}
+
+/// The host and port of the session advertised as `name`.
+fn resolve(name: &str) -> anyhow::Result<(String, u16)> {
+    let mut lookup = DnsSd::spawn(&["-L", name, SERVICE_TYPE, "local"])?;
+    let stdout = lookup.take_stdout()?;
+    let (tx, rx) = mpsc::channel();
+    // Ends once `dns-sd` is killed with `lookup`.
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(address) = parse_resolve_line(&line) {
+                let _ = tx.send(address);
+                return;
+            }
+        }
+    });
+    rx.recv_timeout(PEER_TIMEOUT)
+        .with_context(|| format!("failed to resolve {name}"))
+}
+
+/// A running `dns-sd`, which keeps advertising, browsing, or resolving until
+/// it is dropped.
+pub struct DnsSd(Child);
+
+impl DnsSd {
+    fn spawn(args: &[&str]) -> anyhow::Result<Self> {
+        let child = Command::new("dns-sd")
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("failed to run dns-sd")?;
+        Ok(Self(child))
+    }
+
+    fn take_stdout(&mut self) -> anyhow::Result<ChildStdout> {
+        self.0.stdout.take().context("no stdout")
+    }
+}
+
+impl Drop for DnsSd {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// `title \t subtitle \t message \t urgent`, where an empty subtitle is none
+/// and `urgent` is `0` or `1`.
+fn encode(notification: &Notification) -> String {
+    let escape = |s: &str| escape_bytes(s.as_bytes());
+    format!(
+        "{}\t{}\t{}\t{}\n",
+        escape(&notification.title),
+        escape(notification.subtitle.as_deref().unwrap_or_default()),
+        escape(&notification.message),
+        u8::from(notification.is_urgent),
+    )
+}
+
+fn decode(line: &str) -> anyhow::Result<Notification> {
+    let unescape =
+        |s: &str| -> anyhow::Result<String> { Ok(String::from_utf8(parse_escaped_str(s)?)?) };
+    let fields: Vec<_> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+    let [title, subtitle, message, urgent] = fields[..] else {
+        bail!("expected 4 fields, got {}", fields.len());
+    };
+    let subtitle = unescape(subtitle)?;
+    Ok(Notification {
+        title: unescape(title)?,
+        subtitle: (!subtitle.is_empty()).then_some(subtitle),
+        message: unescape(message)?,
+        is_urgent: urgent == "1",
+        is_mirrored: true,
+        ..Default::default()
+    })
+}
+
+/// `12:34:56.789  Add  2  14 local.  _caloud._tcp.  caloud-desk-123`: whether
+/// the session was added or removed, and its name.
+fn parse_browse_line(line: &str) -> Option<(bool, String)> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let [_, action, _, _, _, service_type, name @ ..] = fields.as_slice() else {
+        return None;
+    };
+    if service_type.trim_end_matches('.') != SERVICE_TYPE || name.is_empty() {
+        return None;
+    }
+    let is_added = match *action {
+        "Add" => true,
+        "Rmv" => false,
+        _ => return None,
+    };
+    Some((is_added, name.join(" ")))
+}
+
+/// `... can be reached at desk.local.:52345 (interface 14)`
+fn parse_resolve_line(line: &str) -> Option<(String, u16)> {
+    let (_, rest) = line.split_once(" can be reached at ")?;
+    let address = rest.split_whitespace().next()?;
+    let (host, port) = address.rsplit_once(':')?;
+    Some((host.trim_end_matches('.').to_string(), port.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let notification = Notification {
+            title: "Claude Code".to_string(),
+            subtitle: Some("caloud".to_string()),
+            message: "Claude needs your permission\tto use Bash:\nrm -rf ✳".to_string(),
+            is_urgent: true,
+            ..Default::default()
+        };
+        let line = encode(&notification);
+        assert_eq!(line.matches('\n').count(), 1);
+        let received = decode(&line).unwrap();
+        assert!(received.is_mirrored);
+        assert_eq!(
+            received,
+            Notification {
+                is_mirrored: true,
+                ..notification
+            }
+        );
+        let untitled = decode("Claude Code\t\tDone\t0\n").unwrap();
+        assert_eq!(untitled.subtitle, None);
+        assert!(!untitled.is_urgent);
+        assert!(decode("Claude Code\tDone\n").is_err());
+    }
+
+    #[test]
+    fn sealed_messages() {
+        let key = MirrorKey::parse(&"0f".repeat(32)).unwrap();
+        let message = key.seal("Claude Code\t\tDone\t0\n");
+        let other = MirrorKey::parse(&"a0".repeat(32)).unwrap();
+        let mut seen = SeenNonces::default();
+        assert!(other.open(&message, &mut seen).is_err());
+        let mut forged = message.clone();
+        *forged.last_mut().unwrap() ^= 1;
+        assert!(key.open(&forged, &mut seen).is_err());
+        assert!(key.open(b"Claude Code\t\tDone\t0\n", &mut seen).is_err());
+
+        assert_eq!(
+            key.open(&message, &mut seen).unwrap(),
+            "Claude Code\t\tDone\t0\n"
+        );
+        let error = key.open(&message, &mut seen).unwrap_err();
+        assert!(format!("{error:#}").contains("received already"));
+
+        assert!(MirrorKey::parse("0f0f").is_err());
+        assert!(MirrorKey::parse(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn seen_nonces_are_forgotten_once_too_old() {
+        let mut seen = SeenNonces::default();
+        assert!(seen.insert(b"a", 1000, 1000));
+        assert!(!seen.insert(b"a", 1000, 1060));
+        assert!(seen.insert(b"b", 1061, 1061));
+        assert_eq!(seen.0.len(), 1);
+    }
+
+    #[test]
+    fn key_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("caloud-mirror-key-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", "0f".repeat(32))).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let error = MirrorKey::read(&path).unwrap_err();
+        assert!(format!("{error:#}").contains("not private"));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(MirrorKey::read(&path).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dns_sd_output() {
+        assert_eq!(
+            parse_browse_line(
+                "12:34:56.789  Add        2  14 local.               _caloud._tcp.        caloud-desk-123"
+            ),
+            Some((true, "caloud-desk-123".to_string()))
+        );
+        assert_eq!(
+            parse_browse_line(
+                "12:34:57.001  Rmv        0  14 local.               _caloud._tcp.        caloud-desk-123"
+            ),
+            Some((false, "caloud-desk-123".to_string()))
+        );
+        assert_eq!(
+            parse_browse_line(
+                "Timestamp     A/R    Flags  if Domain               Service Type         Instance Name"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_resolve_line(
+                "12:34:56.800  caloud-desk-123._caloud._tcp.local. can be reached at desk.local.:52345 (interface 14)"
+            ),
+            Some(("desk.local".to_string(), 52345))
+        );
+        assert_eq!(parse_resolve_line("Lookup caloud-desk-123"), None);
+    }
+}
@@ -0,0 +1,70 @@
+//! Notification text as it is to be shown, spoken, and logged: composed to
+//! NFC, since the terminal may hand over decomposed sequences that
+//! Notification Center renders apart and that would not compare equal to the
+//! same text composed, and without the invisible characters that reorder or
+//! hide text.
+
+use crate::event::Notification;
+#[cfg(target_os = "macos")]
+use crate::macos::unicode::precompose;
+use std::borrow::Cow;
+
+/// Zero-width and bidirectional formatting characters.  The zero width
+/// joiner and non-joiner are kept, since emoji sequences and some scripts
+/// need them.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'
+            | '\u{2060}'
+            | '\u{FEFF}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{061C}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// `text` without zero-width and bidirectional formatting characters.
+pub fn strip_invisible(text: &str) -> Cow<'_, str> {
+    match text.contains(is_invisible) {
+        true => Cow::Owned(text.replace(is_invisible, "")),
+        false => Cow::Borrowed(text),
+    }
+}
+
+/// `text` composed to NFC, without invisible characters.  Only macOS, with
+/// Foundation, composes it; elsewhere it is left as it is.
+pub fn normalize(text: &str) -> String {
+    let text = strip_invisible(text);
+    #[cfg(target_os = "macos")]
+    return precompose(&text);
+    #[cfg(not(target_os = "macos"))]
+    return text.into_owned();
+}
+
+/// `notification` with its title, subtitle, and message normalized.
+pub fn notification(notification: Notification) -> Notification {
+    Notification {
+        title: normalize(&notification.title),
+        subtitle: notification.subtitle.as_deref().map(normalize),
+        message: normalize(&notification.message),
+        ..notification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invisible_characters() {
+        assert_eq!(
+            strip_invisible("\u{202E}Task\u{200B} done\u{2069}\u{FEFF}"),
+            "Task done"
+        );
+        assert_eq!(strip_invisible("👩\u{200D}💻"), "👩\u{200D}💻");
+        assert!(matches!(strip_invisible("Task done"), Cow::Borrowed(_)));
+    }
+}
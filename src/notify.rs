@@ -0,0 +1,193 @@
+//! `caloud notify`: deliver a test notification the way a session delivers
+//! those of `claude`, step by step, to find out why banners or clicks do not
+//! work without starting `claude`.
+
+use crate::event::Notification;
+#[cfg(target_os = "macos")]
+use crate::macos::application::find_host_application;
+#[cfg(target_os = "macos")]
+use crate::macos::notification::{
+    activation_count, deliver_if_osc9_unsupported, deliver_with_osascript, is_osc9_supported,
+    set_global_delegate,
+};
+#[cfg(target_os = "macos")]
+use crate::macos::run_loop::MainRunLoop;
+use crate::normalize;
+#[cfg(not(target_os = "macos"))]
+use crate::platform::{Native, Platform};
+#[cfg(target_os = "macos")]
+use anyhow::Context;
+use anyhow::bail;
+use lexopt::prelude::*;
+#[cfg(target_os = "macos")]
+use objc2_foundation::MainThreadMarker;
+use std::ffi::OsString;
+#[cfg(target_os = "macos")]
+use std::fs::OpenOptions;
+#[cfg(target_os = "macos")]
+use std::io::Write;
+use std::time::Duration;
+#[cfg(target_os = "macos")]
+use std::time::Instant;
+
+const DEFAULT_TITLE: &str = "Claude Code";
+const DEFAULT_BODY: &str = "Test notification from caloud";
+
+/// How long to wait for a click on the banner by default.
+const DEFAULT_WAIT: Duration = Duration::from_secs(10);
+
+const USAGE: &str = "usage: caloud notify [--title TITLE] [--subtitle SUBTITLE] [--body BODY] \
+                     [--wait SECONDS]";
+
+#[derive(Debug)]
+struct Options {
+    notification: Notification,
+    wait: Duration,
+}
+
+/// Deliver the notification, print what happened, and return whether it was
+/// delivered.
+pub fn run(args: &[OsString]) -> anyhow::Result<bool> {
+    let options = parse_args(args)?;
+    let notification = normalize::notification(options.notification);
+    deliver(&notification, options.wait)
+}
+
+#[cfg(target_os = "macos")]
+fn deliver(notification: &Notification, wait: Duration) -> anyhow::Result<bool> {
+    let host = find_host_application().and_then(|app| app.bundleIdentifier());
+    match &host {
+        Some(bundle_identifier) => println!("[pass] host terminal: {bundle_identifier}"),
+        None => println!("[skip] host terminal: no ancestor process is an application bundle"),
+    }
+
+    // As in a session, a terminal that shows OSC 9 itself is left to it.
+    if is_osc9_supported() {
+        write_osc9(&notification.message)?;
+        println!("[pass] OSC 9: written to the terminal, which shows the notification itself");
+        return Ok(true);
+    }
+
+    set_global_delegate().context("set_global_delegate")?;
+    match deliver_if_osc9_unsupported(notification) {
+        Ok(_) => println!("[pass] Notification Center: delivered as Terminal"),
+        Err(e) => {
+            println!("[fail] Notification Center: {e:#}");
+            println!(
+                "       hint: allow notifications for Terminal in System Settings > Notifications"
+            );
+            return match deliver_with_osascript(notification) {
+                Ok(()) => {
+                    println!(
+                        "[pass] osascript: delivered as Script Editor; clicks are not handled"
+                    );
+                    Ok(true)
+                }
+                Err(e) => {
+                    println!("[fail] osascript: {e:#}");
+                    Ok(false)
+                }
+            };
+        }
+    }
+
+    if wait.is_zero() {
+        return Ok(true);
+    }
+    // Clicks arrive through the main run loop, as in a session.
+    println!(
+        "Waiting {} seconds for a click on the banner, which should bring the terminal to the front",
+        wait.as_secs()
+    );
+    let main_thread_marker = MainThreadMarker::new().context("not on the main thread")?;
+    let main_run_loop = MainRunLoop::new(main_thread_marker);
+    let clicks = activation_count();
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        main_run_loop.run_once(Some(deadline));
+        if activation_count() > clicks {
+            println!("[pass] click: received");
+            return Ok(true);
+        }
+    }
+    println!("[skip] click: none within {} seconds", wait.as_secs());
+    Ok(true)
+}
+
+/// `notify-send` reports no clicks, so there is nothing to wait for.
+#[cfg(not(target_os = "macos"))]
+fn deliver(notification: &Notification, _wait: Duration) -> anyhow::Result<bool> {
+    match Native::deliver(notification) {
+        Ok(_) => {
+            println!("[pass] {}: delivered", Native::NOTIFIER);
+            Ok(true)
+        }
+        Err(e) => {
+            println!("[fail] {}: {e:#}", Native::NOTIFIER);
+            Ok(false)
+        }
+    }
+}
+
+fn parse_args(args: &[OsString]) -> anyhow::Result<Options> {
+    let mut notification = Notification {
+        title: DEFAULT_TITLE.to_string(),
+        message: DEFAULT_BODY.to_string(),
+        ..Default::default()
+    };
+    let mut wait = DEFAULT_WAIT;
+    let mut parser = lexopt::Parser::from_args(args);
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("title") => notification.title = parser.value()?.string()?,
+            Long("subtitle") => notification.subtitle = Some(parser.value()?.string()?),
+            Long("body") => notification.message = parser.value()?.string()?,
+            Long("wait") => {
+                let value = parser.value()?.string()?;
+                match value.parse() {
+                    Ok(secs) => wait = Duration::from_secs(secs),
+                    _ => bail!("invalid value for --wait: {}", value),
+                }
+            }
+            _ => bail!("{}\n{USAGE}", arg.unexpected()),
+        }
+    }
+    Ok(Options { notification, wait })
+}
+
+/// `OSC 9 ; message BEL`, to the terminal rather than stdout.
+#[cfg(target_os = "macos")]
+fn write_osc9(message: &str) -> anyhow::Result<()> {
+    let mut tty = OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context("failed to open /dev/tty")?;
+    let message = message.replace(char::is_control, " ");
+    tty.write_all(format!("\x1b]9;{message}\x07").as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Options> {
+        parse_args(&args.iter().map(OsString::from).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn options() {
+        let options = parse(&["--title", "Build", "--body=finished", "--wait=0"]).unwrap();
+        assert_eq!(options.notification.title, "Build");
+        assert_eq!(options.notification.subtitle, None);
+        assert_eq!(options.notification.message, "finished");
+        assert_eq!(options.wait, Duration::ZERO);
+
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.notification.message, DEFAULT_BODY);
+        assert_eq!(options.wait, DEFAULT_WAIT);
+
+        assert!(parse(&["--wait=soon"]).is_err());
+        assert!(parse(&["--sound=Glass"]).is_err());
+    }
+}
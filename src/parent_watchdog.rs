@@ -0,0 +1,111 @@
+//! Terminating `claude` if caloud dies without reaping it, e.g. when it
+//! crashes or is force-quit, so that no session is left running headless.
+//!
+//! A watchdog process waits on a pipe whose write end only caloud holds: it
+//! reads EOF when caloud dies, and a byte when caloud has reaped `claude`.
+//! The watchdog leaves the session of the terminal, so that closing the
+//! terminal does not kill it with caloud.
+
+use anyhow::Context;
+use nix::errno::Errno;
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
+use nix::libc;
+use nix::unistd::{ForkResult, Pid, fork, pipe};
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{AsRawFd, OwnedFd};
+
+/// How long `claude` has to exit on SIGHUP before it is killed.
+const GRACE_SECS: libc::c_uint = 5;
+
+pub struct ParentWatchdog {
+    write: OwnedFd,
+}
+
+impl ParentWatchdog {
+    /// Start watching over `child`.  Call once the child has been exec'd.
+    pub fn spawn(child: Pid) -> anyhow::Result<Self> {
+        let (read, write) = pipe().context("pipe() failed")?;
+        // Not to be held by the other processes caloud starts.
+        fcntl(&write, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).context("fcntl() failed")?;
+        // SAFETY: the watchdog makes only async-signal-safe calls before it
+        // exits.
+        match unsafe { fork() }.context("fork() failed")? {
+            ForkResult::Child => {
+                drop(write);
+                watch(read.as_raw_fd(), child)
+            }
+            ForkResult::Parent { .. } => Ok(Self { write }),
+        }
+    }
+
+    /// `claude` has been reaped, so its PID may soon be another's.
+    pub fn stand_down(self) {
+        let _ = File::from(self.write).write_all(b"\0");
+    }
+}
+
+fn watch(read: libc::c_int, child: Pid) -> ! {
+    unsafe {
+        libc::setsid();
+        // Neither the PTY nor the pipes of caloud are kept open.
+        for fd in 0..libc::getdtablesize() {
+            if fd != read {
+                libc::close(fd);
+            }
+        }
+        let mut byte = 0u8;
+        let n = loop {
+            let n = libc::read(read, (&raw mut byte).cast(), 1);
+            if n >= 0 || Errno::last() != Errno::EINTR {
+                break n;
+            }
+        };
+        if n == 0 {
+            // The process group, for `claude` under `--restart-on-crash`.
+            libc::killpg(child.as_raw(), libc::SIGHUP);
+            libc::sleep(GRACE_SECS);
+            libc::killpg(child.as_raw(), libc::SIGKILL);
+        }
+        libc::_exit(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    fn sleep() -> (std::process::Child, Pid) {
+        // The leader of its process group, as `claude` is of its session.
+        let child = Command::new("sleep")
+            .arg("60")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+        (child, pid)
+    }
+
+    #[test]
+    fn kills_child_when_caloud_dies() {
+        let (mut child, pid) = sleep();
+        let watchdog = ParentWatchdog::spawn(pid).unwrap();
+        // As if caloud died.
+        drop(watchdog);
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGHUP));
+    }
+
+    #[test]
+    fn stands_down() {
+        let (mut child, pid) = sleep();
+        ParentWatchdog::spawn(pid).unwrap().stand_down();
+        thread::sleep(Duration::from_millis(100));
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
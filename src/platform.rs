@@ -0,0 +1,53 @@
+//! The desktop services that a session alerts the user through, with a
+//! backend for each target OS chosen at compile time: Notification Center,
+//! `say`, and `afplay` on macOS, and `notify-send`, a TTS command such as
+//! `espeak-ng` or `piper`, and `paplay` on Linux.
+
+use crate::event::Notification;
+use crate::macos::say::Voice;
+use std::process::Child;
+
+#[cfg(target_os = "linux")]
+pub use crate::linux::platform::Linux as Native;
+#[cfg(target_os = "macos")]
+pub use crate::macos::platform::MacOs as Native;
+
+pub trait Platform {
+    /// What shows notifications, for the `--debug` log.
+    const NOTIFIER: &str;
+
+    /// What shows notifications when [`Self::NOTIFIER`] fails, if anything.
+    const FALLBACK_NOTIFIER: &str;
+
+    /// Plays the audio file it is given, for `--sound`.
+    const SOUND_PLAYER: &str;
+
+    /// Show `notification` on the desktop, returning whether it was shown,
+    /// rather than left to a terminal that shows OSC 9 itself.
+    fn deliver(notification: &Notification) -> anyhow::Result<bool>;
+
+    /// Show `notification` with [`Self::FALLBACK_NOTIFIER`].
+    fn deliver_fallback(notification: &Notification) -> anyhow::Result<()>;
+
+    /// Start speaking `text` with the arguments of `--say`; the caller waits
+    /// for the child, or kills it to stop in the middle.
+    fn speak(args: &[String], text: &str) -> anyhow::Result<Child>;
+
+    /// Find out at startup rather than on every notification whether the
+    /// arguments of `--say` are turned down, e.g. for an unknown voice.
+    fn validate_speech(args: &[String]) -> anyhow::Result<()>;
+
+    /// The voices that `-v` selects in `--say`.
+    fn list_voices() -> anyhow::Result<Vec<Voice>>;
+
+    /// The bundle identifier of the terminal app that caloud runs in, if
+    /// found.
+    fn host_terminal() -> Option<String>;
+
+    /// Whether the host terminal shows OSC 9 notifications itself.
+    fn is_osc9_supported() -> bool;
+
+    /// Whether the host terminal is the frontmost app, i.e. the user is
+    /// looking at it.
+    fn is_host_terminal_active() -> bool;
+}
@@ -0,0 +1,113 @@
+//! `--progress-after`: say how long `claude` has been working once it works
+//! for longer than a threshold, at doubling times (5, 10, 20, 40 minutes…).
+
+use crate::reminder::Reminder;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The spinner is redrawn many times a second while `claude` works; this long
+/// without it means the work is done.
+pub const SPINNER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Follows the spinner and announces the time elapsed while it keeps being drawn.
+pub struct ProgressReminder {
+    threshold: Duration,
+    /// When the spinner was last drawn.
+    spinner_seen_at: Arc<Mutex<Option<Instant>>>,
+    reminder: Reminder,
+}
+
+impl ProgressReminder {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            spinner_seen_at: Arc::default(),
+            reminder: Reminder::default(),
+        }
+    }
+
+    /// Note that the spinner has been drawn.  If `claude` was not working
+    /// before, `announce` is called with the elapsed time at each of
+    /// [`intervals`] for as long as the spinner keeps being drawn.  Returns
+    /// whether a working period has started.
+    pub fn spinner_drawn(&mut self, announce: impl Fn(Duration) + Send + 'static) -> bool {
+        let now = Instant::now();
+        let last_seen = self.spinner_seen_at.lock().unwrap().replace(now);
+        if last_seen.is_some_and(|at| now - at <= SPINNER_TIMEOUT) {
+            return false;
+        }
+        let spinner_seen_at = Arc::clone(&self.spinner_seen_at);
+        self.reminder
+            .start_with_intervals(intervals(self.threshold), move |_| {
+                let last_seen = *spinner_seen_at.lock().unwrap();
+                if last_seen.is_none_or(|at| at.elapsed() > SPINNER_TIMEOUT) {
+                    return false;
+                }
+                announce(now.elapsed());
+                true
+            });
+        true
+    }
+}
+
+/// The waits between announcements for `threshold`: the first at `threshold`,
+/// and then each time the elapsed time doubles.
+pub fn intervals(threshold: Duration) -> impl Iterator<Item = Duration> + Send + 'static {
+    std::iter::once(threshold).chain((0..).map(move |n| threshold.saturating_mul(1 << n.min(16))))
+}
+
+/// `Claude is still working, 5 minutes elapsed`
+pub fn message(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let elapsed = match secs {
+        0..60 => plural(secs, "second"),
+        60..3600 => plural(secs / 60, "minute"),
+        _ => match secs % 3600 / 60 {
+            0 => plural(secs / 3600, "hour"),
+            minutes => format!(
+                "{} {}",
+                plural(secs / 3600, "hour"),
+                plural(minutes, "minute")
+            ),
+        },
+    };
+    format!("Claude is still working, {elapsed} elapsed")
+}
+
+fn plural(n: u64, unit: &str) -> String {
+    match n {
+        1 => format!("1 {unit}"),
+        n => format!("{n} {unit}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_time_doubles() {
+        let minute = Duration::from_secs(60);
+        let elapsed: Vec<_> = intervals(5 * minute)
+            .take(5)
+            .scan(Duration::ZERO, |elapsed, interval| {
+                *elapsed += interval;
+                Some(elapsed.as_secs() / 60)
+            })
+            .collect();
+        assert_eq!(elapsed, [5, 10, 20, 40, 80]);
+    }
+
+    #[test]
+    fn messages() {
+        let message = |secs| message(Duration::from_secs(secs));
+        assert_eq!(message(45), "Claude is still working, 45 seconds elapsed");
+        assert_eq!(message(61), "Claude is still working, 1 minute elapsed");
+        assert_eq!(message(600), "Claude is still working, 10 minutes elapsed");
+        assert_eq!(message(7200), "Claude is still working, 2 hours elapsed");
+        assert_eq!(
+            message(3900),
+            "Claude is still working, 1 hour 5 minutes elapsed"
+        );
+    }
+}
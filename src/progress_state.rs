@@ -0,0 +1,110 @@
+//! `--progress-state`: show whether `claude` is working or waiting as the
+//! progress of the tab or taskbar button, in terminals that support ConEmu's
+//! `OSC 9 ; 4`, such as Windows Terminal.
+//!
+//! <https://conemu.github.io/en/AnsiEscapeCodes.html#ConEmu_specific_OSC>
+
+use crate::progress::SPINNER_TIMEOUT;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Indeterminate progress.
+pub const WORKING: &[u8] = b"\x1b]9;4;3\x07";
+
+/// A full bar in the paused state, shown in the warning color.
+pub const WAITING: &[u8] = b"\x1b]9;4;4;100\x07";
+
+/// No progress.
+pub const CLEARED: &[u8] = b"\x1b]9;4;0\x07";
+
+type Emit = dyn Fn(&[u8]) + Send + Sync;
+
+/// Follows the spinner, and emits [`WORKING`] when it starts being drawn and
+/// [`WAITING`] when it stops.
+pub struct ProgressState {
+    state: Arc<Mutex<State>>,
+    emit: Arc<Emit>,
+    timeout: Duration,
+}
+
+#[derive(Default)]
+struct State {
+    spinner_seen_at: Option<Instant>,
+    is_working: bool,
+}
+
+impl ProgressState {
+    /// `emit` writes a sequence to the terminal, from this thread or another.
+    pub fn new(emit: impl Fn(&[u8]) + Send + Sync + 'static) -> Self {
+        Self::with_timeout(SPINNER_TIMEOUT, emit)
+    }
+
+    fn with_timeout(timeout: Duration, emit: impl Fn(&[u8]) + Send + Sync + 'static) -> Self {
+        Self {
+            state: Arc::default(),
+            emit: Arc::new(emit),
+            timeout,
+        }
+    }
+
+    /// Note that the spinner has been drawn.  When `claude` starts working, a
+    /// thread is left to watch for the spinner to stop.
+    pub fn spinner_drawn(&self) {
+        // Sequences are emitted with the lock held so that they never reach
+        // the terminal out of order.
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.spinner_seen_at = Some(Instant::now());
+        if std::mem::replace(&mut state.is_working, true) {
+            return;
+        }
+        (self.emit)(WORKING);
+        let shared = Arc::clone(&self.state);
+        let emit = Arc::clone(&self.emit);
+        let timeout = self.timeout;
+        thread::spawn(move || {
+            loop {
+                let mut state = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                let idle = state.spinner_seen_at.map_or(timeout, |at| at.elapsed());
+                if idle >= timeout {
+                    state.is_working = false;
+                    emit(WAITING);
+                    return;
+                }
+                drop(state);
+                thread::sleep(timeout - idle);
+            }
+        });
+    }
+
+    /// Clear the progress, as `claude` has exited.
+    pub fn clear(&self) {
+        let _state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        (self.emit)(CLEARED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn working_then_waiting() {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let progress_state = ProgressState::with_timeout(Duration::from_millis(50), move |s| {
+            tx.lock().unwrap().send(s.to_vec()).unwrap();
+        });
+        progress_state.spinner_drawn();
+        progress_state.spinner_drawn();
+        assert_eq!(rx.recv().unwrap(), WORKING);
+        let timeout = Duration::from_secs(10);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), WAITING);
+        progress_state.spinner_drawn();
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), WORKING);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), WAITING);
+        progress_state.clear();
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), CLEARED);
+    }
+}
@@ -0,0 +1,166 @@
+//! PTY master helpers.
+//!
+//! The master is switched into packet mode (`TIOCPKT`), in which every read
+//! returns a one-byte header: `TIOCPKT_DATA` followed by output from the slave,
+//! or a lone status byte reporting an event on the slave side (output
+//! stopped/started by XOFF/XON, queues flushed).  [`PacketReader`] strips the
+//! headers and exposes the status bytes to the output loop.
+
+use nix::ioctl_write_ptr_bad;
+use nix::libc;
+use std::io::{self, Read};
+use std::os::fd::AsRawFd;
+
+// The same on macOS and Linux, but only in `libc` for the BSDs.
+const TIOCPKT_DATA: libc::c_int = 0;
+const TIOCPKT_FLUSHWRITE: libc::c_int = 2;
+const TIOCPKT_STOP: libc::c_int = 4;
+
+pub fn enable_packet_mode<Fd: AsRawFd>(fd: &Fd) -> nix::Result<()> {
+    ioctl_write_ptr_bad!(set_packet_mode, libc::TIOCPKT, libc::c_int);
+
+    unsafe { set_packet_mode(fd.as_raw_fd(), &1) }.map(drop)
+}
+
+/// Status bits carried by a control packet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PacketStatus(libc::c_int);
+
+impl PacketStatus {
+    /// The output queue of the slave has been flushed (e.g. by `tcflush(3)`).
+    pub fn flush_write(self) -> bool {
+        self.0 & TIOCPKT_FLUSHWRITE != 0
+    }
+
+    /// Output has been stopped by XOFF (`^S`).
+    pub fn stop(self) -> bool {
+        self.0 & TIOCPKT_STOP != 0
+    }
+}
+
+/// A [`Read`] adapter for a PTY master in packet mode.
+///
+/// Data packets are returned without their header.  A control packet makes
+/// `read` fail with [`io::ErrorKind::Interrupted`] so that the caller can
+/// inspect [`PacketReader::take_status`] before reading again.
+pub struct PacketReader<R> {
+    inner: R,
+    status: PacketStatus,
+}
+
+impl<R: Read> PacketReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            status: PacketStatus::default(),
+        }
+    }
+
+    /// Return the status bits received since the last call and clear them.
+    pub fn take_status(&mut self) -> PacketStatus {
+        std::mem::take(&mut self.status)
+    }
+}
+
+impl<R: Read> Read for PacketReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // A one-byte buffer would only ever hold the header.
+        if buf.len() == 1 {
+            let mut scratch = [0u8; 2];
+            let n = self.read(&mut scratch)?;
+            buf[..n].copy_from_slice(&scratch[..n]);
+            return Ok(n);
+        }
+
+        let n = self.inner.read(buf)?;
+        if n == 0 {
+            return Ok(0);
+        }
+
+        match libc::c_int::from(buf[0]) {
+            TIOCPKT_DATA => {
+                buf.copy_within(1..n, 0);
+                Ok(n - 1)
+            }
+            status => {
+                self.status.0 |= status;
+                Err(io::ErrorKind::Interrupted.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::pty::openpty;
+    use nix::sys::termios::{InputFlags, SetArg, tcgetattr, tcsetattr};
+    use std::fs::File;
+    use std::io::Write;
+
+    const TIOCPKT_START: libc::c_int = 8;
+
+    #[test]
+    fn data_packet_header_is_stripped() {
+        let (mut master, _, mut slave) = open_packet_mode_pty();
+        slave.write_all(b"hello").unwrap();
+
+        let mut buf = [0u8; 16];
+        let n = read_data(&mut master, &mut buf);
+        assert_eq!(&buf[..n], b"hello");
+    }
+
+    #[test]
+    fn xoff_and_xon_are_reported() {
+        let (mut master, mut input, slave) = open_packet_mode_pty();
+        let mut termios = tcgetattr(&slave).unwrap();
+        termios.input_flags.insert(InputFlags::IXON);
+        tcsetattr(&slave, SetArg::TCSANOW, &termios).unwrap();
+
+        input.write_all(b"\x13").unwrap();
+        assert!(read_status(&mut master).stop());
+
+        input.write_all(b"\x11").unwrap();
+        assert_ne!(read_status(&mut master).0 & TIOCPKT_START, 0);
+    }
+
+    /// Return a packet-mode reader and a writer for the master, and the slave.
+    fn open_packet_mode_pty() -> (PacketReader<File>, File, File) {
+        let pty = openpty(None, None).unwrap();
+        enable_packet_mode(&pty.master).unwrap();
+        let input = File::from(pty.master.try_clone().unwrap());
+        (
+            PacketReader::new(File::from(pty.master)),
+            input,
+            File::from(pty.slave),
+        )
+    }
+
+    fn read_data(reader: &mut PacketReader<File>, buf: &mut [u8]) -> usize {
+        loop {
+            match reader.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => return result.unwrap(),
+            }
+        }
+    }
+
+    fn read_status(reader: &mut PacketReader<File>) -> PacketStatus {
+        let mut buf = [0u8; 16];
+        loop {
+            match reader.read(&mut buf) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    let status = reader.take_status();
+                    if status != PacketStatus::default() {
+                        return status;
+                    }
+                }
+                result => panic!("unexpected read result: {result:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! `--record`: the output of `claude` as read from the PTY, before any
+//! reformatting, with when each read happened, so that a session can be
+//! replayed through `caloud filter` or `caloud reformat`, e.g. to reproduce a
+//! line wrapping bug or to make a snapshot fixture.
+//!
+//! A recording is [`MAGIC`] followed by one chunk per read: the microseconds
+//! since recording started and the length of the data, both big-endian, as a
+//! `u64` and a `u32`, then the data itself.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::time::Instant;
+
+pub const MAGIC: &[u8] = b"caloud-recording 1\n";
+
+const CHUNK_HEADER_LEN: usize = 12;
+
+pub struct Recorder {
+    /// `None` once writing has failed.
+    file: Option<File>,
+    started_at: Instant,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    /// Start a recording at `path`, readable by the current user only, as it
+    /// has everything `claude` printed.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(MAGIC)?;
+        Ok(Self {
+            file: Some(file),
+            started_at: Instant::now(),
+            error: None,
+        })
+    }
+
+    /// A reader that records what it reads from `inner`.
+    pub fn tee<R: Read>(&mut self, inner: R) -> Tee<'_, R> {
+        Tee {
+            inner,
+            recorder: self,
+        }
+    }
+
+    /// The error that stopped recording, once.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn record(&mut self, data: &[u8]) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let micros = self.started_at.elapsed().as_micros() as u64;
+        // Written at once, so that a chunk is whole unless the disk is full.
+        let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + data.len());
+        chunk.extend_from_slice(&micros.to_be_bytes());
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(data);
+        if let Err(e) = file.write_all(&chunk) {
+            self.file = None;
+            self.error = Some(e);
+        }
+    }
+}
+
+pub struct Tee<'a, R> {
+    inner: R,
+    recorder: &'a mut Recorder,
+}
+
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.recorder.record(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// The data of the recording read from `input`, or `input` as it is if it is
+/// not a recording.
+pub fn open<'a>(mut input: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    let mut magic = vec![0; MAGIC.len()];
+    let n = read_full(&mut input, &mut magic)?;
+    if n == MAGIC.len() && magic == MAGIC {
+        return Ok(Box::new(Playback {
+            inner: input,
+            remaining: 0,
+        }));
+    }
+    magic.truncate(n);
+    Ok(Box::new(Cursor::new(magic).chain(input)))
+}
+
+/// Reads the data of the chunks, and what there is of the last one if it is
+/// cut short, as by a crash.
+struct Playback<R> {
+    inner: R,
+    /// Of the chunk being read.
+    remaining: usize,
+}
+
+impl<R: Read> Read for Playback<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let mut header = [0; CHUNK_HEADER_LEN];
+            if read_full(&mut self.inner, &mut header)? < CHUNK_HEADER_LEN {
+                return Ok(0);
+            }
+            self.remaining = u32::from_be_bytes(header[8..].try_into().unwrap()) as usize;
+        }
+        let len = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+/// Fill `buf` unless EOF comes first, and return how much was read.
+fn read_full(mut reader: impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    fn read_all(input: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        open(input).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("caloud-record-{}", std::process::id()));
+        let mut recorder = Recorder::create(&path).unwrap();
+        let mut tee = recorder.tee(&b"\x1b[1mhello"[..]);
+        let mut buf = [0; 6];
+        while tee.read(&mut buf).unwrap() > 0 {}
+        assert!(recorder.take_error().is_none());
+        drop(recorder);
+        assert_eq!(std::fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        let recording = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // Two reads, with their headers.
+        assert_eq!(recording.len(), MAGIC.len() + 2 * CHUNK_HEADER_LEN + 9);
+        assert_eq!(read_all(&recording), b"\x1b[1mhello");
+    }
+
+    #[test]
+    fn truncated_recordings_are_read_as_far_as_they_go() {
+        let mut recording = MAGIC.to_vec();
+        recording.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2]);
+        recording.extend_from_slice(b"ab");
+        recording.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 9]);
+        recording.extend_from_slice(b"cd");
+        assert_eq!(read_all(&recording), b"abcd");
+        recording.truncate(recording.len() - 8);
+        assert_eq!(read_all(&recording), b"ab");
+    }
+
+    #[test]
+    fn other_input_is_read_as_it_is() {
+        assert_eq!(read_all(b"caloud"), b"caloud");
+        assert_eq!(read_all(b""), b"");
+        let capture = b"\x1b[2Chttps://example.com/aaaaaaaaaaaaaaaaaaaaaaaa\r\n";
+        assert_eq!(read_all(capture), capture);
+    }
+}
@@ -0,0 +1,86 @@
+//! `--permission-reminder` and `--progress-after`: repeat an alert while a
+//! question stays unanswered or `claude` keeps working.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Give up reminding after this many times.
+const MAX_REMINDERS: u32 = 10;
+
+/// Runs a callback on a background thread every `interval`, or after each of
+/// a series of intervals, until it returns `false`, the reminder is restarted,
+/// or it is dropped.
+#[derive(Default)]
+pub struct Reminder {
+    cancel: Option<Sender<()>>,
+}
+
+impl Reminder {
+    /// Replace the running reminder, if any.  `remind` gets the number of the
+    /// reminder, starting at 1.
+    pub fn start(&mut self, interval: Duration, remind: impl FnMut(u32) -> bool + Send + 'static) {
+        self.start_with_intervals(std::iter::repeat(interval), remind);
+    }
+
+    /// Like [`Reminder::start`], waiting each interval in turn.
+    pub fn start_with_intervals(
+        &mut self,
+        intervals: impl Iterator<Item = Duration> + Send + 'static,
+        mut remind: impl FnMut(u32) -> bool + Send + 'static,
+    ) {
+        let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
+        self.cancel = Some(cancel_tx);
+        thread::spawn(move || {
+            for (count, interval) in (1..=MAX_REMINDERS).zip(intervals) {
+                // Dropping the sender wakes this up right away, so the thread
+                // never outlives the reminder by an interval.
+                match cancel_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) if remind(count) => {}
+                    _ => break,
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn reminds_until_answered() {
+        let counts = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done_rx) = mpsc::channel();
+        let mut reminder = Reminder::default();
+        let recorded = Arc::clone(&counts);
+        reminder.start(Duration::from_millis(1), move |count| {
+            recorded.lock().unwrap().push(count);
+            let answered = count == 3;
+            if answered {
+                done_tx.send(()).unwrap();
+            }
+            !answered
+        });
+        done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(*counts.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn dropping_stops_reminding() {
+        let (tx, rx) = mpsc::channel();
+        let mut reminder = Reminder::default();
+        reminder.start(Duration::from_secs(60), move |count| {
+            tx.send(count).unwrap();
+            true
+        });
+        drop(reminder);
+        // The sender is dropped with the thread once it has stopped.
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
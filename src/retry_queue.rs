@@ -0,0 +1,102 @@
+//! Notifications that could not be shown, retried with exponential backoff
+//! until they are or the retries run out.
+
+use std::time::{Duration, Instant};
+
+/// The wait before the first retry, doubled for each one after it.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Give up after this many retries.
+pub const MAX_RETRIES: u32 = 5;
+
+pub struct RetryQueue<T> {
+    entries: Vec<Entry<T>>,
+}
+
+struct Entry<T> {
+    item: T,
+    retries: u32,
+    due: Instant,
+}
+
+impl<T> Default for RetryQueue<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> RetryQueue<T> {
+    /// Retry `item`, which has failed after being retried `retries` times,
+    /// once its backoff has passed.  Returns `false` if it is given up.
+    pub fn push(&mut self, item: T, retries: u32, now: Instant) -> bool {
+        if retries >= MAX_RETRIES {
+            return false;
+        }
+        self.entries.push(Entry {
+            item,
+            retries: retries + 1,
+            due: now + backoff(retries),
+        });
+        true
+    }
+
+    /// The items whose retry is due, in the order they were pushed, with the
+    /// number of the retry.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(T, u32)> {
+        let (due, pending) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| entry.due <= now);
+        self.entries = pending;
+        due.into_iter()
+            .map(|entry: Entry<T>| (entry.item, entry.retries))
+            .collect()
+    }
+
+    /// When the next retry is due, if any is.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.entries.iter().map(|entry| entry.due).min()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The wait before retrying an item that has been retried `retries` times.
+pub fn backoff(retries: u32) -> Duration {
+    INITIAL_BACKOFF * 2u32.pow(retries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_until_given_up() {
+        let start = Instant::now();
+        let mut queue = RetryQueue::default();
+        assert!(queue.push("a", 0, start));
+        assert!(queue.push("b", 2, start));
+        assert_eq!(queue.next_due(), Some(start + Duration::from_secs(5)));
+        assert!(queue.take_due(start).is_empty());
+
+        let now = start + Duration::from_secs(5);
+        assert_eq!(queue.take_due(now), [("a", 1)]);
+        assert_eq!(queue.len(), 1);
+        assert!(queue.push("a", 1, now));
+        assert_eq!(queue.next_due(), Some(now + Duration::from_secs(10)));
+        assert_eq!(
+            queue.take_due(start + Duration::from_secs(20)),
+            [("b", 3), ("a", 2)]
+        );
+        assert!(queue.is_empty());
+        assert!(!queue.push("c", MAX_RETRIES, now));
+        assert!(queue.is_empty());
+    }
+}
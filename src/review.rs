@@ -0,0 +1,289 @@
+//! `--review-key`: a menu drawn over the session that lists the last
+//! notifications, to read one again in full or hear it again after stepping
+//! away.  It is drawn on the alternate screen, so that closing it restores
+//! the screen of `claude`, whose output is held back meanwhile.
+
+use crate::event::Notification;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Notifications listed, one for each digit.
+const MAX_ENTRIES: usize = 9;
+
+/// Switch to the alternate screen, which saves the cursor.
+const OPEN: &[u8] = b"\x1b[?1049h";
+/// Back to the screen of `claude`, with autowrap on again.
+const CLOSE: &[u8] = b"\x1b[?7h\x1b[?1049l";
+/// Clear, with autowrap off so that a long line is cut at the edge.
+const CLEAR_UNWRAPPED: &[u8] = b"\x1b[?7l\x1b[H\x1b[2J";
+const CLEAR_WRAPPED: &[u8] = b"\x1b[?7h\x1b[H\x1b[2J";
+
+/// Keys that go back: `q`, Escape, Return, and Backspace.
+const BACK_KEYS: &[&[u8]] = &[b"q", b"\x1b", b"\r", b"\x7f"];
+
+/// Shared by the notification thread, which records what it delivers, the
+/// output thread, which writes through [`ReviewGate`], and the input thread,
+/// which opens the menu and passes it the keys typed.
+#[derive(Clone)]
+pub struct Review(Arc<Mutex<State>>);
+
+struct State {
+    /// The key that opens the menu, which also closes it.
+    key: Vec<u8>,
+    /// Newest first.
+    entries: VecDeque<(Instant, Notification)>,
+    view: Option<View>,
+    /// The output of `claude` written while the menu is open.
+    held: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    List,
+    /// The entry at this index, in full.
+    Entry(usize),
+}
+
+impl Review {
+    pub fn new(key: Vec<u8>) -> Self {
+        Self(Arc::new(Mutex::new(State {
+            key,
+            entries: VecDeque::new(),
+            view: None,
+            held: Vec::new(),
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn record(&self, notification: &Notification) {
+        let mut state = self.lock();
+        state
+            .entries
+            .push_front((Instant::now(), notification.clone()));
+        state.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.lock().view.is_some()
+    }
+
+    /// Draw the list on `terminal`.
+    pub fn open(&self, terminal: &mut impl Write) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.view.is_some() {
+            return Ok(());
+        }
+        state.view = Some(View::List);
+        terminal.write_all(OPEN)?;
+        terminal.write_all(&state.render(View::List, Instant::now()))?;
+        terminal.flush()
+    }
+
+    /// Act on `key`, typed while the menu is open.  Returns the message to
+    /// speak again, if asked.
+    pub fn key(&self, key: &[u8], terminal: &mut impl Write) -> io::Result<Option<String>> {
+        let mut state = self.lock();
+        let Some(view) = state.view else {
+            return Ok(None);
+        };
+        let selected = match key {
+            [digit @ b'1'..=b'9'] => Some(usize::from(digit - b'1')),
+            _ => None,
+        };
+        let next = match (view, selected) {
+            _ if key == state.key => None,
+            (_, Some(i)) if i < state.entries.len() => Some(View::Entry(i)),
+            (View::Entry(i), None) if key == b"s" => {
+                return Ok(Some(state.entries[i].1.message.clone()));
+            }
+            (View::Entry(_), None) if BACK_KEYS.contains(&key) => Some(View::List),
+            (View::List, None) if BACK_KEYS.contains(&key) => None,
+            _ => return Ok(None),
+        };
+        match next {
+            Some(view) => {
+                state.view = next;
+                terminal.write_all(&state.render(view, Instant::now()))?;
+                terminal.flush()?;
+            }
+            None => state.close(terminal)?,
+        }
+        Ok(None)
+    }
+
+    /// Restore the screen of `claude`, if the menu is open, e.g. once it has
+    /// exited.
+    pub fn close(&self, terminal: &mut impl Write) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.view.is_none() {
+            return Ok(());
+        }
+        state.close(terminal)
+    }
+}
+
+impl State {
+    fn close(&mut self, terminal: &mut impl Write) -> io::Result<()> {
+        self.view = None;
+        terminal.write_all(CLOSE)?;
+        terminal.write_all(&std::mem::take(&mut self.held))?;
+        terminal.flush()
+    }
+
+    fn render(&self, view: View, now: Instant) -> Vec<u8> {
+        let mut out = Vec::new();
+        match view {
+            View::List => {
+                out.extend_from_slice(CLEAR_UNWRAPPED);
+                out.extend_from_slice(b"Notifications (1-9 to read, q to go back)\r\n\r\n");
+                if self.entries.is_empty() {
+                    out.extend_from_slice(b"None yet\r\n");
+                }
+                for (i, (at, notification)) in self.entries.iter().enumerate() {
+                    let message = notification.message.lines().next().unwrap_or_default();
+                    out.extend_from_slice(
+                        format!(
+                            "{}  {:<12}  {}: {}\r\n",
+                            i + 1,
+                            age(now.saturating_duration_since(*at)),
+                            printable(&notification.title),
+                            printable(message)
+                        )
+                        .as_bytes(),
+                    );
+                }
+            }
+            View::Entry(i) => {
+                let (at, notification) = &self.entries[i];
+                out.extend_from_slice(CLEAR_WRAPPED);
+                let mut heading = printable(&notification.title);
+                if let Some(subtitle) = &notification.subtitle {
+                    heading = format!("{heading} - {}", printable(subtitle));
+                }
+                let age = age(now.saturating_duration_since(*at));
+                out.extend_from_slice(format!("{heading} ({age})\r\n\r\n").as_bytes());
+                for line in notification.message.lines() {
+                    out.extend_from_slice(format!("{}\r\n", printable(line)).as_bytes());
+                }
+                out.extend_from_slice(b"\r\ns to speak, q to go back\r\n");
+            }
+        }
+        out
+    }
+}
+
+/// `text` without control characters, which could move the cursor or
+/// start escape sequences.
+fn printable(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+fn age(elapsed: Duration) -> String {
+    match elapsed.as_secs() {
+        0..60 => "just now".to_string(),
+        secs @ 60..3600 => format!("{} min ago", secs / 60),
+        secs => format!("{} h ago", secs / 3600),
+    }
+}
+
+/// The terminal as the output thread writes to it: held back while the
+/// menu is open, to be written once it closes.  `inner` must be the terminal
+/// the menu is drawn on.
+pub struct ReviewGate<W: Write> {
+    inner: W,
+    review: Option<Review>,
+}
+
+impl<W: Write> ReviewGate<W> {
+    pub fn new(inner: W, review: Option<Review>) -> Self {
+        Self { inner, review }
+    }
+}
+
+impl<W: Write> Write for ReviewGate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(review) = &self.review else {
+            return self.inner.write(buf);
+        };
+        // Locked while writing, so that the menu is not drawn in between.
+        let mut state = review.lock();
+        if state.view.is_some() {
+            state.held.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(message: &str) -> Notification {
+        Notification {
+            title: "Claude Code".to_string(),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn menu() {
+        let review = Review::new(b"\x1bn".to_vec());
+        review.record(&notification("Task done"));
+        review.record(&notification("Claude is waiting\nfor your input"));
+        let mut terminal = Vec::new();
+        let mut output = ReviewGate::new(Vec::new(), Some(review.clone()));
+
+        review.open(&mut terminal).unwrap();
+        assert!(review.is_open());
+        let list = String::from_utf8_lossy(&terminal).into_owned();
+        assert!(list.contains("1  just now      Claude Code: Claude is waiting\r\n"));
+        assert!(list.contains("2  just now      Claude Code: Task done\r\n"));
+
+        // The output of `claude` waits until the menu is closed.
+        output.write_all(b"output").unwrap();
+        assert!(output.inner.is_empty());
+
+        terminal.clear();
+        assert_eq!(review.key(b"1", &mut terminal).unwrap(), None);
+        assert!(String::from_utf8_lossy(&terminal).contains("Claude is waiting\r\nfor your input"));
+        assert_eq!(
+            review.key(b"s", &mut terminal).unwrap().as_deref(),
+            Some("Claude is waiting\nfor your input")
+        );
+        assert_eq!(review.key(b"3", &mut terminal).unwrap(), None);
+        review.key(b"q", &mut terminal).unwrap();
+        assert!(review.is_open());
+
+        terminal.clear();
+        review.key(b"\x1bn", &mut terminal).unwrap();
+        assert!(!review.is_open());
+        assert_eq!(terminal, [CLOSE, b"output"].concat());
+        output.write_all(b"more").unwrap();
+        assert_eq!(output.inner, b"more");
+
+        review.open(&mut terminal).unwrap();
+        terminal.clear();
+        review.close(&mut terminal).unwrap();
+        review.close(&mut terminal).unwrap();
+        assert_eq!(terminal, CLOSE);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(age(Duration::from_secs(59)), "just now");
+        assert_eq!(age(Duration::from_secs(180)), "3 min ago");
+        assert_eq!(age(Duration::from_secs(7300)), "2 h ago");
+        assert_eq!(printable("\x1b[2Jdone"), "[2Jdone");
+    }
+}
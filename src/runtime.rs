@@ -1,13 +1,135 @@
-use crate::claude::ClaudeCommand;
-use crate::input_rewrite::rewriter::InputRewriter;
-use crate::macos::say::SayCommand;
+use crate::abbreviation::Abbreviations;
+use crate::auto_respond::AutoResponder;
+use crate::claude::ChildCommand;
+use crate::claude_session::ClaudeSession;
+use crate::code_block_destination::CodeBlockDestination;
+use crate::debug_log::DebugLog;
+use crate::emoji::EmojiMode;
+use crate::file_watch::FileMatcher;
+use crate::focus_policy::FocusRule;
+use crate::git::GitRepository;
+use crate::input_rewrite::pipeline::InputPipeline;
+use crate::macos::appearance::AppearanceMode;
+use crate::macos::say::{SayCommand, SayWhen};
+use crate::macos::sound::SoundTheme;
+use crate::mirror::MirrorKey;
+use crate::recording::Recorder;
+use crate::redact::Redactor;
+use crate::shell_command::ShellCommand;
+use crate::supervisor::RestartPolicy;
+use crate::truncation::LengthLimits;
+use crate::tty_text::alert::AlertKind;
 use crate::tty_text::reformat::Reformatter;
+use crate::voice_pool::VoiceAssignment;
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub struct Runtime {
+    /// Name of the directory `claude` runs in, shown alongside notifications;
+    /// with `--git-context`, the repository and branch at startup.
+    pub session_name: Option<String>,
+    /// The repository `claude` runs in, with `--git-context`.
+    pub git_repository: Option<GitRepository>,
     pub notification_center_delivery_enabled: bool,
+    /// Whether OSC 9 notifications are forwarded to the host terminal.
+    pub osc9_passthrough_enabled: bool,
     pub say_command: Option<SayCommand>,
-    pub input_rewriter: InputRewriter,
-    pub zwsp_after_updown_arrow: bool,
+    pub say_when: SayWhen,
+    /// Translates notifications before they are spoken.
+    pub translate_command: Option<ShellCommand>,
+    /// What is shortened in everything spoken.
+    pub speech_abbreviations: Abbreviations,
+    pub speech_emoji: EmojiMode,
+    /// Voices to pick the voice of this session from.
+    pub voice_pool: Vec<String>,
+    pub voice_assignment: VoiceAssignment,
+    /// Answer color queries for dark or light mode in place of the host terminal.
+    pub appearance: Option<AppearanceMode>,
+    /// Announce notifications through VoiceOver.
+    pub voiceover_enabled: bool,
+    /// Read out the assistant's messages, with the `--say` voice if given.
+    pub speak_output_enabled: bool,
+    /// Read out the options of questions `claude` asks.
+    pub speak_questions_enabled: bool,
+    /// Read out the name of each tool `claude` starts.
+    pub speak_tools_enabled: bool,
+    /// Read out only the last message of each turn, once `claude` is waiting,
+    /// and none of the notifications but permission requests.
+    pub speak_final_enabled: bool,
+    /// Repeat the alert for a permission prompt until something is typed.
+    pub permission_reminder: Option<Duration>,
+    /// Announce how long `claude` has been working once it works this long.
+    pub progress_after: Option<Duration>,
+    /// Notify when `claude` prints nothing for this long while working.
+    pub stall_timeout: Option<Duration>,
+    /// Show whether `claude` is working with `OSC 9 ; 4` progress.
+    pub progress_state_enabled: bool,
+    pub sound_theme: SoundTheme,
+    pub input_pipeline: InputPipeline,
+    /// Typed to copy the last assistant message to the pasteboard.
+    pub copy_key: Option<Vec<u8>>,
+    /// Typed to open the menu of the last notifications.
+    pub review_key: Option<Vec<u8>>,
+    /// Where to copy fenced code blocks in the assistant's messages.
+    pub code_block_destination: Option<CodeBlockDestination>,
+    pub code_block_min_lines: usize,
+    /// Post a notification when a code block is copied.
+    pub code_block_notify_enabled: bool,
+    pub auto_responder: AutoResponder,
+    /// What to notify about beyond the notifications of `claude`.
+    pub alert_kinds: Vec<AlertKind>,
+    /// Patterns for `alert_kinds` in addition to the built-in ones.
+    pub alert_patterns: Vec<(AlertKind, Regex)>,
+    /// Notify when `claude` exits with an error or is killed by a signal.
+    pub exit_notify_enabled: bool,
+    /// Notify when files under the project directory matching these change.
+    pub file_matcher: Option<FileMatcher>,
+    /// Show notifications in the terminal title for this long.
+    pub title_flash: Option<Duration>,
+    /// Ring the bell of the terminal for each notification.
+    pub bell_enabled: bool,
+    /// Deliver notifications together at most once per this long.
+    pub digest: Option<Duration>,
+    /// Masks secrets in what leaves caloud.
+    pub redactor: Option<Redactor>,
+    pub length_limits: LengthLimits,
+    /// The session ID of Claude Code, once known.
+    pub claude_session: Arc<ClaudeSession>,
+    /// Forward notifications to the sessions on the LAN that receive them,
+    /// sealed with this key.
+    pub mirror_send: Option<MirrorKey>,
+    /// Advertise this session on the LAN and deliver notifications forwarded
+    /// to it, sealed with this key.
+    pub mirror_receive: Option<MirrorKey>,
+    /// What to do with notifications during each Focus.
+    pub focus_rules: Vec<FocusRule>,
+    /// Post the events of the session as distributed notifications.
+    pub broadcast_enabled: bool,
     pub reformatter: Reformatter,
-    pub claude_command: ClaudeCommand,
+    /// The columns assumed when the window size cannot be read, e.g. when
+    /// stdin is not a terminal.
+    pub fallback_width: u16,
+    /// The bytes of output read from `claude` at most at a time, and held
+    /// back until a fragment is complete.
+    pub buffer_size: usize,
+    /// Write a transcript without repaints instead of the TUI.
+    pub screen_reader_enabled: bool,
+    pub claude_command: ChildCommand,
+    /// Start `claude` again when it crashes.
+    pub restart_policy: Option<RestartPolicy>,
+    /// Print exit status, elapsed time, and resource usage when `claude` exits.
+    pub summary_enabled: bool,
+    /// Run when the session begins and ends.
+    pub on_start: Option<ShellCommand>,
+    pub on_exit: Option<ShellCommand>,
+    /// The directory `claude` runs in.
+    pub cwd: PathBuf,
+    pub control_socket: Option<PathBuf>,
+    /// A named pipe whose contents are typed into `claude`.
+    pub input_fifo: Option<PathBuf>,
+    /// `--record`: the output of `claude` as read, before reformatting.
+    pub recorder: Option<Recorder>,
+    pub debug_log: DebugLog,
 }
@@ -0,0 +1,67 @@
+//! `caloud send TITLE [MESSAGE]`: post a notification from a script as
+//! `OSC 777`, which a caloud session running the script delivers like those
+//! of `claude`.  Run inside a session, it also checks the receiving side end
+//! to end.
+
+use anyhow::{Context, bail};
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+pub fn run(args: &[OsString]) -> anyhow::Result<()> {
+    let args = args
+        .iter()
+        .map(|arg| arg.to_str().context("arguments must be valid UTF-8"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let (title, message) = match args[..] {
+        [title] => (title, ""),
+        [title, message] => (title, message),
+        _ => bail!("usage: caloud send TITLE [MESSAGE]"),
+    };
+    let sequence = sequence(title, message, std::env::var_os("TMUX").is_some());
+    // The terminal rather than stdout, which a script may be capturing.
+    match OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => tty.write_all(&sequence)?,
+        Err(_) => {
+            let mut stdout = io::stdout();
+            stdout.write_all(&sequence)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// `OSC 777 ; notify ; title ; message BEL`, wrapped to pass through tmux to
+/// the terminal outside it when `in_tmux`.
+fn sequence(title: &str, message: &str, in_tmux: bool) -> Vec<u8> {
+    // A control character would end the sequence early, and a `;` in the
+    // title would move the rest of it into the message.
+    let clean = |s: &str| s.replace(char::is_control, " ");
+    let osc = format!(
+        "\x1b]777;notify;{};{}\x07",
+        clean(title).replace(';', ","),
+        clean(message)
+    );
+    if !in_tmux {
+        return osc.into_bytes();
+    }
+    // https://github.com/tmux/tmux/wiki/FAQ#what-is-the-passthrough-escape-sequence-and-how-do-i-use-it
+    format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b")).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc_777() {
+        assert_eq!(
+            sequence("Build; CI", "finished; 0 errors\n", false),
+            b"\x1b]777;notify;Build, CI;finished; 0 errors \x07"
+        );
+        assert_eq!(
+            sequence("Build", "finished", true),
+            b"\x1bPtmux;\x1b\x1b]777;notify;Build;finished\x07\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Commands given as one option value, e.g. `--translate-command` and
+//! `--on-exit`, split into words like a shell would and waited for with a
+//! time limit.
+
+use anyhow::{Context, bail};
+use std::fmt;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone)]
+pub struct ShellCommand {
+    command: String,
+    argv: Vec<String>,
+}
+
+impl ShellCommand {
+    /// Split `command` into words like a shell would.
+    pub fn parse(command: &str) -> anyhow::Result<Self> {
+        let argv = shell_words::split(command).context("failed to parse the command")?;
+        if argv.is_empty() {
+            bail!("empty command");
+        }
+        Ok(Self {
+            command: command.to_string(),
+            argv,
+        })
+    }
+
+    /// The program that is run.
+    pub fn program(&self) -> &str {
+        &self.argv[0]
+    }
+
+    /// A [`Command`] that runs it, for the caller to set up and spawn.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.argv[0]);
+        command.args(&self.argv[1..]);
+        command
+    }
+
+    /// Wait up to `timeout` for `child`, spawned from this command, to
+    /// succeed.  `Ok(false)` if it is still running by then, for the caller
+    /// to kill or to leave to finish on its own.
+    pub fn wait(&self, child: &mut Child, timeout: Duration) -> anyhow::Result<bool> {
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait().context("failed to wait")? {
+                break status;
+            }
+            if started.elapsed() > timeout {
+                return Ok(false);
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        if !status.success() {
+            bail!("{} exited with {status}", self.argv[0]);
+        }
+        Ok(true)
+    }
+}
+
+/// The command as given.
+impl fmt::Display for ShellCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let command = ShellCommand::parse("sh -c 'exit 0'").unwrap();
+        assert_eq!(command.program(), "sh");
+        assert_eq!(command.to_string(), "sh -c 'exit 0'");
+        assert!(ShellCommand::parse("").is_err());
+        assert!(ShellCommand::parse("'unclosed").is_err());
+    }
+
+    #[test]
+    fn wait() {
+        let timeout = Duration::from_secs(10);
+        let command = ShellCommand::parse("true").unwrap();
+        assert!(
+            command
+                .wait(&mut command.command().spawn().unwrap(), timeout)
+                .unwrap()
+        );
+        let command = ShellCommand::parse("false").unwrap();
+        assert!(
+            command
+                .wait(&mut command.command().spawn().unwrap(), timeout)
+                .is_err()
+        );
+
+        let command = ShellCommand::parse("sleep 10").unwrap();
+        let mut child = command.command().spawn().unwrap();
+        assert!(!command.wait(&mut child, Duration::ZERO).unwrap());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+//! What the speech thread has yet to say, ordered by priority: an urgent
+//! utterance, such as a permission prompt, cuts in ahead of the others and
+//! interrupts what is being said, which is said again after it; the stream
+//! of output and tool names is dropped when speech falls behind.
+
+use std::collections::VecDeque;
+
+/// Beyond this many queued utterances, low priority ones are dropped.
+const MAX_BACKLOG: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpeechPriority {
+    /// Interrupts what is being said and goes ahead of what is queued.
+    Urgent,
+    Normal,
+    /// Dropped when speech falls behind, and when interrupted.
+    Low,
+}
+
+/// Text for the speech thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utterance {
+    pub text: String,
+    pub priority: SpeechPriority,
+}
+
+impl Utterance {
+    pub fn new(text: impl Into<String>, priority: SpeechPriority) -> Self {
+        Self {
+            text: text.into(),
+            priority,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SpeechQueue {
+    utterances: VecDeque<Utterance>,
+}
+
+impl SpeechQueue {
+    /// Queue `utterance` last, or an urgent one after the other urgent ones.
+    /// Returns `false` if it is dropped as low priority under load.
+    pub fn push(&mut self, utterance: Utterance) -> bool {
+        if self.utterances.len() >= MAX_BACKLOG {
+            // A newer one takes the place of the oldest low priority one.
+            match self
+                .utterances
+                .iter()
+                .position(|queued| queued.priority == SpeechPriority::Low)
+            {
+                Some(i) if utterance.priority != SpeechPriority::Low => {
+                    self.utterances.remove(i);
+                }
+                _ if utterance.priority == SpeechPriority::Low => return false,
+                _ => {}
+            }
+        }
+        if utterance.priority == SpeechPriority::Urgent {
+            let i = self.position(|priority| priority != SpeechPriority::Urgent);
+            self.utterances.insert(i, utterance);
+        } else {
+            self.utterances.push_back(utterance);
+        }
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Utterance> {
+        self.utterances.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utterances.is_empty()
+    }
+
+    /// Whether an urgent utterance is waiting, to interrupt one that is not.
+    pub fn has_urgent(&self) -> bool {
+        self.utterances
+            .front()
+            .is_some_and(|queued| queued.priority == SpeechPriority::Urgent)
+    }
+
+    /// Put back `utterance`, which was interrupted, to be said again ahead of
+    /// the others of its priority, unless it is low priority.  The low priority ones
+    /// queued are dropped as well, being out of date by the time the
+    /// interruption is over.  Returns how many were dropped.
+    pub fn interrupted(&mut self, utterance: Utterance) -> usize {
+        let dropped = self.drop_low();
+        match utterance.priority {
+            SpeechPriority::Low => dropped + 1,
+            priority => {
+                let i = self.position(|queued| queued >= priority);
+                self.utterances.insert(i, utterance);
+                dropped
+            }
+        }
+    }
+
+    /// Drop the low priority utterances queued.  Returns how many there were.
+    pub fn drop_low(&mut self) -> usize {
+        let len = self.utterances.len();
+        self.utterances
+            .retain(|queued| queued.priority != SpeechPriority::Low);
+        len - self.utterances.len()
+    }
+
+    /// The index of the first queued utterance whose priority matches, or
+    /// the end.
+    fn position(&self, predicate: impl Fn(SpeechPriority) -> bool) -> usize {
+        self.utterances
+            .iter()
+            .position(|queued| predicate(queued.priority))
+            .unwrap_or(self.utterances.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(queue: &mut SpeechQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|utterance| utterance.text)
+            .collect()
+    }
+
+    #[test]
+    fn priorities() {
+        let mut queue = SpeechQueue::default();
+        assert!(queue.push(Utterance::new("Bash", SpeechPriority::Low)));
+        assert!(queue.push(Utterance::new("Task done", SpeechPriority::Normal)));
+        assert!(!queue.has_urgent());
+        assert!(queue.push(Utterance::new("Allow?", SpeechPriority::Urgent)));
+        assert!(queue.has_urgent());
+        // Under load, a low priority one is dropped, or makes room for another.
+        assert!(!queue.push(Utterance::new("Read", SpeechPriority::Low)));
+        assert!(queue.push(Utterance::new("Waiting", SpeechPriority::Normal)));
+        assert_eq!(texts(&mut queue), ["Allow?", "Task done", "Waiting"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn interruption() {
+        let mut queue = SpeechQueue::default();
+        queue.push(Utterance::new("Edit", SpeechPriority::Low));
+        queue.push(Utterance::new("Waiting", SpeechPriority::Normal));
+        queue.push(Utterance::new("Allow?", SpeechPriority::Urgent));
+        assert_eq!(
+            queue.interrupted(Utterance::new("Task done", SpeechPriority::Normal)),
+            1
+        );
+        assert_eq!(
+            queue.interrupted(Utterance::new("Bash", SpeechPriority::Low)),
+            1
+        );
+        assert_eq!(texts(&mut queue), ["Allow?", "Task done", "Waiting"]);
+    }
+}
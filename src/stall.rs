@@ -0,0 +1,117 @@
+//! `--stall-timeout`: notice when `claude` stops printing in the middle of
+//! its work, as when it hangs or loses its connection, and when it goes on.
+//!
+//! While `claude` works, its spinner is redrawn many times a second, so a
+//! silence that follows the spinner means it is stuck; one that follows
+//! anything else means it is waiting for input.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const RESUMED_MESSAGE: &str = "Claude is printing again";
+
+struct State {
+    output_at: Instant,
+    /// Whether the spinner was drawn in the last output.
+    working: bool,
+    stalled: bool,
+}
+
+/// Watches the output for silences of `timeout` on a background thread,
+/// until dropped.
+pub struct StallWatch {
+    state: Arc<Mutex<State>>,
+    _cancel: Sender<()>,
+}
+
+impl StallWatch {
+    /// `on_stall` is called once for each silence of `timeout` while `claude`
+    /// is working.
+    pub fn new(timeout: Duration, on_stall: impl Fn() + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            output_at: Instant::now(),
+            working: false,
+            stalled: false,
+        }));
+        let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
+        let watched = Arc::clone(&state);
+        thread::spawn(move || {
+            loop {
+                let wait = {
+                    let state = watched.lock().unwrap_or_else(PoisonError::into_inner);
+                    match state.working && !state.stalled {
+                        true => timeout.saturating_sub(state.output_at.elapsed()),
+                        false => timeout,
+                    }
+                };
+                // Dropping the sender wakes this up right away.
+                if cancel_rx.recv_timeout(wait) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+                let mut state = watched.lock().unwrap_or_else(PoisonError::into_inner);
+                if state.working && !state.stalled && state.output_at.elapsed() >= timeout {
+                    state.stalled = true;
+                    drop(state);
+                    on_stall();
+                }
+            }
+        });
+        Self {
+            state,
+            _cancel: cancel_tx,
+        }
+    }
+
+    /// Note output from `claude`, in which the spinner was drawn if
+    /// `working`.  Returns whether it ends a stall.
+    pub fn output(&self, working: bool) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.output_at = Instant::now();
+        state.working = working;
+        std::mem::take(&mut state.stalled)
+    }
+}
+
+/// `Claude appears stuck, no output for 10 minutes`
+pub fn message(timeout: Duration) -> String {
+    match timeout.as_secs() / 60 {
+        1 => "Claude appears stuck, no output for 1 minute".to_string(),
+        minutes => format!("Claude appears stuck, no output for {minutes} minutes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stalls_only_while_working() {
+        let (tx, rx) = mpsc::channel();
+        let watch = StallWatch::new(Duration::from_millis(20), move || {
+            let _ = tx.send(());
+        });
+        // Waiting for input.
+        watch.output(false);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        watch.output(true);
+        rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        // Once per silence.
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        assert!(watch.output(true));
+        assert!(!watch.output(true));
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            message(Duration::from_secs(60)),
+            "Claude appears stuck, no output for 1 minute"
+        );
+        assert_eq!(
+            message(Duration::from_secs(600)),
+            "Claude appears stuck, no output for 10 minutes"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! `--restart-on-crash`: run `claude` under a process that starts it again
+//! when it crashes, so that the PTY, and everything caloud does with it,
+//! outlives each `claude`.
+//!
+//! The supervisor is the child caloud forks onto the PTY.  It runs `claude`
+//! in its process group, so that the signals caloud sends to the group, e.g.
+//! for job control, reach both, and it exits with the exit code of the
+//! last `claude`.  Each restart is announced by an `OSC 777` notification
+//! written to the PTY, which caloud delivers as it would one from `claude`.
+//! The PID of each `claude` is reported to caloud through a [`PidReport`],
+//! as caloud's own child is the supervisor.
+
+use crate::claude::{ChildCommand, ExecHandshake};
+use anyhow::Context;
+use nix::errno::Errno;
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
+use nix::sys::signal::{Signal, kill};
+use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+use nix::unistd::{ForkResult, Pid, fork};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Used when `--restart-on-crash` is given without a number.
+pub const DEFAULT_MAX_RESTARTS: u32 = 3;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    /// Run for each restart, e.g. with `--continue` added.
+    pub restart_command: ChildCommand,
+}
+
+/// The PID of the running `claude`, which changes with each restart.
+#[derive(Clone)]
+pub struct ClaudePid(Arc<AtomicI32>);
+
+impl ClaudePid {
+    pub fn new(pid: Pid) -> Self {
+        Self(Arc::new(AtomicI32::new(pid.as_raw())))
+    }
+
+    pub fn get(&self) -> Pid {
+        Pid::from_raw(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// A pipe on which the supervisor writes the PID of each `claude` it starts,
+/// as 4 native-endian bytes.
+pub struct PidReport {
+    read: OwnedFd,
+    write: OwnedFd,
+}
+
+impl PidReport {
+    /// Call before forking.
+    pub fn new() -> anyhow::Result<Self> {
+        let (read, write) = nix::unistd::pipe().context("pipe() failed")?;
+        // Not to be held by `claude`.
+        for fd in [&read, &write] {
+            fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).context("fcntl() failed")?;
+        }
+        Ok(Self { read, write })
+    }
+
+    /// In caloud: the PID of the first `claude`, which the supervisor reports
+    /// before the exec handshake completes, kept up to date on a background
+    /// thread.  `supervisor` is the PID until then.
+    pub fn follow(self, supervisor: Pid) -> ClaudePid {
+        drop(self.write);
+        let mut read = File::from(self.read);
+        let claude_pid = ClaudePid::new(receive(&mut read).unwrap_or(supervisor));
+        let current = claude_pid.clone();
+        thread::spawn(move || {
+            while let Some(pid) = receive(&mut read) {
+                current.0.store(pid.as_raw(), Ordering::Relaxed);
+            }
+        });
+        claude_pid
+    }
+}
+
+fn receive(read: &mut File) -> Option<Pid> {
+    let mut pid = [0; 4];
+    read.read_exact(&mut pid).ok()?;
+    Some(Pid::from_raw(i32::from_ne_bytes(pid)))
+}
+
+/// In the child forked onto the PTY: run `claude_command`, and then
+/// `policy.restart_command` after each crash, up to `policy.max_restarts`
+/// times, reporting the PID of each to `pid_report`.
+pub fn run(
+    exec_handshake: ExecHandshake,
+    pid_report: PidReport,
+    claude_command: &ChildCommand,
+    policy: &RestartPolicy,
+) -> ! {
+    drop(pid_report.read);
+    let mut pid_report = File::from(pid_report.write);
+    let mut exec_handshake = Some(exec_handshake);
+    let mut restarts = 0;
+    loop {
+        let command = match restarts {
+            0 => claude_command,
+            _ => &policy.restart_command,
+        };
+        // SAFETY: the supervisor, a child forked by caloud, has no other
+        // thread.
+        let child = match unsafe { fork() } {
+            Ok(ForkResult::Child) => match exec_handshake.take() {
+                // Only the first start is reported to caloud, as without the
+                // supervisor.
+                Some(exec_handshake) => exec_handshake.exec(command),
+                None => {
+                    let Err(e) = command.exec();
+                    eprintln!("caloud: failed to restart {}: {e:#}\r", command.tool());
+                    exit(127)
+                }
+            },
+            Ok(ForkResult::Parent { child }) => child,
+            Err(e) => {
+                eprintln!("caloud: fork() failed: {e}\r");
+                exit(1)
+            }
+        };
+        // Before the exec handshake completes, for the first.
+        let _ = pid_report.write_all(&child.as_raw().to_ne_bytes());
+        // Closes the write end, so that caloud hears from the child alone.
+        exec_handshake = None;
+        let exit_code = wait(child);
+        if !is_crash(exit_code) || restarts == policy.max_restarts {
+            exit(exit_code);
+        }
+        restarts += 1;
+        let backoff = backoff(restarts);
+        announce(&format!(
+            "{} exited with code {exit_code}; restarting in {}s ({restarts}/{})",
+            command.tool(),
+            backoff.as_secs(),
+            policy.max_restarts,
+        ));
+        thread::sleep(backoff);
+    }
+}
+
+/// Wait for `child` to exit, stopping along with it, and return its exit
+/// code, as a shell would report it.
+fn wait(child: Pid) -> i32 {
+    loop {
+        match waitpid(child, Some(WaitPidFlag::WUNTRACED)) {
+            Ok(WaitStatus::Exited(_, code)) => return code,
+            Ok(WaitStatus::Signaled(_, signal, _)) => return 128 + signal as i32,
+            // caloud continues the whole process group.
+            Ok(WaitStatus::Stopped(..)) => {
+                let _ = kill(Pid::this(), Signal::SIGSTOP);
+            }
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(_) => return 1,
+        }
+    }
+}
+
+/// Whether an exit with `exit_code` is a crash rather than the user quitting
+/// or the command not being found.
+fn is_crash(exit_code: i32) -> bool {
+    const DELIBERATE_SIGNALS: [Signal; 3] = [Signal::SIGHUP, Signal::SIGINT, Signal::SIGTERM];
+    match exit_code {
+        0 | 126 | 127 => false,
+        code => !DELIBERATE_SIGNALS
+            .iter()
+            .any(|&signal| code == 128 + signal as i32),
+    }
+}
+
+/// 1s, 2s, 4s, and so on, up to [`MAX_BACKOFF`].
+fn backoff(restarts: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(restarts - 1))
+        .min(MAX_BACKOFF)
+}
+
+/// Show `message` in the terminal and post it as a notification.
+fn announce(message: &str) {
+    let mut stdout = io::stdout();
+    let _ = write!(
+        stdout,
+        "\r\ncaloud: {message}\r\n\x1b]777;notify;caloud;{message}\x07"
+    );
+    let _ = stdout.flush();
+}
+
+fn exit(exit_code: i32) -> ! {
+    let _ = io::stdout().flush();
+    // SAFETY: _exit() skips the atexit handlers inherited from caloud.
+    unsafe { nix::libc::_exit(exit_code) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crashes() {
+        assert!(is_crash(1));
+        assert!(is_crash(128 + Signal::SIGSEGV as i32));
+        assert!(is_crash(128 + Signal::SIGKILL as i32));
+        assert!(!is_crash(0));
+        assert!(!is_crash(127));
+        assert!(!is_crash(128 + Signal::SIGINT as i32));
+        assert!(!is_crash(128 + Signal::SIGHUP as i32));
+    }
+
+    #[test]
+    fn reported_pids_are_followed() {
+        let pid_report = PidReport::new().unwrap();
+        let mut write = File::from(pid_report.write.try_clone().unwrap());
+        for pid in [42, 43] {
+            write.write_all(&i32::to_ne_bytes(pid)).unwrap();
+        }
+        let claude_pid = pid_report.follow(Pid::from_raw(1));
+        // The first is read before returning.
+        assert!([42, 43].contains(&claude_pid.get().as_raw()));
+        for _ in 0..100 {
+            if claude_pid.get() == Pid::from_raw(43) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(claude_pid.get(), Pid::from_raw(43));
+
+        drop(write);
+        let pid_report = PidReport::new().unwrap();
+        // The supervisor until one is reported.
+        assert_eq!(pid_report.follow(Pid::from_raw(1)).get(), Pid::from_raw(1));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_minute() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(7), MAX_BACKOFF);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+}
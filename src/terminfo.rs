@@ -0,0 +1,91 @@
+//! Minimal terminfo database lookup.
+//!
+//! Only answers whether an entry exists, which is enough to decide whether the
+//! child can use the `TERM` inherited from the outer terminal.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// `TERM` used when the inherited one has no terminfo entry on this machine.
+pub const FALLBACK_TERM: &str = "xterm-256color";
+
+const DEFAULT_DIRS: &[&str] = &[
+    "/etc/terminfo",
+    "/lib/terminfo",
+    "/usr/share/terminfo",
+    "/usr/lib/terminfo",
+];
+
+pub fn exists(name: &str) -> bool {
+    exists_in(&search_dirs(), name)
+}
+
+/// Search order of ncurses: `$TERMINFO`, `~/.terminfo`, `$TERMINFO_DIRS`
+/// (where an empty element means the system directories), then the system
+/// directories.
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    dirs.extend(env::var_os("TERMINFO").map(PathBuf::from));
+    dirs.extend(env::home_dir().map(|home| home.join(".terminfo")));
+    for dir in env::var_os("TERMINFO_DIRS")
+        .as_deref()
+        .map(env::split_paths)
+        .into_iter()
+        .flatten()
+    {
+        if dir.as_os_str().is_empty() {
+            dirs.extend(DEFAULT_DIRS.iter().map(PathBuf::from));
+        } else {
+            dirs.push(dir);
+        }
+    }
+    dirs.extend(DEFAULT_DIRS.iter().map(PathBuf::from));
+    dirs
+}
+
+fn exists_in(dirs: &[PathBuf], name: &str) -> bool {
+    let Some(first) = name.chars().next() else {
+        return false;
+    };
+    if name.contains('/') {
+        return false;
+    }
+
+    // Entries live under the first character of the name, spelled either as
+    // the character itself or (on macOS and other case-insensitive file
+    // systems) as its hexadecimal code.
+    let subdirs = [
+        OsString::from(first.to_string()),
+        OsString::from(format!("{:02x}", u32::from(first))),
+    ];
+    dirs.iter().any(|dir| {
+        subdirs
+            .iter()
+            .any(|subdir| Path::new(dir).join(subdir).join(name).is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lookup() {
+        let dir = env::temp_dir().join(format!("caloud-terminfo-{}", std::process::id()));
+        fs::create_dir_all(dir.join("x")).unwrap();
+        fs::create_dir_all(dir.join("67")).unwrap();
+        fs::write(dir.join("x").join("xterm-test"), b"").unwrap();
+        fs::write(dir.join("67").join("ghostty-test"), b"").unwrap();
+
+        let dirs = [dir.clone()];
+        assert!(exists_in(&dirs, "xterm-test"));
+        assert!(exists_in(&dirs, "ghostty-test"));
+        assert!(!exists_in(&dirs, "missing"));
+        assert!(!exists_in(&dirs, ""));
+        assert!(!exists_in(&dirs, "x/xterm-test"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+//! `--title-flash`: show a notification in the terminal title for a while, as
+//! a cue in the tab bar even with desktop notifications turned off.
+
+/// Longer notifications are cut short to fit in a tab.
+const MAX_SUMMARY_CHARS: usize = 40;
+
+/// The title set by `claude`, held back while a notification is shown in
+/// its place.
+#[derive(Debug, Default)]
+pub struct TitleFlash {
+    /// The `OSC 0` sequence as `claude` wrote it.
+    title: Option<Vec<u8>>,
+    is_flashing: bool,
+}
+
+impl TitleFlash {
+    /// Record the title `claude` sets with `sequence`; `true` if it is to be
+    /// forwarded now, `false` if it waits for the flash to end.
+    pub fn set(&mut self, sequence: &[u8]) -> bool {
+        self.title = Some(sequence.to_vec());
+        !self.is_flashing
+    }
+
+    /// The sequence that shows `message` in place of the title.
+    pub fn flash(&mut self, message: &str) -> Vec<u8> {
+        self.is_flashing = true;
+        format!("\x1b]0;{}\x07", summary(message)).into_bytes()
+    }
+
+    /// The sequence that shows the title of `claude` again, or `None` if no
+    /// flash is shown.  Without a title from `claude`, the terminal's default
+    /// comes back.
+    pub fn restore(&mut self) -> Option<Vec<u8>> {
+        if !std::mem::take(&mut self.is_flashing) {
+            return None;
+        }
+        Some(
+            self.title
+                .clone()
+                .unwrap_or_else(|| b"\x1b]0;\x07".to_vec()),
+        )
+    }
+}
+
+/// `message` on one line, cut to [`MAX_SUMMARY_CHARS`].
+fn summary(message: &str) -> String {
+    let line = message
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(char::is_control, "");
+    match line.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_and_restore() {
+        let mut title_flash = TitleFlash::default();
+        assert!(title_flash.set(b"\x1b]0;\xe2\x9c\xb3 Task\x07"));
+        assert_eq!(
+            title_flash.flash("Claude is waiting\nfor your input"),
+            b"\x1b]0;Claude is waiting for your input\x07"
+        );
+        // Held back, and shown when the flash ends.
+        assert!(!title_flash.set(b"\x1b]0;Done\x1b\\"));
+        assert_eq!(title_flash.restore().unwrap(), b"\x1b]0;Done\x1b\\");
+        assert_eq!(title_flash.restore(), None);
+        assert!(title_flash.set(b"\x1b]0;Next\x07"));
+    }
+
+    #[test]
+    fn restore_without_title() {
+        let mut title_flash = TitleFlash::default();
+        title_flash.flash("Done");
+        assert_eq!(title_flash.restore().unwrap(), b"\x1b]0;\x07");
+    }
+
+    #[test]
+    fn long_messages_are_cut() {
+        let message = "Claude needs your permission to use Bash: cargo test --workspace";
+        assert_eq!(
+            summary(message),
+            "Claude needs your permission to use Bash…"
+        );
+        assert_eq!(summary("Done\x01"), "Done");
+    }
+}
@@ -0,0 +1,70 @@
+//! The titles `claude` has set, and when, for the `titles` command of
+//! `--control-socket`: a rough account of what it was doing over time.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Older titles are forgotten.
+const MAX_TITLES: usize = 100;
+
+/// Shared between the output thread, which records the titles, and the
+/// control socket, which reports them.
+#[derive(Clone, Default)]
+pub struct TitleHistory(Arc<Mutex<VecDeque<(SystemTime, String)>>>);
+
+impl TitleHistory {
+    /// Record `title`, unless it is the title already.
+    pub fn push(&self, title: &str) {
+        self.push_at(SystemTime::now(), title);
+    }
+
+    fn push_at(&self, at: SystemTime, title: &str) {
+        let mut titles = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if titles.back().is_some_and(|(_, last)| last == title) {
+            return;
+        }
+        if titles.len() == MAX_TITLES {
+            titles.pop_front();
+        }
+        titles.push_back((at, title.to_string()));
+    }
+
+    /// `title: <unix time> <title>` lines, oldest first.
+    pub fn format(&self) -> String {
+        let titles = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        titles
+            .iter()
+            .map(|(at, title)| {
+                let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                format!("title: {secs} {}\n", title.replace(char::is_control, " "))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bounded_and_deduplicated() {
+        let history = TitleHistory::default();
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        history.push_at(at(10), "✳ Claude Code");
+        history.push_at(at(11), "✳ Claude Code");
+        history.push_at(at(12), "✳ Fix the\nbuild");
+        assert_eq!(
+            history.format(),
+            "title: 10 ✳ Claude Code\ntitle: 12 ✳ Fix the build\n"
+        );
+
+        for i in 0..MAX_TITLES as u64 {
+            history.push_at(at(100 + i), &i.to_string());
+        }
+        let formatted = history.format();
+        assert_eq!(formatted.lines().count(), MAX_TITLES);
+        assert!(formatted.starts_with("title: 100 0\n"));
+    }
+}
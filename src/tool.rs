@@ -0,0 +1,146 @@
+//! What caloud knows about the AI CLIs it can run: how each is run
+//! non-interactively, so that it is run without a PTY, and how its output
+//! shows that a task has finished, for those that post no notification.
+
+use anyhow::bail;
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug)]
+pub struct Tool {
+    pub name: &'static str,
+    /// Arguments anywhere on its command line with which it prints something
+    /// and exits.
+    non_interactive_args: &'static [&'static str],
+    /// Subcommands, as its first argument, that run without its TUI.
+    non_interactive_subcommands: &'static [&'static str],
+    /// Lines of its output that end a task, matched case-insensitively.
+    completion_patterns: &'static [&'static str],
+}
+
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "claude",
+        non_interactive_args: &["-p", "--print", "-v", "--version", "-h", "--help"],
+        non_interactive_subcommands: &[],
+        // It posts a notification itself.
+        completion_patterns: &[],
+    },
+    Tool {
+        name: "codex",
+        non_interactive_args: &["-V", "--version", "-h", "--help"],
+        non_interactive_subcommands: &[
+            "exec",
+            "e",
+            "apply",
+            "a",
+            "login",
+            "logout",
+            "mcp",
+            "completion",
+        ],
+        completion_patterns: &[],
+    },
+    Tool {
+        name: "aider",
+        non_interactive_args: &[
+            "-m",
+            "--message",
+            "-f",
+            "--message-file",
+            "--version",
+            "-h",
+            "--help",
+        ],
+        non_interactive_subcommands: &[],
+        // The usage report after each reply, e.g. `Tokens: 2.1k sent, 310
+        // received. Cost: $0.01 message, $0.03 session.`
+        completion_patterns: &[r"^tokens: \S+ sent, \S+ received"],
+    },
+    Tool {
+        name: "gemini",
+        non_interactive_args: &["-p", "--prompt", "-v", "--version", "-h", "--help"],
+        non_interactive_subcommands: &["mcp", "extensions"],
+        completion_patterns: &[],
+    },
+];
+
+/// For a tool not in [`TOOLS`].
+const DEFAULT_NON_INTERACTIVE_ARGS: &[&str] = &["--version", "-h", "--help"];
+
+pub fn find(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+/// `name` if it is in [`TOOLS`], for `--tool`.
+pub fn parse(name: &str) -> anyhow::Result<&'static Tool> {
+    match find(name) {
+        Some(tool) => Ok(tool),
+        None => bail!(
+            "unknown tool: {name} (expected {})",
+            TOOLS
+                .iter()
+                .map(|tool| tool.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Whether the tool named `name` runs non-interactively with `args`.
+pub fn is_non_interactive<'a>(name: &str, args: impl IntoIterator<Item = &'a str>) -> bool {
+    let (non_interactive_args, non_interactive_subcommands) = match find(name) {
+        Some(tool) => (tool.non_interactive_args, tool.non_interactive_subcommands),
+        None => (DEFAULT_NON_INTERACTIVE_ARGS, &[][..]),
+    };
+    args.into_iter().enumerate().any(|(i, arg)| {
+        non_interactive_args.contains(&arg)
+            || (i == 0 && non_interactive_subcommands.contains(&arg))
+    })
+}
+
+impl Tool {
+    pub fn completion_patterns(&self) -> Vec<Regex> {
+        self.completion_patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .expect("completion patterns must be valid")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_interactive() {
+        assert!(is_non_interactive(
+            "claude",
+            ["--model", "opus", "-p", "hi"]
+        ));
+        assert!(!is_non_interactive("claude", ["--resume"]));
+        assert!(is_non_interactive("codex", ["exec", "fix the build"]));
+        // Only as the subcommand.
+        assert!(!is_non_interactive("codex", ["explain", "exec"]));
+        assert!(is_non_interactive("aider", ["--message", "add tests"]));
+        // -v is not --version for every tool.
+        assert!(!is_non_interactive("aider", ["-v"]));
+        assert!(is_non_interactive("unknown", ["--help"]));
+        assert!(!is_non_interactive("unknown", ["-p"]));
+    }
+
+    #[test]
+    fn completion_patterns() {
+        let patterns = find("aider").unwrap().completion_patterns();
+        assert!(patterns[0].is_match("Tokens: 2.1k sent, 310 received. Cost: $0.01 message"));
+        assert!(!patterns[0].is_match("Tokens are counted per message"));
+        assert_eq!(
+            parse("cursor").err().unwrap().to_string(),
+            "unknown tool: cursor (expected claude, codex, aider, gemini)"
+        );
+    }
+}
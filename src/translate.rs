@@ -0,0 +1,75 @@
+//! `--translate-command`: turn notifications into another language before
+//! they are spoken, leaving what is shown untouched.
+
+use crate::shell_command::ShellCommand;
+use anyhow::{Context, bail};
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+
+/// A translation that takes longer is given up, and the original is spoken.
+const TRANSLATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Translate `text` with `command`, which reads it on stdin and writes the
+/// translation to stdout, e.g. `trans -brief :ja` or `llm -m local "Translate
+/// into Japanese"`.
+pub fn translate(command: &ShellCommand, text: &str) -> anyhow::Result<String> {
+    let mut child = command
+        .command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {}", command.program()))?;
+    // Written and read while the command runs, so that neither a long text
+    // nor a long translation fills a pipe and holds it up until it times out.
+    let mut stdin = child.stdin.take().context("no stdin")?;
+    let text = text.to_string();
+    thread::spawn(move || {
+        // A command that does not read all of it still gets to answer.
+        let _ = stdin.write_all(text.as_bytes());
+    });
+    let mut stdout = child.stdout.take().context("no stdout")?;
+    let reader = thread::spawn(move || {
+        let mut translation = String::new();
+        stdout.read_to_string(&mut translation).map(|_| translation)
+    });
+    if !command.wait(&mut child, TRANSLATE_TIMEOUT)? {
+        let _ = child.kill();
+        let _ = child.wait();
+        bail!("timed out after {} seconds", TRANSLATE_TIMEOUT.as_secs());
+    }
+    let translation = reader
+        .join()
+        .ok()
+        .context("the reader panicked")?
+        .context("failed to read the translation")?;
+    let translation = translation.trim();
+    if translation.is_empty() {
+        bail!("empty translation");
+    }
+    Ok(translation.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(command: &str) -> ShellCommand {
+        ShellCommand::parse(command).unwrap()
+    }
+
+    #[test]
+    fn translation() {
+        assert_eq!(
+            translate(&command("tr a-z A-Z"), "Claude is waiting\n").unwrap(),
+            "CLAUDE IS WAITING"
+        );
+        // More than a pipe holds, either way.
+        let long = "a".repeat(1 << 20);
+        assert_eq!(translate(&command("cat"), &long).unwrap(), long);
+        assert!(translate(&command("false"), "x").is_err());
+        assert!(translate(&command("cat"), " ").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! `--max-title-length`, `--max-message-length`, and `--truncate`: shorten
+//! notifications before they are shown, since Notification Center clips a
+//! long one wherever it happens to run out of room.
+
+use crate::event::Notification;
+use anyhow::bail;
+use std::borrow::Cow;
+
+const ELLIPSIS: char = '…';
+
+/// Which part of a text too long is cut off, as in `NSLineBreakMode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Truncation {
+    /// `…the end`
+    Head,
+    /// `The begin…`
+    #[default]
+    Tail,
+    /// `The be…e end`
+    Middle,
+}
+
+impl Truncation {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "head" => Ok(Self::Head),
+            "tail" => Ok(Self::Tail),
+            "middle" => Ok(Self::Middle),
+            _ => bail!("unknown truncation: {s} (expected head, tail, middle)"),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Head => "head",
+            Self::Tail => "tail",
+            Self::Middle => "middle",
+        }
+    }
+
+    /// `text` cut to at most `max_chars` characters, the ellipsis included.
+    pub fn apply(self, text: &str, max_chars: usize) -> Cow<'_, str> {
+        let len = text.chars().count();
+        if len <= max_chars {
+            return Cow::Borrowed(text);
+        }
+        let kept = max_chars.saturating_sub(1);
+        let (head, tail) = match self {
+            Self::Head => (0, kept),
+            Self::Tail => (kept, 0),
+            Self::Middle => (kept.div_ceil(2), kept / 2),
+        };
+        let start = text.char_indices().nth(head).map_or(0, |(i, _)| i);
+        let end = text
+            .char_indices()
+            .nth(len - tail)
+            .map_or(text.len(), |(i, _)| i);
+        Cow::Owned(format!(
+            "{}{ELLIPSIS}{}",
+            text[..start].trim_end(),
+            text[end..].trim_start()
+        ))
+    }
+}
+
+/// The longest title, subtitle, and message to show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LengthLimits {
+    /// Applies to the subtitle as well.
+    pub max_title_chars: Option<usize>,
+    pub max_message_chars: Option<usize>,
+    pub truncation: Truncation,
+}
+
+impl LengthLimits {
+    /// `notification` as it is if it fits, or shortened.
+    pub fn apply<'a>(&self, notification: &'a Notification) -> Cow<'a, Notification> {
+        let cut = |text: &'a str, max_chars: Option<usize>| match max_chars {
+            Some(max_chars) => self.truncation.apply(text, max_chars),
+            None => Cow::Borrowed(text),
+        };
+        let title = cut(&notification.title, self.max_title_chars);
+        let subtitle = notification
+            .subtitle
+            .as_deref()
+            .map(|subtitle| cut(subtitle, self.max_title_chars));
+        let message = cut(&notification.message, self.max_message_chars);
+        let is_cut = matches!(title, Cow::Owned(_))
+            || matches!(subtitle, Some(Cow::Owned(_)))
+            || matches!(message, Cow::Owned(_));
+        if !is_cut {
+            return Cow::Borrowed(notification);
+        }
+        Cow::Owned(Notification {
+            title: title.into_owned(),
+            subtitle: subtitle.map(Cow::into_owned),
+            message: message.into_owned(),
+            ..notification.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate() {
+        let text = "Refactored the parser and updated the tests";
+        assert_eq!(Truncation::Tail.apply(text, 20), "Refactored the pars…");
+        assert_eq!(Truncation::Head.apply(text, 20), "…d updated the tests");
+        assert_eq!(Truncation::Middle.apply(text, 20), "Refactored…the tests");
+        assert_eq!(Truncation::Tail.apply("日本語のテキスト", 4), "日本語…");
+        assert!(matches!(
+            Truncation::Middle.apply(text, 100),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(Truncation::parse("middle").unwrap(), Truncation::Middle);
+        assert!(Truncation::parse("end").is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let notification = Notification {
+            title: "Claude Code".to_string(),
+            subtitle: Some("caloud".to_string()),
+            message: "Task done".to_string(),
+            ..Default::default()
+        };
+        let limits = LengthLimits {
+            max_title_chars: Some(6),
+            ..LengthLimits::default()
+        };
+        let shown = limits.apply(&notification);
+        assert_eq!(shown.title, "Claud…");
+        assert_eq!(shown.subtitle.as_deref(), Some("caloud"));
+        assert_eq!(shown.message, "Task done");
+        assert!(matches!(
+            LengthLimits::default().apply(&notification),
+            Cow::Borrowed(_)
+        ));
+    }
+}
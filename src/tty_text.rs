@@ -1,3 +1,12 @@
+pub mod alert;
 pub mod buffer;
+pub mod code_block;
 pub mod fragment;
+pub mod message;
+pub mod osc_scanner;
+pub mod question;
 pub mod reformat;
+pub mod reformat_writer;
+pub mod spinner;
+pub mod tool_call;
+pub mod transcript;
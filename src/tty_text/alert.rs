@@ -0,0 +1,293 @@
+//! Lines of the output that mean a run needs the user even though `claude`
+//! posts no notification, such as a usage limit that stalls it for hours or
+//! a context about to be compacted.
+
+use crate::tty_text::fragment::Fragment;
+use crate::tty_text::spinner::is_status_line;
+use regex::{Regex, RegexBuilder};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Messages of `claude` and the API about usage and rate limits, matched
+/// case-insensitively.
+const RATE_LIMIT_PATTERNS: &[&str] = &[
+    r"usage limit reached",
+    r"\blimit reached\b.*\bresets?\b",
+    r"you've hit your (usage )?limit",
+    r"\brate limit (reached|exceeded)\b",
+];
+
+/// Messages of `claude` about the context running out and being compacted,
+/// matched case-insensitively.
+const COMPACTION_PATTERNS: &[&str] = &[
+    r"context left until auto-?compact",
+    r"\bcontext low\b",
+    r"\bconversation compacted\b",
+];
+
+const TASK_FINISHED_MESSAGE: &str = "Task finished";
+
+/// When a limit is lifted: `resets 3pm (Asia/Tokyo)` or `reset at 5pm.`
+const RESET_PATTERN: &str = r"(?i)\bresets?\s+(?:at\s+)?(?P<reset>.+?)[\s.·∙]*$";
+
+/// How many alert lines are remembered, so that a redrawn line is reported once.
+const RECENT_LINES: usize = 16;
+
+/// A remembered line that has not been seen for this long is reported again,
+/// e.g. the warning of the next compaction.
+const RECENT_EXPIRY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// A usage or rate limit stalls the run.
+    RateLimit,
+    /// The context is nearly full or has just been compacted.
+    Compaction,
+    /// A tool other than `claude` has finished a task, by the completion
+    /// patterns of its `--tool` preset.
+    TaskFinished,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub line: String,
+    /// When the limit is lifted, as shown.
+    pub reset: Option<String>,
+}
+
+impl Alert {
+    /// Text for a notification.
+    pub fn message(&self) -> String {
+        match (self.kind, &self.reset) {
+            (AlertKind::RateLimit, Some(reset)) => format!("Usage limit reached, resets {reset}"),
+            (AlertKind::RateLimit, None) => format!("Usage limit reached: {}", self.line),
+            // Drop key hints such as `· ctrl+o for history`.
+            (AlertKind::Compaction, _) => match self.line.split_once(" · ") {
+                Some((message, _)) => message.to_string(),
+                None => self.line.clone(),
+            },
+            (AlertKind::TaskFinished, _) => TASK_FINISHED_MESSAGE.to_string(),
+        }
+    }
+}
+
+pub struct AlertDetector {
+    line: String,
+    patterns: Vec<(AlertKind, Regex)>,
+    reset: Regex,
+    /// Lines reported lately, by [`dedup_key`], and when they were last seen.
+    recent: VecDeque<(String, Instant)>,
+}
+
+impl AlertDetector {
+    /// Watch for the built-in patterns of each kind in `kinds`, and for the
+    /// extra patterns given for each kind.
+    pub fn new(kinds: &[AlertKind], extra_patterns: &[(AlertKind, Regex)]) -> Self {
+        let mut patterns: Vec<_> = kinds
+            .iter()
+            .flat_map(|&kind| {
+                builtin_patterns(kind).iter().map(move |pattern| {
+                    let regex = RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .expect("built-in alert patterns must be valid");
+                    (kind, regex)
+                })
+            })
+            .collect();
+        patterns.extend(extra_patterns.iter().cloned());
+        Self {
+            line: String::new(),
+            patterns,
+            reset: Regex::new(RESET_PATTERN).expect("RESET_PATTERN must be valid"),
+            recent: VecDeque::with_capacity(RECENT_LINES),
+        }
+    }
+
+    /// Feed the next fragment, returning an alert if it completes a line with one.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> Option<Alert> {
+        self.push_at(fragment, Instant::now())
+    }
+
+    fn push_at(&mut self, fragment: &Fragment<'_>, now: Instant) -> Option<Alert> {
+        if fragment.is_sgr() {
+            return None;
+        }
+        if !fragment.is_plain_text() {
+            return self.finish_line(now);
+        }
+        self.line
+            .push_str(&String::from_utf8_lossy(fragment.data()));
+        if self.line.ends_with('\n') {
+            return self.finish_line(now);
+        }
+        None
+    }
+
+    fn finish_line(&mut self, now: Instant) -> Option<Alert> {
+        let line = std::mem::take(&mut self.line);
+        if is_status_line(line.trim_end()) {
+            return None;
+        }
+        // Bullets, tree lines, and box borders are not part of the message.
+        let line = line
+            .trim_start_matches(|c: char| !c.is_alphanumeric())
+            .trim_end_matches(|c: char| c.is_whitespace() || c == '│');
+        if line.is_empty() {
+            return None;
+        }
+        let (kind, _) = self
+            .patterns
+            .iter()
+            .find(|(_, regex)| regex.is_match(line))?;
+        let key = dedup_key(line);
+        if *kind == AlertKind::TaskFinished {
+            // Every report is another task, however alike.
+        } else if let Some(index) = self.recent.iter().position(|(recent, _)| *recent == key) {
+            let (_, last_seen) = self.recent.remove(index)?;
+            self.recent.push_back((key, now));
+            if now.duration_since(last_seen) < RECENT_EXPIRY {
+                return None;
+            }
+        } else {
+            if self.recent.len() == RECENT_LINES {
+                self.recent.pop_front();
+            }
+            self.recent.push_back((key, now));
+        }
+        let reset = self
+            .reset
+            .captures(line)
+            .map(|captures| captures["reset"].to_string());
+        Some(Alert {
+            kind: *kind,
+            line: line.to_string(),
+            reset,
+        })
+    }
+}
+
+fn builtin_patterns(kind: AlertKind) -> &'static [&'static str] {
+    match kind {
+        AlertKind::RateLimit => RATE_LIMIT_PATTERNS,
+        AlertKind::Compaction => COMPACTION_PATTERNS,
+        // Only those of the tool.
+        AlertKind::TaskFinished => &[],
+    }
+}
+
+/// The letters of `line`, so that a line redrawn with another spinner frame,
+/// elapsed time, or percentage counts as the same.
+fn dedup_key(line: &str) -> String {
+    line.chars().filter(|c| c.is_alphabetic()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    fn detect(detector: &mut AlertDetector, data: &str) -> Vec<Alert> {
+        detect_at(detector, data, Instant::now())
+    }
+
+    fn detect_at(detector: &mut AlertDetector, data: &str, now: Instant) -> Vec<Alert> {
+        FragmentList::parse(data.as_bytes(), false)
+            .into_inner()
+            .iter()
+            .filter_map(|fragment| detector.push_at(fragment, now))
+            .collect()
+    }
+
+    #[test]
+    fn rate_limits() {
+        let mut detector = AlertDetector::new(&[AlertKind::RateLimit], &[]);
+        let alerts = detect(
+            &mut detector,
+            "\
+            ⏺ Working on it.\r\n\
+              ⎿  \x1b[31mClaude usage limit reached. Your limit will reset at 5pm (America/New_York).\x1b[39m\r\n\
+            5-hour limit reached ∙ resets 3pm\r\n\
+            API Error: Rate limit exceeded\r\n",
+        );
+        let resets: Vec<_> = alerts.iter().map(|alert| alert.reset.as_deref()).collect();
+        assert_eq!(resets, [Some("5pm (America/New_York)"), Some("3pm"), None]);
+        assert_eq!(
+            alerts[0].message(),
+            "Usage limit reached, resets 5pm (America/New_York)"
+        );
+        assert_eq!(
+            alerts[2].message(),
+            "Usage limit reached: API Error: Rate limit exceeded"
+        );
+        // Redrawn lines are reported once.
+        assert!(detect(&mut detector, "5-hour limit reached ∙ resets 3pm\r\n").is_empty());
+    }
+
+    #[test]
+    fn compactions() {
+        let mut detector = AlertDetector::new(&[AlertKind::Compaction], &[]);
+        let start = Instant::now();
+        let messages =
+            |alerts: Vec<Alert>| -> Vec<String> { alerts.iter().map(Alert::message).collect() };
+        assert_eq!(
+            messages(detect_at(
+                &mut detector,
+                "\
+                Context left until auto-compact: 9%\r\n\
+                Context left until auto-compact: 8%\r\n\
+                ✻ Compacting conversation… (3s)\r\n\
+                ⏺ Conversation compacted · ctrl+o for history\r\n",
+                start,
+            )),
+            [
+                "Context left until auto-compact: 9%",
+                "Conversation compacted"
+            ]
+        );
+        // A warning shown all along is reported once, the next one again.
+        let later = start + RECENT_EXPIRY / 2;
+        let warning = "Context low (7% remaining) · Run /compact to compact & continue\r\n";
+        assert_eq!(
+            messages(detect_at(&mut detector, warning, start)),
+            ["Context low (7% remaining)"]
+        );
+        assert!(detect_at(&mut detector, warning, later).is_empty());
+        assert!(detect_at(&mut detector, warning, later + RECENT_EXPIRY / 2).is_empty());
+        assert_eq!(
+            detect_at(&mut detector, warning, later + RECENT_EXPIRY * 2).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn tasks_finished() {
+        let extra = [(
+            AlertKind::TaskFinished,
+            Regex::new("^Tokens: .* sent").unwrap(),
+        )];
+        let mut detector = AlertDetector::new(&[AlertKind::TaskFinished], &extra);
+        let report = "Tokens: 2.1k sent, 310 received. Cost: $0.01 message, $0.03 session.\r\n";
+        let alerts = detect(&mut detector, &format!("{report}> \r\n{report}"));
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].message(), "Task finished");
+    }
+
+    #[test]
+    fn extra_patterns() {
+        let extra = [(AlertKind::RateLimit, Regex::new("overloaded").unwrap())];
+        let mut detector = AlertDetector::new(&[], &extra);
+        assert_eq!(
+            detect(
+                &mut detector,
+                "API Error: overloaded\r\nusage limit reached\r\n"
+            ),
+            [Alert {
+                kind: AlertKind::RateLimit,
+                line: "API Error: overloaded".to_string(),
+                reset: None,
+            }]
+        );
+    }
+}
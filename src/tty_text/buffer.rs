@@ -1,41 +1,162 @@
 use crate::tty_text::fragment::{Fragment, FragmentList};
 use crate::tty_text::reformat::Reformatter;
 
+/// Ring buffer of `capacity` bytes between PTY reads and fragment parsing.
+///
+/// The storage is `2 * capacity` bytes and every byte written at
+/// `i < capacity` is mirrored to `i + capacity`, so the readable region `data[head..head + len]` is always
+/// contiguous even when it wraps around.  Unconsumed bytes never have to be
+/// moved back to the front; the cost is one copy of each byte read.
 #[derive(Debug, PartialEq)]
-pub struct Buffer<const N: usize> {
-    data: [u8; N],
-    start: usize,
-    end: usize,
+pub struct Buffer {
+    data: Box<[u8]>,
+    capacity: usize,
+    head: usize,
+    len: usize,
 }
 
-impl<const N: usize> Buffer<N> {
-    pub fn new() -> Self {
+impl Buffer {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
         Self {
-            data: [0; N],
-            start: 0,
-            end: 0,
+            data: vec![0; 2 * capacity].into_boxed_slice(),
+            capacity,
+            head: 0,
+            len: 0,
         }
     }
 
     pub fn is_full(&self) -> bool {
-        self.start == 0 && self.end == N
+        self.len == self.capacity
     }
 
     pub fn read_fragments(&mut self, formatter: &Reformatter) -> Vec<Fragment<'_>> {
-        let fragments = FragmentList::parse(&self.data[self.start..self.end], self.is_full());
-        let (consumed, fragments) = formatter.reformat(fragments, self.is_full());
-        self.start += consumed;
+        let is_full = self.is_full();
+        let fragments = FragmentList::parse(&self.data[self.head..][..self.len], is_full);
+        let (consumed, fragments) = formatter.reformat(fragments, is_full);
+        self.head = (self.head + consumed) % self.capacity;
+        self.len -= consumed;
         fragments
     }
 
+    /// Return the unconsumed bytes as-is and mark them consumed.
+    pub fn take_pending(&mut self) -> &[u8] {
+        self.take_pending_prefix(self.len)
+    }
+
+    /// Return the unconsumed bytes up to an incomplete escape sequence at the
+    /// end, if any, as-is and mark them consumed; the rest is kept until it
+    /// is complete.
+    pub fn take_complete(&mut self) -> &[u8] {
+        let complete = FragmentList::parse(&self.data[self.head..][..self.len], false).size();
+        self.take_pending_prefix(complete)
+    }
+
+    /// The number of unconsumed bytes.
+    pub fn pending_len(&self) -> usize {
+        self.len
+    }
+
+    /// Return the first `len` unconsumed bytes as-is and mark them consumed,
+    /// e.g. those held back before the last read.
+    pub fn take_pending_prefix(&mut self, len: usize) -> &[u8] {
+        let len = len.min(self.len);
+        let pending = &self.data[self.head..][..len];
+        self.head = (self.head + len) % self.capacity;
+        self.len -= len;
+        pending
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
     pub fn extend_from_read(&mut self, mut r: impl std::io::Read) -> std::io::Result<usize> {
-        if 0 < self.start && N <= 2 * self.end {
-            self.data.copy_within(self.start..self.end, 0);
-            self.end -= self.start;
-            self.start = 0;
+        if self.len == 0 {
+            // Start over at the front to get the longest contiguous free region.
+            self.head = 0;
         }
-        let n = r.read(&mut self.data[self.end..])?;
-        self.end += n;
+        let tail = (self.head + self.len) % self.capacity;
+        let writable = usize::min(self.capacity - self.len, self.capacity - tail);
+        let n = r.read(&mut self.data[tail..][..writable])?;
+        self.data.copy_within(tail..tail + n, tail + self.capacity);
+        self.len += n;
         Ok(n)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::property_test;
+    use std::io::Read;
+
+    #[property_test]
+    fn pending_bytes_survive_wraparound(
+        #[strategy = prop::collection::vec(any::<u8>(), 0..256)] input: Vec<u8>,
+        #[strategy = prop::collection::vec(1usize..16, 1..64)] consume_sizes: Vec<usize>,
+    ) {
+        let mut buffer = Buffer::new(16);
+        let mut reader = ChunkedReader(&input);
+        let mut output = Vec::new();
+
+        for &consume_size in consume_sizes.iter().cycle() {
+            if buffer.extend_from_read(&mut reader).unwrap() == 0 && buffer.len == 0 {
+                break;
+            }
+            let pending = &buffer.data[buffer.head..][..buffer.len];
+            let consumed = consume_size.min(pending.len());
+            output.extend_from_slice(&pending[..consumed]);
+            buffer.head = (buffer.head + consumed) % 16;
+            buffer.len -= consumed;
+            if buffer.len == 0 || reader.0.is_empty() {
+                output.extend_from_slice(buffer.take_pending());
+            }
+        }
+
+        prop_assert_eq!(output, input);
+    }
+
+    #[test]
+    fn full_buffer() {
+        let mut buffer = Buffer::new(4);
+        assert_eq!(buffer.extend_from_read(&b"abcdef"[..]).unwrap(), 4);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.take_pending(), b"abcd");
+        assert!(!buffer.is_full());
+    }
+
+    #[test]
+    fn pending_prefix() {
+        let mut buffer = Buffer::new(8);
+        buffer.extend_from_read(&b"abc"[..]).unwrap();
+        let held = buffer.pending_len();
+        buffer.extend_from_read(&b"def"[..]).unwrap();
+        assert_eq!(buffer.take_pending_prefix(held), b"abc");
+        assert_eq!(buffer.pending_len(), 3);
+        assert_eq!(buffer.take_pending_prefix(10), b"def");
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn complete_fragments() {
+        let mut buffer = Buffer::new(16);
+        buffer.extend_from_read(&b"ab\ncd\x1b]0;ti"[..]).unwrap();
+        assert_eq!(buffer.take_complete(), b"ab\ncd");
+        assert_eq!(buffer.take_pending(), b"\x1b]0;ti");
+    }
+
+    /// Reader that returns at most 5 bytes per call.
+    struct ChunkedReader<'a>(&'a [u8]);
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(5);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! `--copy-code-blocks`: copy each fenced code block in the assistant's
+//! messages as soon as its closing fence is printed.  Where the blocks go is
+//! up to the caller.
+
+use crate::tty_text::message::MessageLine;
+
+/// Collects the lines between a ```` ``` ```` (or `~~~`) fence and the
+/// matching closing fence.  A new message discards an unclosed block.
+pub struct CodeBlockDetector {
+    min_lines: usize,
+    /// The opening fence and the lines so far, inside a block.
+    open: Option<(String, Vec<String>)>,
+}
+
+impl CodeBlockDetector {
+    pub fn new(min_lines: usize) -> Self {
+        Self {
+            min_lines,
+            open: None,
+        }
+    }
+
+    /// Feed the next message line, returning a block it completes that has
+    /// at least the minimum number of lines.
+    pub fn push(&mut self, line: &MessageLine) -> Option<String> {
+        if line.is_first {
+            self.open = None;
+        }
+        let trimmed = line.text.trim();
+        match &mut self.open {
+            None => {
+                self.open = opening_fence(trimmed).map(|fence| (fence.to_string(), Vec::new()));
+                None
+            }
+            Some((fence, lines)) if trimmed == fence.as_str() => {
+                let lines = std::mem::take(lines);
+                self.open = None;
+                (lines.len() >= self.min_lines.max(1)).then(|| lines.join("\n"))
+            }
+            Some((_, lines)) => {
+                lines.push(line.text.clone());
+                None
+            }
+        }
+    }
+}
+
+/// The fence of ```` ```rust ````, i.e. three or more backticks or tildes.
+fn opening_fence(text: &str) -> Option<&str> {
+    let c = text.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = text.len() - text.trim_start_matches(c).len();
+    (len >= 3).then(|| &text[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(min_lines: usize, lines: &[&str]) -> Vec<String> {
+        let mut detector = CodeBlockDetector::new(min_lines);
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, text)| {
+                detector.push(&MessageLine {
+                    text: text.to_string(),
+                    is_first: i == 0,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fenced_blocks() {
+        let lines = [
+            "Run this:",
+            "```sh",
+            "cargo build",
+            "  --release",
+            "```",
+            "and then:",
+            "~~~",
+            "cargo test",
+            "~~~",
+        ];
+        assert_eq!(
+            detect(1, &lines),
+            ["cargo build\n  --release", "cargo test"]
+        );
+        assert_eq!(detect(2, &lines), ["cargo build\n  --release"]);
+    }
+
+    #[test]
+    fn new_message_discards_unclosed_block() {
+        let mut detector = CodeBlockDetector::new(1);
+        let line = |text: &str, is_first| MessageLine {
+            text: text.to_string(),
+            is_first,
+        };
+        assert_eq!(detector.push(&line("```", true)), None);
+        assert_eq!(detector.push(&line("a", false)), None);
+        assert_eq!(detector.push(&line("Next.", true)), None);
+        assert_eq!(detector.push(&line("```", false)), None);
+    }
+
+    #[test]
+    fn fences() {
+        assert_eq!(opening_fence("```rust"), Some("```"));
+        assert_eq!(opening_fence("````"), Some("````"));
+        assert_eq!(opening_fence("~~~"), Some("~~~"));
+        assert_eq!(opening_fence("``"), None);
+        assert_eq!(opening_fence("text"), None);
+    }
+}
@@ -110,6 +110,16 @@ impl<'a> Fragment<'a> {
             && self.data[self.data.len() - 1] == b'C'
     }
 
+    /// CSI Pm m — Character Attributes (SGR)
+    pub(super) fn is_sgr(&self) -> bool {
+        self.data.len() >= 3
+            && self.data.starts_with(b"\x1b[")
+            && self.data[2..self.data.len() - 1]
+                .iter()
+                .all(|b| b.is_ascii_digit() || *b == b';' || *b == b':')
+            && self.data[self.data.len() - 1] == b'm'
+    }
+
     pub(super) fn chomp(&mut self) {
         if let Some(rest) = self.data.strip_suffix(b"\n") {
             self.data = rest;
@@ -146,7 +156,7 @@ pub enum EscapeSequence<'a> {
 
     /// `\x1b[?25h`
     ///
-    /// > ```
+    /// > ```text
     /// > CSI ? Pm h
     /// >           DEC Private Mode Set (DECSET).
     /// >             ...
@@ -165,20 +175,61 @@ pub enum EscapeSequence<'a> {
     ///
     /// > To post a notification:
     /// >
-    /// > ```
+    /// > ```text
     /// > OSC 9 ; [Message content goes here] ST
     /// > ```
     ///
     /// <https://iterm2.com/documentation-escape-codes.html>
     PostNotification(&'a [u8]),
 
+    /// `\x1b]777;notify;title;message\x07`, as posted by `caloud send`; the
+    /// message may contain `;`.  Fields such as `;sound=Glass;id=build` at the
+    /// end are taken as `parameters`, read with [`notification_parameters`].
+    ///
+    /// <https://github.com/exg/rxvt-unicode/blob/master/src/perl/notify>
+    PostTitledNotification {
+        title: &'a [u8],
+        message: &'a [u8],
+        parameters: &'a [u8],
+    },
+
+    /// `\x1b]777;preexec\x07` and the other OSC 777 extensions than `notify`,
+    /// e.g. from shell integration, with what follows `777;`.
+    Osc777(&'a [u8]),
+
+    /// `\x1b]11;?\x07`: the foreground (`10`) or background (`11`) color is
+    /// asked for, to be reported with the same terminator.
+    ///
+    /// > ```text
+    /// > OSC Ps ; Pt ST
+    /// >           ...
+    /// >             Ps = 1 0  ⇒  Change VT100 text foreground color to Pt.
+    /// >             Ps = 1 1  ⇒  Change VT100 text background color to Pt.
+    /// >           ...
+    /// >           If a "?" is given rather than a name or RGB specification,
+    /// >           xterm replies with a control sequence of the same form which
+    /// >           can be used to set the corresponding dynamic color.
+    /// > ```
+    ///
+    /// <https://www.invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands>
+    QueryColor {
+        ps: &'a [u8],
+        terminator: &'a [u8],
+    },
+
+    /// `\x1b[?2031h` / `\x1b[?2031l`: whether to report changes between dark
+    /// and light mode.
+    ///
+    /// <https://contour-terminal.org/vt-extensions/color-palette-update-notifications/>
+    ColorSchemeUpdates(bool),
+
     Incomplete,
 
     Other,
 }
 
 impl<'a> EscapeSequence<'a> {
-    fn parse(data: &'a [u8], allow_incomplete: bool) -> Option<(usize, Self)> {
+    pub(super) fn parse(data: &'a [u8], allow_incomplete: bool) -> Option<(usize, Self)> {
         if data.first() != Some(&b'\x1b') {
             return None;
         }
@@ -200,13 +251,14 @@ impl<'a> EscapeSequence<'a> {
                 .iter()
                 .position(|&b| b == b'\x07')
                 .map(|i| (2 + i, 1));
-            let st = || {
-                data[2..]
-                    .windows(2)
-                    .position(|w| w == b"\x1b\\")
-                    .map(|i| (2 + i, 2))
-            };
-            let Some((parameter_end, terminator_length)) = bel.or_else(st) else {
+            let st = data[2..]
+                .windows(2)
+                .position(|w| w == b"\x1b\\")
+                .map(|i| (2 + i, 2));
+            // The earliest terminator ends the sequence; a later BEL belongs to
+            // the next one.
+            let Some((parameter_end, terminator_length)) = [bel, st].into_iter().flatten().min()
+            else {
                 return emit_incomplete();
             };
             let p = || &data[4..parameter_end];
@@ -218,11 +270,36 @@ impl<'a> EscapeSequence<'a> {
                         .next()
                         .is_some_and(|s| s.iter().all(|b| b.is_ascii_digit()))
             };
+            let end = parameter_end + terminator_length;
+            if let Some(p) = data[2..parameter_end].strip_prefix(b"777;") {
+                let Some(p) = p.strip_prefix(b"notify;") else {
+                    return Some((end, EscapeSequence::Osc777(p)));
+                };
+                let (title, rest) = match p.iter().position(|&b| b == b';') {
+                    Some(i) => (&p[..i], &p[i + 1..]),
+                    None => (p, &b""[..]),
+                };
+                let (message, parameters) = split_notification_parameters(rest);
+                return Some((
+                    end,
+                    EscapeSequence::PostTitledNotification {
+                        title,
+                        message,
+                        parameters,
+                    },
+                ));
+            }
             return Some((
-                parameter_end + terminator_length,
+                end,
                 match &data[2..usize::min(4, parameter_end)] {
                     b"0;" => EscapeSequence::SetWindowAndIconTitle(p()),
                     b"9;" if !has_conemu_osc9_parameter() => EscapeSequence::PostNotification(p()),
+                    b"10" | b"11" if &data[4..parameter_end] == b";?" => {
+                        EscapeSequence::QueryColor {
+                            ps: &data[2..4],
+                            terminator: &data[parameter_end..end],
+                        }
+                    }
                     _ => EscapeSequence::Other,
                 },
             ));
@@ -245,12 +322,55 @@ impl<'a> EscapeSequence<'a> {
         let escape_sequence = match &data[..n] {
             b"\x1b[?2026l" => EscapeSequence::EndSynchronizedUpdate,
             b"\x1b[?25h" => EscapeSequence::ShowCursor,
+            b"\x1b[?2031h" => EscapeSequence::ColorSchemeUpdates(true),
+            b"\x1b[?2031l" => EscapeSequence::ColorSchemeUpdates(false),
             _ => EscapeSequence::Other,
         };
         Some((n, escape_sequence))
     }
 }
 
+/// `message;key=value;key=value` split into the message and the fields at
+/// the end.  The message is never taken for a field, even if it looks like one.
+fn split_notification_parameters(p: &[u8]) -> (&[u8], &[u8]) {
+    let mut message_end = p.len();
+    while let Some(i) = p[..message_end].iter().rposition(|&b| b == b';') {
+        if notification_parameter(&p[i + 1..message_end]).is_none() {
+            break;
+        }
+        message_end = i;
+    }
+    (
+        &p[..message_end],
+        p.get(message_end + 1..).unwrap_or_default(),
+    )
+}
+
+/// `key=value`, where `key` is lowercase ASCII, digits, `-`, and `_`.
+fn notification_parameter(field: &[u8]) -> Option<(&[u8], &[u8])> {
+    let i = field.iter().position(|&b| b == b'=')?;
+    let (key, value) = (&field[..i], &field[i + 1..]);
+    let is_key = key.first().is_some_and(u8::is_ascii_lowercase)
+        && key
+            .iter()
+            .all(|&b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+    is_key.then_some((key, value))
+}
+
+/// The `key=value` fields of [`EscapeSequence::PostTitledNotification`].
+pub fn notification_parameters(parameters: &[u8]) -> Vec<(String, String)> {
+    parameters
+        .split(|&b| b == b';')
+        .filter_map(notification_parameter)
+        .map(|(key, value)| {
+            (
+                String::from_utf8_lossy(key).into_owned(),
+                String::from_utf8_lossy(value).into_owned(),
+            )
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,6 +419,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn st_terminated_sequence_followed_by_bel_terminated_sequence() {
+        assert_eq!(
+            new_fragments(b"\x1b]9;First\x1b\\\x1b]9;Second\x07", false).into_inner(),
+            &[
+                Fragment::new(
+                    b"\x1b]9;First\x1b\\",
+                    Some(EscapeSequence::PostNotification(b"First")),
+                ),
+                Fragment::new(
+                    b"\x1b]9;Second\x07",
+                    Some(EscapeSequence::PostNotification(b"Second")),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn post_titled_notification() {
+        assert_eq!(
+            new_fragments(b"\x1b]777;notify;Build;finished; 0 errors\x1b\\", false).into_inner(),
+            &[Fragment::new(
+                b"\x1b]777;notify;Build;finished; 0 errors\x1b\\",
+                Some(EscapeSequence::PostTitledNotification {
+                    title: b"Build",
+                    message: b"finished; 0 errors",
+                    parameters: b"",
+                }),
+            )],
+        );
+        assert_eq!(
+            new_fragments(b"\x1b]777;preexec\x07", false).into_inner(),
+            &[Fragment::new(
+                b"\x1b]777;preexec\x07",
+                Some(EscapeSequence::Osc777(b"preexec"))
+            )],
+        );
+    }
+
+    #[test]
+    fn post_titled_notification_with_parameters() {
+        let data = b"\x1b]777;notify;Build;done; 0 errors;sound=Glass;x-icon=/a=b.png\x07";
+        let fragments = new_fragments(data, false).into_inner();
+        let Some(EscapeSequence::PostTitledNotification {
+            title,
+            message,
+            parameters,
+        }) = fragments[0].escape_sequence()
+        else {
+            panic!("not a notification: {fragments:?}");
+        };
+        assert_eq!((*title, *message), (&b"Build"[..], &b"done; 0 errors"[..]));
+        assert_eq!(
+            notification_parameters(parameters),
+            [
+                ("sound".to_string(), "Glass".to_string()),
+                ("x-icon".to_string(), "/a=b.png".to_string())
+            ]
+        );
+
+        // The message itself is kept even if it looks like a field.
+        assert_eq!(
+            split_notification_parameters(b"id=1;id=2"),
+            (&b"id=1"[..], &b"id=2"[..])
+        );
+        assert_eq!(
+            split_notification_parameters(b"a;Key=value"),
+            (&b"a;Key=value"[..], &b""[..])
+        );
+    }
+
     #[test]
     fn conemu_set_progress_state() {
         assert_eq!(
@@ -310,6 +501,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn query_color() {
+        assert_eq!(
+            new_fragments(b"\x1b]11;?\x1b\\\x1b]10;?\x07\x1b]11;#000000\x07", false).into_inner(),
+            &[
+                Fragment::new(
+                    b"\x1b]11;?\x1b\\",
+                    Some(EscapeSequence::QueryColor {
+                        ps: b"11",
+                        terminator: b"\x1b\\",
+                    }),
+                ),
+                Fragment::new(
+                    b"\x1b]10;?\x07",
+                    Some(EscapeSequence::QueryColor {
+                        ps: b"10",
+                        terminator: b"\x07",
+                    }),
+                ),
+                Fragment::new(b"\x1b]11;#000000\x07", Some(EscapeSequence::Other)),
+            ],
+        );
+    }
+
+    #[test]
+    fn color_scheme_updates() {
+        assert_eq!(
+            new_fragments(b"\x1b[?2031h", false).into_inner(),
+            &[Fragment::new(
+                b"\x1b[?2031h",
+                Some(EscapeSequence::ColorSchemeUpdates(true))
+            )],
+        );
+    }
+
     #[test]
     fn allowed_incomplete_escape_sequence() {
         let data = b"Test Text\x1b]0;Test";
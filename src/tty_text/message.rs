@@ -0,0 +1,229 @@
+//! Pick the assistant's messages out of the output of `claude`, so that they
+//! can be read out (`--speak-output`) or copied (`--copy-key`).
+
+use crate::tty_text::fragment::Fragment;
+use crate::tty_text::spinner::is_status_line;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Marks the first line of a message, or of a tool call.
+pub(super) const MESSAGE_BULLETS: &[&str] = &["⏺", "●"];
+
+/// Marks the output of a tool call.
+const TOOL_RESULT_MARKER: &str = "⎿";
+
+/// How many recent lines are remembered, so that a line redrawn by the TUI
+/// is not returned again.
+const RECENT_LINES: usize = 64;
+
+/// A line of a message, without the bullet or the indentation of the message.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MessageLine {
+    pub text: String,
+    /// The line with the bullet, which starts a new message.
+    pub is_first: bool,
+}
+
+/// Collects plain text into lines and returns those that belong to a message:
+/// a line starting with a bullet and the indented or blank lines following it.
+/// Escape sequences are skipped, so colors and cursor movement are ignored;
+/// spinners, the input box, and tool calls never make it into a message.
+pub struct MessageExtractor {
+    line: Vec<u8>,
+    in_message: bool,
+    recent: VecDeque<String>,
+}
+
+impl Default for MessageExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageExtractor {
+    pub fn new() -> Self {
+        Self {
+            line: Vec::new(),
+            in_message: false,
+            recent: VecDeque::with_capacity(RECENT_LINES),
+        }
+    }
+
+    /// Feed the next fragment, returning a message line if it completes one.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> Option<MessageLine> {
+        if !fragment.is_plain_text() {
+            return None;
+        }
+        self.line.extend_from_slice(fragment.data());
+        if !self.line.ends_with(b"\n") {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.line)).into_owned();
+        let line = self.classify(line.trim_end())?;
+        // Blank lines repeat between paragraphs and are never a redraw.
+        if !line.text.is_empty() {
+            if self.recent.contains(&line.text) {
+                return None;
+            }
+            if self.recent.len() == RECENT_LINES {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(line.text.clone());
+        }
+        Some(line)
+    }
+
+    fn classify(&mut self, line: &str) -> Option<MessageLine> {
+        // The spinner drawn below a message that is still being written does
+        // not end it.
+        if is_status_line(line) {
+            return None;
+        }
+        let trimmed = line.trim();
+        if let Some(rest) = MESSAGE_BULLETS
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            let rest = rest.trim();
+            self.in_message = !is_tool_call(rest);
+            return (self.in_message && !rest.is_empty()).then(|| MessageLine {
+                text: rest.to_string(),
+                is_first: true,
+            });
+        }
+        if trimmed.is_empty() {
+            return self.in_message.then(|| MessageLine {
+                text: String::new(),
+                is_first: false,
+            });
+        }
+        if !self.in_message || !line.starts_with("  ") || trimmed.starts_with(TOOL_RESULT_MARKER) {
+            self.in_message = false;
+            return None;
+        }
+        Some(MessageLine {
+            text: line[2..].to_string(),
+            is_first: false,
+        })
+    }
+}
+
+/// The most recent message, shared between the output thread, which builds
+/// it line by line, and whatever copies it.
+#[derive(Clone, Default)]
+pub struct LastMessage(Arc<Mutex<String>>);
+
+impl LastMessage {
+    pub fn push(&self, line: &MessageLine) {
+        let mut message = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if line.is_first {
+            message.clear();
+        } else {
+            message.push('\n');
+        }
+        message.push_str(&line.text);
+    }
+
+    /// The message without trailing blank lines, if there has been one.
+    pub fn get(&self) -> Option<String> {
+        let message = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let message = message.trim_end();
+        (!message.is_empty()).then(|| message.to_string())
+    }
+}
+
+/// `Bash(ls -la)`, `Read(src/main.rs)`, and the like.
+fn is_tool_call(text: &str) -> bool {
+    tool_name(text).is_some()
+}
+
+/// `Bash` of `Bash(ls -la)`, if `text` is a tool call.
+pub(super) fn tool_name(text: &str) -> Option<&str> {
+    text.split_once('(').map(|(name, _)| name).filter(|name| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    fn extract(data: &[u8]) -> Vec<String> {
+        let mut extractor = MessageExtractor::new();
+        FragmentList::parse(data, false)
+            .into_inner()
+            .iter()
+            .filter_map(|fragment| extractor.push(fragment))
+            .map(|line| match line.is_first {
+                true => format!("* {}", line.text),
+                false => line.text,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn messages_are_extracted() {
+        let output = "\
+            > explain this\r\n\
+            \r\n\
+            \x1b[38;5;231m⏺\x1b[39m I'll look at the file \x1b[1mfirst\x1b[22m.\r\n\
+            \r\n\
+            ⏺ Read(src/main.rs)\r\n  \
+              ⎿  Read 441 lines\r\n\
+            \r\n\
+            ⏺ It is a PTY wrapper\r\n  \
+              around claude:\r\n\
+            \r\n    \
+                fn main() {}\r\n\
+            ✻ Thinking… (esc to interrupt)\r\n";
+        assert_eq!(
+            extract(output.as_bytes()),
+            [
+                "* I'll look at the file first.",
+                "",
+                "* It is a PTY wrapper",
+                "around claude:",
+                "",
+                "  fn main() {}",
+            ]
+        );
+    }
+
+    #[test]
+    fn redrawn_lines_are_spoken_once() {
+        let output = "⏺ Done.\r\n\x1b[2K\x1b[1A⏺ Done.\r\n";
+        assert_eq!(extract(output.as_bytes()), ["* Done."]);
+    }
+
+    #[test]
+    fn status_lines_are_skipped() {
+        let output = "⏺ First,\r\n✢ Deliberating…\r\n  ↓ 1.2k tokens\r\n  then second.\r\n";
+        assert_eq!(extract(output.as_bytes()), ["* First,", "then second."]);
+    }
+
+    #[test]
+    fn last_message() {
+        let last_message = LastMessage::default();
+        assert_eq!(last_message.get(), None);
+        let mut extractor = MessageExtractor::new();
+        let output = "⏺ First.\r\n\r\n⏺ Second,\r\n  \r\n  in two paragraphs.\r\n\r\n";
+        for fragment in FragmentList::parse(output.as_bytes(), false).into_inner() {
+            if let Some(line) = extractor.push(&fragment) {
+                last_message.push(&line);
+            }
+        }
+        assert_eq!(
+            last_message.get().as_deref(),
+            Some("Second,\n\nin two paragraphs.")
+        );
+    }
+
+    #[test]
+    fn tool_calls() {
+        assert!(is_tool_call("Bash(cargo test)"));
+        assert!(is_tool_call("mcp__github__get_issue(number: 1)"));
+        assert!(!is_tool_call("Done (mostly)."));
+        assert!(!is_tool_call("Done."));
+    }
+}
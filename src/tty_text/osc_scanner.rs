@@ -0,0 +1,115 @@
+use crate::tty_text::fragment::EscapeSequence;
+
+/// OSC sequences longer than this are not worth carrying across reads.
+const MAX_PENDING_LENGTH: usize = 4096;
+
+/// Finds OSC sequences in a byte stream without splitting it into fragments.
+///
+/// This backs the passthrough fast path: output is forwarded as read, and only
+/// the OSC sequences (titles and notifications) are extracted.  An OSC
+/// sequence split across reads is carried over to the next call.
+pub struct OscScanner {
+    pending: Vec<u8>,
+}
+
+impl Default for OscScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OscScanner {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Call `f` for each OSC sequence completed by `data`.
+    pub fn scan(&mut self, data: &[u8], mut f: impl FnMut(&EscapeSequence<'_>)) {
+        let mut pending = std::mem::take(&mut self.pending);
+        let data = if pending.is_empty() {
+            data
+        } else {
+            pending.extend_from_slice(data);
+            &pending[..]
+        };
+
+        let mut i = 0;
+        while let Some(offset) = data[i..].iter().position(|&b| b == b'\x1b') {
+            i += offset;
+            match data.get(i + 1) {
+                Some(b']') => {}
+                Some(_) => {
+                    i += 1;
+                    continue;
+                }
+                None => break,
+            }
+            match EscapeSequence::parse(&data[i..], false) {
+                Some((consumed, escape_sequence)) => {
+                    f(&escape_sequence);
+                    i += consumed;
+                }
+                None => break,
+            }
+        }
+
+        let rest = data.get(i..).unwrap_or_default();
+        if rest.first() == Some(&b'\x1b') && rest.len() <= MAX_PENDING_LENGTH {
+            self.pending = rest.to_vec();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"a\x1b[1mb\x1b]0;Title\x07c\x1b]9;Message\x1b\\d\x1b]9;4;1;50\x07";
+
+    #[test]
+    fn scan_whole() {
+        assert_eq!(
+            scan_chunks(&[DATA]),
+            ["Title(Title)", "Notification(Message)", "Other"],
+        );
+    }
+
+    #[test]
+    fn scan_split_at_every_position() {
+        let expected = scan_chunks(&[DATA]);
+        for i in 0..=DATA.len() {
+            assert_eq!(
+                scan_chunks(&[&DATA[..i], &DATA[i..]]),
+                expected,
+                "split at {i}"
+            );
+        }
+    }
+
+    #[test]
+    fn oversized_sequence_is_dropped() {
+        let long = [b"\x1b]9;".as_slice(), &[b'x'; MAX_PENDING_LENGTH]].concat();
+        assert!(scan_chunks(&[&long, b"\x07"]).is_empty());
+    }
+
+    fn scan_chunks(chunks: &[&[u8]]) -> Vec<String> {
+        let mut scanner = OscScanner::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            scanner.scan(chunk, |escape_sequence| {
+                events.push(match escape_sequence {
+                    EscapeSequence::SetWindowAndIconTitle(title) => {
+                        format!("Title({})", String::from_utf8_lossy(title))
+                    }
+                    EscapeSequence::PostNotification(message) => {
+                        format!("Notification({})", String::from_utf8_lossy(message))
+                    }
+                    other => format!("{other:?}"),
+                });
+            });
+        }
+        events
+    }
+}
@@ -0,0 +1,280 @@
+//! `--speak-questions`: find the numbered options of a question that
+//! `claude` asks (AskUserQuestion, permission prompts) so that they can be
+//! read out and answered by number without looking at the screen.
+
+use crate::tty_text::fragment::Fragment;
+use crate::tty_text::spinner::{is_spinner_line, is_status_line};
+
+/// Marks the highlighted option.
+const SELECTION_MARKER: char = '❯';
+
+/// Box drawing around the selector.
+const BORDERS: &[char] = &['│', '╭', '╮', '╰', '╯', '─'];
+
+/// The key hints below the options, which end the list.
+const FOOTER_HINTS: &[&str] = &["Enter to ", "Esc to "];
+
+/// The option with which a permission prompt is declined.
+const PERMISSION_DECLINE_OPTION: &str = "No, and tell Claude what to do differently";
+
+/// How many lines before a question are kept, e.g. the command that a
+/// permission prompt is about.
+const CONTEXT_LINES: usize = 20;
+
+/// Collects the lines of the output and returns a spoken form of each
+/// question followed by at least two numbered options:
+///
+/// ```text
+/// Which library should we use?
+///
+/// ❯ 1. serde
+///      The usual choice
+///   2. miniserde
+///
+/// Enter to select · Esc to cancel
+/// ```
+///
+/// becomes `Question: Which library should we use?; option 1 serde, option 2 miniserde`.
+/// A question redrawn as the selection moves is returned only once.
+pub struct QuestionDetector {
+    line: String,
+    question: Option<String>,
+    options: Vec<(u32, String)>,
+    last_spoken: Option<String>,
+    context: Vec<String>,
+    /// How many questions have been returned.
+    asked: u64,
+    /// Whether the last one is still shown.
+    is_pending: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Question {
+    /// The spoken form.
+    pub text: String,
+    /// `claude` asks whether a tool may run, and waits until it is answered.
+    pub is_permission_prompt: bool,
+}
+
+impl Default for QuestionDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuestionDetector {
+    pub fn new() -> Self {
+        Self {
+            line: String::new(),
+            question: None,
+            options: Vec::new(),
+            last_spoken: None,
+            context: Vec::with_capacity(CONTEXT_LINES),
+            asked: 0,
+            is_pending: false,
+        }
+    }
+
+    /// The number, counted from 1, of the question last returned while it is
+    /// still shown, i.e. until the spinner shows `claude` working again.
+    pub fn pending(&self) -> Option<u64> {
+        self.is_pending.then_some(self.asked)
+    }
+
+    /// The last lines seen, including those of the question just returned.
+    pub fn context(&self) -> &[String] {
+        &self.context
+    }
+
+    /// Feed the next fragment, returning a question to be spoken if it completes one.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> Option<Question> {
+        if fragment.is_sgr() {
+            return None;
+        }
+        if !fragment.is_plain_text() {
+            // Cursor movement and the end of a frame finish the line, which
+            // the TUI does not always terminate with a newline.
+            return self.finish_line();
+        }
+        self.line
+            .push_str(&String::from_utf8_lossy(fragment.data()));
+        if self.line.ends_with('\n') {
+            return self.finish_line();
+        }
+        None
+    }
+
+    fn finish_line(&mut self) -> Option<Question> {
+        let line = std::mem::take(&mut self.line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        // The spinner may be redrawn between a question and its options.
+        if is_status_line(line) {
+            self.is_pending &= !is_spinner_line(line);
+            return None;
+        }
+        let is_indented = line.starts_with(' ');
+        let text = line.trim_matches(|c: char| c.is_whitespace() || BORDERS.contains(&c));
+        // The bottom of a box ends the options in it.
+        if text.is_empty() && !line.contains('╰') {
+            return None;
+        }
+        if !text.is_empty() {
+            if self.context.len() == CONTEXT_LINES {
+                self.context.remove(0);
+            }
+            self.context.push(text.to_string());
+        }
+        if let Some(option) = parse_option(text) {
+            if option.0 == 1 {
+                self.options.clear();
+            }
+            if option.0 as usize == self.options.len() + 1 {
+                self.options.push(option);
+            }
+            return None;
+        }
+        if self.options.is_empty() {
+            self.question = Some(text.to_string());
+            return None;
+        }
+        // Descriptions of the options are indented under them.
+        if is_indented && !FOOTER_HINTS.iter().any(|hint| text.contains(hint)) {
+            return None;
+        }
+        let options = std::mem::take(&mut self.options);
+        let question = std::mem::replace(
+            &mut self.question,
+            (!text.is_empty()).then(|| text.to_string()),
+        );
+        if options.len() < 2 {
+            return None;
+        }
+        let spoken = format_question(question.as_deref(), &options);
+        if self.last_spoken.as_ref() == Some(&spoken) {
+            return None;
+        }
+        self.last_spoken = Some(spoken.clone());
+        self.asked += 1;
+        self.is_pending = true;
+        Some(Question {
+            text: spoken,
+            is_permission_prompt: is_permission_prompt(question.as_deref(), &options),
+        })
+    }
+}
+
+/// `Do you want to proceed?` with `Yes` first, or any question that can be
+/// declined by telling Claude what to do instead.
+fn is_permission_prompt(question: Option<&str>, options: &[(u32, String)]) -> bool {
+    let asks = question.is_some_and(|q| q.starts_with("Do you want to"))
+        && options.first().is_some_and(|(_, label)| label == "Yes");
+    asks || options
+        .iter()
+        .any(|(_, label)| label.starts_with(PERMISSION_DECLINE_OPTION))
+}
+
+/// `❯ 1. Yes` or `2. No`
+fn parse_option(text: &str) -> Option<(u32, String)> {
+    let text = text.trim_start_matches(SELECTION_MARKER).trim_start();
+    let (number, label) = text.split_once(". ")?;
+    let number = number.parse().ok()?;
+    let label = label.trim();
+    (!label.is_empty()).then(|| (number, label.to_string()))
+}
+
+fn format_question(question: Option<&str>, options: &[(u32, String)]) -> String {
+    let options = options
+        .iter()
+        .map(|(number, label)| format!("option {number} {label}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match question {
+        Some(question) => format!("Question: {question}; {options}"),
+        None => format!("Question: {options}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    fn detect_questions(data: &str) -> Vec<Question> {
+        let mut detector = QuestionDetector::new();
+        FragmentList::parse(data.as_bytes(), false)
+            .into_inner()
+            .iter()
+            .filter_map(|fragment| detector.push(fragment))
+            .collect()
+    }
+
+    #[test]
+    fn ask_user_question() {
+        let frame = "\
+            \x1b[?2026h☐ Library\r\n\
+            \r\n\
+            Which library should we use?\r\n\
+            \r\n\
+            \x1b[36m❯ 1. serde\x1b[39m\r\n     \
+                 The usual choice\r\n  \
+              2. miniserde\r\n  \
+              3. Type something.\r\n\
+            \r\n\
+            Enter to select · Tab/Arrow keys to navigate · Esc to cancel\x1b[?2026l";
+        let expected = "Question: Which library should we use?; \
+            option 1 serde, option 2 miniserde, option 3 Type something.";
+        // Moving the selection redraws the same question.
+        let redrawn = frame
+            .replace("❯ 1. serde", "  1. serde")
+            .replace("  2.", "❯ 2.");
+        assert_eq!(
+            detect_questions(&format!("{frame}\x1b[2K\x1b[1A{redrawn}")),
+            [Question {
+                text: expected.to_string(),
+                is_permission_prompt: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn permission_prompt() {
+        let frame = "\
+            ╭──────────────╮\r\n\
+            │ Bash command │\r\n\
+            │ Do you want to proceed? │\r\n\
+            │ ❯ 1. Yes │\r\n\
+            │   2. No, and tell Claude what to do differently (esc) │\r\n\
+            ╰──────────────╯\r\n";
+        assert_eq!(
+            detect_questions(frame),
+            [Question {
+                text: "Question: Do you want to proceed?; option 1 Yes, \
+                       option 2 No, and tell Claude what to do differently (esc)"
+                    .to_string(),
+                is_permission_prompt: true,
+            }]
+        );
+
+        let mut detector = QuestionDetector::new();
+        for fragment in FragmentList::parse(frame.as_bytes(), false).into_inner() {
+            detector.push(&fragment);
+        }
+        assert_eq!(
+            detector.context()[..2],
+            ["Bash command", "Do you want to proceed?"]
+        );
+        assert_eq!(detector.pending(), Some(1));
+        // Answered: `claude` goes on working.
+        for fragment in
+            FragmentList::parse("✶ Running… (esc to interrupt)\r\n".as_bytes(), false).into_inner()
+        {
+            detector.push(&fragment);
+        }
+        assert_eq!(detector.pending(), None);
+    }
+
+    #[test]
+    fn numbered_list_in_a_message_needs_two_options() {
+        assert!(detect_questions("Steps:\r\n1. Build it\r\nThat is all.\r\n").is_empty());
+    }
+}
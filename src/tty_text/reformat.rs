@@ -2,17 +2,60 @@ mod line_wrapping;
 
 use crate::tty_text::fragment::{Fragment, FragmentList};
 use line_wrapping::adjust_line_wrapping;
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum LineWrapMode {
+    #[default]
     Preserve,
     Adjust,
+    /// `Adjust` while the terminal is at most [`AUTO_ADJUST_MAX_WIDTH`]
+    /// columns wide, `Preserve` otherwise; re-evaluated on every resize.
+    Auto,
+}
+
+/// Widths up to which `claude` is likely to split URLs across lines.
+pub const AUTO_ADJUST_MAX_WIDTH: u16 = 120;
+
+/// Where the decisions of `Adjust` are written, one line each: whether a
+/// line was joined to the one before or left alone, by which heuristic, at
+/// which width, e.g.
+///
+/// ```text
+/// join url-continuation width=40 "  ogin?next=/home"
+/// ```
+#[derive(Clone)]
+pub struct WrapLog(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl WrapLog {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    /// Failures are ignored so that logging never disturbs the output.
+    fn record(&self, decision: &str, heuristic: &str, terminal_width: u16, line: &[u8]) {
+        let mut writer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = writeln!(
+            writer,
+            "{decision} {heuristic} width={terminal_width} {:?}",
+            String::from_utf8_lossy(line)
+        );
+        let _ = writer.flush();
+    }
+}
+
+impl std::fmt::Debug for WrapLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WrapLog")
+    }
 }
 
 #[derive(Debug)]
 pub struct Reformatter {
     terminal_width: u16,
     mode: LineWrapMode,
+    wrap_log: Option<WrapLog>,
 }
 
 impl Reformatter {
@@ -20,28 +63,70 @@ impl Reformatter {
         Self {
             terminal_width,
             mode,
+            wrap_log: None,
         }
     }
 
+    /// Record the decisions of `Adjust` in `wrap_log`, if any.
+    pub fn with_wrap_log(self, wrap_log: Option<WrapLog>) -> Self {
+        Self { wrap_log, ..self }
+    }
+
+    /// Whether output passes through unchanged, so that fragment parsing can be skipped.
+    pub fn is_passthrough(&self) -> bool {
+        self.effective_mode() == LineWrapMode::Preserve
+    }
+
     pub fn set_terminal_width(&mut self, terminal_width: u16) {
         self.terminal_width = terminal_width;
     }
 
+    fn effective_mode(&self) -> LineWrapMode {
+        match self.mode {
+            // Width 0 means it is not known yet.
+            LineWrapMode::Auto if (1..=AUTO_ADJUST_MAX_WIDTH).contains(&self.terminal_width) => {
+                LineWrapMode::Adjust
+            }
+            LineWrapMode::Auto => LineWrapMode::Preserve,
+            mode => mode,
+        }
+    }
+
     pub(super) fn reformat<'a>(
         &self,
         fragments: FragmentList<'a>,
         is_full: bool,
     ) -> (usize, Vec<Fragment<'a>>) {
-        match self.mode {
+        match self.effective_mode() {
             LineWrapMode::Adjust => {
                 let mut fragments = fragments.into_inner();
-                let consumed = adjust_line_wrapping(&mut fragments, is_full, self.terminal_width);
+                let consumed = adjust_line_wrapping(
+                    &mut fragments,
+                    is_full,
+                    self.terminal_width,
+                    self.wrap_log.as_ref(),
+                );
                 (consumed, fragments)
             }
-            LineWrapMode::Preserve => {
+            LineWrapMode::Preserve | LineWrapMode::Auto => {
                 let consumed = fragments.size();
                 (consumed, fragments.into_inner())
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_follows_terminal_width() {
+        let mut reformatter = Reformatter::new(0, LineWrapMode::Auto);
+        assert!(reformatter.is_passthrough());
+        reformatter.set_terminal_width(80);
+        assert!(!reformatter.is_passthrough());
+        reformatter.set_terminal_width(AUTO_ADJUST_MAX_WIDTH + 1);
+        assert!(reformatter.is_passthrough());
+    }
+}
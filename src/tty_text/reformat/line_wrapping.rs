@@ -1,3 +1,4 @@
+use super::WrapLog;
 use crate::tty_text::fragment::{EscapeSequence, Fragment};
 
 const MAX_CONTINUATION_INDENT: usize = 2;
@@ -7,7 +8,13 @@ pub(super) fn adjust_line_wrapping(
     fragments: &mut Vec<Fragment>,
     allow_incomplete: bool,
     terminal_width: u16,
+    log: Option<&WrapLog>,
 ) -> usize {
+    let record = |decision: &str, heuristic: &str, line: &[u8]| {
+        if let Some(log) = log {
+            log.record(decision, heuristic, terminal_width, line);
+        }
+    };
     let mut cursor = FragmentCursor::new(fragments, terminal_width);
     let mut prev: Option<(usize, Vec<u8>)> = None;
 
@@ -23,6 +30,7 @@ pub(super) fn adjust_line_wrapping(
         if let Some((prev_adjusted, prev_line)) = prev.take()
             && has_split_url_scheme(&prev_line, &line)
         {
+            record("join", "split-url-scheme", &line);
             cursor.join();
             cursor.set_position(prev_adjusted);
             adjusted = prev_adjusted;
@@ -31,6 +39,9 @@ pub(super) fn adjust_line_wrapping(
         }
 
         if !should_attempt_url_unwrap(&line, terminal_width) {
+            if line.windows(3).any(|w| w == b"://") {
+                record("skip", "url-not-at-edge", &line);
+            }
             prev = (line_width(&line) == usize::from(terminal_width)).then_some((adjusted, line));
             continue;
         }
@@ -40,11 +51,13 @@ pub(super) fn adjust_line_wrapping(
 
         loop {
             let Some(line) = cursor.extract_line() else {
+                record("wait", "incomplete", &line);
                 cursor.set_position(adjusted);
                 break 'outer;
             };
 
             let Some(margin) = url_continuation_indent(&line) else {
+                record("skip", "not-a-continuation", &line);
                 cursor.rewind();
                 break;
             };
@@ -52,22 +65,28 @@ pub(super) fn adjust_line_wrapping(
             // Only join continuations whose content is all ASCII graphic (no spaces).
             // Lines with spaces are surrounding prose, not URL fragments; joining
             // them would lose the inter-word space consumed by terminal wrapping.
-            if !is_ascii_graphic_run(&line[margin..]) {
+            let heuristic = if is_ascii_graphic_run(&line[margin..]) {
+                "url-continuation"
+            } else {
                 // Exception: if the previous line filled exactly terminal_width
                 // (forced wrap at the boundary), this is likely a URL split.
                 // Cursor-forward escapes within the continuation preserve word-boundary
                 // spaces, so joining the whole line is safe.
                 if usize::from(terminal_width) > previous_line_width {
+                    record("skip", "prose-continuation", &line);
                     cursor.rewind();
                     break;
                 }
-            }
+                "forced-wrap"
+            };
 
+            record("join", heuristic, &line);
             cursor.join();
 
             previous_line_width = line_width(&line);
 
             if !can_have_another_url_continuation(&line, terminal_width) {
+                record("stop", "continuation-ends", &line);
                 cursor.rewind();
                 break;
             }
@@ -163,12 +182,19 @@ impl<'a, 'b> FragmentCursor<'a, 'b> {
                     EscapeSequence::EndSynchronizedUpdate
                     | EscapeSequence::ShowCursor
                     | EscapeSequence::SetWindowAndIconTitle(_)
-                    | EscapeSequence::PostNotification(_),
+                    | EscapeSequence::PostNotification(_)
+                    | EscapeSequence::PostTitledNotification { .. },
                 ) => {
                     found = true;
                     break;
                 }
-                Some(EscapeSequence::Incomplete | EscapeSequence::Other) => {}
+                Some(
+                    EscapeSequence::QueryColor { .. }
+                    | EscapeSequence::ColorSchemeUpdates(_)
+                    | EscapeSequence::Osc777(_)
+                    | EscapeSequence::Incomplete
+                    | EscapeSequence::Other,
+                ) => {}
             }
         }
 
@@ -416,7 +442,7 @@ mod tests {
             let name = stem.strip_suffix(".capture").unwrap();
             let data = std::fs::read(path).unwrap();
             let mut fragments = FragmentList::parse(&data, false).into_inner();
-            adjust_line_wrapping(&mut fragments, false, 40); // column width set in capture.exp
+            adjust_line_wrapping(&mut fragments, false, 40, None); // column width set in capture.exp
             let output: Vec<u8> = fragments.iter().flat_map(|f| f.data()).copied().collect();
             let mut settings = insta::Settings::clone_current();
             settings.set_prepend_module_to_snapshot(false);
@@ -428,12 +454,29 @@ mod tests {
         });
     }
 
+    #[test]
+    fn wrap_log() {
+        let path = std::env::temp_dir().join(format!("caloud-wrap-{}.log", std::process::id()));
+        let log = WrapLog::new(std::fs::File::create(&path).unwrap());
+        let data = b"see https://example.com/aaaaaaaaaaaaaaaaaaaaaaaaaaaa\n  bbbb\n\
+                     - https://example.com/\n  cc dd\n\n";
+        let mut fragments = FragmentList::parse(data, false).into_inner();
+        adjust_line_wrapping(&mut fragments, false, 52, Some(&log));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "join url-continuation width=52 \"  bbbb\"\n\
+             stop continuation-ends width=52 \"  bbbb\"\n\
+             skip url-not-at-edge width=52 \"- https://example.com/\"\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[property_test]
     fn consumes_all_when_no_pending_url(
         #[strategy = arb_pty_input_without_pending_url()] (tw, data): (u16, Vec<u8>),
     ) {
         let mut fragments = FragmentList::parse(&data, false).into_inner();
-        let consumed = adjust_line_wrapping(&mut fragments, false, tw);
+        let consumed = adjust_line_wrapping(&mut fragments, false, tw, None);
         prop_assert_eq!(consumed, data.len());
     }
 
@@ -446,7 +489,7 @@ mod tests {
         ),
     ) {
         let mut fragments = FragmentList::parse(&data, false).into_inner();
-        let consumed = adjust_line_wrapping(&mut fragments, false, tw);
+        let consumed = adjust_line_wrapping(&mut fragments, false, tw, None);
         prop_assert_eq!(consumed + pending_length, data.len());
     }
 
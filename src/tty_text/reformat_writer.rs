@@ -0,0 +1,104 @@
+//! The output processing of a session as a [`Write`] adapter, so that any
+//! byte stream can be run through it.
+
+use crate::tty_text::buffer::Buffer;
+use crate::tty_text::fragment::EscapeSequence;
+use crate::tty_text::reformat::Reformatter;
+use std::io::{self, Write};
+
+/// Holds a line back until it is known whether the next one continues it.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Splits what is written into fragments, reformats them like the output of
+/// `claude`, and writes them to `inner`, calling `on_escape_sequence` for
+/// each escape sequence, e.g. to deliver the notifications posted with OSC 9
+/// or OSC 777.
+pub struct ReformatWriter<W: Write, F: FnMut(&EscapeSequence<'_>)> {
+    inner: W,
+    reformatter: Reformatter,
+    buffer: Buffer,
+    on_escape_sequence: F,
+}
+
+impl<W: Write, F: FnMut(&EscapeSequence<'_>)> ReformatWriter<W, F> {
+    pub fn new(inner: W, reformatter: Reformatter, on_escape_sequence: F) -> Self {
+        Self {
+            inner,
+            reformatter,
+            buffer: Buffer::new(BUFFER_SIZE),
+            on_escape_sequence,
+        }
+    }
+
+    /// Flush what is held back and return the writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, F: FnMut(&EscapeSequence<'_>)> Write for ReformatWriter<W, F> {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let n = self.buffer.extend_from_read(&mut buf)?;
+        for fragment in self.buffer.read_fragments(&self.reformatter) {
+            self.inner.write_all(fragment.data())?;
+            if let Some(escape_sequence) = fragment.escape_sequence() {
+                (self.on_escape_sequence)(escape_sequence);
+            }
+        }
+        Ok(n)
+    }
+
+    /// Write out what is held back, even an incomplete escape sequence or a
+    /// line that the next write might have continued.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.write_all(self.buffer.take_pending())?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::reformat::LineWrapMode;
+
+    #[test]
+    fn escape_sequences_are_reported_across_writes() {
+        let mut notifications = Vec::new();
+        let mut writer = ReformatWriter::new(
+            Vec::new(),
+            Reformatter::new(80, LineWrapMode::Preserve),
+            |escape_sequence: &EscapeSequence<'_>| {
+                if let EscapeSequence::PostNotification(message) = escape_sequence {
+                    notifications.push(message.to_vec());
+                }
+            },
+        );
+        writer.write_all(b"a\n\x1b]9;Do").unwrap();
+        writer.write_all(b"ne\x07b").unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(output, b"a\n\x1b]9;Done\x07b");
+        assert_eq!(notifications, [b"Done"]);
+    }
+
+    #[test]
+    fn captures_are_reformatted_as_in_snapshots() {
+        let snapshots = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/tty_text/reformat/snapshots");
+        for name in ["bare", "codespan", "inline", "ordered", "unordered"] {
+            let capture = std::fs::read(snapshots.join(format!("{name}.capture.raw"))).unwrap();
+            let expected = std::fs::read(snapshots.join(format!("{name}.snap.raw"))).unwrap();
+            // The width of the captures, as for `caloud reformat --width=40`.
+            let mut writer = ReformatWriter::new(
+                Vec::new(),
+                Reformatter::new(40, LineWrapMode::Adjust),
+                |_: &EscapeSequence<'_>| {},
+            );
+            writer.write_all(&capture).unwrap();
+            // The snapshots leave out the last line, which is unterminated.
+            let last_line_start = capture.iter().rposition(|&b| b == b'\n').unwrap() + 1;
+            let expected = [&expected[..], &capture[last_line_start..]].concat();
+            assert!(writer.into_inner().unwrap() == expected, "{name}");
+        }
+    }
+}
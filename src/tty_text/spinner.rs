@@ -0,0 +1,127 @@
+//! The spinner that `claude` shows while it works, e.g.
+//! `✻ Pondering… (42s · ↓ 1.2k tokens · esc to interrupt)`.
+
+use crate::tty_text::fragment::Fragment;
+
+/// Spinner lines are redrawn every frame with a new glyph and timer.
+const SPINNER_HINTS: &[&str] = &["esc to interrupt", "ctrl+c to interrupt"];
+
+/// The glyphs the spinner cycles through, which also mark the summary of a
+/// finished turn (`✻ Worked for 2m 5s`) and a collapsed thought (`∴ Thought
+/// for 3s`).
+const SPINNER_GLYPHS: &[char] = &['·', '✢', '✳', '✶', '✻', '✽', '*', '∴'];
+
+pub fn is_spinner_line(line: &str) -> bool {
+    SPINNER_HINTS.iter().any(|hint| line.contains(hint))
+}
+
+/// Whether `line` only tells what `claude` is up to, and is soon redrawn or
+/// replaced: the spinner, even when too narrow for its hint
+/// (`✻ Deliberating…`), the summary of a turn, or a token counter.  Such
+/// lines are neither spoken, transcribed, nor matched against.
+pub fn is_status_line(line: &str) -> bool {
+    if is_spinner_line(line) || is_token_counter(line) {
+        return true;
+    }
+    // Drawn from the first column, unlike the lines of a message.
+    let Some(status) = line
+        .strip_prefix(SPINNER_GLYPHS)
+        .and_then(|rest| rest.strip_prefix(' '))
+    else {
+        return false;
+    };
+    let mut words = status.split_whitespace();
+    let first = words.next().unwrap_or_default();
+    first.ends_with('…')
+        || (words.next() == Some("for")
+            && words
+                .next()
+                .is_some_and(|elapsed| elapsed.starts_with(|c: char| c.is_ascii_digit())))
+}
+
+/// `↓ 1.2k tokens`, as shown next to the spinner.
+fn is_token_counter(line: &str) -> bool {
+    line.match_indices(['↑', '↓']).any(|(i, arrow)| {
+        let count = line[i + arrow.len()..].trim_start();
+        let digits = count
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .unwrap_or(count.len());
+        let unit = count[digits..].trim_start_matches('k');
+        digits > 0 && unit.starts_with(" tokens")
+    })
+}
+
+/// Tells, fragment by fragment, when a spinner line has been drawn.
+pub struct SpinnerDetector {
+    line: String,
+}
+
+impl Default for SpinnerDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpinnerDetector {
+    pub fn new() -> Self {
+        Self {
+            line: String::new(),
+        }
+    }
+
+    /// Feed the next fragment, returning whether it completes a spinner line.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> bool {
+        if fragment.is_sgr() {
+            return false;
+        }
+        if !fragment.is_plain_text() {
+            // The spinner is redrawn in place, so cursor movement ends it.
+            return self.finish_line();
+        }
+        self.line
+            .push_str(&String::from_utf8_lossy(fragment.data()));
+        self.line.ends_with('\n') && self.finish_line()
+    }
+
+    fn finish_line(&mut self) -> bool {
+        is_spinner_line(&std::mem::take(&mut self.line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    #[test]
+    fn spinner_frames() {
+        let mut detector = SpinnerDetector::new();
+        let frames = "\
+            ⏺ Running the tests.\r\n\
+            \x1b[38;5;174m✻\x1b[39m Pondering… (1s · esc to interrupt)\x1b[2K\x1b[1G\
+            \x1b[38;5;174m✶\x1b[39m Pondering… (2s · esc to interrupt)\r\n\
+            > \r\n";
+        let detected = FragmentList::parse(frames.as_bytes(), false)
+            .into_inner()
+            .iter()
+            .filter(|fragment| detector.push(fragment))
+            .count();
+        assert_eq!(detected, 2);
+    }
+
+    #[test]
+    fn status_lines() {
+        assert!(is_status_line(
+            "✻ Pondering… (42s · ↓ 1.2k tokens · esc to interrupt)"
+        ));
+        assert!(is_status_line("✢ Deliberating…"));
+        assert!(is_status_line("* Thinking… (12s)"));
+        assert!(is_status_line("✻ Worked for 2m 5s"));
+        assert!(is_status_line("∴ Thought for 3s (ctrl+o to show thinking)"));
+        assert!(is_status_line("  ↑ 340 tokens"));
+        assert!(!is_status_line("⏺ Loading the config…"));
+        assert!(!is_status_line("  * Loading…"));
+        assert!(!is_status_line("* Works for me"));
+        assert!(!is_status_line("Upload ↑ 3 files"));
+    }
+}
@@ -0,0 +1,87 @@
+//! `--speak-tools`: notice when `claude` starts a tool, so that its name can
+//! be read out while the screen is out of sight.
+
+use crate::tty_text::fragment::Fragment;
+use crate::tty_text::message::{MESSAGE_BULLETS, tool_name};
+use std::collections::VecDeque;
+
+/// How many recent tool calls are remembered, so that a call redrawn by the
+/// TUI, e.g. as its output grows, is not returned again.
+const RECENT_CALLS: usize = 16;
+
+/// Collects plain text into lines and returns the name of the tool for each
+/// line starting a tool call, such as `⏺ Bash(cargo test)`.
+pub struct ToolCallDetector {
+    line: Vec<u8>,
+    recent: VecDeque<String>,
+}
+
+impl Default for ToolCallDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolCallDetector {
+    pub fn new() -> Self {
+        Self {
+            line: Vec::new(),
+            recent: VecDeque::with_capacity(RECENT_CALLS),
+        }
+    }
+
+    /// Feed the next fragment, returning a tool name if it completes the
+    /// first line of a tool call.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> Option<String> {
+        if !fragment.is_plain_text() {
+            return None;
+        }
+        self.line.extend_from_slice(fragment.data());
+        if !self.line.ends_with(b"\n") {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.line)).into_owned();
+        let call = MESSAGE_BULLETS
+            .iter()
+            .find_map(|bullet| line.trim().strip_prefix(bullet))?
+            .trim()
+            .to_string();
+        let name = tool_name(&call)?.to_string();
+        // The whole call, so that running the same tool again is announced.
+        if self.recent.contains(&call) {
+            return None;
+        }
+        if self.recent.len() == RECENT_CALLS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(call);
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    #[test]
+    fn tool_names_are_returned_once() {
+        let output = "\
+            \x1b[38;5;231m⏺\x1b[39m I'll run the tests.\r\n\
+            \r\n\
+            ⏺ \x1b[1mBash\x1b[22m(cargo test)\r\n  \
+              ⎿  Running…\r\n\
+            \x1b[2K\x1b[2A⏺ Bash(cargo test)\r\n  \
+              ⎿  test result: ok\r\n\
+            ⏺ Read(src/main.rs)\r\n\
+            ⏺ Bash(cargo clippy)\r\n\
+            ⏺ Done (mostly).\r\n";
+        let mut detector = ToolCallDetector::new();
+        let names: Vec<_> = FragmentList::parse(output.as_bytes(), false)
+            .into_inner()
+            .iter()
+            .filter_map(|fragment| detector.push(fragment))
+            .collect();
+        assert_eq!(names, ["Bash", "Read", "Bash"]);
+    }
+}
@@ -0,0 +1,194 @@
+//! `--screen-reader`: turn the repainted TUI into a transcript that a screen
+//! reader can follow line by line.
+//!
+//! The TUI redraws its lower part in place: it erases lines, moves the cursor
+//! up, and writes them again, mostly unchanged.  A screen reader reading the
+//! terminal hears each redraw as new content.  [`Transcript`] keeps a model
+//! of the recent rows, follows the cursor as it moves up and down, and emits
+//! a row only when its text has changed.  Escape sequences are dropped except
+//! titles and notifications, and every row ends with a real line break.
+
+use crate::tty_text::fragment::{EscapeSequence, Fragment};
+use crate::tty_text::spinner::is_status_line;
+
+/// How many rows are remembered for recognizing redraws.
+const MAX_ROWS: usize = 1000;
+
+pub struct Transcript {
+    rows: Vec<String>,
+    /// Row of the cursor, an index into `rows` or one past the end.
+    cursor_row: usize,
+    line: String,
+    last_emitted_blank: bool,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            cursor_row: 0,
+            line: String::new(),
+            last_emitted_blank: false,
+        }
+    }
+
+    /// Feed the next fragment, returning what to write in its place.
+    pub fn push(&mut self, fragment: &Fragment<'_>) -> Vec<u8> {
+        let mut out = Vec::new();
+        match fragment.escape_sequence() {
+            None => {
+                for c in String::from_utf8_lossy(fragment.data()).chars() {
+                    match c {
+                        '\n' => {
+                            self.commit_line(&mut out, true);
+                            self.cursor_row += 1;
+                        }
+                        '\r' => {}
+                        c => self.line.push(c),
+                    }
+                }
+            }
+            Some(
+                EscapeSequence::SetWindowAndIconTitle(_)
+                | EscapeSequence::PostNotification(_)
+                | EscapeSequence::PostTitledNotification { .. },
+            ) => {
+                out.extend_from_slice(fragment.data());
+            }
+            Some(_) => match parse_csi(fragment.data()) {
+                // Cursor Forward: the TUI skips over blanks instead of writing them.
+                Some((n, b'C')) => self.line.extend(std::iter::repeat_n(' ', n)),
+                // Cursor Up / Down
+                Some((n, b'A')) => {
+                    self.commit_line(&mut out, false);
+                    self.cursor_row = self.cursor_row.saturating_sub(n);
+                }
+                Some((n, b'B')) => {
+                    self.commit_line(&mut out, false);
+                    self.cursor_row += n;
+                }
+                // Colors and the like do not matter to a screen reader.
+                Some((_, b'm')) => {}
+                // Erasing, synchronized updates, cursor visibility, and
+                // anything else ends what is being written on this row.
+                _ => self.commit_line(&mut out, false),
+            },
+        }
+        out
+    }
+
+    /// Emit a line that is still being written, e.g. before exiting.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.commit_line(&mut out, false);
+        out
+    }
+
+    /// Record the line at the cursor row and emit it if the row changed.
+    /// Without a line break, nothing is recorded unless something was written.
+    fn commit_line(&mut self, out: &mut Vec<u8>, is_line_break: bool) {
+        let line = std::mem::take(&mut self.line);
+        let line = line.trim_end();
+        if line.is_empty() && !is_line_break {
+            return;
+        }
+        if self.rows.len() < self.cursor_row {
+            self.rows.resize(self.cursor_row, String::new());
+        }
+        match self.rows.get_mut(self.cursor_row) {
+            Some(row) if row == line => return,
+            Some(row) => line.clone_into(row),
+            None => self.rows.push(line.to_string()),
+        }
+        if self.rows.len() > MAX_ROWS {
+            let excess = self.rows.len() - MAX_ROWS;
+            self.rows.drain(..excess);
+            self.cursor_row -= excess;
+        }
+
+        // Spinner lines are redrawn every frame with a new glyph and timer.
+        if is_status_line(line) {
+            return;
+        }
+        let is_blank = line.is_empty();
+        if is_blank && self.last_emitted_blank {
+            return;
+        }
+        self.last_emitted_blank = is_blank;
+        out.extend_from_slice(line.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+/// `ESC [ Pn F` with a single numeric parameter (default 1), as (Pn, F).
+fn parse_csi(data: &[u8]) -> Option<(usize, u8)> {
+    let (&final_byte, params) = data.strip_prefix(b"\x1b[")?.split_last()?;
+    if !params.iter().all(|b| b.is_ascii_digit() || *b == b';') {
+        return None;
+    }
+    let n = std::str::from_utf8(params)
+        .ok()
+        .and_then(|params| params.parse().ok())
+        .unwrap_or(1);
+    Some((n, final_byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tty_text::fragment::FragmentList;
+
+    fn transcribe(chunks: &[&str]) -> String {
+        let mut transcript = Transcript::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            for fragment in FragmentList::parse(chunk.as_bytes(), false).into_inner() {
+                out.extend(transcript.push(&fragment));
+            }
+        }
+        out.extend(transcript.finish());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn redraws_are_not_repeated() {
+        let frame = |spinner: &str, status: &str| {
+            format!(
+                "\x1b[?2026h\x1b[2K\x1b[1A\x1b[2K\x1b[1A\x1b[2K\x1b[G\
+                 {spinner} Thinking… (esc to interrupt)\r\n\
+                 \x1b[2m{status}\x1b[22m\r\n\
+                 > \x1b[?2026l"
+            )
+        };
+        let output = transcribe(&[
+            "⏺ Hello\x1b[1Cthere.\r\n\r\n\r\n",
+            "\x1b[?2026h\x1b[36m✻\x1b[39m Thinking… (esc to interrupt)\r\nready\r\n> \x1b[?2026l",
+            &frame("✽", "ready"),
+            &frame("✢", "ready"),
+            &frame("✳", "done"),
+        ]);
+        assert_eq!(output, "⏺ Hello there.\r\n\r\nready\r\n>\r\ndone\r\n");
+    }
+
+    #[test]
+    fn titles_and_notifications_pass_through() {
+        assert_eq!(
+            transcribe(&["\x1b]0;Claude\x07a\x1b]9;Done\x07\r\n"]),
+            "\x1b]0;Claude\x07\x1b]9;Done\x07a\r\n"
+        );
+    }
+
+    #[test]
+    fn csi() {
+        assert_eq!(parse_csi(b"\x1b[3A"), Some((3, b'A')));
+        assert_eq!(parse_csi(b"\x1b[A"), Some((1, b'A')));
+        assert_eq!(parse_csi(b"\x1b[38;5;1m"), Some((1, b'm')));
+        assert_eq!(parse_csi(b"\x1b[?25l"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! `caloud mirror NAME`: watch the output of a running session from another
+//! terminal, e.g. on a second monitor or over SSH, without being able to type
+//! into it.  `NAME` is the `--control-socket` of the session, whose `watch`
+//! command streams what the session writes to its terminal.
+
+use anyhow::{Context, bail};
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+/// Writes a viewer has yet to take.  One that falls further behind is cut
+/// off rather than holding the output up or leaving a gap in it.
+const MAX_PENDING_WRITES: usize = 1024;
+
+/// Keys that close the viewer: Ctrl-C, Ctrl-D, and `q`.
+const QUIT_KEYS: &[u8] = &[0x03, 0x04, b'q'];
+
+/// Show the cursor and reset the colors that the session may have left.
+const RESET: &[u8] = b"\x1b[?25h\x1b[0m\r\n";
+
+/// The output of the session, shared between the output thread, which
+/// writes it, and the viewers attached through the control socket.
+#[derive(Clone, Default)]
+pub struct OutputTap(Arc<Mutex<Vec<SyncSender<Vec<u8>>>>>);
+
+impl OutputTap {
+    /// Receive what is written from now on.
+    pub fn subscribe(&self) -> Receiver<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(MAX_PENDING_WRITES);
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(tx);
+        rx
+    }
+
+    fn send(&self, data: &[u8]) {
+        let mut viewers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        viewers.retain(|tx| match tx.try_send(data.to_vec()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+/// A writer that also hands what it writes to the viewers, if any.
+pub struct TeeWriter<W: Write> {
+    inner: W,
+    tap: Option<OutputTap>,
+}
+
+impl<W: Write> TeeWriter<W> {
+    pub fn new(inner: W, tap: Option<OutputTap>) -> Self {
+        Self { inner, tap }
+    }
+}
+
+impl<W: Write> Write for TeeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(tap) = &self.tap {
+            tap.send(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub fn run(args: &[OsString]) -> anyhow::Result<()> {
+    let [name] = args else {
+        bail!("usage: caloud mirror NAME");
+    };
+    let mut stream = UnixStream::connect(name)
+        .with_context(|| format!("failed to connect to {}", name.to_string_lossy()))?;
+    stream.write_all(b"watch\n")?;
+
+    // Raw, like the terminal of the session, so that the output looks the
+    // same and the replies of this terminal to its queries are not echoed.
+    let termios = crate::try_make_raw(io::stdin()).context("try_make_raw")?;
+    let quit = stream.try_clone()?;
+    thread::spawn(move || {
+        let mut key = [0];
+        while io::stdin().read_exact(&mut key).is_ok() {
+            if QUIT_KEYS.contains(&key[0]) {
+                break;
+            }
+        }
+        let _ = quit.shutdown(Shutdown::Both);
+    });
+    let mut stdout = io::stdout().lock();
+    let copied = io::copy(&mut stream, &mut stdout);
+    let _ = stdout.write_all(RESET);
+    let _ = stdout.flush();
+    drop(termios);
+    copied.context("failed to read the session")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tee() {
+        let tap = OutputTap::default();
+        let mut writer = TeeWriter::new(Vec::new(), Some(tap.clone()));
+        writer.write_all(b"before").unwrap();
+        let viewer = tap.subscribe();
+        writer.write_all(b"after").unwrap();
+        assert_eq!(writer.inner, b"beforeafter");
+        assert_eq!(viewer.try_iter().collect::<Vec<_>>(), [b"after"]);
+
+        // A viewer that has gone, or fallen too far behind, is dropped.
+        drop(viewer);
+        let slow = tap.subscribe();
+        writer.write_all(b"x").unwrap();
+        assert_eq!(tap.0.lock().unwrap().len(), 1);
+        for _ in 0..MAX_PENDING_WRITES {
+            writer.write_all(b"x").unwrap();
+        }
+        assert!(tap.0.lock().unwrap().is_empty());
+        assert_eq!(slow.try_iter().count(), MAX_PENDING_WRITES);
+    }
+}
@@ -0,0 +1,136 @@
+//! `--voice-pool`: give each session its own voice, so that it can be told
+//! by ear which project is speaking.
+
+use anyhow::{Context, bail};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VoiceAssignment {
+    /// By the name of the session, so a project keeps its voice across runs.
+    #[default]
+    Hash,
+    /// The next voice for each session started, shared by all sessions of the user.
+    RoundRobin,
+}
+
+impl VoiceAssignment {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "hash" => Ok(Self::Hash),
+            "round-robin" => Ok(Self::RoundRobin),
+            _ => bail!("unknown assignment: {s} (expected hash, round-robin)"),
+        }
+    }
+}
+
+impl fmt::Display for VoiceAssignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hash => "hash",
+            Self::RoundRobin => "round-robin",
+        })
+    }
+}
+
+/// Pick the voice of a session from `pool`, or `None` if it is empty.
+pub fn assign<'a>(
+    pool: &'a [String],
+    assignment: VoiceAssignment,
+    session_name: Option<&str>,
+) -> anyhow::Result<Option<&'a str>> {
+    if pool.is_empty() {
+        return Ok(None);
+    }
+    let n = match assignment {
+        VoiceAssignment::Hash => fnv1a(session_name.unwrap_or_default().as_bytes()),
+        VoiceAssignment::RoundRobin => next_turn(&counter_path())?,
+    };
+    Ok(Some(&pool[(n % pool.len() as u64) as usize]))
+}
+
+/// Where the sessions of the user count their turns.
+fn counter_path() -> PathBuf {
+    std::env::temp_dir().join("caloud-voice-pool")
+}
+
+/// Increment the counter in `path` and return its previous value.
+fn next_turn(path: &Path) -> anyhow::Result<u64> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    // Sessions started together must not get the same turn.
+    file.lock()
+        .with_context(|| format!("failed to lock {}", path.display()))?;
+    let turn = read_counter(&mut file).unwrap_or(0);
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", turn.wrapping_add(1))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(turn)
+}
+
+/// `None` if the file is empty or was left unreadable.
+fn read_counter(file: &mut File) -> Option<u64> {
+    let mut counter = String::new();
+    file.read_to_string(&mut counter).ok()?;
+    counter.trim().parse().ok()
+}
+
+/// A hash that stays the same across builds, unlike that of `std`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Vec<String> {
+        ["Samantha", "Daniel", "Karen"].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn hash_keeps_the_voice_of_a_session() {
+        let pool = pool();
+        let voice = |name| assign(&pool, VoiceAssignment::Hash, Some(name)).unwrap();
+        assert_eq!(voice("caloud"), voice("caloud"));
+        let voices: std::collections::HashSet<_> = ["caloud", "website", "dotfiles", "api"]
+            .into_iter()
+            .map(voice)
+            .collect();
+        assert!(voices.len() > 1);
+        assert_eq!(
+            assign(&[], VoiceAssignment::Hash, Some("caloud")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn round_robin_takes_turns() {
+        let path = std::env::temp_dir().join(format!("caloud-voice-pool-{}", std::process::id()));
+        let turns: Vec<_> = (0..3).map(|_| next_turn(&path).unwrap()).collect();
+        assert_eq!(turns, [0, 1, 2]);
+        std::fs::write(&path, "garbage").unwrap();
+        assert_eq!(next_turn(&path).unwrap(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            VoiceAssignment::parse("round-robin").unwrap(),
+            VoiceAssignment::RoundRobin
+        );
+        assert_eq!(VoiceAssignment::RoundRobin.to_string(), "round-robin");
+        assert!(VoiceAssignment::parse("random").is_err());
+    }
+}
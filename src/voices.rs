@@ -0,0 +1,128 @@
+//! `caloud list-voices` and `caloud say`: find a voice for `--say` and hear
+//! how it pronounces a sample before using it in a session.
+
+use crate::args::Arguments;
+use crate::macos::say::{SayCommand, Voice};
+use crate::platform::{Native, Platform};
+use anyhow::{Context, bail};
+use lexopt::prelude::*;
+use std::ffi::OsString;
+
+/// Spoken by `caloud say` without a text.
+const SAMPLE: &str = "Claude is waiting for your input. Tests pass: 12 of 12.";
+
+/// `caloud list-voices [LOCALE]`: the installed voices as `NAME  LOCALE`
+/// rows, only those whose locale starts with `LOCALE` if given, e.g. `en` or
+/// `en_GB`.
+pub fn list(args: &[OsString]) -> anyhow::Result<()> {
+    let locale = match args {
+        [] => None,
+        [locale] => Some(locale.to_str().context("LOCALE must be valid UTF-8")?),
+        _ => bail!("usage: caloud list-voices [LOCALE]"),
+    };
+    let voices = Native::list_voices()?;
+    print!("{}", format_voices(&voices, locale));
+    Ok(())
+}
+
+fn format_voices(voices: &[Voice], locale: Option<&str>) -> String {
+    // `en-GB` as well as `en_GB`.
+    let locale = locale.map(|locale| locale.replace('-', "_"));
+    let voices: Vec<&Voice> = voices
+        .iter()
+        .filter(|voice| {
+            locale
+                .as_deref()
+                .is_none_or(|locale| voice.locale.starts_with(locale))
+        })
+        .collect();
+    let width = voices
+        .iter()
+        .map(|voice| voice.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    voices
+        .iter()
+        .map(|voice| format!("{:<width$}  {}\n", voice.name, voice.locale))
+        .collect()
+}
+
+/// `caloud say [--voice VOICE] [TEXT...]`: speak `TEXT`, or a sample, with
+/// `--say` as configured, in `VOICE` if given.
+pub fn say(arguments: &Arguments, args: &[OsString]) -> anyhow::Result<()> {
+    let (voice, text) = parse_say_args(args)?;
+    let mut say_command = match arguments.try_build_say_command() {
+        Some(say_command) => say_command?,
+        None => SayCommand::new(Vec::new()),
+    };
+    if let Some(voice) = &voice {
+        // Only `say` lists its voices; a TTS command reports an unknown one.
+        if cfg!(target_os = "macos") && !Native::list_voices()?.iter().any(|v| &v.name == voice) {
+            bail!("{voice} is not installed; run `caloud list-voices` to list voices");
+        }
+        say_command = say_command.with_voice(voice);
+    }
+    let status = say_command
+        .spawn(text.as_deref().unwrap_or(SAMPLE))?
+        .wait()
+        .context("failed to wait for say")?;
+    anyhow::ensure!(status.success(), "say failed: {status}");
+    Ok(())
+}
+
+fn parse_say_args(args: &[OsString]) -> anyhow::Result<(Option<String>, Option<String>)> {
+    let mut voice = None;
+    let mut words = Vec::new();
+    let mut parser = lexopt::Parser::from_args(args);
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("voice") | Short('v') => voice = Some(parser.value()?.string()?),
+            Value(word) => words.push(word.string()?),
+            _ => bail!(
+                "{}\nusage: caloud say [--voice VOICE] [TEXT...]",
+                arg.unexpected()
+            ),
+        }
+    }
+    Ok((voice, (!words.is_empty()).then(|| words.join(" "))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voice_rows() {
+        let voices = [
+            ("Albert", "en_US"),
+            ("Eddy (English (UK))", "en_GB"),
+            ("Kyoko", "ja_JP"),
+        ]
+        .map(|(name, locale)| Voice {
+            name: name.to_string(),
+            locale: locale.to_string(),
+        });
+        assert_eq!(
+            format_voices(&voices, Some("en")),
+            "Albert               en_US\nEddy (English (UK))  en_GB\n"
+        );
+        assert_eq!(format_voices(&voices, Some("ja-JP")), "Kyoko  ja_JP\n");
+        assert_eq!(format_voices(&voices, None).lines().count(), 3);
+    }
+
+    #[test]
+    fn say_args() {
+        let parse =
+            |args: &[&str]| parse_say_args(&args.iter().map(OsString::from).collect::<Vec<_>>());
+        assert_eq!(
+            parse(&["--voice", "Kyoko", "こんにちは"]).unwrap(),
+            (Some("Kyoko".to_string()), Some("こんにちは".to_string()))
+        );
+        assert_eq!(
+            parse(&["git", "rebase", "-v", "Daniel"]).unwrap(),
+            (Some("Daniel".to_string()), Some("git rebase".to_string()))
+        );
+        assert_eq!(parse(&[]).unwrap(), (None, None));
+        assert!(parse(&["--rate=200"]).is_err());
+    }
+}